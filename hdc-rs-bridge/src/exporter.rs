@@ -0,0 +1,352 @@
+//! Prometheus exporter for device farms (requires `exporter` feature)
+//!
+//! An [`Exporter`] samples every device attached to the HDC server on a
+//! fixed interval and keeps the latest values, which the bridge serves in
+//! the Prometheus text format on `GET /metrics`. Scrapes never touch the
+//! devices, so a slow device only delays its own numbers.
+//!
+//! | Metric | Labels |
+//! |---|---|
+//! | `hdc_device_up` | `device`, `connection_type`, `model` |
+//! | `hdc_device_sample_success` | `device` |
+//! | `hdc_device_storage_size_bytes` | `device`, `mountpoint` |
+//! | `hdc_device_storage_available_bytes` | `device`, `mountpoint` |
+//! | `hdc_device_memory_total_bytes` | `device` |
+//! | `hdc_device_memory_available_bytes` | `device` |
+//! | `hdc_device_battery_level_percent` | `device` |
+//! | `hdc_device_battery_temperature_celsius` | `device` |
+//! | `hdc_device_battery_plugged` | `device` |
+//!
+//! `hdc_device_up` is reported for every device the server lists, the
+//! other metrics only for connected ones. Battery metrics are left out for
+//! devices without a battery, which does not count as a failed sample.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use std::time::Duration;
+//! use hdc_rs_bridge::{exporter::Exporter, Bridge};
+//!
+//! let exporter = Exporter::new("127.0.0.1:8710").interval(Duration::from_secs(30));
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:9710").await?;
+//! Bridge::new("127.0.0.1:8710")
+//!     .exporter(exporter)
+//!     .serve(listener)
+//!     .await
+//! # }
+//! ```
+
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use hdc_rs::sysinfo::{BatteryInfo, MemInfo, StorageInfo};
+use hdc_rs::{DeviceInfo, HdcClient, ServerAddr};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, warn};
+
+/// Default time between two samples
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Values read from one device in one round
+#[derive(Debug, Clone)]
+pub struct DeviceSample {
+    /// Device as listed by the server
+    pub info: DeviceInfo,
+    /// Mounted filesystems, empty if the device was not sampled
+    pub storage: Vec<StorageInfo>,
+    /// Memory statistics, `None` if the device was not sampled
+    pub memory: Option<MemInfo>,
+    /// Battery state, `None` if not sampled or the device has no battery
+    pub battery: Option<BatteryInfo>,
+    /// Whether every command of the round succeeded
+    pub success: bool,
+}
+
+/// Periodic sampler behind the `/metrics` endpoint
+#[derive(Clone)]
+pub struct Exporter {
+    client: HdcClient,
+    interval: Duration,
+    samples: Arc<RwLock<Vec<DeviceSample>>>,
+}
+
+impl Exporter {
+    /// Exporter sampling the devices of the HDC server at `server`
+    pub fn new(server: impl Into<ServerAddr>) -> Self {
+        Self {
+            client: HdcClient::lazy(server),
+            interval: DEFAULT_INTERVAL,
+            samples: Arc::default(),
+        }
+    }
+
+    /// Time between two samples (default 15s)
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sample all devices now and keep the result for [`render`](Self::render)
+    ///
+    /// A failed device listing is returned as an error and leaves the
+    /// previous samples in place; failures on single devices are recorded
+    /// in `hdc_device_sample_success`.
+    pub async fn sample(&self) -> hdc_rs::Result<()> {
+        let devices = self.client.clone().list_targets_verbose().await?;
+        let mut rounds = JoinSet::new();
+        for (index, info) in devices.into_iter().enumerate() {
+            let client = self.client.clone();
+            rounds.spawn(async move { (index, sample_device(&client, info).await) });
+        }
+        let mut samples = Vec::with_capacity(rounds.len());
+        while let Some(joined) = rounds.join_next().await {
+            match joined {
+                Ok(sample) => samples.push(sample),
+                Err(e) => warn!("Sampling task failed: {}", e),
+            }
+        }
+        samples.sort_by_key(|(index, _)| *index);
+        *self.samples.write().unwrap() = samples.into_iter().map(|(_, s)| s).collect();
+        Ok(())
+    }
+
+    /// Sample every [`interval`](Self::interval) on a background task
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let exporter = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(exporter.interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                if let Err(e) = exporter.sample().await {
+                    warn!("Listing devices for metrics failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Latest samples in the Prometheus text format
+    pub fn render(&self) -> String {
+        render(&self.samples.read().unwrap())
+    }
+}
+
+async fn sample_device(client: &HdcClient, info: DeviceInfo) -> DeviceSample {
+    let mut sample = DeviceSample {
+        info,
+        storage: Vec::new(),
+        memory: None,
+        battery: None,
+        success: true,
+    };
+    if !sample.info.is_connected() {
+        return sample;
+    }
+    let mut device = client.device(sample.info.connect_key.as_str());
+    match device.storage().await {
+        Ok(storage) => sample.storage = storage,
+        Err(e) => {
+            debug!("Storage of {} unavailable: {}", sample.info.connect_key, e);
+            sample.success = false;
+        }
+    }
+    match device.meminfo().await {
+        Ok(memory) => sample.memory = Some(memory),
+        Err(e) => {
+            debug!("Memory of {} unavailable: {}", sample.info.connect_key, e);
+            sample.success = false;
+        }
+    }
+    match device.battery().await {
+        Ok(battery) => sample.battery = Some(battery),
+        Err(e) => debug!("Battery of {} unavailable: {}", sample.info.connect_key, e),
+    }
+    sample
+}
+
+/// Format `samples` in the Prometheus text exposition format
+pub fn render(samples: &[DeviceSample]) -> String {
+    let mut out = String::new();
+    let connected = || samples.iter().filter(|s| s.info.is_connected());
+
+    family(&mut out, "hdc_device_up", "Whether the device is connected");
+    for s in samples {
+        let labels = [
+            ("device", s.info.connect_key.as_str()),
+            ("connection_type", s.info.connection_type.as_str()),
+            ("model", s.info.model.as_str()),
+        ];
+        sample(
+            &mut out,
+            "hdc_device_up",
+            &labels,
+            s.info.is_connected() as u64,
+        );
+    }
+
+    family(
+        &mut out,
+        "hdc_device_sample_success",
+        "Whether the last sample of the device succeeded",
+    );
+    for s in connected() {
+        let labels = [("device", s.info.connect_key.as_str())];
+        sample(
+            &mut out,
+            "hdc_device_sample_success",
+            &labels,
+            s.success as u64,
+        );
+    }
+
+    for (name, help, value) in [
+        (
+            "hdc_device_storage_size_bytes",
+            "Filesystem size",
+            (|fs: &StorageInfo| fs.total_kb) as fn(&StorageInfo) -> u64,
+        ),
+        (
+            "hdc_device_storage_available_bytes",
+            "Filesystem space available to unprivileged users",
+            |fs: &StorageInfo| fs.available_kb,
+        ),
+    ] {
+        family(&mut out, name, help);
+        for s in connected() {
+            for fs in &s.storage {
+                let labels = [
+                    ("device", s.info.connect_key.as_str()),
+                    ("mountpoint", fs.mounted_on.as_str()),
+                ];
+                sample(&mut out, name, &labels, value(fs) * 1024);
+            }
+        }
+    }
+
+    for (name, help, value) in [
+        (
+            "hdc_device_memory_total_bytes",
+            "Total usable memory",
+            (|m: &MemInfo| m.total_kb) as fn(&MemInfo) -> u64,
+        ),
+        (
+            "hdc_device_memory_available_bytes",
+            "Memory available for new allocations",
+            |m: &MemInfo| m.available_kb,
+        ),
+    ] {
+        family(&mut out, name, help);
+        for s in connected() {
+            if let Some(memory) = &s.memory {
+                let labels = [("device", s.info.connect_key.as_str())];
+                sample(&mut out, name, &labels, value(memory) * 1024);
+            }
+        }
+    }
+
+    for (name, help, value) in [
+        (
+            "hdc_device_battery_level_percent",
+            "Battery charge level",
+            (|b: &BatteryInfo| b.level as f64) as fn(&BatteryInfo) -> f64,
+        ),
+        (
+            "hdc_device_battery_temperature_celsius",
+            "Battery temperature",
+            |b: &BatteryInfo| b.temperature_c,
+        ),
+        (
+            "hdc_device_battery_plugged",
+            "Whether a charger is attached",
+            |b: &BatteryInfo| b.is_plugged() as u8 as f64,
+        ),
+    ] {
+        family(&mut out, name, help);
+        for s in connected() {
+            if let Some(battery) = &s.battery {
+                let labels = [("device", s.info.connect_key.as_str())];
+                sample(&mut out, name, &labels, value(battery));
+            }
+        }
+    }
+    out
+}
+
+fn family(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+        .collect();
+    let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+}
+
+/// Escape a label value as the text format requires
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdc_rs::testing::MockServer;
+
+    #[tokio::test]
+    async fn test_sample_and_render() {
+        let hdc = MockServer::new()
+            .respond(
+                "list targets -v",
+                "dev-1\t\tUSB\tConnected\tNOH-AN00\n127.0.0.1:5555\t\tTCP\tOffline\t\n",
+            )
+            .respond(
+                "shell df -k",
+                "Filesystem 1K-blocks Used Available Use% Mounted on\n\
+                 /dev/block/dm-1 1000 400 600 40% /data\n",
+            )
+            .respond(
+                "shell cat /proc/meminfo",
+                "MemTotal: 2048 kB\nMemAvailable: 512 kB\n",
+            )
+            .respond(
+                "shell hidumper -s BatteryService -a -i",
+                "capacity: 64\npluggedType: 2\ntemperature: 305\n",
+            )
+            .spawn()
+            .await
+            .unwrap();
+
+        let exporter = Exporter::new(hdc.address());
+        exporter.sample().await.unwrap();
+        let text = exporter.render();
+
+        assert!(text.contains("# TYPE hdc_device_up gauge\n"));
+        assert!(text.contains(
+            "hdc_device_up{device=\"dev-1\",connection_type=\"USB\",model=\"NOH-AN00\"} 1\n"
+        ));
+        assert!(text.contains(
+            "hdc_device_up{device=\"127.0.0.1:5555\",connection_type=\"TCP\",model=\"\"} 0\n"
+        ));
+        assert!(text.contains("hdc_device_sample_success{device=\"dev-1\"} 1\n"));
+        assert!(text.contains(
+            "hdc_device_storage_available_bytes{device=\"dev-1\",mountpoint=\"/data\"} 614400\n"
+        ));
+        assert!(text.contains("hdc_device_memory_total_bytes{device=\"dev-1\"} 2097152\n"));
+        assert!(text.contains("hdc_device_battery_level_percent{device=\"dev-1\"} 64\n"));
+        assert!(text.contains("hdc_device_battery_temperature_celsius{device=\"dev-1\"} 30.5\n"));
+        assert!(text.contains("hdc_device_battery_plugged{device=\"dev-1\"} 1\n"));
+        assert!(!text.contains("device=\"127.0.0.1:5555\"}"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
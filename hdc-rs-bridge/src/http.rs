@@ -0,0 +1,357 @@
+//! Just enough HTTP/1.1 for the bridge
+//!
+//! Each connection carries one request with an optional `Content-Length`
+//! body and is closed after the response, so no keep-alive or chunked
+//! encoding is needed.
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Longest accepted request line or header
+const MAX_LINE: usize = 8 * 1024;
+/// Most headers accepted in one request
+const MAX_HEADERS: usize = 64;
+
+/// Parsed request
+#[derive(Debug)]
+pub struct Request {
+    /// Method, e.g. `GET`
+    pub method: String,
+    /// Path segments, percent-decoded
+    pub segments: Vec<String>,
+    /// Query parameters, percent-decoded, in request order
+    pub query: Vec<(String, String)>,
+    /// Headers with lower-case names
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Read one request, rejecting bodies larger than `max_body` bytes
+    ///
+    /// Returns `Ok(None)` if the peer closed the connection before sending
+    /// anything.
+    pub async fn read<R>(reader: &mut R, max_body: usize) -> io::Result<Option<Request>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let Some(mut request) = Self::read_head(reader).await? else {
+            return Ok(None);
+        };
+        request.read_body(reader, max_body).await?;
+        Ok(Some(request))
+    }
+
+    /// Read the request line and headers, leaving the body in `reader`
+    ///
+    /// Returns `Ok(None)` if the peer closed the connection before sending
+    /// anything.
+    pub async fn read_head<R>(reader: &mut R) -> io::Result<Option<Request>>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let Some(request_line) = read_line(reader).await? else {
+            return Ok(None);
+        };
+        let mut parts = request_line.split(' ');
+        let (Some(method), Some(target), Some(_version)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("Malformed request line"));
+        };
+
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(reader)
+                .await?
+                .ok_or_else(|| invalid("Connection closed in headers"))?;
+            if line.is_empty() {
+                break;
+            }
+            if headers.len() == MAX_HEADERS {
+                return Err(invalid("Too many headers"));
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("Malformed header"))?;
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+
+        let mut request = Request {
+            method: method.to_string(),
+            segments: Vec::new(),
+            query: Vec::new(),
+            headers,
+            body: Vec::new(),
+        };
+        if request.header("transfer-encoding").is_some() {
+            return Err(invalid("Chunked bodies are not supported"));
+        }
+        request.content_length()?;
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            request.segments.push(percent_decode(segment)?);
+        }
+        for pair in query.split('&').filter(|s| !s.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            request
+                .query
+                .push((decode_query(name)?, decode_query(value)?));
+        }
+        Ok(Some(request))
+    }
+
+    /// Read the body announced by `Content-Length` into [`body`](Self::body)
+    pub async fn read_body<R>(&mut self, reader: &mut R, max_body: usize) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let length = self.content_length()?;
+        if length > max_body {
+            return Err(invalid("Request body too large"));
+        }
+        self.body.resize(length, 0);
+        reader.read_exact(&mut self.body).await?;
+        Ok(())
+    }
+
+    /// Length of the body from `Content-Length`, 0 without one
+    pub fn content_length(&self) -> io::Result<usize> {
+        match self.header("content-length") {
+            Some(value) => value.parse().map_err(|_| invalid("Invalid Content-Length")),
+            None => Ok(0),
+        }
+    }
+
+    /// First header named `name` (lower case)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// First query parameter named `name`
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Complete response, written with `Connection: close`
+#[derive(Debug)]
+pub struct Response {
+    /// Status code
+    pub status: u16,
+    /// `Content-Type` header
+    pub content_type: &'static str,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// JSON response with an already encoded body
+    pub fn json(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.into_bytes(),
+        }
+    }
+
+    /// `{"error": message}` response
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, format!("{{\"error\":{}}}", json_string(message)))
+    }
+
+    /// Plain text in the Prometheus exposition format
+    pub fn text(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: body.into_bytes(),
+        }
+    }
+
+    /// Raw file contents
+    pub fn bytes(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/octet-stream",
+            body,
+        }
+    }
+
+    /// Write status line, headers and body
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<()> {
+        let head = head(self.status, self.content_type, self.body.len() as u64);
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.flush().await
+    }
+}
+
+/// Write the head of a `200` response whose `length` bytes of raw file
+/// contents follow
+pub async fn write_file_head<W: AsyncWrite + Unpin>(writer: &mut W, length: u64) -> io::Result<()> {
+    writer
+        .write_all(head(200, "application/octet-stream", length).as_bytes())
+        .await
+}
+
+/// Status line and headers of a response with a `Content-Length`
+fn head(status: u16, content_type: &str, length: u64) -> String {
+    format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status),
+        content_type,
+        length
+    )
+}
+
+/// Write the head of a `text/event-stream` response
+pub async fn write_event_stream_head<W: AsyncWrite + Unpin>(writer: &mut W) -> io::Result<()> {
+    writer
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+              Connection: close\r\n\r\n",
+        )
+        .await?;
+    writer.flush().await
+}
+
+/// Write one server-sent event carrying `data`
+pub async fn write_event<W: AsyncWrite + Unpin>(writer: &mut W, data: &str) -> io::Result<()> {
+    let mut event = String::with_capacity(data.len() + 8);
+    for line in data.lines() {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    writer.write_all(event.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Quote `s` as a JSON string
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Decode a query component, where `+` stands for a space
+fn decode_query(s: &str) -> io::Result<String> {
+    percent_decode(&s.replace('+', " "))
+}
+
+/// Decode `%XX` escapes
+pub fn percent_decode(s: &str) -> io::Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut input = s.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'%' => {
+                let hex = [input.next(), input.next()];
+                let [Some(hi), Some(lo)] = hex else {
+                    return Err(invalid("Truncated percent escape"));
+                };
+                let digits = std::str::from_utf8(&[hi, lo])
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| invalid("Invalid percent escape"))?;
+                bytes.push(digits);
+            }
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("Invalid UTF-8 in URL"))
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = (&mut *reader)
+        .take(MAX_LINE as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(invalid("Line too long or truncated"));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("Invalid UTF-8 in header"))?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = b"POST /devices/127.0.0.1%3A5555/shell?timeout=5&x HTTP/1.1\r\n\
+                    Host: bridge\r\nContent-Length: 5\r\n\r\nls -lextra";
+        let mut reader = &raw[..];
+        let request = Request::read(&mut reader, 1024).await.unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.segments, ["devices", "127.0.0.1:5555", "shell"]);
+        assert_eq!(request.query("timeout"), Some("5"));
+        assert_eq!(request.query("x"), Some(""));
+        assert_eq!(request.header("host"), Some("bridge"));
+        assert_eq!(request.body, b"ls -l");
+
+        let mut reader = &raw[..];
+        assert!(Request::read(&mut reader, 4).await.is_err());
+        assert!(Request::read(&mut &b""[..], 4).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(percent_decode("a%2Fb+c").unwrap(), "a/b+c");
+        assert_eq!(decode_query("a%2Bb+c").unwrap(), "a+b c");
+        assert!(percent_decode("%zz").is_err());
+        assert!(percent_decode("%4").is_err());
+        assert_eq!(json_string("say \"hi\"\n\u{1}"), r#""say \"hi\"\n\u0001""#);
+    }
+}
@@ -0,0 +1,870 @@
+//! HTTP bridge to an HDC server
+//!
+//! Exposes the devices attached to one HDC server over plain HTTP, so CI
+//! workers on other machines can drive them without an hdc installation.
+//! Every request opens its own channel through [`hdc_rs`]; a device is
+//! addressed by its connect key, percent-encoded if it contains `:`.
+//!
+//! | Method | Path | Body | Response |
+//! |---|---|---|---|
+//! | `GET` | `/devices` | | JSON array of devices from `list targets -v` |
+//! | `POST` | `/devices/{id}/shell[?timeout=secs]` | command | `{"output": ...}` |
+//! | `PUT` | `/devices/{id}/files?path=/remote/file` | file contents | `{"output": ...}` |
+//! | `GET` | `/devices/{id}/files?path=/remote/file` | | file contents |
+//! | `GET` | `/devices/{id}/logs[?tag=..&level=..]` | | hilog entries as server-sent events |
+//! | `GET` | `/metrics` | | Prometheus metrics (`exporter` feature) |
+//!
+//! With the `websocket` feature, `GET` requests on the `logs` and `shell`
+//! routes may upgrade to WebSocket, see the `websocket` module.
+//!
+//! Failures are returned as `{"error": ..., "kind": ...}`, where `kind` is
+//! [`ErrorKind::as_str`](hdc_rs::ErrorKind::as_str) for errors reported by
+//! HDC. With a [token](Bridge::token) set, requests must carry
+//! `Authorization: Bearer <token>`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! hdc_rs_bridge::Bridge::new("127.0.0.1:8710")
+//!     .token("secret")
+//!     .serve(listener)
+//!     .await
+//! # }
+//! ```
+//!
+//! ```text
+//! curl -H 'Authorization: Bearer secret' localhost:8080/devices
+//! curl -H 'Authorization: Bearer secret' -d 'uname -a' localhost:8080/devices/FMR0223C13000649/shell
+//! ```
+
+#[cfg(feature = "exporter")]
+pub mod exporter;
+pub mod http;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hdc_rs::file::FileTransferOptions;
+use hdc_rs::hilog::{HilogFilter, LogLevel};
+use hdc_rs::{Backoff, DeviceInfo, ErrorKind, HdcClient, HdcError, LogStream, ServerAddr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+use crate::http::{json_string, Request, Response};
+
+/// Largest accepted upload, streamed to a temporary file
+pub const MAX_UPLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Largest body of other requests, e.g. a shell command, held in memory
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Connections served at once unless set with [`Bridge::max_connections`]
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// Limit for receiving the request head, and for each read of the body
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pause after failed accepts, growing with each failure in a row
+const ACCEPT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_millis(10),
+    max: Duration::from_secs(1),
+    multiplier: 2.0,
+};
+
+/// Interval of SSE comments that detect clients gone while logs are quiet
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Names temporary files of concurrent transfers
+static TRANSFER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// HTTP front end for one HDC server
+pub struct Bridge {
+    client: HdcClient,
+    token: Option<String>,
+    max_connections: usize,
+    #[cfg(feature = "websocket")]
+    origins: Vec<String>,
+    #[cfg(feature = "exporter")]
+    exporter: Option<exporter::Exporter>,
+}
+
+/// What a route produced
+enum Reply {
+    Full(Response),
+    Events(LogStream),
+    File(Download),
+}
+
+/// Pulled file, streamed to the client from its temporary copy
+struct Download {
+    local: TempFile,
+    file: tokio::fs::File,
+    length: u64,
+}
+
+impl From<Response> for Reply {
+    fn from(response: Response) -> Self {
+        Reply::Full(response)
+    }
+}
+
+impl Bridge {
+    /// Bridge to the HDC server at `server`
+    pub fn new(server: impl Into<ServerAddr>) -> Self {
+        Self {
+            client: HdcClient::lazy(server),
+            token: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            #[cfg(feature = "websocket")]
+            origins: Vec::new(),
+            #[cfg(feature = "exporter")]
+            exporter: None,
+        }
+    }
+
+    /// Require `Authorization: Bearer <token>` on every request
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Serve at most `max` connections at once (default
+    /// [`DEFAULT_MAX_CONNECTIONS`])
+    ///
+    /// Further connections wait in the listen backlog. Log streams and
+    /// WebSocket sessions hold their connection until they end.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max.max(1);
+        self
+    }
+
+    /// Accept WebSocket upgrades from pages served at `origin`, e.g.
+    /// `https://dashboard.lab`
+    ///
+    /// Browsers send the page's origin with every upgrade, and any page can
+    /// open a WebSocket to `127.0.0.1`, so upgrades carrying an `Origin`
+    /// not added here are refused. Clients outside a browser send none and
+    /// are not affected.
+    #[cfg(feature = "websocket")]
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins
+            .push(origin.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Serve device metrics on `/metrics`, sampled by `exporter`
+    ///
+    /// Sampling starts with [`serve`](Self::serve) and stops when it returns.
+    #[cfg(feature = "exporter")]
+    pub fn exporter(mut self, exporter: exporter::Exporter) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    /// Accept and answer connections until the returned future is dropped
+    ///
+    /// Failed accepts, e.g. when out of file descriptors, are logged and
+    /// retried after a pause that grows while they keep failing.
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        info!("Serving HTTP on {}", listener.local_addr()?);
+        #[cfg(feature = "exporter")]
+        let _sampler = self.exporter.as_ref().map(|e| AbortOnDrop(e.spawn()));
+        let permits = Arc::new(Semaphore::new(self.max_connections));
+        let bridge = Arc::new(self);
+        let mut failures = 0;
+        loop {
+            let permit = permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("connection semaphore is never closed");
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => {
+                    failures = 0;
+                    accepted
+                }
+                Err(e) => {
+                    failures += 1;
+                    let delay = ACCEPT_BACKOFF.delay(failures);
+                    warn!(
+                        "Accepting a connection failed, retrying in {:?}: {}",
+                        delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+            let bridge = bridge.clone();
+            tokio::spawn(async move {
+                let _permit = permit;
+                if let Err(e) = bridge.handle_connection(stream).await {
+                    debug!("Connection from {} ended: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut request = match timed(Request::read_head(&mut reader)).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) => return reject(e, &mut writer).await,
+        };
+        debug!("{} /{}", request.method, request.segments.join("/"));
+
+        #[cfg(feature = "websocket")]
+        if websocket::is_upgrade(&request) {
+            return self.upgrade(request, reader, writer).await;
+        }
+
+        // Checked before any body is taken in
+        if !self.authorized(&request) {
+            return Response::error(401, "Missing or invalid bearer token")
+                .write_to(&mut writer)
+                .await;
+        }
+        let upload = if is_upload(&request) {
+            match receive_upload(&request, &mut reader).await {
+                Ok(local) => Some(local),
+                Err(e) => return reject(e, &mut writer).await,
+            }
+        } else {
+            if let Err(e) = timed(request.read_body(&mut reader, MAX_BODY_BYTES)).await {
+                return reject(e, &mut writer).await;
+            }
+            None
+        };
+
+        match self.route(&request, upload).await {
+            Reply::Full(response) => response.write_to(&mut writer).await,
+            Reply::Events(logs) => stream_events(logs, &mut writer).await,
+            Reply::File(download) => send_file(download, &mut writer).await,
+        }
+    }
+
+    async fn route(&self, request: &Request, upload: Option<TempFile>) -> Reply {
+        let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+        let result = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["devices"]) => self.list_devices().await,
+            ("POST", ["devices", device, "shell"]) => self.shell(device, request).await,
+            ("PUT", ["devices", device, "files"]) => match upload {
+                Some(local) => self.push(device, request, local).await,
+                None => Ok(Response::error(400, "Missing file contents")),
+            },
+            ("GET", ["devices", device, "files"]) => return self.pull(device, request).await,
+            ("GET", ["devices", device, "logs"]) => return self.logs(device, request).await,
+            #[cfg(feature = "exporter")]
+            ("GET", ["metrics"]) => Ok(self.metrics()),
+            (_, ["devices"] | ["devices", _, "shell" | "files" | "logs"]) => {
+                Ok(Response::error(405, "Method not allowed"))
+            }
+            _ => Ok(Response::error(404, "No such route")),
+        };
+        result.unwrap_or_else(error_response).into()
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let given = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        // Browsers cannot set headers on WebSocket requests
+        #[cfg(feature = "websocket")]
+        let given = given.or_else(|| {
+            websocket::is_upgrade(request)
+                .then(|| request.query("token"))
+                .flatten()
+        });
+        given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+    }
+
+    /// Whether the page that opened an upgrade, if any, may use the bridge
+    #[cfg(feature = "websocket")]
+    fn origin_allowed(&self, request: &Request) -> bool {
+        match request.header("origin") {
+            Some(origin) => self
+                .origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin)),
+            None => true,
+        }
+    }
+
+    /// Switch to WebSocket for the `logs` and `shell` routes
+    #[cfg(feature = "websocket")]
+    async fn upgrade(
+        &self,
+        request: Request,
+        reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+        mut writer: tokio::net::tcp::OwnedWriteHalf,
+    ) -> io::Result<()> {
+        enum Session {
+            Logs(LogStream),
+            Shell(Box<websocket::ShellChannel>),
+        }
+
+        if !self.origin_allowed(&request) {
+            warn!(
+                "Refused WebSocket upgrade from origin {:?}",
+                request.header("origin")
+            );
+            return Response::error(403, "Origin not allowed")
+                .write_to(&mut writer)
+                .await;
+        }
+        if !self.authorized(&request) {
+            return Response::error(401, "Missing or invalid bearer token")
+                .write_to(&mut writer)
+                .await;
+        }
+        // Clients wait for the 101 before sending frames
+        if !reader.buffer().is_empty() {
+            return Response::error(400, "Data sent before the upgrade completed")
+                .write_to(&mut writer)
+                .await;
+        }
+
+        let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+        let session = match segments.as_slice() {
+            ["devices", device, "logs"] => match log_filter(&request) {
+                Ok(filter) => self
+                    .client
+                    .device(*device)
+                    .hilog_watch(filter, "")
+                    .await
+                    .map(Session::Logs),
+                Err(response) => return response.write_to(&mut writer).await,
+            },
+            ["devices", device, "shell"] => self
+                .open_shell(device)
+                .await
+                .map(|shell| Session::Shell(Box::new(shell))),
+            _ => {
+                return Response::error(404, "No such route")
+                    .write_to(&mut writer)
+                    .await
+            }
+        };
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => return error_response(e).write_to(&mut writer).await,
+        };
+
+        let stream = reader
+            .into_inner()
+            .reunite(writer)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let ws = websocket::accept(&request, stream).await?;
+        match session {
+            Session::Logs(logs) => websocket::stream_logs(logs, ws).await,
+            Session::Shell(shell) => websocket::relay_shell(*shell, ws).await,
+        }
+    }
+
+    /// Start an interactive shell on `device` and take its channel
+    #[cfg(feature = "websocket")]
+    async fn open_shell(&self, device: &str) -> hdc_rs::Result<websocket::ShellChannel> {
+        let mut client = self.client.device(device);
+        client.connect().await?;
+        let mut client = client.into_inner();
+        client.send_command("shell").await?;
+        client.into_framed()
+    }
+
+    async fn list_devices(&self) -> hdc_rs::Result<Response> {
+        let devices = self.client.clone().list_targets_verbose().await?;
+        let items: Vec<String> = devices.iter().map(device_json).collect();
+        Ok(Response::json(200, format!("[{}]", items.join(","))))
+    }
+
+    async fn shell(&self, device: &str, request: &Request) -> hdc_rs::Result<Response> {
+        let Ok(command) = std::str::from_utf8(&request.body) else {
+            return Ok(Response::error(400, "Command is not valid UTF-8"));
+        };
+        if command.trim().is_empty() {
+            return Ok(Response::error(400, "Empty command"));
+        }
+        let mut client = self.client.device(device);
+        let output = match request.query("timeout") {
+            Some(secs) => {
+                let Some(wait) = secs
+                    .parse()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                else {
+                    return Ok(Response::error(400, "Invalid timeout"));
+                };
+                client.shell_with_timeout(command, wait).await?
+            }
+            None => client.shell(command).await?,
+        };
+        Ok(output_response(&output))
+    }
+
+    async fn push(
+        &self,
+        device: &str,
+        request: &Request,
+        local: TempFile,
+    ) -> hdc_rs::Result<Response> {
+        let Some(remote) = request.query("path") else {
+            return Ok(Response::error(400, "Missing path parameter"));
+        };
+        let output = self
+            .client
+            .device(device)
+            .file_send(&local.path_str(), remote, FileTransferOptions::default())
+            .await?;
+        Ok(output_response(&output))
+    }
+
+    async fn pull(&self, device: &str, request: &Request) -> Reply {
+        let Some(remote) = request.query("path") else {
+            return Response::error(400, "Missing path parameter").into();
+        };
+        let local = TempFile::new();
+        let pulled = self
+            .client
+            .device(device)
+            .file_recv(remote, &local.path_str(), FileTransferOptions::default())
+            .await;
+        if let Err(e) = pulled {
+            return error_response(e).into();
+        }
+        match open_download(local).await {
+            Ok(download) => Reply::File(download),
+            Err(e) => error_response(e.into()).into(),
+        }
+    }
+
+    #[cfg(feature = "exporter")]
+    fn metrics(&self) -> Response {
+        match &self.exporter {
+            Some(exporter) => Response::text(exporter.render()),
+            None => Response::error(404, "No exporter configured"),
+        }
+    }
+
+    async fn logs(&self, device: &str, request: &Request) -> Reply {
+        let filter = match log_filter(request) {
+            Ok(filter) => filter,
+            Err(response) => return response.into(),
+        };
+        match self.client.device(device).hilog_watch(filter, "").await {
+            Ok(logs) => Reply::Events(logs),
+            Err(e) => error_response(e).into(),
+        }
+    }
+}
+
+/// Whether `request` uploads a file, whose body is streamed to disk
+fn is_upload(request: &Request) -> bool {
+    let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+    request.method == "PUT" && matches!(segments.as_slice(), ["devices", _, "files"])
+}
+
+/// Stream the body of an upload into a temporary file
+async fn receive_upload<R: AsyncRead + Unpin>(
+    request: &Request,
+    reader: &mut R,
+) -> io::Result<TempFile> {
+    let mut remaining = request.content_length()? as u64;
+    if remaining > MAX_UPLOAD_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Request body too large",
+        ));
+    }
+    let local = TempFile::new();
+    let mut file = tokio::fs::File::create(&local.0).await?;
+    let mut buf = vec![0; 64 * 1024];
+    while remaining > 0 {
+        let want = remaining.min(buf.len() as u64) as usize;
+        let read = timed(reader.read(&mut buf[..want])).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        file.write_all(&buf[..read]).await?;
+        remaining -= read as u64;
+    }
+    file.flush().await?;
+    Ok(local)
+}
+
+/// Run a read of the request, failing with `TimedOut` after
+/// [`READ_TIMEOUT`]
+async fn timed<T>(read: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    tokio::time::timeout(READ_TIMEOUT, read)
+        .await
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Timed out reading the request",
+            ))
+        })
+}
+
+/// Answer a request that could not be read, if the client can still
+/// be told
+async fn reject<W: AsyncWrite + Unpin>(error: io::Error, writer: &mut W) -> io::Result<()> {
+    let status = match error.kind() {
+        io::ErrorKind::InvalidData => 400,
+        io::ErrorKind::TimedOut => 408,
+        _ => return Err(error),
+    };
+    Response::error(status, &error.to_string())
+        .write_to(writer)
+        .await
+}
+
+/// Build the hilog filter from the `tag` and `level` query parameters
+///
+/// Tags other than plain names are refused, since they end up in the
+/// device shell command.
+fn log_filter(request: &Request) -> Result<HilogFilter, Response> {
+    let mut filter = HilogFilter::new();
+    if let Some(tag) = request.query("tag") {
+        filter = filter.tag(tag);
+        if filter.validate().is_err() {
+            return Err(Response::error(
+                400,
+                "Invalid tag, expected letters, digits, '_', '.' or '-'",
+            ));
+        }
+    }
+    if let Some(level) = request.query("level") {
+        let Some(level) = LogLevel::from_letter(level) else {
+            return Err(Response::error(
+                400,
+                "Invalid level, expected one of D, I, W, E, F",
+            ));
+        };
+        filter = filter.min_level(level);
+    }
+    Ok(filter)
+}
+
+/// Open the pulled copy in `local` for sending
+async fn open_download(local: TempFile) -> io::Result<Download> {
+    let file = tokio::fs::File::open(&local.0).await?;
+    let length = file.metadata().await?.len();
+    Ok(Download {
+        local,
+        file,
+        length,
+    })
+}
+
+/// Stream a pulled file as the response body, without holding it in memory
+async fn send_file<W: AsyncWrite + Unpin>(download: Download, writer: &mut W) -> io::Result<()> {
+    let Download {
+        local: _local,
+        file,
+        length,
+    } = download;
+    http::write_file_head(writer, length).await?;
+    tokio::io::copy(&mut file.take(length), writer).await?;
+    writer.flush().await
+}
+
+/// Forward log entries as events until the log or the client goes away
+async fn stream_events<W: AsyncWrite + Unpin>(
+    mut logs: LogStream,
+    writer: &mut W,
+) -> io::Result<()> {
+    http::write_event_stream_head(writer).await?;
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    keepalive.tick().await;
+    loop {
+        tokio::select! {
+            entry = logs.next() => match entry {
+                Some(entry) => http::write_event(writer, &entry.to_string()).await?,
+                None => return Ok(()),
+            },
+            _ = keepalive.tick() => {
+                tokio::io::AsyncWriteExt::write_all(writer, b": keep-alive\n\n").await?;
+            }
+        }
+    }
+}
+
+/// Stops a background task when dropped
+#[cfg(feature = "exporter")]
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+#[cfg(feature = "exporter")]
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Local file for one transfer, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new() -> Self {
+        let id = TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+        let name = format!("hdc_rs_bridge_{}_{}", std::process::id(), id);
+        TempFile(std::env::temp_dir().join(name))
+    }
+
+    fn path_str(&self) -> String {
+        self.0.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Compare secrets in time independent of where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn device_json(device: &DeviceInfo) -> String {
+    format!(
+        "{{\"connect_key\":{},\"connection_type\":{},\"state\":{},\"model\":{}}}",
+        json_string(&device.connect_key),
+        json_string(&device.connection_type),
+        json_string(&device.state),
+        json_string(&device.model)
+    )
+}
+
+fn output_response(output: &str) -> Response {
+    Response::json(200, format!("{{\"output\":{}}}", json_string(output)))
+}
+
+/// Map an HDC failure to a status code and `{"error", "kind"}` body
+fn error_response(error: HdcError) -> Response {
+    let kind = error.kind();
+    let status = match kind {
+        ErrorKind::DeviceNotFound | ErrorKind::PathNotExist => 404,
+        ErrorKind::PermissionDenied => 403,
+        ErrorKind::DeviceOffline | ErrorKind::Busy => 503,
+        ErrorKind::Timeout => 504,
+        _ => 502,
+    };
+    warn!("Request failed: {}", error);
+    let body = format!(
+        "{{\"error\":{},\"kind\":{}}}",
+        json_string(&error.to_string()),
+        json_string(kind.as_str())
+    );
+    Response::json(status, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdc_rs::testing::{FakeDevice, MockServer};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Send a raw request to a bridge in front of `server` and read the reply
+    async fn exchange(bridge: Bridge, request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(bridge.serve(listener));
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        server.abort();
+        reply
+    }
+
+    #[tokio::test]
+    async fn test_devices_and_shell() {
+        let hdc = MockServer::new()
+            .respond(
+                "list targets -v",
+                "dev-1\t\tUSB\tConnected\tNOH-AN00\n127.0.0.1:5555\t\tTCP\tOffline\t\n",
+            )
+            .respond("shell echo \"hi\"", "\"hi\"\n")
+            .spawn()
+            .await
+            .unwrap();
+
+        let reply = exchange(Bridge::new(hdc.address()), "GET /devices HTTP/1.1\r\n\r\n").await;
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(reply.ends_with(
+            "[{\"connect_key\":\"dev-1\",\"connection_type\":\"USB\",\"state\":\"Connected\",\
+             \"model\":\"NOH-AN00\"},{\"connect_key\":\"127.0.0.1:5555\",\
+             \"connection_type\":\"TCP\",\"state\":\"Offline\",\"model\":\"\"}]"
+        ));
+
+        let reply = exchange(
+            Bridge::new(hdc.address()),
+            "POST /devices/dev-1/shell HTTP/1.1\r\nContent-Length: 9\r\n\r\necho \"hi\"",
+        )
+        .await;
+        assert!(
+            reply.ends_with("\r\n\r\n{\"output\":\"\\\"hi\\\"\\n\"}"),
+            "{}",
+            reply
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_streams_file() {
+        let phone = FakeDevice::new("dev-1")
+            .when_command_prefix("file recv /data/local/tmp/app.log ")
+            .sends_file("line 1\nline 2\n");
+        let hdc = MockServer::new().device(phone).spawn().await.unwrap();
+
+        let reply = exchange(
+            Bridge::new(hdc.address()),
+            "GET /devices/dev-1/files?path=/data/local/tmp/app.log HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"), "{}", reply);
+        assert!(reply.contains("Content-Length: 14\r\n"), "{}", reply);
+        assert!(reply.ends_with("\r\n\r\nline 1\nline 2\n"), "{}", reply);
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_routing() {
+        let bridge = || Bridge::new("127.0.0.1:9").token("secret");
+
+        let reply = exchange(bridge(), "GET /devices HTTP/1.1\r\n\r\n").await;
+        assert!(reply.starts_with("HTTP/1.1 401 "));
+        let reply = exchange(
+            bridge(),
+            "GET /nowhere HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 404 "));
+        let reply = exchange(
+            bridge(),
+            "DELETE /devices/dev-1/shell HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 405 "));
+        let reply = exchange(
+            bridge(),
+            "PUT /devices/dev-1/files HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 400 "));
+        assert!(reply.ends_with("{\"error\":\"Missing path parameter\"}"));
+        let reply = exchange(
+            bridge(),
+            "GET /devices/dev-1/logs?tag=x;reboot HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 400 "), "{}", reply);
+
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_limits() {
+        let reply = exchange(
+            Bridge::new("127.0.0.1:9"),
+            "PUT /devices/dev-1/files?path=/x HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n",
+        )
+        .await;
+        assert!(reply.starts_with("HTTP/1.1 400 "), "{}", reply);
+        let reply = exchange(
+            Bridge::new("127.0.0.1:9"),
+            "POST /devices/dev-1/shell HTTP/1.1\r\nContent-Length: 100000\r\n\r\n",
+        )
+        .await;
+        assert!(reply.ends_with("{\"error\":\"Request body too large\"}"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let bridge = Bridge::new("127.0.0.1:9").max_connections(1);
+        let server = tokio::spawn(bridge.serve(listener));
+
+        let mut idle = TcpStream::connect(address).await.unwrap();
+        idle.write_all(b"GET /devices HTTP/1.1\r\n").await.unwrap();
+        let mut queued = TcpStream::connect(address).await.unwrap();
+        queued
+            .write_all(b"GET /nowhere HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut byte = [0];
+        let waiting = tokio::time::timeout(Duration::from_secs(1), queued.read(&mut byte)).await;
+        assert!(waiting.is_err(), "second connection served past the limit");
+
+        let mut reply = String::new();
+        idle.read_to_string(&mut reply).await.unwrap();
+        assert!(reply.starts_with("HTTP/1.1 408 "), "{}", reply);
+        let mut reply = String::new();
+        queued.read_to_string(&mut reply).await.unwrap();
+        assert!(reply.starts_with("HTTP/1.1 404 "), "{}", reply);
+        server.abort();
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn test_websocket_shell() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let hdc = MockServer::new()
+            .respond("shell", "$ ")
+            .respond("echo hi\n", "hi\n$ ")
+            .spawn()
+            .await
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let bridge = Bridge::new(hdc.address())
+            .token("secret")
+            .allow_origin("https://dashboard.lab/");
+        let server = tokio::spawn(bridge.serve(listener));
+
+        let url = format!("ws://{}/devices/dev-1/shell?token=wrong", address);
+        let stream = TcpStream::connect(address).await.unwrap();
+        assert!(tokio_tungstenite::client_async(url, stream).await.is_err());
+
+        let url = format!("ws://{}/devices/dev-1/shell?token=secret", address);
+        let mut request = url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", "https://evil.example".parse().unwrap());
+        let stream = TcpStream::connect(address).await.unwrap();
+        assert!(tokio_tungstenite::client_async(request, stream)
+            .await
+            .is_err());
+
+        let mut request = url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", "https://dashboard.lab".parse().unwrap());
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (mut ws, _) = tokio_tungstenite::client_async(request, stream)
+            .await
+            .unwrap();
+        let prompt = ws.next().await.unwrap().unwrap();
+        assert_eq!(prompt.into_data(), &b"$ "[..]);
+        ws.send(Message::text("echo hi\n")).await.unwrap();
+        let output = ws.next().await.unwrap().unwrap();
+        assert_eq!(output.into_data(), &b"hi\n$ "[..]);
+        server.abort();
+    }
+}
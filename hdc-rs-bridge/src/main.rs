@@ -0,0 +1,143 @@
+//! Command line entry point of the HTTP bridge
+
+use std::process::ExitCode;
+
+use hdc_rs::ServerAddr;
+use hdc_rs_bridge::Bridge;
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding the bearer token
+const TOKEN_ENV: &str = "HDC_BRIDGE_TOKEN";
+
+const USAGE: &str = "\
+Usage: hdc-rs-bridge [--listen ADDR] [--server ADDR] [--token TOKEN] [--metrics SECS]
+                     [--allow-origin ORIGIN]...
+
+Options:
+  --listen ADDR   Address to serve HTTP on (default: 127.0.0.1:8080)
+  --server ADDR   HDC server to bridge (default: $OHOS_HDC_SERVER_PORT or 127.0.0.1:8710)
+  --token TOKEN   Require 'Authorization: Bearer TOKEN' (default: $HDC_BRIDGE_TOKEN)
+  --metrics SECS  Sample devices every SECS seconds for /metrics (needs the exporter feature)
+  --allow-origin ORIGIN
+                  Accept WebSocket upgrades from pages at ORIGIN (needs the websocket feature)
+  -h, --help      Print this help";
+
+struct Options {
+    listen: String,
+    server: Option<String>,
+    token: Option<String>,
+    metrics: Option<u64>,
+    origins: Vec<String>,
+}
+
+fn parse_args() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        listen: "127.0.0.1:8080".to_string(),
+        server: None,
+        token: std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty()),
+        metrics: None,
+        origins: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--listen" => options.listen = value()?,
+            "--server" => options.server = Some(value()?),
+            "--token" => options.token = Some(value()?),
+            "--metrics" => {
+                let secs = value()?;
+                match secs.parse() {
+                    Ok(secs) if secs > 0 => options.metrics = Some(secs),
+                    _ => return Err(format!("Invalid metrics interval: {}", secs)),
+                }
+            }
+            "--allow-origin" => options.origins.push(value()?),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(Some(options))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("hdc_rs_bridge=info")),
+        )
+        .init();
+
+    let options = match parse_args() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let server = match options.server {
+        Some(address) => ServerAddr::parse(&address),
+        None => match ServerAddr::from_env() {
+            Ok(address) => address,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let listener = match TcpListener::bind(&options.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Cannot listen on {}: {}", options.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut bridge = Bridge::new(server.clone());
+    if let Some(secs) = options.metrics {
+        #[cfg(feature = "exporter")]
+        {
+            let exporter = hdc_rs_bridge::exporter::Exporter::new(server)
+                .interval(std::time::Duration::from_secs(secs));
+            bridge = bridge.exporter(exporter);
+        }
+        #[cfg(not(feature = "exporter"))]
+        {
+            eprintln!(
+                "--metrics {} needs hdc-rs-bridge built with the exporter feature",
+                secs
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+    if !options.origins.is_empty() {
+        #[cfg(feature = "websocket")]
+        {
+            bridge = options
+                .origins
+                .into_iter()
+                .fold(bridge, Bridge::allow_origin);
+        }
+        #[cfg(not(feature = "websocket"))]
+        {
+            eprintln!("--allow-origin needs hdc-rs-bridge built with the websocket feature");
+            return ExitCode::FAILURE;
+        }
+    }
+    match options.token {
+        Some(token) => bridge = bridge.token(token),
+        None => tracing::warn!("No token set, every client can drive the devices"),
+    }
+    if let Err(e) = bridge.serve(listener).await {
+        eprintln!("Bridge stopped: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,150 @@
+//! WebSocket sessions (requires `websocket` feature)
+//!
+//! Browsers cannot read server-sent events with custom headers nor write to
+//! a running HTTP request, so the bridge also accepts WebSocket upgrades:
+//!
+//! | Path | Messages from the bridge | Messages to the bridge |
+//! |---|---|---|
+//! | `/devices/{id}/logs[?tag=..&level=..]` | one text message per hilog entry | ignored |
+//! | `/devices/{id}/shell` | binary shell output | text or binary keyboard input |
+//!
+//! Browsers cannot set `Authorization` on WebSocket requests either, so
+//! upgrades may carry the bearer token as `?token=...` instead.
+//!
+//! Any page open in a browser on the host could otherwise reach a bridge
+//! on `127.0.0.1`, so upgrades sent with an `Origin` header are refused
+//! unless the origin was added with
+//! [`Bridge::allow_origin`](crate::Bridge::allow_origin).
+//!
+//! # Example
+//!
+//! ```text
+//! const ws = new WebSocket("ws://lab:8080/devices/FMR0223C13000649/shell?token=secret");
+//! ws.binaryType = "arraybuffer";
+//! ws.onmessage = (e) => term.write(new Uint8Array(e.data));
+//! term.onData((keys) => ws.send(keys));
+//! ```
+
+use std::io;
+
+use futures_util::{SinkExt, StreamExt};
+use hdc_rs::protocol::HdcChannelCodec;
+use hdc_rs::{HdcStream, LogStream};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tokio_util::codec::Framed;
+use tracing::debug;
+
+use crate::http::Request;
+
+/// Device channel running an interactive shell
+pub type ShellChannel = Framed<HdcStream, HdcChannelCodec>;
+
+/// Whether `request` asks to switch to the WebSocket protocol
+pub fn is_upgrade(request: &Request) -> bool {
+    let upgrade = request
+        .header("upgrade")
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let connection = request.header("connection").is_some_and(|value| {
+        value
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    });
+    upgrade && connection && request.method == "GET"
+}
+
+/// Answer the upgrade and wrap `stream` as a server-side WebSocket
+///
+/// Returns an `InvalidData` error if the request has no
+/// `Sec-WebSocket-Key`.
+pub async fn accept<S>(request: &Request, mut stream: S) -> io::Result<WebSocketStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let key = request
+        .header("sec-websocket-key")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Sec-WebSocket-Key"))?;
+    let head = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(WebSocketStream::from_raw_socket(stream, Role::Server, None).await)
+}
+
+/// Send log entries as text messages until the log or the client goes away
+pub async fn stream_logs<S>(mut logs: LogStream, mut ws: WebSocketStream<S>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        tokio::select! {
+            entry = logs.next() => match entry {
+                Some(entry) => ws.send(Message::text(entry.to_string())).await.map_err(to_io)?,
+                None => break,
+            },
+            message = ws.next() => match message {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(to_io(e)),
+            },
+        }
+    }
+    let _ = ws.close(None).await;
+    Ok(())
+}
+
+/// Relay keyboard input to `shell` and its output back to the client
+///
+/// Ends when either side closes; closing the WebSocket drops the channel,
+/// which ends the shell on the device.
+pub async fn relay_shell<S>(mut shell: ShellChannel, mut ws: WebSocketStream<S>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        tokio::select! {
+            output = shell.next() => match output {
+                Some(Ok(data)) => {
+                    ws.send(Message::binary(data.freeze())).await.map_err(to_io)?;
+                }
+                Some(Err(e)) => {
+                    debug!("Shell channel ended: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(keys))) => send_input(&mut shell, keys.as_bytes()).await?,
+                Some(Ok(Message::Binary(keys))) => send_input(&mut shell, &keys).await?,
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(to_io(e)),
+            },
+        }
+    }
+    let _ = ws.close(None).await;
+    Ok(())
+}
+
+async fn send_input(shell: &mut ShellChannel, keys: &[u8]) -> io::Result<()> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    shell
+        .send(keys)
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()))
+}
+
+fn to_io(error: tokio_tungstenite::tungstenite::Error) -> io::Error {
+    match error {
+        tokio_tungstenite::tungstenite::Error::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
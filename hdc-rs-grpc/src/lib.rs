@@ -0,0 +1,517 @@
+//! gRPC service for remote device access
+//!
+//! Exposes the devices attached to one HDC server through the
+//! `hdc.v1.DeviceService` defined in `proto/hdc.proto`, so tools in any
+//! language with a gRPC stack can drive them. Unlike the HTTP bridge, file
+//! contents and logs travel as message streams, which suits large binary
+//! transfers.
+//!
+//! Every call opens its own channel through [`hdc_rs`]. Failures map to
+//! gRPC status codes (see [`status`]) with the
+//! [`ErrorKind::as_str`](hdc_rs::ErrorKind::as_str) of the HDC error in the
+//! `hdc-error-kind` metadata entry. With a [token](DeviceService::token)
+//! set, calls must carry `authorization: Bearer <token>` metadata.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use hdc_rs_grpc::DeviceService;
+//!
+//! tonic::transport::Server::builder()
+//!     .add_service(DeviceService::new("127.0.0.1:8710").token("secret").into_server())
+//!     .serve("127.0.0.1:50051".parse()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hdc_rs::file::FileTransferOptions;
+use hdc_rs::hilog::{HilogFilter, LogLevel};
+use hdc_rs::{ErrorKind, HdcClient, HdcError, RebootMode, ServerAddr};
+use tokio::io::AsyncReadExt;
+use tokio_stream::{Stream, StreamExt};
+use tonic::metadata::MetadataValue;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, warn};
+
+/// Generated messages, client and server for `hdc.v1`
+pub mod proto {
+    tonic::include_proto!("hdc.v1");
+}
+
+use proto::device_service_server::{self, DeviceServiceServer};
+
+/// Size of the chunks `PullFile` streams
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default limit on the size of a `PushFile` upload, spooled to a temporary file
+pub const MAX_UPLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Names temporary files of concurrent transfers
+static TRANSFER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Server stream of the given message type
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// `hdc.v1.DeviceService` backed by one HDC server
+pub struct DeviceService {
+    client: HdcClient,
+    token: Option<String>,
+    max_upload_bytes: u64,
+}
+
+/// Checks the bearer token of every call
+#[derive(Clone)]
+pub struct BearerAuth {
+    expected: Option<Arc<str>>,
+}
+
+impl DeviceService {
+    /// Service for the devices of the HDC server at `server`
+    pub fn new(server: impl Into<ServerAddr>) -> Self {
+        Self {
+            client: HdcClient::lazy(server),
+            token: None,
+            max_upload_bytes: MAX_UPLOAD_BYTES,
+        }
+    }
+
+    /// Require `authorization: Bearer <token>` metadata on every call
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Refuse `PushFile` uploads larger than `bytes` with `RESOURCE_EXHAUSTED`
+    /// (default [`MAX_UPLOAD_BYTES`])
+    pub fn max_upload_bytes(mut self, bytes: u64) -> Self {
+        self.max_upload_bytes = bytes;
+        self
+    }
+
+    /// Wrap the service for [`tonic::transport::Server::add_service`]
+    pub fn into_server(mut self) -> InterceptedService<DeviceServiceServer<Self>, BearerAuth> {
+        let auth = BearerAuth {
+            expected: self.token.take().map(Arc::from),
+        };
+        DeviceServiceServer::with_interceptor(self, auth)
+    }
+}
+
+impl Interceptor for BearerAuth {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(expected) = &self.expected else {
+            return Ok(request);
+        };
+        let given = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match given {
+            Some(given) if given == &**expected => Ok(request),
+            _ => Err(Status::unauthenticated("Missing or invalid bearer token")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl device_service_server::DeviceService for DeviceService {
+    async fn list_devices(
+        &self,
+        _request: Request<proto::ListDevicesRequest>,
+    ) -> Result<Response<proto::ListDevicesResponse>, Status> {
+        let devices = self
+            .client
+            .clone()
+            .list_targets_verbose()
+            .await
+            .map_err(status)?;
+        let devices = devices
+            .into_iter()
+            .map(|device| proto::Device {
+                connect_key: device.connect_key,
+                connection_type: device.connection_type,
+                state: device.state,
+                model: device.model,
+            })
+            .collect();
+        Ok(Response::new(proto::ListDevicesResponse { devices }))
+    }
+
+    async fn shell(
+        &self,
+        request: Request<proto::ShellRequest>,
+    ) -> Result<Response<proto::ShellResponse>, Status> {
+        let request = request.into_inner();
+        if request.command.trim().is_empty() {
+            return Err(Status::invalid_argument("Empty command"));
+        }
+        let mut client = self.client.device(required(&request.device, "device")?);
+        let output = if request.timeout_secs > 0.0 {
+            let wait = Duration::try_from_secs_f64(request.timeout_secs)
+                .map_err(|_| Status::invalid_argument("Invalid timeout"))?;
+            client.shell_with_timeout(&request.command, wait).await
+        } else {
+            client.shell(&request.command).await
+        };
+        let output = output.map_err(status)?;
+        Ok(Response::new(proto::ShellResponse { output }))
+    }
+
+    async fn push_file(
+        &self,
+        request: Request<Streaming<proto::PushChunk>>,
+    ) -> Result<Response<proto::TransferResponse>, Status> {
+        let mut chunks = request.into_inner();
+        let first = chunks
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("No chunks sent"))?;
+        let target = first
+            .target
+            .ok_or_else(|| Status::invalid_argument("First chunk has no target"))?;
+        let device = required(&target.device, "device")?;
+        let remote = required(&target.remote_path, "remote_path")?;
+
+        let local = TempFile::new();
+        let mut file = tokio::fs::File::create(&local.0).await.map_err(io_status)?;
+        let mut data = first.data;
+        let mut written = 0u64;
+        loop {
+            written += data.len() as u64;
+            if written > self.max_upload_bytes {
+                return Err(Status::resource_exhausted(format!(
+                    "Upload larger than {} bytes",
+                    self.max_upload_bytes
+                )));
+            }
+            tokio::io::AsyncWriteExt::write_all(&mut file, &data)
+                .await
+                .map_err(io_status)?;
+            match chunks.message().await? {
+                Some(chunk) => data = chunk.data,
+                None => break,
+            }
+        }
+        tokio::io::AsyncWriteExt::flush(&mut file)
+            .await
+            .map_err(io_status)?;
+        drop(file);
+
+        let output = self
+            .client
+            .device(device)
+            .file_send(&local.path_str(), remote, FileTransferOptions::default())
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::TransferResponse { output }))
+    }
+
+    type PullFileStream = ResponseStream<proto::FileChunk>;
+
+    async fn pull_file(
+        &self,
+        request: Request<proto::PullFileRequest>,
+    ) -> Result<Response<Self::PullFileStream>, Status> {
+        let request = request.into_inner();
+        let device = required(&request.device, "device")?;
+        let remote = required(&request.remote_path, "remote_path")?;
+
+        let local = TempFile::new();
+        self.client
+            .device(device)
+            .file_recv(remote, &local.path_str(), FileTransferOptions::default())
+            .await
+            .map_err(status)?;
+        let mut file = tokio::fs::File::open(&local.0).await.map_err(io_status)?;
+
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        tokio::spawn(async move {
+            // Keep the file until it has been sent
+            let _local = local;
+            loop {
+                let mut data = vec![0; CHUNK_SIZE];
+                let chunk = match file.read(&mut data).await {
+                    Ok(0) => return,
+                    Ok(read) => {
+                        data.truncate(read);
+                        Ok(proto::FileChunk { data })
+                    }
+                    Err(e) => Err(io_status(e)),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        )))
+    }
+
+    type HilogStream = ResponseStream<proto::LogEntry>;
+
+    async fn hilog(
+        &self,
+        request: Request<proto::HilogRequest>,
+    ) -> Result<Response<Self::HilogStream>, Status> {
+        let request = request.into_inner();
+        let mut filter = HilogFilter::new();
+        if !request.tag.is_empty() {
+            filter = filter.tag(&request.tag);
+            filter.validate().map_err(|_| {
+                Status::invalid_argument("Invalid tag, expected letters, digits, '_', '.' or '-'")
+            })?;
+        }
+        if !request.min_level.is_empty() {
+            let level = LogLevel::from_letter(&request.min_level).ok_or_else(|| {
+                Status::invalid_argument("Invalid level, expected one of D, I, W, E, F")
+            })?;
+            filter = filter.min_level(level);
+        }
+        let logs = self
+            .client
+            .device(required(&request.device, "device")?)
+            .hilog_watch(filter, "")
+            .await
+            .map_err(status)?;
+        let entries = logs.map(|entry| {
+            Ok(proto::LogEntry {
+                timestamp: entry.timestamp.to_string(),
+                pid: entry.pid,
+                tid: entry.tid,
+                level: entry.level.as_letter().to_string(),
+                line: entry.to_string(),
+                domain: entry.domain,
+                tag: entry.tag,
+                message: entry.message,
+            })
+        });
+        Ok(Response::new(Box::pin(entries)))
+    }
+
+    async fn screenshot(
+        &self,
+        request: Request<proto::DeviceRequest>,
+    ) -> Result<Response<proto::ScreenshotResponse>, Status> {
+        let request = request.into_inner();
+        let jpeg = self
+            .client
+            .device(required(&request.device, "device")?)
+            .screenshot()
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::ScreenshotResponse { jpeg }))
+    }
+
+    async fn reboot(
+        &self,
+        request: Request<proto::RebootRequest>,
+    ) -> Result<Response<proto::RebootResponse>, Status> {
+        let request = request.into_inner();
+        let mode = match request.mode() {
+            proto::RebootMode::Normal => RebootMode::Normal,
+            proto::RebootMode::Bootloader => RebootMode::Bootloader,
+            proto::RebootMode::Recovery => RebootMode::Recovery,
+        };
+        self.client
+            .device(required(&request.device, "device")?)
+            .reboot(mode)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::RebootResponse {}))
+    }
+}
+
+/// Map an HDC failure to a status, tagged with its [`ErrorKind`]
+///
+/// | Kind | Code |
+/// |---|---|
+/// | `DeviceNotFound`, `PathNotExist` | `NOT_FOUND` |
+/// | `PermissionDenied` | `PERMISSION_DENIED` |
+/// | `DeviceOffline`, `Busy`, `ConnectionLost` | `UNAVAILABLE` |
+/// | `Timeout` | `DEADLINE_EXCEEDED` |
+/// | anything else | `INTERNAL` |
+pub fn status(error: HdcError) -> Status {
+    let kind = error.kind();
+    let message = error.to_string();
+    let mut status = match kind {
+        ErrorKind::DeviceNotFound | ErrorKind::PathNotExist => Status::not_found(message),
+        ErrorKind::PermissionDenied => Status::permission_denied(message),
+        ErrorKind::DeviceOffline | ErrorKind::Busy | ErrorKind::ConnectionLost => {
+            Status::unavailable(message)
+        }
+        ErrorKind::Timeout => Status::deadline_exceeded(message),
+        _ => Status::internal(message),
+    };
+    warn!("Call failed: {}", error);
+    status
+        .metadata_mut()
+        .insert("hdc-error-kind", MetadataValue::from_static(kind.as_str()));
+    status
+}
+
+fn io_status(error: std::io::Error) -> Status {
+    debug!("Local file error: {}", error);
+    Status::internal(format!("Local file error: {}", error))
+}
+
+fn required<'a>(value: &'a str, name: &str) -> Result<&'a str, Status> {
+    if value.is_empty() {
+        return Err(Status::invalid_argument(format!("Missing {}", name)));
+    }
+    Ok(value)
+}
+
+/// Local file for one transfer, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new() -> Self {
+        let id = TRANSFER_ID.fetch_add(1, Ordering::Relaxed);
+        let name = format!("hdc_rs_grpc_{}_{}", std::process::id(), id);
+        TempFile(std::env::temp_dir().join(name))
+    }
+
+    fn path_str(&self) -> String {
+        self.0.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdc_rs::testing::MockServer;
+    use proto::device_service_client::DeviceServiceClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+
+    /// Serve `service` on a loopback port and connect a client to it
+    async fn connect(service: DeviceService) -> DeviceServiceClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        DeviceServiceClient::connect(format!("http://{}", address))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_devices_and_shell() {
+        let hdc = MockServer::new()
+            .respond(
+                "list targets -v",
+                "dev-1\t\tUSB\tConnected\tNOH-AN00\n127.0.0.1:5555\t\tTCP\tOffline\t\n",
+            )
+            .respond("shell uname", "Linux\n")
+            .respond("shell missing", "[Fail]Device not found")
+            .spawn()
+            .await
+            .unwrap();
+        let mut client = connect(DeviceService::new(hdc.address())).await;
+
+        let devices = client
+            .list_devices(proto::ListDevicesRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .devices;
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].model, "NOH-AN00");
+        assert_eq!(devices[1].connect_key, "127.0.0.1:5555");
+
+        let shell = |command: &str| proto::ShellRequest {
+            device: "dev-1".to_string(),
+            command: command.to_string(),
+            timeout_secs: 0.0,
+        };
+        let output = client.shell(shell("uname")).await.unwrap().into_inner();
+        assert_eq!(output.output, "Linux\n");
+
+        let error = client.shell(shell("missing")).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::NotFound);
+        assert_eq!(
+            error.metadata().get("hdc-error-kind").unwrap(),
+            "device_not_found"
+        );
+        let error = client.shell(shell(" ")).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_push_file_limit() {
+        let mut client = connect(DeviceService::new("127.0.0.1:9").max_upload_bytes(4)).await;
+        let chunks = vec![
+            proto::PushChunk {
+                target: Some(proto::PushTarget {
+                    device: "dev-1".to_string(),
+                    remote_path: "/data/local/tmp/x".to_string(),
+                }),
+                data: b"abc".to_vec(),
+            },
+            proto::PushChunk {
+                target: None,
+                data: b"de".to_vec(),
+            },
+        ];
+        let error = client
+            .push_file(tokio_stream::iter(chunks))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let mut client = connect(DeviceService::new("127.0.0.1:9").token("secret")).await;
+        let error = client
+            .list_devices(proto::ListDevicesRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::Unauthenticated);
+
+        let mut request = Request::new(proto::HilogRequest {
+            device: "dev-1".to_string(),
+            tag: String::new(),
+            min_level: "X".to_string(),
+        });
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        let error = client.hilog(request).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        let mut request = Request::new(proto::HilogRequest {
+            device: "dev-1".to_string(),
+            tag: "x;reboot".to_string(),
+            min_level: String::new(),
+        });
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        let error = client.hilog(request).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,102 @@
+//! Command line entry point of the gRPC service
+
+use std::net::SocketAddr;
+use std::process::ExitCode;
+
+use hdc_rs::ServerAddr;
+use hdc_rs_grpc::DeviceService;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding the bearer token
+const TOKEN_ENV: &str = "HDC_GRPC_TOKEN";
+
+const USAGE: &str = "\
+Usage: hdc-rs-grpc [--listen ADDR] [--server ADDR] [--token TOKEN]
+
+Options:
+  --listen ADDR   Address to serve gRPC on (default: 127.0.0.1:50051)
+  --server ADDR   HDC server to expose (default: $OHOS_HDC_SERVER_PORT or 127.0.0.1:8710)
+  --token TOKEN   Require 'authorization: Bearer TOKEN' metadata (default: $HDC_GRPC_TOKEN)
+  -h, --help      Print this help";
+
+struct Options {
+    listen: String,
+    server: Option<String>,
+    token: Option<String>,
+}
+
+fn parse_args() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        listen: "127.0.0.1:50051".to_string(),
+        server: None,
+        token: std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty()),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--listen" => options.listen = value()?,
+            "--server" => options.server = Some(value()?),
+            "--token" => options.token = Some(value()?),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(Some(options))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("hdc_rs_grpc=info")),
+        )
+        .init();
+
+    let options = match parse_args() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let server = match options.server {
+        Some(address) => ServerAddr::parse(&address),
+        None => match ServerAddr::from_env() {
+            Ok(address) => address,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let listen: SocketAddr = match options.listen.parse() {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("Invalid listen address {}: {}", options.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut service = DeviceService::new(server);
+    match options.token {
+        Some(token) => service = service.token(token),
+        None => tracing::warn!("No token set, every client can drive the devices"),
+    }
+    tracing::info!("Serving gRPC on {}", listen);
+    let result = tonic::transport::Server::builder()
+        .add_service(service.into_server())
+        .serve(listen)
+        .await;
+    if let Err(e) = result {
+        eprintln!("Service stopped: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,91 @@
+//! Python exceptions mirroring `hdc_rs::HdcError`
+//!
+//! Every exception derives from `HdcError`, so callers can catch all
+//! failures at once or only the ones they handle.
+
+use hdc_rs::{ErrorKind, HdcError as RustHdcError};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    hdc_rs_py,
+    HdcError,
+    PyException,
+    "Base class of all HDC errors"
+);
+create_exception!(
+    hdc_rs_py,
+    HdcTimeoutError,
+    HdcError,
+    "An operation timed out"
+);
+create_exception!(
+    hdc_rs_py,
+    HdcConnectionError,
+    HdcError,
+    "The connection to the HDC server failed or was closed"
+);
+create_exception!(
+    hdc_rs_py,
+    ProtocolError,
+    HdcError,
+    "The server sent data that could not be understood"
+);
+create_exception!(
+    hdc_rs_py,
+    DeviceNotFoundError,
+    HdcError,
+    "No device with the given connect key"
+);
+create_exception!(
+    hdc_rs_py,
+    CommandError,
+    HdcError,
+    "The server or device reported a failure"
+);
+create_exception!(hdc_rs_py, AuthError, HdcError, "Host authentication failed");
+create_exception!(
+    hdc_rs_py,
+    ConfigError,
+    HdcError,
+    "Invalid client configuration"
+);
+
+/// Convert a Rust error into the matching Python exception
+pub(crate) fn to_py_err(err: RustHdcError) -> PyErr {
+    let message = err.to_string();
+    match err.kind() {
+        ErrorKind::Timeout => return HdcTimeoutError::new_err(message),
+        ErrorKind::DeviceNotFound => return DeviceNotFoundError::new_err(message),
+        _ => {}
+    }
+    match err {
+        RustHdcError::Io(_) | RustHdcError::NotConnected | RustHdcError::ChannelClosed { .. } => {
+            HdcConnectionError::new_err(message)
+        }
+        RustHdcError::Protocol(_)
+        | RustHdcError::HandshakeFailed(_)
+        | RustHdcError::InvalidBanner(_)
+        | RustHdcError::Desync(_)
+        | RustHdcError::BufferError(_)
+        | RustHdcError::Utf8(_) => ProtocolError::new_err(message),
+        RustHdcError::Auth(_) => AuthError::new_err(message),
+        RustHdcError::Config(_) => ConfigError::new_err(message),
+        _ => CommandError::new_err(message),
+    }
+}
+
+/// Add the exception classes to the module
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("HdcError", py.get_type::<HdcError>())?;
+    m.add("HdcTimeoutError", py.get_type::<HdcTimeoutError>())?;
+    m.add("HdcConnectionError", py.get_type::<HdcConnectionError>())?;
+    m.add("ProtocolError", py.get_type::<ProtocolError>())?;
+    m.add("DeviceNotFoundError", py.get_type::<DeviceNotFoundError>())?;
+    m.add("CommandError", py.get_type::<CommandError>())?;
+    m.add("AuthError", py.get_type::<AuthError>())?;
+    m.add("ConfigError", py.get_type::<ConfigError>())?;
+    Ok(())
+}
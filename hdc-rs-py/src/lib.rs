@@ -1,9 +1,667 @@
 use hdc_rs::app::{InstallOptions as RustInstallOptions, UninstallOptions as RustUninstallOptions};
-use hdc_rs::blocking::HdcClient as RustHdcClient;
-use hdc_rs::file::FileTransferOptions as RustFileTransferOptions;
-use hdc_rs::forward::ForwardNode as RustForwardNode;
-use pyo3::exceptions::PyRuntimeError;
+use std::sync::{mpsc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use hdc_rs::blocking::{HdcClient as RustHdcClient, HilogIter as RustHilogIter, StopHandle};
+use hdc_rs::file::{FileTransferOptions as RustFileTransferOptions, TransferProgress};
+use hdc_rs::forward::{
+    ForwardEntry as RustForwardEntry, ForwardNode as RustForwardNode,
+    ForwardTask as RustForwardTask,
+};
+use hdc_rs::sysinfo::{
+    BatteryInfo as RustBatteryInfo, MemInfo as RustMemInfo, ProcessInfo as RustProcessInfo,
+    StorageInfo as RustStorageInfo,
+};
+use hdc_rs::{
+    DeviceEvent as RustDeviceEvent, DeviceInfo as RustDeviceInfo, HdcError as RustHdcError,
+    RebootMode, Timeouts,
+};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::BTreeMap;
+
+mod errors;
+
+use errors::to_py_err;
+
+/// Device entry from the verbose target list
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+struct DeviceInfo {
+    /// Connect key (serial number or "ip:port")
+    connect_key: String,
+    /// Connection state, e.g. "Connected", "Offline" or "Unauthorized"
+    state: String,
+    /// Transport, e.g. "USB" or "TCP"
+    connection_type: String,
+    /// Device name reported by the daemon, usually its model
+    model: String,
+}
+
+#[pymethods]
+impl DeviceInfo {
+    /// Whether the device is online and authorized
+    #[getter]
+    fn is_connected(&self) -> bool {
+        self.state.eq_ignore_ascii_case("Connected")
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DeviceInfo(connect_key={:?}, state={:?}, connection_type={:?}, model={:?})",
+            self.connect_key, self.state, self.connection_type, self.model
+        )
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.connect_key == other.connect_key
+            && self.state == other.state
+            && self.connection_type == other.connection_type
+            && self.model == other.model
+    }
+}
+
+impl From<RustDeviceInfo> for DeviceInfo {
+    fn from(info: RustDeviceInfo) -> Self {
+        Self {
+            connect_key: info.connect_key,
+            state: info.state,
+            connection_type: info.connection_type,
+            model: info.model,
+        }
+    }
+}
+
+/// Endpoint of a forward or reverse port mapping
+///
+/// Nodes are validated when created, so invalid ports or names raise
+/// ProtocolError before anything is sent to the server.
+#[pyclass(frozen, eq)]
+#[derive(Clone, PartialEq)]
+struct ForwardNode {
+    inner: RustForwardNode,
+}
+
+#[pymethods]
+impl ForwardNode {
+    /// Parse a node from its protocol string (e.g., "tcp:8080")
+    #[staticmethod]
+    fn parse(s: &str) -> PyResult<Self> {
+        RustForwardNode::parse(s)
+            .map(|inner| Self { inner })
+            .map_err(to_py_err)
+    }
+
+    /// TCP port
+    #[staticmethod]
+    fn tcp(port: u16) -> PyResult<Self> {
+        Self::checked(RustForwardNode::Tcp(port))
+    }
+
+    /// Unix domain socket in the filesystem namespace
+    #[staticmethod]
+    fn localfilesystem(name: String) -> PyResult<Self> {
+        Self::checked(RustForwardNode::LocalFilesystem(name))
+    }
+
+    /// Unix domain socket in the reserved namespace
+    #[staticmethod]
+    fn localreserved(name: String) -> PyResult<Self> {
+        Self::checked(RustForwardNode::LocalReserved(name))
+    }
+
+    /// Unix domain socket in the abstract namespace
+    #[staticmethod]
+    fn localabstract(name: String) -> PyResult<Self> {
+        Self::checked(RustForwardNode::LocalAbstract(name))
+    }
+
+    /// Device node
+    #[staticmethod]
+    fn dev(name: String) -> PyResult<Self> {
+        Self::checked(RustForwardNode::Dev(name))
+    }
+
+    /// JDWP process (remote only)
+    #[staticmethod]
+    fn jdwp(pid: u32) -> PyResult<Self> {
+        Self::checked(RustForwardNode::Jdwp(pid))
+    }
+
+    /// Ark debugger (remote only)
+    #[staticmethod]
+    #[pyo3(signature = (pid, tid, debugger="Debugger".to_string()))]
+    fn ark(pid: u32, tid: u32, debugger: String) -> PyResult<Self> {
+        Self::checked(RustForwardNode::Ark { pid, tid, debugger })
+    }
+
+    fn __str__(&self) -> String {
+        self.inner.as_protocol_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ForwardNode({:?})", self.inner.as_protocol_string())
+    }
+}
+
+impl ForwardNode {
+    fn checked(inner: RustForwardNode) -> PyResult<Self> {
+        inner.validate().map_err(to_py_err)?;
+        Ok(Self { inner })
+    }
+}
+
+/// Forward node argument: a ForwardNode or its protocol string
+#[derive(FromPyObject)]
+enum NodeArg {
+    Node(ForwardNode),
+    Str(String),
+}
+
+impl NodeArg {
+    fn into_node(self) -> PyResult<RustForwardNode> {
+        match self {
+            NodeArg::Node(node) => Ok(node.inner),
+            NodeArg::Str(s) => RustForwardNode::parse(&s).map_err(to_py_err),
+        }
+    }
+}
+
+/// Forward or reverse port mapping reported by the server
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+struct ForwardTask {
+    /// Local (host) node, e.g. "tcp:8080"
+    local: String,
+    /// Remote (device) node, e.g. "tcp:8080"
+    remote: String,
+    /// True for fport tasks, False for rport tasks
+    is_forward: bool,
+    /// Device the task belongs to, if known
+    connect_key: Option<String>,
+    /// Task string accepted by fport_remove
+    task_string: String,
+}
+
+#[pymethods]
+impl ForwardTask {
+    fn __repr__(&self) -> String {
+        format!(
+            "ForwardTask(local={:?}, remote={:?}, is_forward={}, connect_key={:?})",
+            self.local,
+            self.remote,
+            if self.is_forward { "True" } else { "False" },
+            self.connect_key
+        )
+    }
+}
+
+impl From<RustForwardEntry> for ForwardTask {
+    fn from(entry: RustForwardEntry) -> Self {
+        let task = entry.task;
+        Self {
+            local: task.local_node.as_protocol_string(),
+            remote: task.remote_node.as_protocol_string(),
+            is_forward: task.is_forward,
+            task_string: task.task_string(),
+            connect_key: Some(entry.connect_key),
+        }
+    }
+}
+
+/// Outcome of an install or uninstall
+///
+/// Failures reported by the device are returned with success=False instead
+/// of raising; connection problems still raise HdcError.
+#[pyclass(frozen, get_all)]
+struct AppResult {
+    /// Whether the device reported success
+    success: bool,
+    /// Error code reported by the device, e.g. "E001003"
+    code: Option<String>,
+    /// Device output, or the failure message
+    message: String,
+}
+
+#[pymethods]
+impl AppResult {
+    fn __bool__(&self) -> bool {
+        self.success
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AppResult(success={}, code={:?}, message={:?})",
+            if self.success { "True" } else { "False" },
+            self.code,
+            self.message
+        )
+    }
+}
+
+impl AppResult {
+    fn from_result(result: hdc_rs::Result<String>) -> PyResult<Self> {
+        match result {
+            Ok(message) => Ok(Self {
+                success: true,
+                code: None,
+                message,
+            }),
+            Err(RustHdcError::Remote { code, message }) => Ok(Self {
+                success: false,
+                code,
+                message,
+            }),
+            Err(RustHdcError::CommandFailed(message)) => Ok(Self {
+                success: false,
+                code: None,
+                message,
+            }),
+            Err(e) => Err(to_py_err(e)),
+        }
+    }
+}
+
+/// Call a Python progress callback with (bytes_done, total)
+fn report_progress(callback: &PyObject, progress: &TransferProgress) {
+    Python::with_gil(|py| {
+        if let Err(e) = callback.call1(py, (progress.bytes, progress.total)) {
+            // Print error but don't stop the transfer
+            eprintln!("Callback error: {}", e);
+        }
+    });
+}
+
+/// Iterator over device log lines, returned by HdcClient.hilog_iter
+#[pyclass]
+struct HilogIterator {
+    inner: Option<RustHilogIter>,
+}
+
+#[pymethods]
+impl HilogIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<String>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(None);
+        };
+        match py.allow_threads(|| inner.next()) {
+            Some(line) => line.map(Some).map_err(to_py_err),
+            None => {
+                self.inner = None;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Stop streaming and release the connection
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) {
+        self.close();
+    }
+}
+
+/// How long the stop watcher waits on the event between checks
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stop `stop` once the Python threading.Event `event` is set
+///
+/// The watcher exits when `stop` is stopped by either side.
+fn watch_stop_event(event: PyObject, stop: StopHandle) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.is_stopped() {
+            // Event.wait releases the GIL while it blocks
+            let set = Python::with_gil(|py| {
+                event
+                    .call_method1(py, "wait", (STOP_POLL_INTERVAL.as_secs_f64(),))
+                    .and_then(|ret| ret.extract::<bool>(py))
+                    .unwrap_or(false)
+            });
+            if set {
+                stop.stop();
+            }
+        }
+    })
+}
+
+/// Run `f` with a stop handle tied to the optional `stop_event`
+fn with_stop_event<T>(stop_event: Option<PyObject>, f: impl FnOnce(&StopHandle) -> T) -> T {
+    let stop = StopHandle::new();
+    let watcher = stop_event.map(|event| watch_stop_event(event, stop.clone()));
+    let result = f(&stop);
+    stop.stop();
+    if let Some(watcher) = watcher {
+        let _ = watcher.join();
+    }
+    result
+}
+
+/// Process running on the device (a row of `ps -ef`)
+#[pyclass(frozen, get_all)]
+struct ProcessInfo {
+    /// Owning user name
+    user: String,
+    /// Process ID
+    pid: u32,
+    /// Parent process ID
+    ppid: u32,
+    /// Command line
+    command: String,
+}
+
+#[pymethods]
+impl ProcessInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "ProcessInfo(user={:?}, pid={}, ppid={}, command={:?})",
+            self.user, self.pid, self.ppid, self.command
+        )
+    }
+}
+
+impl From<RustProcessInfo> for ProcessInfo {
+    fn from(info: RustProcessInfo) -> Self {
+        Self {
+            user: info.user,
+            pid: info.pid,
+            ppid: info.ppid,
+            command: info.command,
+        }
+    }
+}
+
+/// Filesystem usage on the device (a row of `df -k`)
+#[pyclass(frozen, get_all)]
+struct StorageInfo {
+    /// Filesystem or device name
+    filesystem: String,
+    /// Size in kilobytes
+    total_kb: u64,
+    /// Used space in kilobytes
+    used_kb: u64,
+    /// Free space in kilobytes
+    available_kb: u64,
+    /// Mount point
+    mounted_on: String,
+}
+
+#[pymethods]
+impl StorageInfo {
+    /// Used share of the filesystem, from 0.0 to 1.0
+    #[getter]
+    fn usage(&self) -> f64 {
+        if self.total_kb == 0 {
+            0.0
+        } else {
+            self.used_kb as f64 / self.total_kb as f64
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "StorageInfo(filesystem={:?}, total_kb={}, used_kb={}, available_kb={}, mounted_on={:?})",
+            self.filesystem, self.total_kb, self.used_kb, self.available_kb, self.mounted_on
+        )
+    }
+}
+
+impl From<RustStorageInfo> for StorageInfo {
+    fn from(info: RustStorageInfo) -> Self {
+        Self {
+            filesystem: info.filesystem,
+            total_kb: info.total_kb,
+            used_kb: info.used_kb,
+            available_kb: info.available_kb,
+            mounted_on: info.mounted_on,
+        }
+    }
+}
+
+/// Memory statistics from /proc/meminfo, in kilobytes
+#[pyclass(frozen, get_all)]
+struct MemInfo {
+    /// MemTotal
+    total_kb: u64,
+    /// MemFree
+    free_kb: u64,
+    /// MemAvailable
+    available_kb: u64,
+    /// Cached
+    cached_kb: u64,
+    /// SwapTotal
+    swap_total_kb: u64,
+    /// SwapFree
+    swap_free_kb: u64,
+    /// Every field as a dict, e.g. {"MemTotal": 7989448, ...}
+    fields: BTreeMap<String, u64>,
+}
+
+#[pymethods]
+impl MemInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "MemInfo(total_kb={}, free_kb={}, available_kb={})",
+            self.total_kb, self.free_kb, self.available_kb
+        )
+    }
+}
+
+impl From<RustMemInfo> for MemInfo {
+    fn from(info: RustMemInfo) -> Self {
+        Self {
+            total_kb: info.total_kb,
+            free_kb: info.free_kb,
+            available_kb: info.available_kb,
+            cached_kb: info.cached_kb,
+            swap_total_kb: info.swap_total_kb,
+            swap_free_kb: info.swap_free_kb,
+            fields: info.fields,
+        }
+    }
+}
+
+/// Battery state from the battery service
+#[pyclass(frozen, get_all)]
+struct BatteryInfo {
+    /// Charge level in percent
+    level: u8,
+    /// One of "not_charging", "charging", "disabled", "full", "unknown"
+    status: String,
+    /// One of "none", "ac", "usb", "wireless", "unknown"
+    charger: String,
+    /// Temperature in degrees Celsius
+    temperature_c: f64,
+    /// Voltage in millivolts, 0 if not reported
+    voltage_mv: u32,
+    /// Every field of the dump as a dict
+    fields: BTreeMap<String, String>,
+}
+
+#[pymethods]
+impl BatteryInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "BatteryInfo(level={}, status='{}', charger='{}', temperature_c={})",
+            self.level, self.status, self.charger, self.temperature_c
+        )
+    }
+}
+
+impl From<RustBatteryInfo> for BatteryInfo {
+    fn from(info: RustBatteryInfo) -> Self {
+        Self {
+            level: info.level,
+            status: info.status.to_string(),
+            charger: info.charger.to_string(),
+            temperature_c: info.temperature_c,
+            voltage_mv: info.voltage_mv,
+            fields: info.fields,
+        }
+    }
+}
+
+/// Convert a `timeout=` argument in seconds
+fn timeout_arg(timeout: Option<f64>) -> PyResult<Option<Duration>> {
+    timeout
+        .map(|secs| {
+            Duration::try_from_secs_f64(secs)
+                .map_err(|_| PyValueError::new_err(format!("Invalid timeout: {}", secs)))
+        })
+        .transpose()
+}
+
+/// Run `f` with every time limit of `client` set to `timeout`, if given
+fn with_timeout<T>(
+    client: &mut RustHdcClient,
+    timeout: Option<Duration>,
+    f: impl FnOnce(&mut RustHdcClient) -> T,
+) -> T {
+    let Some(timeout) = timeout else {
+        return f(client);
+    };
+    let saved = *client.timeouts();
+    client.set_timeouts(Timeouts::uniform(timeout));
+    let result = f(client);
+    client.set_timeouts(saved);
+    result
+}
+
+/// Device connect or disconnect, yielded by DeviceMonitor
+#[pyclass(frozen, get_all)]
+struct DeviceEvent {
+    /// "connected" or "disconnected"
+    kind: &'static str,
+    /// Device the event is about
+    device_id: String,
+}
+
+#[pymethods]
+impl DeviceEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "DeviceEvent(kind={:?}, device_id={:?})",
+            self.kind, self.device_id
+        )
+    }
+}
+
+impl From<RustDeviceEvent> for DeviceEvent {
+    fn from(event: RustDeviceEvent) -> Self {
+        let kind = match event {
+            RustDeviceEvent::Connected(_) => "connected",
+            RustDeviceEvent::Disconnected(_) => "disconnected",
+        };
+        Self {
+            kind,
+            device_id: event.device_id().to_string(),
+        }
+    }
+}
+
+/// Background device monitor, returned by HdcClient.start_device_monitor
+///
+/// A thread polls the device list on its own connection and queues one
+/// event per change, so callers can consume events whenever they like.
+#[pyclass]
+struct DeviceMonitor {
+    rx: Mutex<mpsc::Receiver<RustDeviceEvent>>,
+    stop: StopHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[pymethods]
+impl DeviceMonitor {
+    /// Iterate over events, blocking until each one arrives
+    ///
+    /// Iteration ends when the monitor is stopped.
+    fn events(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> Option<DeviceEvent> {
+        let rx = &self.rx;
+        py.allow_threads(|| rx.lock().ok()?.recv().ok())
+            .map(DeviceEvent::from)
+    }
+
+    /// Next queued event
+    ///
+    /// Args:
+    ///     timeout: Seconds to wait; 0 returns immediately (default: wait forever)
+    ///
+    /// Returns:
+    ///     DeviceEvent, or None if nothing arrived in time or the monitor stopped
+    #[pyo3(signature = (timeout=None))]
+    fn get(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<DeviceEvent>> {
+        let timeout = timeout_arg(timeout)?;
+        let rx = &self.rx;
+        let event = py.allow_threads(|| {
+            let rx = rx.lock().ok()?;
+            match timeout {
+                Some(timeout) => rx.recv_timeout(timeout).ok(),
+                None => rx.recv().ok(),
+            }
+        });
+        Ok(event.map(DeviceEvent::from))
+    }
+
+    /// Whether the monitor is still polling
+    #[getter]
+    fn running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Stop polling and wait for the background thread
+    fn stop(&mut self, py: Python<'_>) {
+        self.stop.stop();
+        if let Some(thread) = self.thread.take() {
+            py.allow_threads(|| {
+                let _ = thread.join();
+            });
+        }
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python<'_>,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) {
+        self.stop(py);
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
 
 /// Python wrapper for HDC client
 #[pyclass]
@@ -24,9 +682,10 @@ impl HdcClient {
     /// Example:
     ///     >>> client = HdcClient("127.0.0.1:8710")
     #[new]
-    fn new(addr: &str) -> PyResult<Self> {
-        let inner =
-            RustHdcClient::connect(addr).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    fn new(py: Python<'_>, addr: &str) -> PyResult<Self> {
+        let inner = py
+            .allow_threads(|| RustHdcClient::connect(addr))
+            .map_err(to_py_err)?;
         Ok(Self { inner })
     }
 
@@ -39,10 +698,26 @@ impl HdcClient {
     ///     >>> devices = client.list_targets()
     ///     >>> print(devices)
     ///     ['FMR0223C13000649']
-    fn list_targets(&mut self) -> PyResult<Vec<String>> {
-        self.inner
-            .list_targets()
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    fn list_targets(&mut self, py: Python<'_>) -> PyResult<Vec<String>> {
+        py.allow_threads(|| self.inner.list_targets())
+            .map_err(to_py_err)
+    }
+
+    /// List all devices with their state, connection type and model
+    ///
+    /// Unlike list_targets, offline and unauthorized devices are included.
+    ///
+    /// Returns:
+    ///     List of DeviceInfo
+    ///
+    /// Example:
+    ///     >>> for info in client.list_targets_verbose():
+    ///     ...     print(info.connect_key, info.state, info.model)
+    fn list_targets_verbose(&mut self, py: Python<'_>) -> PyResult<Vec<DeviceInfo>> {
+        let devices = py
+            .allow_threads(|| self.inner.list_targets_verbose())
+            .map_err(to_py_err)?;
+        Ok(devices.into_iter().map(DeviceInfo::from).collect())
     }
 
     /// Connect to a specific device
@@ -52,16 +727,16 @@ impl HdcClient {
     ///
     /// Example:
     ///     >>> client.connect_device("FMR0223C13000649")
-    fn connect_device(&mut self, device_id: &str) -> PyResult<()> {
-        self.inner
-            .connect_device(device_id)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    fn connect_device(&mut self, py: Python<'_>, device_id: &str) -> PyResult<()> {
+        py.allow_threads(|| self.inner.connect_device(device_id))
+            .map_err(to_py_err)
     }
 
     /// Execute a shell command on the device
     ///
     /// Args:
     ///     command: Shell command to execute
+    ///     timeout: Seconds to wait before raising HdcTimeoutError (default: client setting)
     ///
     /// Returns:
     ///     Command output as string
@@ -69,10 +744,12 @@ impl HdcClient {
     /// Example:
     ///     >>> output = client.shell("ls -l /data")
     ///     >>> print(output)
-    fn shell(&mut self, command: &str) -> PyResult<String> {
-        self.inner
-            .shell(command)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    ///     >>> client.shell("find / -name '*.so'", timeout=600)
+    #[pyo3(signature = (command, timeout=None))]
+    fn shell(&mut self, py: Python<'_>, command: &str, timeout: Option<f64>) -> PyResult<String> {
+        let timeout = timeout_arg(timeout)?;
+        py.allow_threads(|| with_timeout(&mut self.inner, timeout, |c| c.shell(command)))
+            .map_err(to_py_err)
     }
 
     /// Send a file to device
@@ -84,32 +761,52 @@ impl HdcClient {
     ///     hold_timestamp: Whether to hold/preserve file timestamp (default: False)
     ///     sync_mode: Only update newer files (default: False)
     ///     mode_sync: Enable mode sync (default: False)
+    ///     progress: Callable receiving (bytes_done, total) during the transfer;
+    ///         total is None while unknown (default: None)
+    ///     timeout: Seconds without progress before raising HdcTimeoutError
+    ///         (default: client setting)
     ///
     /// Returns:
     ///     Transfer result message
     ///
     /// Example:
+    ///     >>> def on_progress(done, total):
+    ///     ...     print(f"{done}/{total}")
+    ///     >>> client.file_send("big.bin", "/data/local/tmp/big.bin", progress=on_progress)
     ///     >>> result = client.file_send("local.txt", "/data/local/tmp/remote.txt")
     ///     >>> print(result)
-    #[pyo3(signature = (local_path, remote_path, compress=false, hold_timestamp=false, sync_mode=false, mode_sync=false))]
+    #[pyo3(signature = (local_path, remote_path, compress=false, hold_timestamp=false, sync_mode=false, mode_sync=false, progress=None, timeout=None))]
+    #[allow(clippy::too_many_arguments)]
     fn file_send(
         &mut self,
+        py: Python<'_>,
         local_path: &str,
         remote_path: &str,
         compress: bool,
         hold_timestamp: bool,
         sync_mode: bool,
         mode_sync: bool,
+        progress: Option<PyObject>,
+        timeout: Option<f64>,
     ) -> PyResult<String> {
         let options = RustFileTransferOptions::new()
             .compress(compress)
             .hold_timestamp(hold_timestamp)
             .sync_mode(sync_mode)
             .mode_sync(mode_sync);
+        let timeout = timeout_arg(timeout)?;
 
-        self.inner
-            .file_send(local_path, remote_path, options)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        py.allow_threads(|| {
+            with_timeout(&mut self.inner, timeout, |client| match progress {
+                Some(callback) => {
+                    client.file_send_with_progress(local_path, remote_path, options, |p| {
+                        report_progress(&callback, p)
+                    })
+                }
+                None => client.file_send(local_path, remote_path, options),
+            })
+        })
+        .map_err(to_py_err)
     }
 
     /// Receive a file from device
@@ -121,39 +818,59 @@ impl HdcClient {
     ///     hold_timestamp: Whether to hold/preserve file timestamp (default: False)
     ///     sync_mode: Only update newer files (default: False)
     ///     mode_sync: Enable mode sync (default: False)
+    ///     progress: Callable receiving (bytes_done, total) during the transfer;
+    ///         total is None while unknown (default: None)
+    ///     timeout: Seconds without progress before raising HdcTimeoutError
+    ///         (default: client setting)
     ///
     /// Returns:
     ///     Transfer result message
     ///
     /// Example:
+    ///     >>> def on_progress(done, total):
+    ///     ...     print(f"{done}/{total}")
+    ///     >>> client.file_recv("/data/local/tmp/big.bin", "big.bin", progress=on_progress)
     ///     >>> result = client.file_recv("/data/local/tmp/remote.txt", "local.txt")
     ///     >>> print(result)
-    #[pyo3(signature = (remote_path, local_path, compress=false, hold_timestamp=false, sync_mode=false, mode_sync=false))]
+    #[pyo3(signature = (remote_path, local_path, compress=false, hold_timestamp=false, sync_mode=false, mode_sync=false, progress=None, timeout=None))]
+    #[allow(clippy::too_many_arguments)]
     fn file_recv(
         &mut self,
+        py: Python<'_>,
         remote_path: &str,
         local_path: &str,
         compress: bool,
         hold_timestamp: bool,
         sync_mode: bool,
         mode_sync: bool,
+        progress: Option<PyObject>,
+        timeout: Option<f64>,
     ) -> PyResult<String> {
         let options = RustFileTransferOptions::new()
             .compress(compress)
             .hold_timestamp(hold_timestamp)
             .sync_mode(sync_mode)
             .mode_sync(mode_sync);
+        let timeout = timeout_arg(timeout)?;
 
-        self.inner
-            .file_recv(remote_path, local_path, options)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        py.allow_threads(|| {
+            with_timeout(&mut self.inner, timeout, |client| match progress {
+                Some(callback) => {
+                    client.file_recv_with_progress(remote_path, local_path, options, |p| {
+                        report_progress(&callback, p)
+                    })
+                }
+                None => client.file_recv(remote_path, local_path, options),
+            })
+        })
+        .map_err(to_py_err)
     }
 
     /// Create a forward port mapping (local -> device)
     ///
     /// Args:
-    ///     local: Local forward node (e.g., "tcp:8080")
-    ///     remote: Remote forward node (e.g., "tcp:8080")
+    ///     local: Local forward node (e.g., "tcp:8080" or ForwardNode.tcp(8080))
+    ///     remote: Remote forward node (e.g., "tcp:8080" or ForwardNode.jdwp(1234))
     ///
     /// Returns:
     ///     Forward result message
@@ -161,22 +878,20 @@ impl HdcClient {
     /// Example:
     ///     >>> result = client.fport("tcp:8080", "tcp:8080")
     ///     >>> print(result)
-    fn fport(&mut self, local: &str, remote: &str) -> PyResult<String> {
-        let local_node =
-            RustForwardNode::parse(local).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        let remote_node =
-            RustForwardNode::parse(remote).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    ///     >>> client.fport(ForwardNode.tcp(8700), ForwardNode.jdwp(1234))
+    fn fport(&mut self, py: Python<'_>, local: NodeArg, remote: NodeArg) -> PyResult<String> {
+        let local_node = local.into_node()?;
+        let remote_node = remote.into_node()?;
 
-        self.inner
-            .fport(local_node, remote_node)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        py.allow_threads(|| self.inner.fport(local_node, remote_node))
+            .map_err(to_py_err)
     }
 
     /// Create a reverse port mapping (device -> local)
     ///
     /// Args:
-    ///     remote: Remote forward node (e.g., "tcp:9090")
-    ///     local: Local forward node (e.g., "tcp:9090")
+    ///     remote: Remote forward node (e.g., "tcp:9090" or ForwardNode.tcp(9090))
+    ///     local: Local forward node (e.g., "tcp:9090" or ForwardNode.tcp(9090))
     ///
     /// Returns:
     ///     Reverse forward result message
@@ -184,15 +899,12 @@ impl HdcClient {
     /// Example:
     ///     >>> result = client.rport("tcp:9090", "tcp:9090")
     ///     >>> print(result)
-    fn rport(&mut self, remote: &str, local: &str) -> PyResult<String> {
-        let remote_node =
-            RustForwardNode::parse(remote).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
-        let local_node =
-            RustForwardNode::parse(local).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    fn rport(&mut self, py: Python<'_>, remote: NodeArg, local: NodeArg) -> PyResult<String> {
+        let remote_node = remote.into_node()?;
+        let local_node = local.into_node()?;
 
-        self.inner
-            .rport(remote_node, local_node)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        py.allow_threads(|| self.inner.rport(remote_node, local_node))
+            .map_err(to_py_err)
     }
 
     /// Remove a forward port mapping
@@ -206,10 +918,76 @@ impl HdcClient {
     /// Example:
     ///     >>> result = client.fport_remove("tcp:8080 tcp:8080")
     ///     >>> print(result)
-    fn fport_remove(&mut self, task_str: &str) -> PyResult<String> {
-        self.inner
-            .fport_remove(task_str)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    fn fport_remove(&mut self, py: Python<'_>, task_str: &str) -> PyResult<String> {
+        py.allow_threads(|| self.inner.fport_remove(task_str))
+            .map_err(to_py_err)
+    }
+
+    /// Remove a reverse port mapping
+    ///
+    /// Args:
+    ///     remote: Remote forward node (e.g., "tcp:9090" or ForwardNode.tcp(9090))
+    ///     local: Local forward node (e.g., "tcp:9090" or ForwardNode.tcp(9090))
+    ///
+    /// Returns:
+    ///     Remove result message
+    ///
+    /// Example:
+    ///     >>> client.rport_remove("tcp:9090", "tcp:9090")
+    fn rport_remove(
+        &mut self,
+        py: Python<'_>,
+        remote: NodeArg,
+        local: NodeArg,
+    ) -> PyResult<String> {
+        let remote_node = remote.into_node()?;
+        let local_node = local.into_node()?;
+        let task_str = RustForwardTask::reverse(remote_node, local_node).task_string();
+
+        py.allow_threads(|| self.inner.fport_remove(&task_str))
+            .map_err(to_py_err)
+    }
+
+    /// List forward and reverse port mappings
+    ///
+    /// Args:
+    ///     device_id: Only list tasks of this device (default: all devices)
+    ///
+    /// Returns:
+    ///     List of ForwardTask
+    ///
+    /// Example:
+    ///     >>> for task in client.fport_list():
+    ///     ...     print(task.connect_key, task.local, task.remote, task.is_forward)
+    #[pyo3(signature = (device_id=None))]
+    fn fport_list(
+        &mut self,
+        py: Python<'_>,
+        device_id: Option<&str>,
+    ) -> PyResult<Vec<ForwardTask>> {
+        let tasks = py
+            .allow_threads(|| match device_id {
+                Some(id) => self.inner.fport_list_for(id),
+                None => self.inner.fport_list_tasks(),
+            })
+            .map_err(to_py_err)?;
+        Ok(tasks.into_iter().map(ForwardTask::from).collect())
+    }
+
+    /// Remove all forward and reverse port mappings
+    ///
+    /// Args:
+    ///     device_id: Only remove tasks of this device (default: all devices)
+    ///
+    /// Returns:
+    ///     Number of removed tasks
+    ///
+    /// Example:
+    ///     >>> removed = client.remove_all_forwards()
+    #[pyo3(signature = (device_id=None))]
+    fn remove_all_forwards(&mut self, py: Python<'_>, device_id: Option<&str>) -> PyResult<usize> {
+        py.allow_threads(|| self.inner.remove_all_forwards(device_id))
+            .map_err(to_py_err)
     }
 
     /// Install an application on the device
@@ -218,20 +996,44 @@ impl HdcClient {
     ///     packages: List of package paths (.hap or .hsp files)
     ///     replace: Replace existing application (default: False)
     ///     shared: Install shared bundle for multi-apps (default: False)
+    ///     downgrade: Allow installing an older version (default: False)
+    ///     user_id: Install for this user (default: current user)
+    ///     extra_flags: Flags passed verbatim, e.g. ["-w", "180"] (default: None)
+    ///     timeout: Seconds to wait before raising HdcTimeoutError (default: client setting)
     ///
     /// Returns:
-    ///     Install result message
+    ///     AppResult with success flag, error code and message
     ///
     /// Example:
     ///     >>> result = client.install(["app.hap"], replace=True)
-    ///     >>> print(result)
-    #[pyo3(signature = (packages, replace=false, shared=false))]
-    fn install(&mut self, packages: Vec<String>, replace: bool, shared: bool) -> PyResult<String> {
-        let options = RustInstallOptions { replace, shared };
+    ///     >>> if not result.success:
+    ///     ...     print(result.code, result.message)
+    #[pyo3(signature = (packages, replace=false, shared=false, downgrade=false, user_id=None, extra_flags=None, timeout=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn install(
+        &mut self,
+        py: Python<'_>,
+        packages: Vec<String>,
+        replace: bool,
+        shared: bool,
+        downgrade: bool,
+        user_id: Option<u32>,
+        extra_flags: Option<Vec<String>>,
+        timeout: Option<f64>,
+    ) -> PyResult<AppResult> {
+        let mut options = RustInstallOptions::new()
+            .replace(replace)
+            .shared(shared)
+            .downgrade(downgrade);
+        options.user_id = user_id;
+        options.extra_flags = extra_flags.unwrap_or_default();
+        let timeout = timeout_arg(timeout)?;
         let package_refs: Vec<&str> = packages.iter().map(|s| s.as_str()).collect();
-        self.inner
-            .install(&package_refs, options)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        AppResult::from_result(py.allow_threads(|| {
+            with_timeout(&mut self.inner, timeout, |client| {
+                client.install(&package_refs, options)
+            })
+        }))
     }
 
     /// Uninstall an application from the device
@@ -242,18 +1044,59 @@ impl HdcClient {
     ///     shared: Remove shared bundle (default: False)
     ///
     /// Returns:
-    ///     Uninstall result message
+    ///     AppResult with success flag, error code and message
     ///
     /// Example:
     ///     >>> result = client.uninstall("com.example.app")
-    ///     >>> print(result)
+    ///     >>> print(result.success)
     #[pyo3(signature = (package, keep_data=false, shared=false))]
-    fn uninstall(&mut self, package: &str, keep_data: bool, shared: bool) -> PyResult<String> {
+    fn uninstall(
+        &mut self,
+        py: Python<'_>,
+        package: &str,
+        keep_data: bool,
+        shared: bool,
+    ) -> PyResult<AppResult> {
         let options = RustUninstallOptions { keep_data, shared };
 
-        self.inner
-            .uninstall(package, options)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+        AppResult::from_result(py.allow_threads(|| self.inner.uninstall(package, options)))
+    }
+
+    /// Start an ability of an installed application
+    ///
+    /// Args:
+    ///     bundle: Bundle name of the application
+    ///     ability: Ability to start (e.g., "EntryAbility")
+    ///
+    /// Returns:
+    ///     AppResult with success flag and the output of `aa start`
+    ///
+    /// Example:
+    ///     >>> client.start_app("com.example.app", "EntryAbility")
+    fn start_app(&mut self, py: Python<'_>, bundle: &str, ability: &str) -> PyResult<AppResult> {
+        AppResult::from_result(py.allow_threads(|| self.inner.start_app(bundle, ability)))
+    }
+
+    /// Force-stop an application
+    ///
+    /// Args:
+    ///     bundle: Bundle name of the application
+    ///
+    /// Returns:
+    ///     AppResult with success flag and the output of `aa force-stop`
+    fn stop_app(&mut self, py: Python<'_>, bundle: &str) -> PyResult<AppResult> {
+        AppResult::from_result(py.allow_threads(|| self.inner.stop_app(bundle)))
+    }
+
+    /// Delete the data and cache of an application
+    ///
+    /// Args:
+    ///     bundle: Bundle name of the application
+    ///
+    /// Returns:
+    ///     AppResult with success flag and the output of `bm clean`
+    fn clear_app_data(&mut self, py: Python<'_>, bundle: &str) -> PyResult<AppResult> {
+        AppResult::from_result(py.allow_threads(|| self.inner.clear_app_data(bundle)))
     }
 
     /// Get device logs (hilog)
@@ -269,24 +1112,205 @@ impl HdcClient {
     ///     >>> print(logs)
     ///     >>> # With filter
     ///     >>> logs = client.hilog("-t MyTag")
-    fn hilog(&mut self, args: Option<&str>) -> PyResult<String> {
-        self.inner
-            .hilog(args)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    fn hilog(&mut self, py: Python<'_>, args: Option<&str>) -> PyResult<String> {
+        py.allow_threads(|| self.inner.hilog(args))
+            .map_err(to_py_err)
+    }
+
+    /// Capture the device screen
+    ///
+    /// Returns:
+    ///     JPEG image as bytes
+    ///
+    /// Example:
+    ///     >>> with open("screen.jpeg", "wb") as f:
+    ///     ...     f.write(client.screenshot())
+    fn screenshot<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let image = py
+            .allow_threads(|| self.inner.screenshot())
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &image))
+    }
+
+    /// Record the device screen
+    ///
+    /// Args:
+    ///     duration: Recording length in seconds
+    ///
+    /// Returns:
+    ///     MP4 video as bytes
+    ///
+    /// Example:
+    ///     >>> with open("screen.mp4", "wb") as f:
+    ///     ...     f.write(client.screen_record(10))
+    fn screen_record<'py>(
+        &mut self,
+        py: Python<'py>,
+        duration: f64,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let duration = Duration::try_from_secs_f64(duration)
+            .map_err(|_| PyValueError::new_err(format!("Invalid duration: {}", duration)))?;
+        let video = py
+            .allow_threads(|| self.inner.screen_record(duration))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &video))
+    }
+
+    /// List processes running on the device
+    ///
+    /// Returns:
+    ///     List of ProcessInfo
+    ///
+    /// Example:
+    ///     >>> pids = [p.pid for p in client.processes() if p.command == "com.example.app"]
+    fn processes(&mut self, py: Python<'_>) -> PyResult<Vec<ProcessInfo>> {
+        let processes = py
+            .allow_threads(|| self.inner.processes())
+            .map_err(to_py_err)?;
+        Ok(processes.into_iter().map(ProcessInfo::from).collect())
+    }
+
+    /// Filesystem usage on the device
+    ///
+    /// Returns:
+    ///     List of StorageInfo
+    ///
+    /// Example:
+    ///     >>> data = next(fs for fs in client.storage() if fs.mounted_on == "/data")
+    ///     >>> assert data.available_kb > 1024 * 1024
+    fn storage(&mut self, py: Python<'_>) -> PyResult<Vec<StorageInfo>> {
+        let storage = py
+            .allow_threads(|| self.inner.storage())
+            .map_err(to_py_err)?;
+        Ok(storage.into_iter().map(StorageInfo::from).collect())
+    }
+
+    /// Memory statistics of the device
+    ///
+    /// Returns:
+    ///     MemInfo
+    ///
+    /// Example:
+    ///     >>> mem = client.meminfo()
+    ///     >>> print(mem.available_kb, mem.fields["Buffers"])
+    fn meminfo(&mut self, py: Python<'_>) -> PyResult<MemInfo> {
+        py.allow_threads(|| self.inner.meminfo())
+            .map(MemInfo::from)
+            .map_err(to_py_err)
+    }
+
+    /// Battery level, charging state and temperature of the device
+    ///
+    /// Returns:
+    ///     BatteryInfo
+    ///
+    /// Example:
+    ///     >>> battery = client.battery()
+    ///     >>> assert battery.level >= 30 or battery.charger != "none"
+    fn battery(&mut self, py: Python<'_>) -> PyResult<BatteryInfo> {
+        py.allow_threads(|| self.inner.battery())
+            .map(BatteryInfo::from)
+            .map_err(to_py_err)
+    }
+
+    /// Names of the bundles installed on the device
+    ///
+    /// Returns:
+    ///     List of bundle names
+    ///
+    /// Example:
+    ///     >>> assert "com.example.app" in client.list_bundles()
+    fn list_bundles(&mut self, py: Python<'_>) -> PyResult<Vec<String>> {
+        py.allow_threads(|| self.inner.list_bundles())
+            .map_err(to_py_err)
     }
 
     /// Wait for a device to be connected
     ///
+    /// Args:
+    ///     timeout: Seconds to wait before raising HdcTimeoutError (default: wait forever)
+    ///
     /// Returns:
     ///     Device ID of the connected device
     ///
     /// Example:
-    ///     >>> device_id = client.wait_for_device()
+    ///     >>> device_id = client.wait_for_device(timeout=60)
     ///     >>> print(f"Device connected: {device_id}")
-    fn wait_for_device(&mut self) -> PyResult<String> {
-        self.inner
-            .wait_for_device()
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    #[pyo3(signature = (timeout=None))]
+    fn wait_for_device(&mut self, py: Python<'_>, timeout: Option<f64>) -> PyResult<String> {
+        let timeout = timeout_arg(timeout)?;
+        py.allow_threads(|| match timeout {
+            Some(wait) => self.inner.wait_for_device_timeout(wait),
+            None => self.inner.wait_for_device(),
+        })
+        .map_err(to_py_err)
+    }
+
+    /// Restart the connected device
+    ///
+    /// Args:
+    ///     mode: "normal", "bootloader", "recovery" or "flashd" (default: "normal")
+    ///
+    /// Example:
+    ///     >>> client.reboot()
+    ///     >>> client.wait_for_boot(timeout=120)
+    #[pyo3(signature = (mode="normal"))]
+    fn reboot(&mut self, py: Python<'_>, mode: &str) -> PyResult<()> {
+        let mode: RebootMode = mode.parse().map_err(to_py_err)?;
+        py.allow_threads(|| self.inner.reboot(mode))
+            .map_err(to_py_err)
+    }
+
+    /// Switch the connected device to TCP debugging
+    ///
+    /// Args:
+    ///     port: TCP port the device listens on (e.g., 5555)
+    ///
+    /// Returns:
+    ///     Server response
+    ///
+    /// Example:
+    ///     >>> client.tmode_port(5555)
+    ///     >>> client.tconn("192.168.1.10:5555")
+    fn tmode_port(&mut self, py: Python<'_>, port: u16) -> PyResult<String> {
+        py.allow_threads(|| self.inner.tmode_port(port))
+            .map_err(to_py_err)
+    }
+
+    /// Switch the connected device back to USB debugging
+    ///
+    /// Returns:
+    ///     Server response
+    fn tmode_usb(&mut self, py: Python<'_>) -> PyResult<String> {
+        py.allow_threads(|| self.inner.tmode_usb())
+            .map_err(to_py_err)
+    }
+
+    /// Connect the server to a device over TCP
+    ///
+    /// Args:
+    ///     address: Device address as "ip:port"
+    ///
+    /// Returns:
+    ///     Server response
+    fn tconn(&mut self, py: Python<'_>, address: &str) -> PyResult<String> {
+        py.allow_threads(|| self.inner.tconn(address))
+            .map_err(to_py_err)
+    }
+
+    /// Wait until the connected device has finished booting
+    ///
+    /// Args:
+    ///     timeout: Seconds to wait before raising HdcTimeoutError (default: 120)
+    ///
+    /// Example:
+    ///     >>> client.reboot()
+    ///     >>> client.wait_for_boot(timeout=180)
+    #[pyo3(signature = (timeout=120.0))]
+    fn wait_for_boot(&mut self, py: Python<'_>, timeout: f64) -> PyResult<()> {
+        let wait = timeout_arg(Some(timeout))?.unwrap_or_default();
+        py.allow_threads(|| self.inner.wait_for_boot(wait))
+            .map_err(to_py_err)
     }
 
     /// Stream device logs continuously with callback
@@ -294,6 +1318,8 @@ impl HdcClient {
     /// Args:
     ///     callback: Python callable that receives log chunks (str). Return True to continue, False to stop.
     ///     args: Optional hilog arguments (e.g., "-t MyTag")
+    ///     stop_event: Optional threading.Event; setting it stops streaming,
+    ///         even while no logs arrive (default: None)
     ///
     /// Example:
     ///     >>> def log_handler(log_chunk):
@@ -302,27 +1328,106 @@ impl HdcClient {
     ///     >>> client.hilog_stream(log_handler)
     ///     >>> # With filter
     ///     >>> client.hilog_stream(log_handler, args="-t MyTag")
-    #[pyo3(signature = (callback, args=None))]
-    fn hilog_stream(&mut self, callback: PyObject, args: Option<&str>) -> PyResult<()> {
-        Python::with_gil(|py| {
-            self.inner
-                .hilog_stream(args, |log_chunk: &str| {
-                    // Call Python callback with log chunk
-                    let result = callback.call1(py, (log_chunk,));
-
-                    match result {
-                        Ok(ret) => {
-                            // Check if callback returned True/False
-                            ret.extract::<bool>(py).unwrap_or(false)
-                        }
-                        Err(e) => {
-                            // Print error but don't stop streaming
-                            eprintln!("Callback error: {}", e);
-                            false
-                        }
-                    }
-                })
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    ///     >>> # Stop from another thread
+    ///     >>> stop = threading.Event()
+    ///     >>> threading.Timer(10, stop.set).start()
+    ///     >>> client.hilog_stream(log_handler, stop_event=stop)
+    #[pyo3(signature = (callback, args=None, stop_event=None))]
+    fn hilog_stream(
+        &mut self,
+        py: Python<'_>,
+        callback: PyObject,
+        args: Option<&str>,
+        stop_event: Option<PyObject>,
+    ) -> PyResult<()> {
+        py.allow_threads(|| {
+            with_stop_event(stop_event, |stop| {
+                self.inner
+                    .hilog_stream_until(args, stop, |log_chunk: &str| {
+                        // Only hold the GIL while the Python callback runs
+                        Python::with_gil(|py| {
+                            let result = callback.call1(py, (log_chunk,));
+
+                            match result {
+                                Ok(ret) => {
+                                    // Check if callback returned True/False
+                                    ret.extract::<bool>(py).unwrap_or(false)
+                                }
+                                Err(e) => {
+                                    // Print error but don't stop streaming
+                                    eprintln!("Callback error: {}", e);
+                                    false
+                                }
+                            }
+                        })
+                    })
+            })
+        })
+        .map_err(to_py_err)
+    }
+
+    /// Iterate over device log lines
+    ///
+    /// Lines are read on a background task, so the loop can stop at any
+    /// time with `break`; the stream is closed when the iterator is
+    /// garbage collected or close() is called.
+    ///
+    /// Args:
+    ///     args: Optional hilog arguments (e.g., "-t MyTag")
+    ///
+    /// Returns:
+    ///     Iterator of log lines (str)
+    ///
+    /// Example:
+    ///     >>> for line in client.hilog_iter("-t MyTag"):
+    ///     ...     if "ready" in line:
+    ///     ...         break
+    #[pyo3(signature = (args=None))]
+    fn hilog_iter(&self, args: Option<&str>) -> HilogIterator {
+        HilogIterator {
+            inner: Some(self.inner.hilog_iter(args)),
+        }
+    }
+
+    /// Start monitoring devices on a background thread
+    ///
+    /// Args:
+    ///     interval_secs: Polling interval in seconds (default: 2)
+    ///
+    /// Returns:
+    ///     DeviceMonitor; devices already connected are reported first
+    ///
+    /// Example:
+    ///     >>> monitor = client.start_device_monitor(1)
+    ///     >>> for event in monitor.events():
+    ///     ...     print(event.kind, event.device_id)
+    ///     >>> # Or poll without blocking, e.g. from a GUI timer
+    ///     >>> event = monitor.get(timeout=0)
+    ///     >>> monitor.stop()
+    #[pyo3(signature = (interval_secs=2.0))]
+    fn start_device_monitor(&self, interval_secs: f64) -> PyResult<DeviceMonitor> {
+        let interval = timeout_arg(Some(interval_secs))?.unwrap_or_default();
+        let mut client = self.inner.clone();
+        let stop = StopHandle::new();
+        let (tx, rx) = mpsc::channel();
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let mut previous = Vec::new();
+                let result = client.monitor_devices_until(interval, &stop, |devices| {
+                    let events = RustDeviceEvent::diff(&previous, devices);
+                    previous = devices.to_vec();
+                    events.into_iter().all(|event| tx.send(event).is_ok())
+                });
+                if let Err(e) = result {
+                    eprintln!("Device monitor error: {}", e);
+                }
+            }
+        });
+        Ok(DeviceMonitor {
+            rx: Mutex::new(rx),
+            stop,
+            thread: Some(thread),
         })
     }
 
@@ -334,37 +1439,50 @@ impl HdcClient {
     /// Args:
     ///     callback: Python callable that receives list of device IDs. Return True to continue, False to stop.
     ///     interval_secs: Polling interval in seconds (default: 2, recommended: 1-3 seconds)
+    ///     stop_event: Optional threading.Event; setting it stops monitoring
+    ///         without waiting for the next poll (default: None)
     ///
     /// Example:
     ///     >>> def device_monitor(devices):
     ///     ...     print(f"Devices: {devices}")
     ///     ...     return True  # Continue monitoring
     ///     >>> client.monitor_devices(device_monitor, interval_secs=2)
-    #[pyo3(signature = (callback, interval_secs=2))]
-    fn monitor_devices(&mut self, callback: PyObject, interval_secs: u64) -> PyResult<()> {
-        Python::with_gil(|py| {
-            self.inner
-                .monitor_devices(interval_secs, |devices: &[String]| {
-                    // Convert to Python list
-                    let py_list = devices.to_vec();
-
-                    // Call Python callback with device list
-                    let result = callback.call1(py, (py_list,));
-
-                    match result {
-                        Ok(ret) => {
-                            // Check if callback returned True/False
-                            ret.extract::<bool>(py).unwrap_or(false)
-                        }
-                        Err(e) => {
-                            // Print error but don't stop monitoring
-                            eprintln!("Callback error: {}", e);
-                            false
-                        }
-                    }
-                })
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    #[pyo3(signature = (callback, interval_secs=2, stop_event=None))]
+    fn monitor_devices(
+        &mut self,
+        py: Python<'_>,
+        callback: PyObject,
+        interval_secs: u64,
+        stop_event: Option<PyObject>,
+    ) -> PyResult<()> {
+        let interval = Duration::from_secs(interval_secs);
+        py.allow_threads(|| {
+            with_stop_event(stop_event, |stop| {
+                self.inner
+                    .monitor_devices_until(interval, stop, |devices: &[String]| {
+                        // Convert to Python list
+                        let py_list = devices.to_vec();
+
+                        // Only hold the GIL while the Python callback runs
+                        Python::with_gil(|py| {
+                            let result = callback.call1(py, (py_list,));
+
+                            match result {
+                                Ok(ret) => {
+                                    // Check if callback returned True/False
+                                    ret.extract::<bool>(py).unwrap_or(false)
+                                }
+                                Err(e) => {
+                                    // Print error but don't stop monitoring
+                                    eprintln!("Callback error: {}", e);
+                                    false
+                                }
+                            }
+                        })
+                    })
+            })
         })
+        .map_err(to_py_err)
     }
 }
 
@@ -372,5 +1490,17 @@ impl HdcClient {
 #[pymodule]
 fn hdc_rs_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<HdcClient>()?;
+    m.add_class::<DeviceInfo>()?;
+    m.add_class::<ForwardNode>()?;
+    m.add_class::<ForwardTask>()?;
+    m.add_class::<AppResult>()?;
+    m.add_class::<HilogIterator>()?;
+    m.add_class::<ProcessInfo>()?;
+    m.add_class::<StorageInfo>()?;
+    m.add_class::<MemInfo>()?;
+    m.add_class::<BatteryInfo>()?;
+    m.add_class::<DeviceEvent>()?;
+    m.add_class::<DeviceMonitor>()?;
+    errors::register(m)?;
     Ok(())
 }
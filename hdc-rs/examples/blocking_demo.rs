@@ -4,9 +4,8 @@
 //! which is ideal for FFI bindings like PyO3 or synchronous contexts.
 //!
 //! **Note**: HDC server may close the connection after each command when
-//! connected to a specific device. This example demonstrates the recommended
-//! pattern of reconnecting to the device before each operation to ensure
-//! reliability.
+//! connected to a specific device. The client is set to reconnect
+//! automatically, so no `connect_device` call is needed between commands.
 
 use hdc_rs::blocking::HdcClient;
 use hdc_rs::forward::ForwardNode;
@@ -24,6 +23,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Connect to HDC server (synchronous!)
     println!("Connecting to HDC server...");
     let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    client.set_auto_reconnect(true);
     println!("✓ Connected\n");
 
     // List devices
@@ -60,15 +60,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let output = client.shell("uname -a")?;
         println!("Output:\n{}\n", output.trim());
 
-        // Reconnect to device before next command (HDC server may close connection)
         println!("Getting system property: ro.product.model");
-        client.connect_device(device_id)?;
         let output = client.shell("param get ro.product.model")?;
         println!("Device model: {}\n", output.trim());
 
-        // Reconnect before port forwarding
         println!("Setting up port forwarding (local:8080 -> device:8080)");
-        client.connect_device(device_id)?;
         let local = ForwardNode::Tcp(8080);
         let remote = ForwardNode::Tcp(8080);
         let task_str = format!(
@@ -80,9 +76,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Ok(result) => {
                 println!("✓ Forward created: {}\n", result);
 
-                // Reconnect and clean up
                 println!("Removing port forward...");
-                client.connect_device(device_id)?;
                 client.fport_remove(&task_str)?;
                 println!("✓ Forward removed\n");
             }
@@ -91,9 +85,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
-        // Reconnect for hilog
         println!("Getting device logs (hilog)...");
-        client.connect_device(device_id)?;
         let logs = client.hilog(None)?;
         let lines: Vec<&str> = logs.lines().take(10).collect();
         println!("First 10 log lines:");
@@ -141,23 +133,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "clear" => {
                     print!("\x1B[2J\x1B[1;1H");
                 }
-                cmd => {
-                    // Reconnect before each shell command
-                    if let Err(e) = client.connect_device(device_id) {
-                        eprintln!("Error reconnecting: {}", e);
-                        continue;
-                    }
-                    match client.shell(cmd) {
-                        Ok(output) => {
-                            if !output.trim().is_empty() {
-                                print!("{}", output);
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Error: {}", e);
+                cmd => match client.shell(cmd) {
+                    Ok(output) => {
+                        if !output.trim().is_empty() {
+                            print!("{}", output);
                         }
                     }
-                }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                    }
+                },
             }
         }
     }
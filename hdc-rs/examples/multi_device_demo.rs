@@ -0,0 +1,60 @@
+//! Example: Run the same task on every connected device
+//!
+//! `run_on_devices` gives each device its own connection and runs the task
+//! on a bounded number of devices at a time, so there is no need to spawn a
+//! thread or task per device by hand.
+//!
+//! Run with:
+//! ```bash
+//! cargo run --example multi_device_demo
+//! ```
+
+use hdc_rs::{HdcClient, HdcError};
+
+/// Devices worked on at the same time
+const CONCURRENCY: usize = 4;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter("hdc_rs=info")
+        .init();
+
+    println!("HDC Rust Client - Multi-Device Demo");
+    println!("===================================\n");
+
+    let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    let devices = client.list_targets().await?;
+    if devices.is_empty() {
+        eprintln!("✗ No devices found!");
+        return Ok(());
+    }
+    println!(
+        "✓ Found {} device(s), running {} at a time\n",
+        devices.len(),
+        CONCURRENCY
+    );
+
+    let results = client
+        .run_on_devices(devices, CONCURRENCY, |mut device| async move {
+            let name = device.shell("param get const.product.name").await?;
+            let version = device.shell("param get const.ohos.fullname").await?;
+            let uptime = device.shell("uptime").await?;
+            Ok::<_, HdcError>(format!(
+                "{} / {} / {}",
+                name.trim(),
+                version.trim(),
+                uptime.trim()
+            ))
+        })
+        .await;
+
+    for (device_id, result) in results {
+        match result {
+            Ok(summary) => println!("✓ {}: {}", device_id, summary),
+            Err(e) => println!("✗ {}: {}", device_id, e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,16 @@
+//! Forward node strings as listed by `fport ls` or typed by users
+
+#![no_main]
+
+use hdc_rs::forward::ForwardNode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(node) = ForwardNode::parse(text) {
+        assert!(node.validate().is_ok());
+        assert_eq!(ForwardNode::parse(&node.to_string()).unwrap(), node);
+    }
+});
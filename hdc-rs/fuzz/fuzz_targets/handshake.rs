@@ -0,0 +1,29 @@
+//! Channel handshakes as sent by an untrusted server
+
+#![no_main]
+
+use hdc_rs::protocol::{ChannelHandShake, ServerFeatures};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(handshake) = ChannelHandShake::from_bytes(data) else {
+        assert!(data.len() < ChannelHandShake::SIZE_WITHOUT_VERSION);
+        return;
+    };
+
+    // Everything the client reads after the handshake
+    let _ = handshake.verify_banner();
+    let _ = handshake.get_channel_id();
+    let _ = handshake.get_connect_key();
+    let _ = handshake.get_version();
+    let _ = ServerFeatures::from_handshake(&handshake);
+    assert!(handshake.max_packet_size() <= hdc_rs::protocol::MAX_PACKET_SIZE);
+
+    // The fields are copied verbatim; a partial version field is dropped
+    let len = if data.len() >= ChannelHandShake::SIZE {
+        ChannelHandShake::SIZE
+    } else {
+        ChannelHandShake::SIZE_WITHOUT_VERSION
+    };
+    assert_eq!(&handshake.to_bytes()[..len], &data[..len]);
+});
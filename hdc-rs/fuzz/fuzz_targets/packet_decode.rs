@@ -0,0 +1,41 @@
+//! Length-prefixed packets split at arbitrary read boundaries
+
+#![no_main]
+
+use bytes::BytesMut;
+use hdc_rs::protocol::{HdcChannelCodec, MAX_PACKET_SIZE, PACKET_LENGTH_SIZE};
+use libfuzzer_sys::fuzz_target;
+use tokio_util::codec::Decoder;
+
+fuzz_target!(|data: &[u8]| {
+    // First byte picks the size of each read
+    let Some((&chunk, data)) = data.split_first() else {
+        return;
+    };
+    let chunk = usize::from(chunk).max(1);
+
+    let mut codec = HdcChannelCodec::new();
+    let mut src = BytesMut::new();
+    let mut consumed = 0;
+    for read in data.chunks(chunk) {
+        src.extend_from_slice(read);
+        loop {
+            match codec.decode(&mut src) {
+                Ok(Some(packet)) => {
+                    let start = consumed + PACKET_LENGTH_SIZE;
+                    assert!(packet.len() <= MAX_PACKET_SIZE);
+                    assert_eq!(&packet[..], &data[start..start + packet.len()]);
+                    consumed = start + packet.len();
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // The stream position is lost for good
+                    assert!(codec.decode(&mut src).is_err());
+                    return;
+                }
+            }
+        }
+        // Oversized lengths are rejected before anything is reserved
+        assert!(src.capacity() <= 2 * (PACKET_LENGTH_SIZE + MAX_PACKET_SIZE) + chunk);
+    }
+});
@@ -1,5 +1,19 @@
 //! Application management functionality
 
+use crate::error::{HdcError, Result};
+
+/// Check the output of an `aa` or `bm` command, which report success in text
+///
+/// The tools print e.g. `start ability successfully.` and an `error:` line
+/// otherwise, while the shell itself always succeeds.
+pub(crate) fn check_tool_output(output: String) -> Result<String> {
+    if output.to_ascii_lowercase().contains("successfully") {
+        Ok(output)
+    } else {
+        Err(HdcError::CommandFailed(output.trim().to_string()))
+    }
+}
+
 /// Application install options
 #[derive(Debug, Clone, Default)]
 pub struct InstallOptions {
@@ -7,6 +21,12 @@ pub struct InstallOptions {
     pub replace: bool,
     /// Install shared bundle for multi-apps
     pub shared: bool,
+    /// Allow installing an older version over a newer one
+    pub downgrade: bool,
+    /// Install for this user instead of the current one
+    pub user_id: Option<u32>,
+    /// Flags appended verbatim, for options without a setter
+    pub extra_flags: Vec<String>,
 }
 
 impl InstallOptions {
@@ -27,15 +47,40 @@ impl InstallOptions {
         self
     }
 
+    /// Set downgrade option
+    pub fn downgrade(mut self, downgrade: bool) -> Self {
+        self.downgrade = downgrade;
+        self
+    }
+
+    /// Install for a specific user
+    pub fn user_id(mut self, user_id: u32) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// Append a raw flag, such as `-w 180`
+    pub fn extra_flag(mut self, flag: impl Into<String>) -> Self {
+        self.extra_flags.push(flag.into());
+        self
+    }
+
     /// Convert to command line flags
     pub fn to_flags(&self) -> String {
         let mut flags = Vec::new();
         if self.replace {
-            flags.push("-r");
+            flags.push("-r".to_string());
         }
         if self.shared {
-            flags.push("-s");
+            flags.push("-s".to_string());
+        }
+        if self.downgrade {
+            flags.push("-d".to_string());
         }
+        if let Some(user_id) = self.user_id {
+            flags.push(format!("-u {}", user_id));
+        }
+        flags.extend(self.extra_flags.iter().cloned());
         flags.join(" ")
     }
 }
@@ -91,6 +136,23 @@ mod tests {
 
         let opts = InstallOptions::new().replace(true).shared(true);
         assert_eq!(opts.to_flags(), "-r -s");
+
+        let opts = InstallOptions::new()
+            .downgrade(true)
+            .user_id(100)
+            .extra_flag("-w 180");
+        assert_eq!(opts.to_flags(), "-d -u 100 -w 180");
+    }
+
+    #[test]
+    fn test_check_tool_output() {
+        assert!(check_tool_output("start ability successfully.\n".to_string()).is_ok());
+        let err =
+            check_tool_output("error: bundle name is not existed.\n".to_string()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Command failed: error: bundle name is not existed."
+        );
     }
 
     #[test]
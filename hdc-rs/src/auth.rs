@@ -0,0 +1,214 @@
+//! Host key authentication (requires the `auth` feature)
+//!
+//! hdc daemons in secure mode only accept hosts whose RSA public key the
+//! user has approved on the device. During the session handshake the daemon
+//! sends a random token which the host signs with its private key; unknown
+//! hosts send their public key instead and wait for approval.
+//!
+//! Keys are stored like the reference tools do: a PKCS#1 PEM private key at
+//! `~/.harmony/hdckey` and the public key next to it as `hdckey.pub`, so a
+//! key already approved for the official hdc is reused.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::auth::AuthConfig;
+//! use hdc_rs::HdcClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = HdcClient::new("127.0.0.1:8710");
+//! client.set_auth(AuthConfig::new("/secure/hdckey").generate_if_missing(false));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey, EncodeRsaPublicKey, LineEnding};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use tracing::info;
+
+use crate::error::{HdcError, Result};
+
+/// RSA key size used for newly generated host keys
+pub const HOST_KEY_BITS: usize = 3072;
+
+/// Where and how to obtain the host key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthConfig {
+    key_path: PathBuf,
+    generate_if_missing: bool,
+}
+
+impl AuthConfig {
+    /// Use the private key stored at `key_path`
+    pub fn new(key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            key_path: key_path.into(),
+            generate_if_missing: true,
+        }
+    }
+
+    /// Default key location, `~/.harmony/hdckey`
+    pub fn default_key_path() -> PathBuf {
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        home.join(".harmony").join("hdckey")
+    }
+
+    /// Generate and store a new key if none exists (default: true)
+    pub fn generate_if_missing(mut self, enable: bool) -> Self {
+        self.generate_if_missing = enable;
+        self
+    }
+
+    /// Path of the private key
+    pub fn key_path(&self) -> &Path {
+        &self.key_path
+    }
+
+    /// Load the configured key, generating it first if allowed
+    pub fn load_key(&self) -> Result<HostKey> {
+        if self.key_path.exists() {
+            return HostKey::load(&self.key_path);
+        }
+        if !self.generate_if_missing {
+            return Err(HdcError::Auth(format!(
+                "Host key not found at {}",
+                self.key_path.display()
+            )));
+        }
+
+        info!("Generating host key at {}", self.key_path.display());
+        let key = HostKey::generate()?;
+        key.save(&self.key_path)?;
+        Ok(key)
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self::new(Self::default_key_path())
+    }
+}
+
+/// RSA key pair identifying this host to hdc daemons
+#[derive(Clone)]
+pub struct HostKey {
+    private: RsaPrivateKey,
+}
+
+impl std::fmt::Debug for HostKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostKey").finish_non_exhaustive()
+    }
+}
+
+impl HostKey {
+    /// Generate a new key pair
+    pub fn generate() -> Result<Self> {
+        Self::generate_with_bits(HOST_KEY_BITS)
+    }
+
+    pub(crate) fn generate_with_bits(bits: usize) -> Result<Self> {
+        let private = RsaPrivateKey::new(&mut rsa::rand_core::OsRng, bits)
+            .map_err(|e| HdcError::Auth(format!("Key generation failed: {}", e)))?;
+        Ok(Self { private })
+    }
+
+    /// Load a PKCS#1 PEM private key
+    pub fn load(path: &Path) -> Result<Self> {
+        let pem = fs::read_to_string(path)?;
+        let private = RsaPrivateKey::from_pkcs1_pem(&pem)
+            .map_err(|e| HdcError::Auth(format!("Invalid host key {}: {}", path.display(), e)))?;
+        Ok(Self { private })
+    }
+
+    /// Store the private key at `path` and the public key at `<path>.pub`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let private_pem = self
+            .private
+            .to_pkcs1_pem(LineEnding::LF)
+            .map_err(|e| HdcError::Auth(e.to_string()))?;
+        fs::write(path, private_pem.as_bytes())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        let mut pub_path = path.as_os_str().to_owned();
+        pub_path.push(".pub");
+        fs::write(PathBuf::from(pub_path), self.public_key_pem()?)?;
+        Ok(())
+    }
+
+    /// Public key as PKCS#1 PEM, as sent to the daemon for approval
+    pub fn public_key_pem(&self) -> Result<String> {
+        RsaPublicKey::from(&self.private)
+            .to_pkcs1_pem(LineEnding::LF)
+            .map_err(|e| HdcError::Auth(e.to_string()))
+    }
+
+    /// Sign a daemon auth token (raw PKCS#1 v1.5, no digest prefix)
+    pub fn sign_token(&self, token: &[u8]) -> Result<Vec<u8>> {
+        self.private
+            .sign(Pkcs1v15Sign::new_unprefixed(), token)
+            .map_err(|e| HdcError::Auth(format!("Signing failed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_key_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hdc-rs-auth-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("hdckey")
+    }
+
+    #[test]
+    fn test_generate_save_load_sign() {
+        let path = temp_key_path("roundtrip");
+        let key = HostKey::generate_with_bits(1024).unwrap();
+        key.save(&path).unwrap();
+        assert!(path.with_extension("pub").exists());
+
+        let loaded = AuthConfig::new(&path)
+            .generate_if_missing(false)
+            .load_key()
+            .unwrap();
+        assert_eq!(
+            loaded.public_key_pem().unwrap(),
+            key.public_key_pem().unwrap()
+        );
+
+        let token = b"0123456789abcdef0123";
+        let signature = loaded.sign_token(token).unwrap();
+        RsaPublicKey::from(&key.private)
+            .verify(Pkcs1v15Sign::new_unprefixed(), token, &signature)
+            .unwrap();
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_missing_key_without_generation() {
+        let path = temp_key_path("missing");
+        let err = AuthConfig::new(&path)
+            .generate_if_missing(false)
+            .load_key()
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Auth(_)));
+    }
+}
@@ -13,15 +13,89 @@
 //! println!("Devices: {:?}", devices);
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
+//!
+//! Each client from [`HdcClient::connect`] owns a current-thread runtime,
+//! which runs on whichever thread calls into the client. Applications with
+//! many clients can run them all on one multi-thread runtime instead, see
+//! [`HdcClient::connect_shared`] and [`HdcClient::connect_with_runtime`].
+
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    app::InstallOptions,
+    app::UninstallOptions,
+    file::{FileTransferOptions, TransferProgress},
+    forward::{ForwardEntry, ForwardNode, ForwardTask},
+    hilog::{HilogFilter, LineBuffer},
+    DeviceEvent, RebootMode, Result, Timeouts,
+};
+
+/// Runtime started by the first [`shared_runtime`] call
+static SHARED_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Handle to the process-wide runtime shared by blocking clients
+///
+/// The multi-thread runtime is started on first use and lives until the
+/// process exits.
+pub fn shared_runtime() -> Result<Handle> {
+    if let Some(runtime) = SHARED_RUNTIME.get() {
+        return Ok(runtime.handle().clone());
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(crate::HdcError::Io)?;
+    // Another thread may have won the race; its runtime is kept
+    Ok(SHARED_RUNTIME.get_or_init(|| runtime).handle().clone())
+}
+
+fn new_runtime() -> Result<Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(crate::HdcError::Io)
+}
+
+/// Runtime a blocking client runs its futures on
+///
+/// An owned current-thread runtime only makes progress inside `block_on`,
+/// so iterators keep a reference and wait through it rather than parking.
+enum Executor {
+    Owned(Runtime),
+    Shared(Handle),
+}
 
-use crate::{app::InstallOptions, app::UninstallOptions, file::FileTransferOptions, Result};
+impl Executor {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match self {
+            Self::Owned(runtime) => runtime.block_on(future),
+            Self::Shared(handle) => handle.block_on(future),
+        }
+    }
+
+    fn handle(&self) -> &Handle {
+        match self {
+            Self::Owned(runtime) => runtime.handle(),
+            Self::Shared(handle) => handle,
+        }
+    }
+}
 
 /// Blocking HDC client
 ///
 /// This is a synchronous wrapper around the async [`crate::HdcClient`].
-/// It creates a tokio runtime internally to execute async operations.
+/// It creates a current-thread tokio runtime internally to execute async
+/// operations, unless connected with [`connect_shared`](Self::connect_shared)
+/// or [`connect_with_runtime`](Self::connect_with_runtime).
 pub struct HdcClient {
-    runtime: tokio::runtime::Runtime,
+    runtime: Arc<Executor>,
     inner: crate::HdcClient,
 }
 
@@ -37,16 +111,86 @@ impl HdcClient {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn connect(addr: &str) -> Result<Self> {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()
-            .map_err(crate::HdcError::Io)?;
+        Self::connect_on(Executor::Owned(new_runtime()?), addr)
+    }
 
+    /// Connect to HDC server using the process-wide [`shared_runtime`]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// // Both clients run on the same thread pool
+    /// let mut a = HdcClient::connect_shared("127.0.0.1:8710")?;
+    /// let mut b = HdcClient::connect_shared("127.0.0.1:8710")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn connect_shared(addr: &str) -> Result<Self> {
+        Self::connect_with_runtime(addr, shared_runtime()?)
+    }
+
+    /// Connect to HDC server using an existing runtime
+    ///
+    /// `handle` must belong to a multi-thread runtime: a current-thread
+    /// runtime only makes progress inside its own `block_on`. As with any
+    /// blocking client, calls must not be made from within that runtime.
+    pub fn connect_with_runtime(addr: &str, handle: Handle) -> Result<Self> {
+        Self::connect_on(Executor::Shared(handle), addr)
+    }
+
+    fn connect_on(runtime: Executor, addr: &str) -> Result<Self> {
+        let runtime = Arc::new(runtime);
         let inner = runtime.block_on(crate::HdcClient::connect(addr))?;
 
         Ok(Self { runtime, inner })
     }
 
+    /// Re-open closed channels automatically
+    ///
+    /// See [`crate::HdcClient::set_auto_reconnect`].
+    pub fn set_auto_reconnect(&mut self, enable: bool) {
+        self.inner.set_auto_reconnect(enable);
+    }
+
+    /// Set the time limits for connecting and waiting on commands
+    ///
+    /// See [`crate::HdcClient::set_timeouts`]. A call that runs into a limit
+    /// fails with [`HdcError::Timeout`](crate::HdcError::Timeout) instead of
+    /// blocking the thread indefinitely.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.inner.set_timeouts(timeouts);
+    }
+
+    /// Time limits in use
+    pub fn timeouts(&self) -> &Timeouts {
+        self.inner.timeouts()
+    }
+
+    /// Handle for `device_id` with its own channel
+    ///
+    /// The handle shares this client's settings and runtime but not its
+    /// connection, see [`crate::HdcClient::device`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// let client = HdcClient::connect("127.0.0.1:8710")?;
+    /// let mut phone = client.device("phone-serial");
+    /// let mut watch = client.device("watch-serial");
+    /// println!("{}", phone.shell("uptime")?);
+    /// println!("{}", watch.shell("uptime")?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn device(&self, device_id: impl Into<String>) -> DeviceClient {
+        DeviceClient {
+            runtime: self.runtime.clone(),
+            inner: self.inner.device(device_id),
+        }
+    }
+
     /// List all connected devices
     ///
     /// # Example
@@ -65,6 +209,11 @@ impl HdcClient {
         self.runtime.block_on(self.inner.list_targets())
     }
 
+    /// List devices with their connection type, state and model
+    pub fn list_targets_verbose(&mut self) -> Result<Vec<crate::DeviceInfo>> {
+        self.runtime.block_on(self.inner.list_targets_verbose())
+    }
+
     /// Connect to a specific device
     ///
     /// # Example
@@ -102,6 +251,23 @@ impl HdcClient {
         self.runtime.block_on(self.inner.shell(command))
     }
 
+    /// Execute a shell command, waiting at most `timeout` for its output
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use std::time::Duration;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// let output = client.shell_with_timeout("uptime", Duration::from_secs(5))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn shell_with_timeout(&mut self, command: &str, timeout: Duration) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.shell_with_timeout(command, timeout))
+    }
+
     /// Create a forward port mapping (local -> device)
     ///
     /// # Example
@@ -173,6 +339,36 @@ impl HdcClient {
         self.runtime.block_on(self.inner.fport_remove(task_str))
     }
 
+    /// List all forward/reverse tasks
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// for entry in client.fport_list_tasks()? {
+    ///     println!("{}: {}", entry.connect_key, entry.task.task_string());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn fport_list_tasks(&mut self) -> Result<Vec<ForwardEntry>> {
+        self.runtime.block_on(self.inner.fport_list_tasks())
+    }
+
+    /// List forward/reverse tasks belonging to one device
+    pub fn fport_list_for(&mut self, device_id: &str) -> Result<Vec<ForwardEntry>> {
+        self.runtime.block_on(self.inner.fport_list_for(device_id))
+    }
+
+    /// Remove all forward/reverse tasks, or only those of `device_id`
+    ///
+    /// Returns the number of tasks that were removed.
+    pub fn remove_all_forwards(&mut self, device_id: Option<&str>) -> Result<usize> {
+        self.runtime
+            .block_on(self.inner.remove_all_forwards(device_id))
+    }
+
     /// Install an application on the device
     ///
     /// # Example
@@ -195,6 +391,112 @@ impl HdcClient {
         self.runtime.block_on(self.inner.install(packages, options))
     }
 
+    /// Run a firmware operation on a device in flashd mode
+    ///
+    /// See [`crate::HdcClient::flash`].
+    pub fn flash(&mut self, operation: crate::flash::FlashOperation) -> Result<String> {
+        self.runtime.block_on(self.inner.flash(operation))
+    }
+
+    /// Copy a raw partition image to the host
+    ///
+    /// See [`crate::HdcClient::dump_partition`].
+    pub fn dump_partition(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: crate::partition::DumpOptions,
+    ) -> Result<crate::partition::PartitionDump> {
+        self.runtime
+            .block_on(self.inner.dump_partition(name, dest, options))
+    }
+
+    /// Copy a raw partition image, reporting progress to `progress`
+    ///
+    /// The callback runs on the calling thread. See
+    /// [`crate::HdcClient::dump_partition_with_progress`].
+    pub fn dump_partition_with_progress<F>(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: crate::partition::DumpOptions,
+        progress: F,
+    ) -> Result<crate::partition::PartitionDump>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        self.runtime.block_on(
+            self.inner
+                .dump_partition_with_progress(name, dest, options, progress),
+        )
+    }
+
+    /// Run a firmware operation, reporting progress to `progress`
+    ///
+    /// The callback runs on the calling thread. See
+    /// [`crate::HdcClient::flash_with_progress`].
+    pub fn flash_with_progress<F>(
+        &mut self,
+        operation: crate::flash::FlashOperation,
+        progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&crate::flash::FlashProgress),
+    {
+        self.runtime
+            .block_on(self.inner.flash_with_progress(operation, progress))
+    }
+
+    /// Install a package on a device in recovery mode
+    ///
+    /// See [`crate::HdcClient::sideload`].
+    pub fn sideload(&mut self, path: impl AsRef<std::path::Path>) -> Result<String> {
+        self.runtime.block_on(self.inner.sideload(path))
+    }
+
+    /// Push an OTA update package and reboot into the updater
+    ///
+    /// See [`crate::HdcClient::apply_ota`].
+    pub fn apply_ota(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: crate::ota::OtaOptions,
+    ) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.apply_ota(package_path, options))
+    }
+
+    /// Apply an OTA update, reporting progress to `progress`
+    ///
+    /// The callback runs on the calling thread. See
+    /// [`crate::HdcClient::apply_ota_with_progress`].
+    pub fn apply_ota_with_progress<F>(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: crate::ota::OtaOptions,
+        progress: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&crate::flash::FlashProgress),
+    {
+        self.runtime.block_on(
+            self.inner
+                .apply_ota_with_progress(package_path, options, progress),
+        )
+    }
+
+    /// Install an application, failing if the install makes no progress
+    /// for `timeout`
+    pub fn install_with_timeout(
+        &mut self,
+        packages: &[&str],
+        options: InstallOptions,
+        timeout: Duration,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.with_timeout(timeout).install(packages, options))
+    }
+
     /// Uninstall an application from the device
     ///
     /// # Example
@@ -217,6 +519,73 @@ impl HdcClient {
             .block_on(self.inner.uninstall(package, options))
     }
 
+    /// Start an ability of an installed app
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    /// client.clear_app_data("com.example.app")?;
+    /// client.start_app("com.example.app", "EntryAbility")?;
+    /// client.stop_app("com.example.app")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn start_app(&mut self, bundle: &str, ability: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.start_app(bundle, ability))
+    }
+
+    /// Force-stop an app
+    pub fn stop_app(&mut self, bundle: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.stop_app(bundle))
+    }
+
+    /// Delete an app's data and cache
+    pub fn clear_app_data(&mut self, bundle: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.clear_app_data(bundle))
+    }
+
+    /// Restart the selected device
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use hdc_rs::RebootMode;
+    /// use std::time::Duration;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    /// client.reboot(RebootMode::Normal)?;
+    /// client.wait_for_boot(Duration::from_secs(120))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reboot(&mut self, mode: RebootMode) -> Result<()> {
+        self.runtime.block_on(self.inner.reboot(mode))
+    }
+
+    /// Switch the selected device to TCP debugging on `port`
+    pub fn tmode_port(&mut self, port: u16) -> Result<String> {
+        self.runtime.block_on(self.inner.tmode_port(port))
+    }
+
+    /// Switch the selected device back to USB debugging
+    pub fn tmode_usb(&mut self) -> Result<String> {
+        self.runtime.block_on(self.inner.tmode_usb())
+    }
+
+    /// Ask the server to connect to a device over TCP (`ip:port`)
+    pub fn tconn(&mut self, address: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.tconn(address))
+    }
+
+    /// Wait until the selected device reports a completed boot
+    pub fn wait_for_boot(&mut self, wait: Duration) -> Result<()> {
+        self.runtime.block_on(self.inner.wait_for_boot(wait))
+    }
+
     /// Send a file to the device
     ///
     /// # Example
@@ -244,6 +613,62 @@ impl HdcClient {
             .block_on(self.inner.file_send(local_path, remote_path, options))
     }
 
+    /// Send a file, reporting progress to `progress`
+    ///
+    /// The callback runs on the calling thread, so it may update UI state
+    /// that is not `Send`. See [`crate::HdcClient::file_send_with_progress`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use hdc_rs::file::FileTransferOptions;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    ///
+    /// let options = FileTransferOptions::default();
+    /// client.file_send_with_progress("big.bin", "/data/local/tmp/big.bin", options, |p| {
+    ///     if let Some(fraction) = p.fraction() {
+    ///         println!("{:.0}%", fraction * 100.0);
+    ///     }
+    /// })?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn file_send_with_progress<F>(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+        progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        self.runtime.block_on(self.inner.file_send_with_progress(
+            local_path,
+            remote_path,
+            options,
+            progress,
+        ))
+    }
+
+    /// Send a file, failing if the transfer makes no progress for `timeout`
+    pub fn file_send_with_timeout(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+        timeout: Duration,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(
+                self.inner
+                    .with_timeout(timeout)
+                    .file_send(local_path, remote_path, options),
+            )
+    }
+
     /// Receive a file from the device
     ///
     /// # Example
@@ -271,6 +696,43 @@ impl HdcClient {
             .block_on(self.inner.file_recv(remote_path, local_path, options))
     }
 
+    /// Receive a file, reporting progress to `progress` on the calling thread
+    ///
+    /// See [`file_send_with_progress`](Self::file_send_with_progress).
+    pub fn file_recv_with_progress<F>(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+        progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        self.runtime.block_on(self.inner.file_recv_with_progress(
+            remote_path,
+            local_path,
+            options,
+            progress,
+        ))
+    }
+
+    /// Receive a file, failing if the transfer makes no progress for `timeout`
+    pub fn file_recv_with_timeout(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+        timeout: Duration,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(
+                self.inner
+                    .with_timeout(timeout)
+                    .file_recv(remote_path, local_path, options),
+            )
+    }
+
     /// Get device logs (hilog) with buffering
     ///
     /// # Example
@@ -294,6 +756,106 @@ impl HdcClient {
         self.runtime.block_on(self.inner.hilog(args))
     }
 
+    /// Get device logs matching a [`HilogFilter`]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use hdc_rs::hilog::HilogFilter;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    /// let logs = client.hilog_filtered(HilogFilter::new().tag("MyTag"))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn hilog_filtered(&mut self, filter: impl Into<HilogFilter>) -> Result<String> {
+        self.runtime.block_on(self.inner.hilog_filtered(filter))
+    }
+
+    /// Capture the device screen as JPEG bytes
+    ///
+    /// See [`crate::HdcClient::screenshot`].
+    pub fn screenshot(&mut self) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.inner.screenshot())
+    }
+
+    /// Record the device screen for `duration` as MP4 bytes
+    ///
+    /// See [`crate::HdcClient::screen_record`].
+    pub fn screen_record(&mut self, duration: Duration) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.inner.screen_record(duration))
+    }
+
+    /// List processes running on the device
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    /// for process in client.processes()? {
+    ///     println!("{} {}", process.pid, process.command);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn processes(&mut self) -> Result<Vec<crate::sysinfo::ProcessInfo>> {
+        self.runtime.block_on(self.inner.processes())
+    }
+
+    /// Filesystem usage on the device
+    pub fn storage(&mut self) -> Result<Vec<crate::sysinfo::StorageInfo>> {
+        self.runtime.block_on(self.inner.storage())
+    }
+
+    /// Memory statistics of the device
+    pub fn meminfo(&mut self) -> Result<crate::sysinfo::MemInfo> {
+        self.runtime.block_on(self.inner.meminfo())
+    }
+
+    /// Battery state of the device
+    ///
+    /// See [`crate::HdcClient::battery`].
+    pub fn battery(&mut self) -> Result<crate::sysinfo::BatteryInfo> {
+        self.runtime.block_on(self.inner.battery())
+    }
+
+    /// Network interfaces with their counters
+    pub fn net_stats(&mut self) -> Result<Vec<crate::sysinfo::InterfaceStats>> {
+        self.runtime.block_on(self.inner.net_stats())
+    }
+
+    /// TCP and UDP sockets with their owning processes
+    pub fn connections(&mut self) -> Result<Vec<crate::sysinfo::SocketInfo>> {
+        self.runtime.block_on(self.inner.connections())
+    }
+
+    /// Wi-Fi state of the device
+    ///
+    /// See [`crate::HdcClient::wifi_info`].
+    pub fn wifi_info(&mut self) -> Result<crate::sysinfo::WifiInfo> {
+        self.runtime.block_on(self.inner.wifi_info())
+    }
+
+    /// SELinux enforcement mode of the device
+    pub fn selinux_mode(&mut self) -> Result<crate::selinux::SelinuxMode> {
+        self.runtime.block_on(self.inner.selinux_mode())
+    }
+
+    /// Recent SELinux denials from the kernel log and hilog
+    ///
+    /// See [`crate::HdcClient::avc_denials`].
+    pub fn avc_denials(&mut self) -> Result<Vec<crate::selinux::AvcDenial>> {
+        self.runtime.block_on(self.inner.avc_denials())
+    }
+
+    /// Names of the bundles installed on the device
+    pub fn list_bundles(&mut self) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.list_bundles())
+    }
+
     /// Wait for a device to be connected
     ///
     /// This will block until a device is found.
@@ -314,6 +876,26 @@ impl HdcClient {
         self.runtime.block_on(self.inner.wait_for_device())
     }
 
+    /// Wait for a device to connect, giving up after `wait`
+    ///
+    /// Fails with [`HdcError::Timeout`](crate::HdcError::Timeout) if no
+    /// device shows up in time.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use std::time::Duration;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// let device = client.wait_for_device_timeout(Duration::from_secs(60))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn wait_for_device_timeout(&mut self, wait: Duration) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.wait_for_device_timeout(wait))
+    }
+
     /// Stream device logs (hilog) with callback
     ///
     /// This method continuously streams logs from the device and calls the callback
@@ -321,7 +903,7 @@ impl HdcClient {
     /// continue streaming or `false` to stop.
     ///
     /// # Arguments
-    /// * `args` - Optional hilog command arguments (e.g., "-t MyTag" for filtering)
+    /// * `args` - Optional hilog command arguments (e.g., "-T MyTag" for filtering)
     /// * `callback` - Function called for each log chunk. Return `true` to continue, `false` to stop.
     ///
     /// # Example
@@ -340,7 +922,7 @@ impl HdcClient {
     /// })?;
     ///
     /// // Stream with filter
-    /// client.hilog_stream(Some("-t MyTag"), |log_chunk| {
+    /// client.hilog_stream(Some("-T MyTag"), |log_chunk| {
     ///     print!("{}", log_chunk);
     ///     true
     /// })?;
@@ -354,6 +936,116 @@ impl HdcClient {
             .block_on(self.inner.hilog_stream(args, callback))
     }
 
+    /// Stream device logs matching a [`HilogFilter`] with callback
+    ///
+    /// See [`hilog_stream`](Self::hilog_stream).
+    pub fn hilog_stream_filtered<F>(
+        &mut self,
+        filter: impl Into<HilogFilter>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.runtime
+            .block_on(self.inner.hilog_stream_filtered(filter, callback))
+    }
+
+    /// Stream device logs until the callback or `stop` ends it
+    ///
+    /// Like [`hilog_stream`](Self::hilog_stream), but [`StopHandle::stop`]
+    /// from another thread returns promptly, even while waiting for output.
+    /// The log channel is closed when stopped this way.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::{HdcClient, StopHandle};
+    /// use std::time::Duration;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    ///
+    /// let stop = StopHandle::new();
+    /// let timer = stop.clone();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(Duration::from_secs(10));
+    ///     timer.stop();
+    /// });
+    /// client.hilog_stream_until(None, &stop, |chunk| {
+    ///     print!("{}", chunk);
+    ///     true
+    /// })?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn hilog_stream_until<F>(
+        &mut self,
+        filter: impl Into<HilogFilter>,
+        stop: &StopHandle,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let inner = &mut self.inner;
+        self.runtime.block_on(async move {
+            tokio::select! {
+                result = inner.hilog_stream_filtered(filter, callback) => return result,
+                _ = stop.token.cancelled() => {}
+            }
+            inner.close().await
+        })
+    }
+
+    /// Iterate over device log lines
+    ///
+    /// The log is read on a separate connection to the current device in the
+    /// background, so this client stays usable and the iterator can be moved
+    /// to another thread. Each item is one line without its newline; a
+    /// failure ends the iteration after being yielded. Dropping the iterator
+    /// closes the log connection.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    ///
+    /// let mut client = HdcClient::connect("127.0.0.1:8710")?;
+    /// client.connect_device("device_id")?;
+    ///
+    /// for line in client.hilog_iter(Some("-T MyTag")) {
+    ///     println!("{}", line?);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn hilog_iter(&self, filter: impl Into<HilogFilter>) -> HilogIter {
+        let filter = filter.into();
+        let mut client = self.inner.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = self.runtime.handle().spawn(async move {
+            let mut lines = LineBuffer::default();
+            let result = client
+                .hilog_stream_filtered(filter, |chunk| {
+                    lines
+                        .push(chunk)
+                        .into_iter()
+                        .all(|line| tx.send(Ok(line)).is_ok())
+                })
+                .await;
+            if let Some(line) = lines.finish() {
+                let _ = tx.send(Ok(line));
+            }
+            if let Err(e) = result {
+                let _ = tx.send(Err(e));
+            }
+        });
+        HilogIter {
+            runtime: self.runtime.clone(),
+            rx,
+            task,
+        }
+    }
+
     /// Monitor device list changes with callback
     ///
     /// This function continuously polls the device list and calls the callback
@@ -392,6 +1084,322 @@ impl HdcClient {
         self.runtime
             .block_on(self.inner.monitor_devices(interval, callback))
     }
+
+    /// Monitor device list changes until the callback or `stop` ends it
+    ///
+    /// Like [`monitor_devices`](Self::monitor_devices), but
+    /// [`StopHandle::stop`] from another thread returns promptly, without
+    /// waiting for the next poll.
+    pub fn monitor_devices_until<F>(
+        &mut self,
+        interval: Duration,
+        stop: &StopHandle,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&[String]) -> bool,
+    {
+        let inner = &mut self.inner;
+        self.runtime.block_on(async move {
+            tokio::select! {
+                result = inner.monitor_devices(interval, callback) => return result,
+                _ = stop.token.cancelled() => {}
+            }
+            inner.close().await
+        })
+    }
+
+    /// Iterate over device connects and disconnects
+    ///
+    /// Polls the device list every `interval` on a separate connection in
+    /// the background, like [`monitor_devices`](Self::monitor_devices), and
+    /// yields one event per change. Devices already connected are reported
+    /// first. Failed polls are skipped; dropping the iterator stops polling.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hdc_rs::blocking::HdcClient;
+    /// use hdc_rs::DeviceEvent;
+    /// use std::time::Duration;
+    ///
+    /// let client = HdcClient::connect("127.0.0.1:8710")?;
+    /// for event in client.device_events(Duration::from_secs(1)) {
+    ///     match event {
+    ///         DeviceEvent::Connected(id) => println!("+ {}", id),
+    ///         DeviceEvent::Disconnected(id) => println!("- {}", id),
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn device_events(&self, interval: Duration) -> DeviceEvents {
+        let mut client = self.inner.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = self.runtime.handle().spawn(async move {
+            let mut previous = Vec::new();
+            let result = client
+                .monitor_devices(interval, |devices| {
+                    let events = DeviceEvent::diff(&previous, devices);
+                    previous = devices.to_vec();
+                    events.into_iter().all(|event| tx.send(event).is_ok())
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("Device events ended with error: {}", e);
+            }
+        });
+        DeviceEvents {
+            runtime: self.runtime.clone(),
+            rx,
+            task,
+        }
+    }
+}
+
+impl Clone for HdcClient {
+    /// Handle on the same runtime with its own channel
+    ///
+    /// See [`crate::HdcClient`]'s `Clone`. Clones can be moved to other
+    /// threads, e.g. to monitor devices while this handle runs commands.
+    fn clone(&self) -> Self {
+        Self {
+            runtime: self.runtime.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Blocking client bound to one device, created with [`HdcClient::device`]
+///
+/// Methods mirror [`crate::DeviceClient`]. The channel is opened on first
+/// use and re-opened whenever a command has consumed it.
+pub struct DeviceClient {
+    runtime: Arc<Executor>,
+    inner: crate::DeviceClient,
+}
+
+impl DeviceClient {
+    /// Connect key this handle is bound to
+    pub fn device_id(&self) -> &str {
+        self.inner.device_id()
+    }
+
+    /// Send a raw command and read the reply as a string
+    pub fn command(&mut self, cmd: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.command(cmd))
+    }
+
+    /// See [`HdcClient::shell`]
+    pub fn shell(&mut self, command: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.shell(command))
+    }
+
+    /// See [`HdcClient::shell_with_timeout`]
+    pub fn shell_with_timeout(&mut self, command: &str, timeout: Duration) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.shell_with_timeout(command, timeout))
+    }
+
+    /// See [`HdcClient::install`]
+    pub fn install(&mut self, packages: &[&str], options: InstallOptions) -> Result<String> {
+        self.runtime.block_on(self.inner.install(packages, options))
+    }
+
+    /// See [`HdcClient::flash`]
+    pub fn flash(&mut self, operation: crate::flash::FlashOperation) -> Result<String> {
+        self.runtime.block_on(self.inner.flash(operation))
+    }
+
+    /// See [`HdcClient::sideload`]
+    pub fn sideload(&mut self, path: impl AsRef<std::path::Path>) -> Result<String> {
+        self.runtime.block_on(self.inner.sideload(path))
+    }
+
+    /// See [`HdcClient::apply_ota`]
+    pub fn apply_ota(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: crate::ota::OtaOptions,
+    ) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.apply_ota(package_path, options))
+    }
+
+    /// See [`HdcClient::uninstall`]
+    pub fn uninstall(&mut self, package: &str, options: UninstallOptions) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.uninstall(package, options))
+    }
+
+    /// See [`HdcClient::start_app`]
+    pub fn start_app(&mut self, bundle: &str, ability: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.start_app(bundle, ability))
+    }
+
+    /// See [`HdcClient::stop_app`]
+    pub fn stop_app(&mut self, bundle: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.stop_app(bundle))
+    }
+
+    /// See [`HdcClient::clear_app_data`]
+    pub fn clear_app_data(&mut self, bundle: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.clear_app_data(bundle))
+    }
+
+    /// See [`HdcClient::reboot`]
+    pub fn reboot(&mut self, mode: RebootMode) -> Result<()> {
+        self.runtime.block_on(self.inner.reboot(mode))
+    }
+
+    /// See [`HdcClient::tmode_port`]
+    pub fn tmode_port(&mut self, port: u16) -> Result<String> {
+        self.runtime.block_on(self.inner.tmode_port(port))
+    }
+
+    /// See [`HdcClient::tmode_usb`]
+    pub fn tmode_usb(&mut self) -> Result<String> {
+        self.runtime.block_on(self.inner.tmode_usb())
+    }
+
+    /// See [`HdcClient::wait_for_boot`]
+    pub fn wait_for_boot(&mut self, wait: Duration) -> Result<()> {
+        self.runtime.block_on(self.inner.wait_for_boot(wait))
+    }
+
+    /// See [`HdcClient::file_send`]
+    pub fn file_send(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.file_send(local_path, remote_path, options))
+    }
+
+    /// See [`HdcClient::file_recv`]
+    pub fn file_recv(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.file_recv(remote_path, local_path, options))
+    }
+
+    /// See [`HdcClient::fport`]
+    pub fn fport(&mut self, local: ForwardNode, remote: ForwardNode) -> Result<String> {
+        self.runtime.block_on(self.inner.fport(local, remote))
+    }
+
+    /// See [`HdcClient::rport`]
+    pub fn rport(&mut self, remote: ForwardNode, local: ForwardNode) -> Result<String> {
+        self.runtime.block_on(self.inner.rport(remote, local))
+    }
+
+    /// Forward tasks of this device
+    pub fn fport_list(&mut self) -> Result<Vec<ForwardTask>> {
+        self.runtime.block_on(self.inner.fport_list())
+    }
+
+    /// See [`HdcClient::hilog_filtered`]
+    pub fn hilog(&mut self, filter: impl Into<HilogFilter>) -> Result<String> {
+        self.runtime.block_on(self.inner.hilog(filter))
+    }
+
+    /// See [`HdcClient::screenshot`]
+    pub fn screenshot(&mut self) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.inner.screenshot())
+    }
+
+    /// See [`HdcClient::screen_record`]
+    pub fn screen_record(&mut self, duration: Duration) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.inner.screen_record(duration))
+    }
+
+    /// See [`HdcClient::hilog_stream_filtered`]
+    pub fn hilog_stream<F>(&mut self, filter: impl Into<HilogFilter>, callback: F) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.runtime
+            .block_on(self.inner.hilog_stream(filter, callback))
+    }
+}
+
+/// Interrupts a blocking stream or monitor from another thread
+///
+/// Clones share the same state. Once stopped, a handle stays stopped; use a
+/// new one for the next call.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    token: CancellationToken,
+}
+
+impl StopHandle {
+    /// Create a handle that has not been stopped
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make calls using this handle return as soon as possible
+    pub fn stop(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether [`stop`](Self::stop) has been called
+    pub fn is_stopped(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// Device log lines, returned by [`HdcClient::hilog_iter`]
+///
+/// Blocks in [`next`](Iterator::next) until a line arrives, so it must not be
+/// driven from async code.
+pub struct HilogIter {
+    runtime: Arc<Executor>,
+    rx: mpsc::UnboundedReceiver<Result<String>>,
+    task: JoinHandle<()>,
+}
+
+impl Iterator for HilogIter {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        self.runtime.block_on(self.rx.recv())
+    }
+}
+
+impl Drop for HilogIter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Device connects and disconnects, returned by [`HdcClient::device_events`]
+///
+/// Blocks in [`next`](Iterator::next) until the device list changes, so it
+/// must not be driven from async code.
+pub struct DeviceEvents {
+    runtime: Arc<Executor>,
+    rx: mpsc::UnboundedReceiver<DeviceEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Iterator for DeviceEvents {
+    type Item = DeviceEvent;
+
+    fn next(&mut self) -> Option<DeviceEvent> {
+        self.runtime.block_on(self.rx.recv())
+    }
+}
+
+impl Drop for DeviceEvents {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
 }
 
 #[cfg(test)]
@@ -405,6 +1413,272 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_shared_runtime() {
+        let handle = shared_runtime().unwrap();
+        let server = handle
+            .block_on(
+                crate::testing::MockServer::new()
+                    .respond("list targets", "device-1\n")
+                    .spawn(),
+            )
+            .unwrap();
+
+        let mut a = HdcClient::connect_shared(server.address()).unwrap();
+        let mut b = HdcClient::connect_with_runtime(server.address(), handle.clone()).unwrap();
+        assert_eq!(a.list_targets().unwrap(), vec!["device-1"]);
+        assert_eq!(b.list_targets().unwrap(), vec!["device-1"]);
+        assert!(matches!(*a.runtime, Executor::Shared(_)));
+        assert_eq!(shared_runtime().unwrap().id(), handle.id());
+    }
+
+    #[test]
+    fn test_current_thread_runtime() {
+        let server = shared_runtime()
+            .unwrap()
+            .block_on(
+                crate::testing::MockServer::new()
+                    .respond("list targets", "device-1\n")
+                    .respond("hilog", "first\nsecond\n")
+                    .spawn(),
+            )
+            .unwrap();
+
+        let mut client = HdcClient::connect(server.address()).unwrap();
+        assert!(matches!(*client.runtime, Executor::Owned(_)));
+
+        // The background log task runs while either thread drives the runtime
+        let iter = client.hilog_iter(None);
+        let lines = std::thread::spawn(move || iter.take(2).collect::<Result<Vec<_>>>());
+        assert_eq!(client.list_targets().unwrap(), vec!["device-1"]);
+        assert_eq!(lines.join().unwrap().unwrap(), ["first", "second"]);
+    }
+
+    #[test]
+    fn test_call_timeouts() {
+        let handle = shared_runtime().unwrap();
+        let listener = handle
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = handle.spawn(async move {
+            let mut conns = Vec::new();
+            // Never answer
+            while let Ok(conn) = crate::testing::MockConnection::accept(&listener).await {
+                conns.push(conn);
+            }
+        });
+
+        let mut client = HdcClient::connect_shared(&address).unwrap();
+        assert!(matches!(
+            client.shell_with_timeout("sleep 100", Duration::from_millis(50)),
+            Err(crate::HdcError::Timeout)
+        ));
+        client.set_auto_reconnect(true);
+        assert!(matches!(
+            client.wait_for_device_timeout(Duration::from_millis(50)),
+            Err(crate::HdcError::Timeout)
+        ));
+        // The default limits are back in place
+        assert_eq!(*client.timeouts(), Timeouts::default());
+        server.abort();
+    }
+
+    #[test]
+    fn test_hilog_iter() {
+        let handle = shared_runtime().unwrap();
+        let server = handle
+            .block_on(
+                crate::testing::MockServer::new()
+                    .respond("hilog", "first\nsecond\n")
+                    .spawn(),
+            )
+            .unwrap();
+
+        let client = HdcClient::connect_shared(server.address()).unwrap();
+        let lines = std::thread::spawn({
+            let iter = client.hilog_iter(None);
+            move || iter.take(2).collect::<Result<Vec<_>>>()
+        });
+        assert_eq!(lines.join().unwrap().unwrap(), ["first", "second"]);
+    }
+
+    #[test]
+    fn test_device_events() {
+        let handle = shared_runtime().unwrap();
+        let listener = handle
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = handle.spawn(async move {
+            // The client's own channel, then one per poll
+            let _conn = crate::testing::MockConnection::accept(&listener).await;
+            for targets in ["a\nb\n", "a\nb\n", "b\nc\n"].iter().cycle() {
+                let mut conn = crate::testing::MockConnection::accept(&listener)
+                    .await
+                    .unwrap();
+                conn.read_command().await.unwrap();
+                conn.send(targets.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = HdcClient::connect_shared(&address).unwrap();
+        let events: Vec<_> = client
+            .device_events(Duration::from_millis(10))
+            .take(4)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                DeviceEvent::Connected("a".to_string()),
+                DeviceEvent::Connected("b".to_string()),
+                DeviceEvent::Disconnected("a".to_string()),
+                DeviceEvent::Connected("c".to_string()),
+            ]
+        );
+        server.abort();
+    }
+
+    #[test]
+    fn test_clone_on_other_thread() {
+        let server = shared_runtime()
+            .unwrap()
+            .block_on(
+                crate::testing::MockServer::new()
+                    .respond("list targets", "device-1\n")
+                    .spawn(),
+            )
+            .unwrap();
+        let mut client = HdcClient::connect(server.address()).unwrap();
+        let mut clone = client.clone();
+
+        let other = std::thread::spawn(move || clone.list_targets().unwrap());
+        assert_eq!(client.list_targets().unwrap(), ["device-1"]);
+        assert_eq!(other.join().unwrap(), ["device-1"]);
+    }
+
+    #[test]
+    fn test_file_recv_with_progress() {
+        let local = std::env::temp_dir().join(format!("hdc-rs-progress-{}", std::process::id()));
+        let local = local.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&local);
+
+        let handle = shared_runtime().unwrap();
+        let listener = handle
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = handle.spawn({
+            let local = local.clone();
+            async move {
+                let mut conn = crate::testing::MockConnection::accept(&listener)
+                    .await
+                    .unwrap();
+                let command = conn.read_command().await.unwrap();
+                assert_eq!(command, format!("file recv /data/a.bin {}", local));
+                // The server writes the local file itself
+                std::fs::write(&local, b"hello").unwrap();
+                tokio::time::sleep(Duration::from_millis(400)).await;
+                conn.send(b"FileTransfer finish, Size:5").await.unwrap();
+            }
+        });
+
+        let mut client = HdcClient::connect_shared(&address).unwrap();
+        let not_send = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let reports = not_send.clone();
+        client
+            .file_recv_with_progress("/data/a.bin", &local, FileTransferOptions::new(), |p| {
+                reports.borrow_mut().push(p.clone())
+            })
+            .unwrap();
+        handle.block_on(server).unwrap();
+        let _ = std::fs::remove_file(&local);
+
+        let reports = not_send.borrow();
+        let last = reports.last().unwrap();
+        assert!(reports.len() >= 2);
+        assert!(last.finished);
+        assert_eq!((last.bytes, last.total), (5, Some(5)));
+        assert_eq!(reports[0].bytes, 5);
+        assert!(!reports[0].finished);
+    }
+
+    #[test]
+    fn test_stop_handle() {
+        let server = shared_runtime()
+            .unwrap()
+            .block_on(
+                crate::testing::MockServer::new()
+                    .respond("hilog", "first\n")
+                    .respond("list targets", "device-1\n")
+                    .spawn(),
+            )
+            .unwrap();
+        let mut client = HdcClient::connect(server.address()).unwrap();
+
+        // Stopped while waiting for more log output
+        let stop = StopHandle::new();
+        let mut chunks = Vec::new();
+        client
+            .hilog_stream_until(None, &stop, |chunk| {
+                chunks.push(chunk.to_string());
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(50));
+                    stop.stop();
+                });
+                true
+            })
+            .unwrap();
+        assert_eq!(chunks, ["first\n"]);
+        assert!(stop.is_stopped());
+
+        // Stopped between polls
+        client.set_auto_reconnect(true);
+        let stop = StopHandle::new();
+        let mut polls = 0;
+        client
+            .monitor_devices_until(Duration::from_secs(60), &stop, |_| {
+                polls += 1;
+                stop.stop();
+                true
+            })
+            .unwrap();
+        assert_eq!(polls, 1);
+    }
+
+    #[test]
+    fn test_device_client() {
+        let handle = shared_runtime().unwrap();
+        let listener = handle
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // Answer each channel with the device it was opened for
+        let server = handle.spawn(async move {
+            loop {
+                let mut conn = crate::testing::MockConnection::accept(&listener)
+                    .await
+                    .unwrap();
+                tokio::spawn(async move {
+                    if conn.read_command().await.is_ok() {
+                        let key = conn.connect_key().to_string();
+                        conn.send(key.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let client = HdcClient::connect(&address).unwrap();
+        let mut one = client.device("dev-1");
+        let mut two = client.device("dev-2");
+        assert_eq!(two.device_id(), "dev-2");
+        assert_eq!(one.shell("getprop").unwrap(), "dev-1");
+        assert_eq!(two.shell("getprop").unwrap(), "dev-2");
+        assert_eq!(one.shell("getprop").unwrap(), "dev-1");
+        server.abort();
+    }
+
     #[test]
     #[ignore] // Requires HDC server running
     fn test_blocking_list_targets() {
@@ -0,0 +1,147 @@
+//! Bug report collection
+//!
+//! [`HdcClient::bugreport`](crate::HdcClient::bugreport) gathers the state
+//! usually asked for in a bug report into one timestamped directory, much
+//! like `adb bugreport`:
+//!
+//! | File | Source |
+//! |------|--------|
+//! | `props.txt` | `param get` |
+//! | `processes.txt` | `ps -ef` |
+//! | `hilog.txt` | `hilog -x` (buffered logs, no follow) |
+//! | `hidumper.txt` | `hidumper -c` |
+//! | `hidumper-mem.txt` | `hidumper --mem` |
+//! | `storage.txt` | `df -k` |
+//! | `meminfo.txt` | `/proc/meminfo` |
+//! | `faultlogs/` | every report under `/data/log/faultlog` |
+//!
+//! A section that fails is recorded in [`BugReport::failures`] instead of
+//! aborting the collection, so a half-working device still yields a report.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let report = client.bugreport("reports").await?;
+//! println!("Wrote {} files to {}", report.files.len(), report.dir.display());
+//! for (section, error) in &report.failures {
+//!     eprintln!("{}: {}", section, error);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::PathBuf;
+
+use crate::sysinfo::{DF_COMMAND, MEMINFO_COMMAND, PS_COMMAND};
+
+/// Shell command sections of a bug report, as `(file name, command)`
+pub const SECTIONS: &[(&str, &str)] = &[
+    ("props.txt", "param get"),
+    ("processes.txt", PS_COMMAND),
+    ("hilog.txt", "hilog -x"),
+    ("hidumper.txt", "hidumper -c"),
+    ("hidumper-mem.txt", "hidumper --mem"),
+    ("storage.txt", DF_COMMAND),
+    ("meminfo.txt", MEMINFO_COMMAND),
+];
+
+/// Directory the fault reports are copied to, inside the report
+pub const FAULTLOG_SUBDIR: &str = "faultlogs";
+
+/// Device command printing the timestamp the report directory is named by
+pub(crate) const DATE_COMMAND: &str = "date +%Y%m%d-%H%M%S";
+
+/// Bug report written by [`HdcClient::bugreport`](crate::HdcClient::bugreport)
+#[derive(Debug, Clone, Default)]
+pub struct BugReport {
+    /// Directory holding the report
+    pub dir: PathBuf,
+    /// Files written, in collection order
+    pub files: Vec<PathBuf>,
+    /// Sections that could not be collected, with the error
+    pub failures: Vec<(String, String)>,
+}
+
+impl BugReport {
+    /// Whether every section was collected
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Name of the report directory for a device and the output of
+/// [`DATE_COMMAND`], falling back to `fallback` for unexpected output
+pub(crate) fn dir_name(connect_key: &str, date: &str, fallback: u64) -> String {
+    let key: String = connect_key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let date = date.trim();
+    if !date.is_empty() && date.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        format!("bugreport-{}-{}", key, date)
+    } else {
+        format!("bugreport-{}-{}", key, fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeDevice, MockServer};
+    use crate::HdcClient;
+
+    #[test]
+    fn test_dir_name() {
+        assert_eq!(
+            dir_name("192.168.1.2:5555", "20261017-093012\n", 0),
+            "bugreport-192_168_1_2_5555-20261017-093012"
+        );
+        assert_eq!(
+            dir_name("dev", "date: bad format\n", 1792229412),
+            "bugreport-dev-1792229412"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bugreport() {
+        let crash = "/data/log/faultlog/faultlogger/cppcrash-com.example.app-20010034-20261017093000123.log";
+        let mut phone = FakeDevice::new("dev-1")
+            .when(DATE_COMMAND)
+            .reply("20261017-093012\n")
+            .when("find /data/log/faultlog -type f")
+            .reply(format!("{}\n", crash))
+            .when(format!("cat '{}'", crash))
+            .reply("Module name:com.example.app\nReason:Signal:SIGSEGV\n");
+        for (name, command) in SECTIONS {
+            if *name != "hidumper-mem.txt" {
+                phone = phone
+                    .when(command)
+                    .reply(format!("output of {}\n", command));
+            }
+        }
+        let server = MockServer::new().device(phone).spawn_in_memory();
+        let dest = std::env::temp_dir().join(format!("hdc_rs_bugreport_{}", std::process::id()));
+
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        client.connect_device("dev-1").await.unwrap();
+        let report = client.bugreport(&dest).await.unwrap();
+
+        assert_eq!(report.dir, dest.join("bugreport-dev_1-20261017-093012"));
+        assert_eq!(report.files.len(), SECTIONS.len());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].0, "hidumper-mem.txt");
+        assert_eq!(
+            std::fs::read_to_string(report.dir.join("props.txt")).unwrap(),
+            "output of param get\n"
+        );
+        let crash = report
+            .dir
+            .join(FAULTLOG_SUBDIR)
+            .join("cppcrash-com.example.app-20010034-20261017093000123.log");
+        assert!(std::fs::read_to_string(crash).unwrap().contains("SIGSEGV"));
+        std::fs::remove_dir_all(&dest).unwrap();
+    }
+}
@@ -0,0 +1,566 @@
+//! Packet capture and replay
+//!
+//! Wrapping a [`ServerAddr`] with [`ServerAddr::record`] writes every packet
+//! of every connection to a capture file, with its direction and the time
+//! since recording started. [`ServerAddr::replay`] later stands in for the
+//! server: each connection the client opens is answered with the next
+//! recorded one, and packets the client sends are checked against the
+//! recording. A user can thus capture a misbehaving session against their
+//! hdc version and the bug can be reproduced without that server.
+//!
+//! Captures are text, one packet per line, so they can be read and edited:
+//!
+//! ```text
+//! # hdc-rs capture v1
+//! 0 1520 < 4f484f5320484443...
+//! 0 1874 > 6c69737420746172676574732d76
+//! ```
+//!
+//! The fields are the connection number, microseconds since the start, `>`
+//! for packets sent by the client or `<` for packets it received, and the
+//! payload in hex without the length prefix.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use hdc_rs::{HdcClient, ServerAddr};
+//!
+//! // Capture a session against the real server
+//! let addr = ServerAddr::record("127.0.0.1:8710", "session.hdccap")?;
+//! let mut client = HdcClient::connect(addr).await?;
+//! client.list_targets().await?;
+//!
+//! // Later, without the server
+//! let mut client = HdcClient::connect(ServerAddr::replay("session.hdccap")?).await?;
+//! client.list_targets().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Instant;
+
+use crate::error::{HdcError, Result};
+use crate::protocol::PacketDirection;
+use crate::transport::{HdcStream, ServerAddr};
+
+/// First line of every capture file
+pub const CAPTURE_HEADER: &str = "# hdc-rs capture v1";
+
+/// One packet in a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    /// Connection the packet belongs to, numbered from 0 in connect order
+    pub connection: u32,
+    /// Time since recording started
+    pub elapsed: Duration,
+    /// Which way the packet travelled
+    pub direction: PacketDirection,
+    /// Payload without the length prefix
+    pub data: Vec<u8>,
+}
+
+impl CapturedPacket {
+    fn to_line(&self) -> String {
+        let arrow = match self.direction {
+            PacketDirection::Sent => '>',
+            PacketDirection::Received => '<',
+        };
+        let mut line = format!(
+            "{} {} {} ",
+            self.connection,
+            self.elapsed.as_micros(),
+            arrow
+        );
+        for byte in &self.data {
+            line.push_str(&format!("{:02x}", byte));
+        }
+        line
+    }
+
+    fn parse_line(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let connection = fields.next()?.parse().ok()?;
+        let elapsed = Duration::from_micros(fields.next()?.parse().ok()?);
+        let direction = match fields.next()? {
+            ">" => PacketDirection::Sent,
+            "<" => PacketDirection::Received,
+            _ => return None,
+        };
+        let hex = fields.next().unwrap_or_default();
+        if fields.next().is_some() || hex.len() % 2 != 0 {
+            return None;
+        }
+        let data = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(Self {
+            connection,
+            elapsed,
+            direction,
+            data,
+        })
+    }
+}
+
+/// A parsed capture file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capture {
+    /// All packets in recording order
+    pub packets: Vec<CapturedPacket>,
+}
+
+impl Capture {
+    /// Read a capture file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse capture text, ignoring blank lines and `#` comments
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut packets = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let packet = CapturedPacket::parse_line(line).ok_or_else(|| {
+                HdcError::Protocol(format!("Invalid capture line {}: {}", number + 1, line))
+            })?;
+            packets.push(packet);
+        }
+        Ok(Self { packets })
+    }
+
+    /// Packets of each connection, in connect order
+    pub fn connections(&self) -> Vec<Vec<CapturedPacket>> {
+        let mut connections: Vec<Vec<CapturedPacket>> = Vec::new();
+        for packet in &self.packets {
+            let index = packet.connection as usize;
+            if connections.len() <= index {
+                connections.resize(index + 1, Vec::new());
+            }
+            connections[index].push(packet.clone());
+        }
+        connections
+    }
+}
+
+/// Writes the packets of recorded connections to a capture
+pub struct Recorder {
+    out: Mutex<Box<dyn Write + Send>>,
+    started: Instant,
+    next_connection: AtomicU32,
+}
+
+impl Recorder {
+    /// Record to `writer`, starting with the capture header
+    pub fn new(writer: impl Write + Send + 'static) -> Result<Self> {
+        let mut out: Box<dyn Write + Send> = Box::new(writer);
+        writeln!(out, "{}", CAPTURE_HEADER)?;
+        out.flush()?;
+        Ok(Self {
+            out: Mutex::new(out),
+            started: Instant::now(),
+            next_connection: AtomicU32::new(0),
+        })
+    }
+
+    /// Record to a new file at `path`, replacing any existing one
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = std::fs::File::create(path)?;
+        Self::new(io::BufWriter::new(file))
+    }
+
+    /// Wrap a freshly connected stream
+    pub(crate) fn wrap(self: &Arc<Self>, stream: HdcStream) -> RecordingStream {
+        RecordingStream {
+            inner: stream,
+            recorder: self.clone(),
+            connection: self.next_connection.fetch_add(1, Ordering::Relaxed),
+            sent: FrameBuffer::default(),
+            received: FrameBuffer::default(),
+        }
+    }
+
+    fn record(&self, connection: u32, direction: PacketDirection, data: Vec<u8>) {
+        let packet = CapturedPacket {
+            connection,
+            elapsed: self.started.elapsed(),
+            direction,
+            data,
+        };
+        let mut out = self.out.lock().unwrap();
+        // Flush every packet so a crash keeps the capture up to that point
+        if let Err(e) = writeln!(out, "{}", packet.to_line()).and_then(|_| out.flush()) {
+            tracing::warn!("Failed to write capture: {}", e);
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("started", &self.started)
+            .field("next_connection", &self.next_connection)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Hands out recorded connections to a replaying client
+#[derive(Debug)]
+pub struct Replayer {
+    connections: Mutex<VecDeque<Vec<CapturedPacket>>>,
+    timing: bool,
+}
+
+impl Replayer {
+    /// Replay `capture` as fast as the client reads
+    pub fn new(capture: Capture) -> Self {
+        Self {
+            connections: Mutex::new(capture.connections().into()),
+            timing: false,
+        }
+    }
+
+    /// Hold back received packets until their recorded time
+    ///
+    /// Times count from each connection's open. Useful to reproduce
+    /// timeout bugs; with paused Tokio time the delays cost nothing.
+    pub fn with_timing(mut self, timing: bool) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Next recorded connection, or an error once all are used up
+    pub(crate) fn connect(&self) -> Result<ReplayStream> {
+        let packets = self
+            .connections
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| {
+                HdcError::Io(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    "Capture has no more connections",
+                ))
+            })?;
+        let offset = packets.first().map_or(Duration::ZERO, |p| p.elapsed);
+        Ok(ReplayStream {
+            packets: packets.into(),
+            offset,
+            opened: Instant::now(),
+            timing: self.timing,
+            delay: None,
+            pending: Vec::new(),
+            written: FrameBuffer::default(),
+            read_waker: None,
+        })
+    }
+}
+
+/// Splits a byte stream into length-prefixed packets
+#[derive(Debug, Default)]
+struct FrameBuffer {
+    buf: Vec<u8>,
+}
+
+impl FrameBuffer {
+    /// Add bytes and take out every packet completed by them
+    fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(bytes);
+        let mut packets = Vec::new();
+        while self.buf.len() >= 4 {
+            let length = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]);
+            let end = 4 + length as usize;
+            if self.buf.len() < end {
+                break;
+            }
+            packets.push(self.buf[4..end].to_vec());
+            self.buf.drain(..end);
+        }
+        packets
+    }
+}
+
+/// Stream that records the packets passing through it
+#[derive(Debug)]
+pub struct RecordingStream {
+    inner: HdcStream,
+    recorder: Arc<Recorder>,
+    connection: u32,
+    sent: FrameBuffer,
+    received: FrameBuffer,
+}
+
+impl AsyncRead for RecordingStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            for packet in this.received.push(&buf.filled()[before..]) {
+                this.recorder
+                    .record(this.connection, PacketDirection::Received, packet);
+            }
+        }
+        result
+    }
+}
+
+impl AsyncWrite for RecordingStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            for packet in this.sent.push(&buf[..written]) {
+                this.recorder
+                    .record(this.connection, PacketDirection::Sent, packet);
+            }
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Stream playing back one recorded connection
+///
+/// Reads return the recorded server packets in order, but only once the
+/// client has written every packet recorded before them. A written packet
+/// that differs from the recording fails with
+/// [`InvalidData`](io::ErrorKind::InvalidData); the end of the recording
+/// reads as end of stream.
+#[derive(Debug)]
+pub struct ReplayStream {
+    packets: VecDeque<CapturedPacket>,
+    offset: Duration,
+    opened: Instant,
+    timing: bool,
+    delay: Option<Pin<Box<tokio::time::Sleep>>>,
+    pending: Vec<u8>,
+    written: FrameBuffer,
+    read_waker: Option<Waker>,
+}
+
+impl ReplayStream {
+    /// Check a packet the client wrote against the next recorded one
+    fn expect_sent(&mut self, data: Vec<u8>) -> io::Result<()> {
+        let position = self
+            .packets
+            .iter()
+            .position(|p| p.direction == PacketDirection::Sent);
+        let Some(expected) = position.and_then(|i| self.packets.remove(i)) else {
+            return Err(diverged(format!(
+                "client sent {} after the recording ended",
+                preview(&data)
+            )));
+        };
+        if expected.data != data {
+            return Err(diverged(format!(
+                "expected {}, client sent {}",
+                preview(&expected.data),
+                preview(&data)
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for ReplayStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.pending.is_empty() {
+            let Some(next) = this.packets.front() else {
+                return Poll::Ready(Ok(()));
+            };
+            if next.direction == PacketDirection::Sent {
+                this.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            if this.timing {
+                let due = this.opened + next.elapsed.saturating_sub(this.offset);
+                let delay = this
+                    .delay
+                    .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(due)));
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.delay = None;
+            }
+            let packet = this.packets.pop_front().unwrap();
+            this.pending
+                .extend_from_slice(&(packet.data.len() as u32).to_be_bytes());
+            this.pending.extend_from_slice(&packet.data);
+        }
+        let count = this.pending.len().min(buf.remaining());
+        buf.put_slice(&this.pending[..count]);
+        this.pending.drain(..count);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for ReplayStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        for packet in this.written.push(buf) {
+            this.expect_sent(packet)?;
+        }
+        if let Some(waker) = this.read_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn diverged(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Replay diverged: {}", message),
+    )
+}
+
+/// Packet as text if printable, else as hex, shortened to 64 bytes
+fn preview(data: &[u8]) -> String {
+    let head = &data[..data.len().min(64)];
+    let text = match std::str::from_utf8(head) {
+        Ok(text) if !text.chars().any(char::is_control) => format!("{:?}", text),
+        _ => format!("{:02x?}", head),
+    };
+    if data.len() > head.len() {
+        format!("{}... ({} bytes)", text, data.len())
+    } else {
+        text
+    }
+}
+
+impl ServerAddr {
+    /// Reach `inner` and record every packet to a capture file at `path`
+    ///
+    /// See the [`capture`](crate::capture) module for the file format.
+    pub fn record(inner: impl Into<ServerAddr>, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::Recording {
+            inner: Box::new(inner.into()),
+            recorder: Arc::new(Recorder::create(path)?),
+        })
+    }
+
+    /// Answer connections from the capture file at `path` instead of a server
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::Replay(Arc::new(Replayer::new(Capture::load(path)?))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+    use crate::HdcClient;
+
+    /// Recorder writing into a buffer that stays readable
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let packet = CapturedPacket {
+            connection: 3,
+            elapsed: Duration::from_micros(1520),
+            direction: PacketDirection::Received,
+            data: b"OHOS\x00\xff".to_vec(),
+        };
+        assert_eq!(packet.to_line(), "3 1520 < 4f484f5300ff");
+        assert_eq!(CapturedPacket::parse_line(&packet.to_line()), Some(packet));
+        assert!(CapturedPacket::parse_line("0 1 > abc").is_none());
+        assert!(CapturedPacket::parse_line("0 1 ? ab").is_none());
+        assert!(Capture::parse("# comment\n\n0 5 > 6869\n").is_ok());
+        assert!(Capture::parse("bogus").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let server = MockServer::new()
+            .respond("list targets", "device-1\n")
+            .respond("shell echo hi", "hi\n")
+            .spawn()
+            .await
+            .unwrap();
+
+        let out = SharedBuf::default();
+        let addr = ServerAddr::Recording {
+            inner: Box::new(server.address().into()),
+            recorder: Arc::new(Recorder::new(out.clone()).unwrap()),
+        };
+        let mut client = HdcClient::connect(addr).await.unwrap();
+        assert_eq!(client.list_targets().await.unwrap(), vec!["device-1"]);
+        let mut device = client.device("device-1");
+        assert_eq!(device.shell("echo hi").await.unwrap(), "hi\n");
+        drop((client, device, server));
+
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert!(text.starts_with(CAPTURE_HEADER));
+        let capture = Capture::parse(&text).unwrap();
+        assert!(capture.connections().len() >= 2);
+
+        let replay = || ServerAddr::Replay(Arc::new(Replayer::new(capture.clone())));
+        let mut client = HdcClient::connect(replay()).await.unwrap();
+        assert_eq!(client.list_targets().await.unwrap(), vec!["device-1"]);
+
+        let mut client = HdcClient::connect(replay()).await.unwrap();
+        let error = client.check_server().await.unwrap_err();
+        assert!(error.to_string().contains("Replay diverged"), "{}", error);
+    }
+}
@@ -1,262 +1,1022 @@
 //! HDC client implementation
 
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpStream;
-use tokio::time::timeout;
-use tracing::{debug, info, warn};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Instant};
+use tracing::{debug, info, instrument, warn, Span};
 
 use crate::error::{HdcError, Result};
-use crate::protocol::{ChannelHandShake, HdcCommand, PacketCodec};
+use crate::file::{FileTransferDirection, FileTransferOptions, TransferProgress};
+use crate::flash::{parse_stage, FlashProgress};
+use crate::hooks::{CommandContext, CommandObserver};
+use crate::protocol::{
+    Channel, HdcChannelCodec, HdcCommand, PacketObserver, Response, ServerFeatures,
+};
+use crate::retry::RetryPolicy;
+use crate::shell;
+use crate::transport::{HdcStream, ServerAddr, Timeouts, DEFAULT_SERVER_ADDR};
 
-/// Default connection timeout
-const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default idle time before a heartbeat is sent on a long-running stream
+const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long [`HdcClient::close`] waits for the server to release the channel
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often file transfers report progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Prints `true` once the device has finished booting
+const BOOT_COMPLETED_COMMAND: &str = "param get bootevent.boot.completed";
+/// Pause between boot state checks
+const BOOT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Device directory screenshots are written to before being pulled
+const SCREENSHOT_DIR: &str = "/data/local/tmp";
+
+/// Device tool recording the screen, with `start -p <file>` and `stop`
+const SCREEN_RECORD_TOOL: &str = "uitest screenRecord";
+
+/// Time allowed on top of a trace's duration for the tool to write it out
+const TRACE_GRACE: Duration = Duration::from_secs(30);
+
+/// Server queries that are safe to send again after a dropped connection
+const IDEMPOTENT_COMMANDS: &[&str] =
+    &["list targets", "list targets -v", "checkserver", "fport ls"];
+
+/// Buffered lines read by `wait_for_log` to find where hilog's replay ends
+const LOG_HISTORY_PROBE_LINES: usize = 32;
 
 /// HDC client for communicating with HDC server
+///
+/// Cloning is cheap: the clone shares this client's settings and connect
+/// key but opens its own channel on first use, so clones can be moved into
+/// spawned tasks and run commands side by side.
 pub struct HdcClient {
-    /// TCP stream to HDC server
-    stream: Option<TcpStream>,
-    /// Server address
-    address: String,
-    /// Packet codec for encoding/decoding
-    codec: PacketCodec,
-    /// Channel ID assigned by server
-    channel_id: u32,
-    /// Whether handshake is complete
-    handshake_ok: bool,
+    /// Settings shared with clones
+    config: Arc<ClientConfig>,
+    /// Handshaken channel to HDC server
+    channel: Option<Channel>,
     /// Current connect key (device identifier)
     connect_key: Option<String>,
+    /// Features from the most recent handshake
+    features: Option<ServerFeatures>,
+    /// When the last packet was received
+    last_activity: Option<Instant>,
+    /// When the last heartbeat was received
+    last_heartbeat: Option<Instant>,
+    /// Whether we sent a heartbeat that the server has not echoed yet
+    heartbeat_pending: bool,
+    /// Re-open the channel when a command is sent after it was closed
+    auto_reconnect: bool,
+    /// Last command sent and when, kept while a command observer is set
+    current_command: Option<(String, Instant)>,
+}
+
+/// Client settings, shared between a client, its clones and builders
+#[derive(Clone)]
+struct ClientConfig {
+    /// Server address
+    server: ServerAddr,
+    /// Host key configuration for secure daemons
+    #[cfg(feature = "auth")]
+    auth: Option<crate::auth::AuthConfig>,
+    /// Host key, loaded on first handshake when auth is configured
+    #[cfg(feature = "auth")]
+    host_key: Option<Arc<crate::auth::HostKey>>,
+    /// Idle time before a heartbeat is sent on streaming commands
+    keepalive_interval: Duration,
+    /// Notified of every packet on this client's channels
+    packet_observer: Option<Arc<dyn PacketObserver>>,
+    /// Notified of every command sent and its replies
+    command_observer: Option<Arc<dyn CommandObserver>>,
+    /// Time limits for connecting and waiting on commands
+    timeouts: Timeouts,
+    /// How one-shot commands are retried
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl ClientConfig {
+    fn new(server: ServerAddr) -> Self {
+        Self {
+            server,
+            #[cfg(feature = "auth")]
+            auth: None,
+            #[cfg(feature = "auth")]
+            host_key: None,
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+            packet_observer: None,
+            command_observer: None,
+            timeouts: Timeouts::default(),
+            retry_policy: None,
+        }
+    }
 }
 
 impl HdcClient {
     /// Create a new HDC client (not connected)
-    pub fn new(address: impl Into<String>) -> Self {
+    ///
+    /// `address` is a `host:port` string or a [`ServerAddr`].
+    pub fn new(address: impl Into<ServerAddr>) -> Self {
+        Self::with_config(Arc::new(ClientConfig::new(address.into())))
+    }
+
+    /// Create a client that connects on first use
+    ///
+    /// Nothing is dialed until the first command, and channels the server
+    /// has closed are re-opened with the stored connect key, so commands do
+    /// not fail with [`HdcError::NotConnected`]. This is [`new`](Self::new)
+    /// with [auto-reconnect](Self::set_auto_reconnect) turned on.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = HdcClient::lazy("127.0.0.1:8710");
+    /// // Connects here
+    /// let devices = client.list_targets().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lazy(address: impl Into<ServerAddr>) -> Self {
+        let mut client = Self::new(address);
+        client.auto_reconnect = true;
+        client
+    }
+
+    fn with_config(config: Arc<ClientConfig>) -> Self {
         Self {
-            stream: None,
-            address: address.into(),
-            codec: PacketCodec::new(),
-            channel_id: 0,
-            handshake_ok: false,
+            config,
+            channel: None,
             connect_key: None,
+            features: None,
+            last_activity: None,
+            last_heartbeat: None,
+            heartbeat_pending: false,
+            auto_reconnect: false,
+            current_command: None,
+        }
+    }
+
+    /// Configure a client before connecting
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = HdcClient::builder()
+    ///     .address("127.0.0.1:8710")
+    ///     .connect_timeout(Duration::from_secs(3))
+    ///     .shell_timeout(Duration::from_secs(30))
+    ///     .auto_reconnect(true)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> HdcClientBuilder {
+        HdcClientBuilder::new()
+    }
+
+    /// Builder with this client's address and settings, without a device
+    pub fn to_builder(&self) -> HdcClientBuilder {
+        HdcClientBuilder {
+            config: (*self.config).clone(),
+            device: None,
+            auto_reconnect: self.auto_reconnect,
         }
     }
 
     /// Connect to HDC server
-    pub async fn connect(address: impl Into<String>) -> Result<Self> {
+    pub async fn connect(address: impl Into<ServerAddr>) -> Result<Self> {
         let mut client = Self::new(address);
         client.connect_internal().await?;
         Ok(client)
     }
 
+    /// Connect to the server the environment points at
+    ///
+    /// Resolves the address like the official client: the port from
+    /// `OHOS_HDC_SERVER_PORT` if set, otherwise `127.0.0.1:8710`. See
+    /// [`ServerAddr::from_env`].
+    pub async fn connect_default() -> Result<Self> {
+        Self::connect(ServerAddr::from_env()?).await
+    }
+
     /// Internal connection method
     async fn connect_internal(&mut self) -> Result<()> {
-        info!("Connecting to HDC server at {}", self.address);
-
-        let stream = timeout(DEFAULT_TIMEOUT, TcpStream::connect(&self.address))
-            .await
-            .map_err(|_| HdcError::Timeout)?
-            .map_err(HdcError::Io)?;
-
-        info!("Connected to HDC server");
-        self.stream = Some(stream);
+        info!("Connecting to HDC server at {}", self.config.server);
 
-        // Perform channel handshake
+        // Connect and perform channel handshake
         self.perform_handshake(None).await?;
+        info!("Connected to HDC server");
 
         Ok(())
     }
 
-    /// Perform channel handshake with server
-    async fn perform_handshake(&mut self, connect_key: Option<&str>) -> Result<()> {
-        let stream = self.stream.as_mut().ok_or(HdcError::NotConnected)?;
+    /// Unconnected client for the same server with the same settings
+    fn sibling(&self) -> Self {
+        let mut client = Self::with_config(self.config.clone());
+        client.auto_reconnect = self.auto_reconnect;
+        client
+    }
+
+    /// Settings for writing, copied first if clones share them
+    fn config_mut(&mut self) -> &mut ClientConfig {
+        Arc::make_mut(&mut self.config)
+    }
+
+    /// Open a new channel with the last connect key
+    pub(crate) async fn reconnect(&mut self) -> Result<()> {
+        #[cfg(feature = "metrics")]
+        crate::metrics::reconnected();
+        let connect_key = self.connect_key.clone();
+        self.perform_handshake(connect_key.as_deref()).await
+    }
+
+    /// Device selected by the last handshake, if any
+    pub fn connect_key(&self) -> Option<&str> {
+        self.connect_key.as_deref()
+    }
+
+    /// Handle for `device_id` with its own channel
+    ///
+    /// The handle shares this client's settings but not its connection, so
+    /// several devices can be driven at once without
+    /// [`connect_device`](Self::connect_device) calls getting in each
+    /// other's way. It connects on first use.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let mut phone = client.device("phone-serial");
+    /// let mut watch = client.device("watch-serial");
+    /// let (a, b) = tokio::join!(phone.shell("uptime"), watch.shell("uptime"));
+    /// println!("{}\n{}", a?, b?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn device(&self, device_id: impl Into<String>) -> crate::device::DeviceClient {
+        let mut client = self.sibling();
+        client.connect_key = Some(device_id.into());
+        client.auto_reconnect = true;
+        crate::device::DeviceClient::new(client)
+    }
 
-        info!("Starting channel handshake");
+    /// Run `task` on each of `device_ids`, at most `concurrency` at a time
+    ///
+    /// Every device gets its own [`DeviceClient`](crate::device::DeviceClient),
+    /// connected before `task` is called, so a device that cannot be reached
+    /// fails without running it. Repeated ids run once, and a `concurrency`
+    /// of 0 counts as 1. Results are returned in the order of `device_ids`,
+    /// one per device, whether its task succeeded or not; a task that panics
+    /// yields [`HdcError::CommandFailed`].
+    ///
+    /// Tasks run on the Tokio runtime and are aborted if the returned future
+    /// is dropped.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let devices = client.list_targets().await?;
+    /// let results = client
+    ///     .run_on_devices(devices, 4, |mut device| async move {
+    ///         device.shell("param get const.product.name").await
+    ///     })
+    ///     .await;
+    /// for (device_id, result) in results {
+    ///     match result {
+    ///         Ok(name) => println!("{}: {}", device_id, name.trim()),
+    ///         Err(e) => println!("{}: {}", device_id, e),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_on_devices<I, S, F, Fut, T>(
+        &self,
+        device_ids: I,
+        concurrency: usize,
+        task: F,
+    ) -> Vec<(String, Result<T>)>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+        F: Fn(crate::device::DeviceClient) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let mut ids: Vec<String> = Vec::new();
+        for device_id in device_ids {
+            let device_id = device_id.into();
+            if !ids.contains(&device_id) {
+                ids.push(device_id);
+            }
+        }
+        let concurrency = concurrency.max(1);
+        info!(
+            "Running task on {} devices, {} at a time",
+            ids.len(),
+            concurrency
+        );
 
-        // Step 1: Read handshake from server
-        let handshake_data = self.codec.read_packet(stream).await?;
-        let received_size = handshake_data.len();
-        debug!("Received handshake data: {} bytes", received_size);
+        let task = Arc::new(task);
+        let mut pending = ids.iter().enumerate();
+        let mut running = JoinSet::new();
+        let mut indices = HashMap::new();
+        let mut results: Vec<Option<Result<T>>> = ids.iter().map(|_| None).collect();
+        loop {
+            while running.len() < concurrency {
+                let Some((index, device_id)) = pending.next() else {
+                    break;
+                };
+                let mut device = self.device(device_id.clone());
+                let task = task.clone();
+                let handle = running.spawn(async move {
+                    device.connect().await?;
+                    task(device).await
+                });
+                indices.insert(handle.id(), index);
+            }
 
-        let mut handshake = ChannelHandShake::from_bytes(&handshake_data)?;
+            let (task_id, result) = match running.join_next_with_id().await {
+                Some(Ok((task_id, result))) => (task_id, result),
+                Some(Err(e)) => (
+                    e.id(),
+                    Err(HdcError::CommandFailed(format!(
+                        "Device task failed: {}",
+                        e
+                    ))),
+                ),
+                None => break,
+            };
+            let index = indices[&task_id];
+            if let Err(e) = &result {
+                warn!("Task on {} failed: {}", ids[index], e);
+            }
+            results[index] = Some(result);
+        }
 
-        // Step 2: Verify banner
-        handshake.verify_banner()?;
-        info!("Banner verified: {:?}", &handshake.banner[..8]);
+        ids.into_iter()
+            .zip(results)
+            .map(|(device_id, result)| (device_id, result.expect("every device task is joined")))
+            .collect()
+    }
 
-        // Step 3: Extract channel ID
-        self.channel_id = handshake.get_channel_id();
-        info!("Assigned channel ID: {}", self.channel_id);
+    /// Server address this client connects to
+    pub fn address(&self) -> &ServerAddr {
+        &self.config.server
+    }
 
-        // Step 4: Check features
-        let is_stable = handshake.is_stable_buf();
-        debug!("Server stable buffer mode: {}", is_stable);
+    /// Open a new channel to the server, replacing any current one
+    async fn perform_handshake(&mut self, connect_key: Option<&str>) -> Result<()> {
+        self.channel = None;
+        self.heartbeat_pending = false;
+
+        #[cfg(feature = "auth")]
+        self.ensure_host_key().await?;
+
+        let stream = self
+            .config
+            .server
+            .connect(self.config.timeouts.connect)
+            .await?;
+        let mut codec = HdcChannelCodec::new();
+        codec.set_observer(self.config.packet_observer.clone());
+        let channel = Channel::handshake_with_codec(stream, connect_key, codec).await?;
+        Span::current().record("channel_id", channel.id());
+        debug!(channel_id = channel.id(), connect_key, "Channel open");
+        self.features = Some(ServerFeatures::from_handshake(channel.server_handshake()));
+        self.channel = Some(channel);
 
-        // Step 5: Set connect key and send response
         if let Some(key) = connect_key {
-            handshake.set_connect_key(key);
+            Span::current().record("connect_key", key);
             self.connect_key = Some(key.to_string());
-            info!("Using connect key: {}", key);
-        } else {
-            // Empty connect key for initial connection
-            handshake.set_connect_key("");
         }
+        Ok(())
+    }
 
-        // Send handshake response with same format as received
-        // If server sent 44 bytes (without version), respond with 44 bytes
-        // If server sent 108 bytes (with version), respond with 108 bytes
-        let response = if received_size >= ChannelHandShake::SIZE {
-            debug!("Sending full handshake response (108 bytes)");
-            handshake.to_bytes()
-        } else {
-            debug!("Sending handshake response without version (44 bytes)");
-            handshake.to_bytes_without_version()
-        };
+    /// Authenticate to secure daemons with the given host key
+    ///
+    /// The key is loaded (or generated) on the next connection, so a missing
+    /// or unreadable key fails early instead of on the first device command.
+    #[cfg(feature = "auth")]
+    pub fn set_auth(&mut self, config: crate::auth::AuthConfig) {
+        self.config_mut().auth = Some(config);
+        self.config_mut().host_key = None;
+    }
 
-        self.codec.write_packet(stream, &response).await?;
+    /// Host key in use, once loaded
+    #[cfg(feature = "auth")]
+    pub fn host_key(&self) -> Option<&crate::auth::HostKey> {
+        self.config.host_key.as_deref()
+    }
 
-        self.handshake_ok = true;
-        info!("Channel handshake completed successfully");
+    #[cfg(feature = "auth")]
+    async fn ensure_host_key(&mut self) -> Result<()> {
+        let Some(config) = self.config.auth.clone() else {
+            return Ok(());
+        };
+        if self.config.host_key.is_some() {
+            return Ok(());
+        }
 
+        // Key generation takes a while; keep it off the async workers
+        let key = tokio::task::spawn_blocking(move || config.load_key())
+            .await
+            .map_err(|e| HdcError::Auth(e.to_string()))??;
+        self.config_mut().host_key = Some(Arc::new(key));
         Ok(())
     }
 
+    /// Largest packet negotiated with the server
+    ///
+    /// 511KB when the server advertises huge buffers, 60KB otherwise.
+    pub fn max_packet_size(&self) -> usize {
+        self.channel
+            .as_ref()
+            .map_or(crate::protocol::MAX_PACKET_SIZE, Channel::max_packet_size)
+    }
+
     /// Get the channel ID
     pub fn channel_id(&self) -> u32 {
-        self.channel_id
+        self.channel.as_ref().map_or(0, Channel::id)
     }
 
-    /// Check if handshake is complete
-    pub fn is_connected(&self) -> bool {
-        self.handshake_ok && self.stream.is_some()
+    /// Features the server advertised in the most recent handshake
+    ///
+    /// Higher-level commands consult these automatically, e.g. heartbeats are
+    /// only sent to servers that understand them.
+    pub fn server_features(&self) -> Option<&ServerFeatures> {
+        self.features.as_ref()
     }
 
-    /// Send raw command string to server
+    fn supports_heartbeat(&self) -> bool {
+        self.features
+            .as_ref()
+            .is_some_and(ServerFeatures::heartbeat)
+    }
+
+    /// Access the raw channel to send commands the client doesn't wrap
     ///
-    /// This is used for simple commands like "list targets", "shell ls", etc.
-    pub async fn send_command(&mut self, command: &str) -> Result<()> {
-        if !self.is_connected() {
-            return Err(HdcError::NotConnected);
-        }
-        if let Some(ref mut tcp_stream) = self.stream {
-            debug!("Sending command: {}", command);
+    /// See [`Channel`] for the packet-level API.
+    pub fn channel(&mut self) -> Result<&mut Channel> {
+        self.channel.as_mut().ok_or(HdcError::NotConnected)
+    }
 
-            // For simple commands, just send the command string
-            let cmd_bytes = command.as_bytes();
-            self.codec.write_packet(tcp_stream, cmd_bytes).await?;
+    /// Take the raw channel out of the client
+    pub fn into_channel(mut self) -> Result<Channel> {
+        self.channel.take().ok_or(HdcError::NotConnected)
+    }
 
-            return Ok(());
+    /// Notify `observer` of every packet sent or received
+    ///
+    /// Applies to the current channel and every channel opened later,
+    /// including the handshake packets.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// use hdc_rs::protocol::TracingObserver;
+    /// use std::sync::Arc;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = HdcClient::new("127.0.0.1:8710");
+    /// client.set_packet_observer(Some(Arc::new(TracingObserver)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_packet_observer(&mut self, observer: Option<Arc<dyn PacketObserver>>) {
+        if let Some(channel) = self.channel.as_mut() {
+            channel.set_observer(observer.clone());
         }
-        Err(HdcError::NotConnected)
+        self.config_mut().packet_observer = observer;
     }
 
-    /// Read response from server
-    pub async fn read_response(&mut self) -> Result<Vec<u8>> {
-        if !self.is_connected() {
-            return Err(HdcError::NotConnected);
-        }
+    /// Notify `observer` of every command sent, its replies and errors
+    ///
+    /// See [`hooks`](crate::hooks) for an example.
+    pub fn set_command_observer(&mut self, observer: Option<Arc<dyn CommandObserver>>) {
+        self.config_mut().command_observer = observer;
+        self.current_command = None;
+    }
 
-        let stream = self.stream.as_mut().unwrap();
-        let data = self.codec.read_packet(stream).await?;
+    /// Set the time limits for connecting and waiting on commands
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.config_mut().timeouts = timeouts;
+    }
 
-        Ok(data)
+    /// Time limits in use
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.config.timeouts
     }
 
-    /// Read response as string
-    pub async fn read_response_string(&mut self) -> Result<String> {
-        let data = self.read_response().await?;
+    /// Use `timeout` for every wait of the next call, instead of the defaults
+    ///
+    /// The override lasts as long as the returned guard, which dereferences
+    /// to the client; the previous time limits are restored when it drops.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let output = client
+    ///     .with_timeout(Duration::from_secs(600))
+    ///     .shell("find / -name '*.so'")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout(&mut self, timeout: Duration) -> TimeoutOverride<'_> {
+        self.with_timeouts(Timeouts::uniform(timeout))
+    }
 
-        if data.is_empty() {
-            return Ok(String::new());
+    /// Use `timeouts` until the returned guard drops
+    pub fn with_timeouts(&mut self, timeouts: Timeouts) -> TimeoutOverride<'_> {
+        let saved = std::mem::replace(&mut self.config_mut().timeouts, timeouts);
+        TimeoutOverride {
+            client: self,
+            saved,
         }
+    }
 
-        // Check if there's a command prefix (2 bytes)
-        if data.len() >= 2 {
-            let cmd_code = u16::from_le_bytes([data[0], data[1]]);
-            if let Some(cmd) = HdcCommand::from_u16(cmd_code) {
-                debug!("Response has command prefix: {:?}", cmd);
-                // Skip command bytes
-                return Ok(String::from_utf8(data[2..].to_vec())?);
-            }
-        }
+    /// Set the idle time before a heartbeat is sent on streaming commands
+    ///
+    /// Long-running streams such as [`hilog_stream`](Self::hilog_stream) send
+    /// a heartbeat whenever nothing has been received for this long, so quiet
+    /// periods don't end the stream. Defaults to 30 seconds.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.config_mut().keepalive_interval = interval;
+    }
 
-        Ok(String::from_utf8(data)?)
+    /// Take the handshaken connection as a framed packet stream
+    ///
+    /// The returned [`Framed`](tokio_util::codec::Framed) can be split into
+    /// independent read and write halves, e.g. to drive a custom protocol
+    /// loop with `select!`.
+    pub fn into_framed(self) -> Result<tokio_util::codec::Framed<HdcStream, HdcChannelCodec>> {
+        Ok(self.into_channel()?.into_framed())
     }
 
-    /// Execute a shell command and return output
+    /// Re-open the channel automatically when sending on a closed one
     ///
-    /// If a device has been selected via `connect_device()`, the command will be
-    /// executed on that device (the device ID is set in the channel's connectKey
-    /// during handshake). Otherwise, HDC server will return an error asking
-    /// to specify a device.
+    /// Most server commands consume their channel. With this enabled the
+    /// next command re-dials and handshakes with the last connect key
+    /// instead of failing with [`HdcError::NotConnected`]. Queries that are
+    /// safe to repeat, such as `list_targets`, `fport_list` or
+    /// `check_server`, are also retried once on a new channel when they find
+    /// the connection dropped (see [`HdcError::is_connection_lost`]). Other
+    /// one-shot commands such as `shell` are only retried if sending them
+    /// failed, as the device may already have run them; set a
+    /// [`RetryPolicy`] with [`set_retry_policy`](Self::set_retry_policy) to
+    /// repeat them anyway. Streaming commands, installs and file transfers
+    /// are not retried. Off by default.
+    pub fn set_auto_reconnect(&mut self, enable: bool) {
+        self.auto_reconnect = enable;
+    }
+
+    /// Retry one-shot commands that fail transiently
     ///
-    /// Note: Each shell command uses up the current channel. After execution,
-    /// the connection is automatically re-established if a device was connected.
-    pub async fn shell(&mut self, cmd: &str) -> Result<String> {
-        info!("Executing shell command: {}", cmd);
+    /// Applies to commands answered with a single reply, such as `shell`,
+    /// `list_targets` or `fport`; streams, installs and file transfers are
+    /// never repeated. Each retry waits for the policy's backoff and opens a
+    /// new channel with the same connect key. `None` (the default) falls
+    /// back to [`RetryPolicy::reconnect_once`] when auto-reconnect is on.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.config_mut().retry_policy = policy;
+    }
 
-        // Save the current connect key before executing
-        let device_id = self.connect_key.clone();
+    /// Retry policy set with [`set_retry_policy`](Self::set_retry_policy)
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.config.retry_policy.as_ref()
+    }
 
-        // Command format is just "shell <cmd>"
-        // Device targeting is done via the connectKey in handshake, not via -t parameter
-        let full_cmd = format!("shell {}", cmd);
+    /// Use `policy` until the returned guard drops
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, RetryPolicy};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let output = client.with_retry(RetryPolicy::new(5)).shell("uptime").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_retry(&mut self, policy: RetryPolicy) -> RetryOverride<'_> {
+        let saved = self.config_mut().retry_policy.replace(policy);
+        RetryOverride {
+            client: self,
+            saved,
+        }
+    }
 
-        self.send_command(&full_cmd).await?;
+    /// Policy in effect for one-shot commands
+    fn effective_retry_policy(&self) -> RetryPolicy {
+        match self.config.retry_policy {
+            Some(policy) => policy,
+            None if self.auto_reconnect => RetryPolicy::reconnect_once(),
+            None => RetryPolicy::none(),
+        }
+    }
 
-        // For shell commands, HDC server sends a single response packet with raw output data
-        // No command code prefix, just the plain output
-        let output = match timeout(Duration::from_secs(5), self.read_response()).await {
-            Ok(Ok(data)) => {
-                debug!("Shell response: {} bytes", data.len());
-                String::from_utf8_lossy(&data).to_string()
-            }
-            Ok(Err(e)) => {
-                debug!("Error reading shell response: {}", e);
-                return Err(e);
-            }
-            Err(_) => {
-                warn!("Timeout reading shell response");
-                return Err(HdcError::Timeout);
-            }
-        };
+    /// Check if handshake is complete
+    pub fn is_connected(&self) -> bool {
+        self.channel.is_some()
+    }
 
-        // Shell command consumes the channel - reconnect if we had a device
-        if let Some(device) = device_id {
-            debug!("Reconnecting to device after shell command");
-            if let Err(e) = self.connect_device(&device).await {
-                warn!("Failed to reconnect after shell: {}", e);
-                // Don't fail the shell command itself, just log the warning
+    /// Send raw command string to server
+    ///
+    /// This is used for simple commands like "list targets", "shell ls", etc.
+    pub async fn send_command(&mut self, command: &str) -> Result<()> {
+        let rewritten = self.config.command_observer.clone().map(|observer| {
+            let mut command = command.to_string();
+            observer.before_send(self.connect_key.as_deref(), &mut command);
+            self.current_command = Some((command.clone(), Instant::now()));
+            command
+        });
+        let command = rewritten.as_deref().unwrap_or(command);
+
+        let result = self.send_command_inner(command).await;
+        if let Err(e) = &result {
+            self.notify_error(e);
+        }
+        result
+    }
+
+    async fn send_command_inner(&mut self, command: &str) -> Result<()> {
+        if !self.is_connected() {
+            if !self.auto_reconnect {
+                return Err(HdcError::NotConnected);
             }
+            debug!("Channel closed, reconnecting");
+            self.reconnect().await?;
         }
+        Span::current().record("command", command);
+        debug!(command, "Sending command");
+        #[cfg(feature = "metrics")]
+        crate::metrics::command_sent(command);
 
-        Ok(output)
+        // For simple commands, just send the command string
+        self.channel()?.send(command.as_bytes()).await
     }
 
-    /// List connected devices/targets
-    pub async fn list_targets(&mut self) -> Result<Vec<String>> {
-        info!("Listing targets");
-
-        self.send_command("list targets").await?;
+    /// Report `result` of reading a reply to the command observer
+    fn notify_reply(&self, result: &Result<bytes::Bytes>) {
+        match result {
+            Ok(data) => {
+                if let Some((observer, ctx)) = self.command_context() {
+                    observer.after_response(&ctx, data);
+                }
+            }
+            Err(e) => self.notify_error(e),
+        }
+    }
 
-        let response = self.read_response_string().await?;
-        debug!("List targets response: {}", response);
+    fn notify_error(&self, error: &HdcError) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::error(error);
+        if let Some((observer, ctx)) = self.command_context() {
+            observer.on_error(&ctx, error);
+        }
+    }
 
-        // Parse device list (format: one device per line)
-        let devices: Vec<String> = response
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty())
-            .map(|line| line.to_string())
-            .collect();
+    fn command_context(&self) -> Option<(&dyn CommandObserver, CommandContext<'_>)> {
+        let observer = self.config.command_observer.as_deref()?;
+        let (command, sent) = self.current_command.as_ref()?;
+        let ctx = CommandContext {
+            connect_key: self.connect_key.as_deref(),
+            command,
+            elapsed: sent.elapsed(),
+        };
+        Some((observer, ctx))
+    }
 
-        info!("Found {} device(s)", devices.len());
-        Ok(devices)
+    /// Read response from server
+    ///
+    /// The returned [`Bytes`](bytes::Bytes) shares the client's read buffer
+    /// rather than copying it.
+    pub async fn read_response(&mut self) -> Result<bytes::Bytes> {
+        let result = match self.recv_reply().await {
+            Err(HdcError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(self.mark_closed(e.to_string()))
+            }
+            result => result,
+        };
+        self.notify_reply(&result);
+        result
     }
 
-    // pub async fn get_device_stream(&self, device_id: &str) -> Result<HdcClient>{
-    //     let stream = timeout(DEFAULT_TIMEOUT, TcpStream::connect(&self.address))
-    //         .await
-    //         .map_err(|_| HdcError::Timeout)?
-    //         .map_err(HdcError::Io)?;
+    /// Read the next reply, answering heartbeats on the way
+    ///
+    /// Unlike [`read_response`](Self::read_response) a dropped connection is
+    /// returned as the raw I/O error, so callers can tell it from a close
+    /// requested by the server.
+    async fn recv_reply(&mut self) -> Result<bytes::Bytes> {
+        if !self.is_connected() {
+            return Err(HdcError::NotConnected);
+        }
+
+        loop {
+            let data = match self.channel()?.recv().await {
+                Ok(data) => data,
+                Err(HdcError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.channel = None;
+                    return Err(HdcError::Io(e));
+                }
+                Err(e @ HdcError::Desync(_)) => {
+                    // Nothing more can be read from this channel
+                    warn!("{}, dropping channel", e);
+                    self.channel = None;
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            };
+            self.last_activity = Some(Instant::now());
+
+            let message = Response::parse(data.clone());
+            match message.command() {
+                Some(HdcCommand::KernelChannelClose) => {
+                    return Err(self.mark_closed(close_reason(&message)));
+                }
+                Some(HdcCommand::HeartbeatMsg) => self.handle_heartbeat().await?,
+                _ => return Ok(data),
+            }
+        }
+    }
+
+    /// Send `command` and read the first reply
+    ///
+    /// Failed attempts are repeated on a new channel as the
+    /// [retry policy](Self::set_retry_policy) allows. `wait` bounds each
+    /// attempt; a channel that timed out may still get the late reply, so it
+    /// is dropped.
+    async fn request(&mut self, command: &str, wait: Option<Duration>) -> Result<bytes::Bytes> {
+        let policy = self.effective_retry_policy();
+        // Without an explicit policy, a command that may have reached the
+        // device is only sent again if running it twice is harmless
+        let resend = self.config.retry_policy.is_some() || IDEMPOTENT_COMMANDS.contains(&command);
+        let mut attempt = 1;
+        loop {
+            let (sent, result) = self.request_once(command, wait).await;
+            match result {
+                Err(e) if (resend || !sent) && policy.should_retry(attempt, &e) => {
+                    let delay = policy.backoff.delay(attempt);
+                    info!(
+                        "Attempt {} of {:?} failed ({}), retrying in {:?}",
+                        attempt, command, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    self.reconnect().await?;
+                    attempt += 1;
+                }
+                Err(HdcError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Err(self.mark_closed(e.to_string()));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send `command` and read its reply
+    ///
+    /// The flag tells whether the command was sent, i.e. whether the
+    /// device may have run it even if the reply failed.
+    async fn request_once(
+        &mut self,
+        command: &str,
+        wait: Option<Duration>,
+    ) -> (bool, Result<bytes::Bytes>) {
+        if let Err(e) = self.send_command(command).await {
+            return (false, Err(e));
+        }
+        let result = match wait {
+            None => self.recv_reply().await,
+            Some(wait) => match timeout(wait, self.recv_reply()).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.channel = None;
+                    Err(HdcError::Timeout)
+                }
+            },
+        };
+        self.notify_reply(&result);
+        (true, result)
+    }
+
+    /// [`request`](Self::request) with the reply decoded as a string
+    ///
+    /// A `[Fail]` reply is returned as [`HdcError::Remote`].
+    async fn request_string(&mut self, command: &str) -> Result<String> {
+        let reply = Response::parse(self.request(command, None).await?).into_string()?;
+        HdcError::check_response(reply)
+    }
+
+    /// Read a response decoded into command code and payload
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::protocol::HdcCommand;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let message = client.read_message().await?;
+    /// match message.command() {
+    ///     Some(HdcCommand::FileFinish) => println!("transfer done"),
+    ///     _ => println!("{}", message.text()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_message(&mut self) -> Result<Response> {
+        let data = self.read_response().await?;
+        let message = Response::parse(data);
+        if let Some(command) = message.command() {
+            debug!("Response has command prefix: {:?}", command);
+        }
+        Ok(message)
+    }
+
+    /// Drop the connection after the server closed the channel
+    fn mark_closed(&mut self, reason: String) -> HdcError {
+        info!("Channel closed by server: {}", reason);
+        self.channel = None;
+        HdcError::ChannelClosed { reason }
+    }
+
+    /// Record a received heartbeat and answer it unless it echoes ours
+    async fn handle_heartbeat(&mut self) -> Result<()> {
+        self.last_heartbeat = self.last_activity;
+        if self.heartbeat_pending {
+            debug!("Received heartbeat reply");
+            self.heartbeat_pending = false;
+            Ok(())
+        } else {
+            debug!("Answering server heartbeat");
+            self.send_control(HdcCommand::HeartbeatMsg).await
+        }
+    }
+
+    /// Send a heartbeat to the server
+    ///
+    /// The server's reply is consumed by the read path and updates
+    /// [`last_heartbeat`](Self::last_heartbeat).
+    pub async fn send_heartbeat(&mut self) -> Result<()> {
+        if !self.is_connected() {
+            return Err(HdcError::NotConnected);
+        }
+        self.send_control(HdcCommand::HeartbeatMsg).await?;
+        self.heartbeat_pending = true;
+        Ok(())
+    }
+
+    /// When the last packet of any kind was received from the server
+    pub fn last_activity(&self) -> Option<std::time::Instant> {
+        self.last_activity.map(Instant::into_std)
+    }
+
+    /// When the last heartbeat was received from the server
+    pub fn last_heartbeat(&self) -> Option<std::time::Instant> {
+        self.last_heartbeat.map(Instant::into_std)
+    }
+
+    /// Send a bare protocol command with no payload
+    async fn send_control(&mut self, command: HdcCommand) -> Result<()> {
+        debug!("Sending control command: {:?}", command);
+        self.channel()?.send_command(command, &[]).await
+    }
+
+    /// Read response as string
+    pub async fn read_response_string(&mut self) -> Result<String> {
+        self.read_message().await?.into_string()
+    }
+
+    /// Execute a shell command and return output
+    ///
+    /// If a device has been selected via `connect_device()`, the command will be
+    /// executed on that device (the device ID is set in the channel's connectKey
+    /// during handshake). Otherwise, HDC server will return an error asking
+    /// to specify a device.
+    ///
+    /// Note: Each shell command uses up the current channel. After execution,
+    /// the connection is automatically re-established if a device was connected.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn shell(&mut self, cmd: &str) -> Result<String> {
+        info!("Executing shell command");
+
+        // Save the current connect key before executing
+        let device_id = self.connect_key.clone();
+
+        // Command format is just "shell <cmd>"
+        // Device targeting is done via the connectKey in handshake, not via -t parameter
+        let full_cmd = format!("shell {}", cmd);
+
+        // For shell commands, HDC server sends a single response packet with raw output data
+        // No command code prefix, just the plain output
+        let output = match self
+            .request(&full_cmd, Some(self.config.timeouts.shell))
+            .await
+        {
+            Ok(data) => {
+                debug!("Shell response: {} bytes", data.len());
+                String::from_utf8_lossy(&data).to_string()
+            }
+            Err(HdcError::Timeout) => {
+                warn!("Timeout reading shell response");
+                return Err(HdcError::Timeout);
+            }
+            Err(e) => {
+                debug!("Error reading shell response: {}", e);
+                return Err(e);
+            }
+        };
+
+        // Shell command consumes the channel - reconnect if we had a device
+        if let Some(device) = device_id {
+            debug!("Reconnecting to device after shell command");
+            if let Err(e) = self.connect_device(&device).await {
+                warn!("Failed to reconnect after shell: {}", e);
+                // Don't fail the shell command itself, just log the warning
+            }
+        }
+
+        // Only a leading tag is the server's; later lines are command output
+        if output.trim_start().starts_with("[Fail]") {
+            return HdcError::check_response(output);
+        }
+        Ok(output)
+    }
+
+    /// Execute a shell command, waiting at most `timeout` for its output
+    pub async fn shell_with_timeout(&mut self, cmd: &str, timeout: Duration) -> Result<String> {
+        self.with_timeout(timeout).shell(cmd).await
+    }
+
+    /// List connected devices/targets
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn list_targets(&mut self) -> Result<Vec<String>> {
+        info!("Listing targets");
+
+        let response = self.request_string("list targets").await?;
+        debug!("List targets response: {}", response);
+
+        // Parse device list (format: one device per line, `[Empty]` if none)
+        let devices: Vec<String> = response
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && *line != "[Empty]")
+            .map(|line| line.to_string())
+            .collect();
+
+        info!("Found {} device(s)", devices.len());
+        Ok(devices)
+    }
+
+    /// List devices with their connection type, state and model
+    ///
+    /// Uses `list targets -v`, which also reports offline and unauthorized
+    /// devices. Lines that cannot be parsed are skipped.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn list_targets_verbose(&mut self) -> Result<Vec<crate::device::DeviceInfo>> {
+        let response = self.request_string("list targets -v").await?;
+        debug!("List targets response: {}", response);
+
+        let devices = response
+            .lines()
+            .filter(|line| !line.trim().is_empty() && line.trim() != "[Empty]")
+            .filter_map(|line| match crate::device::DeviceInfo::parse(line) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    debug!("Skipping target line {:?}: {}", line, e);
+                    None
+                }
+            })
+            .collect();
+        Ok(devices)
+    }
+
+    // pub async fn get_device_stream(&self, device_id: &str) -> Result<HdcClient>{
+    //     let stream = timeout(DEFAULT_TIMEOUT, TcpStream::connect(&self.address))
+    //         .await
+    //         .map_err(|_| HdcError::Timeout)?
+    //         .map_err(HdcError::Io)?;
     //     let mut  client = HdcClient{
     //         stream: Some(stream),
     //         address: self.address.clone(),
@@ -273,37 +1033,30 @@ impl HdcClient {
     ///
     /// This re-establishes the connection with the specified device ID in the handshake.
     /// After calling this, all commands will be executed on the specified device.
+    #[instrument(skip_all, fields(connect_key = device_id, channel_id))]
     pub async fn connect_device(&mut self, device_id: &str) -> Result<()> {
-        info!("Connecting to device: {}", device_id);
+        info!("Connecting to device");
 
         // Close existing connection
-        if self.stream.is_some() {
+        if self.channel.is_some() {
             debug!("Closing existing connection");
-            self.stream = None;
-            self.handshake_ok = false;
         }
 
-        // Reconnect with new device ID
-        let stream = timeout(DEFAULT_TIMEOUT, TcpStream::connect(&self.address))
-            .await
-            .map_err(|_| HdcError::Timeout)?
-            .map_err(HdcError::Io)?;
-
-        self.stream = Some(stream);
-
-        // Perform handshake with connect key
+        // Reconnect and perform handshake with connect key
         self.perform_handshake(Some(device_id)).await?;
-        self.connect_key = Some(device_id.to_string());
 
         Ok(())
     }
 
     /// Check server version
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn check_server(&mut self) -> Result<String> {
         info!("Checking server version");
 
-        self.send_command("checkserver").await?;
-        let response = self.read_response_string().await?;
+        let response = self.request_string("checkserver").await?;
 
         debug!("Server version: {}", response);
         Ok(response)
@@ -316,15 +1069,18 @@ impl HdcClient {
     /// 2. Executes the command
     ///
     /// Note: This changes the client's current device setting.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn target_command(&mut self, device_id: &str, cmd: &str) -> Result<String> {
-        info!("Executing target command on {}: {}", device_id, cmd);
+        info!("Executing target command");
 
         // Connect to device first (sets connectKey in handshake)
         self.connect_device(device_id).await?;
 
         // Send command directly
-        self.send_command(cmd).await?;
-        let output = self.read_response_string().await?;
+        let output = self.request_string(cmd).await?;
         Ok(output)
     }
 
@@ -339,16 +1095,147 @@ impl HdcClient {
         self.shell(cmd).await
     }
 
+    /// Restart the selected device (`target boot`)
+    ///
+    /// The device drops off the server while it restarts, so a channel
+    /// closed before the reply counts as success. Use
+    /// [`wait_for_boot`](Self::wait_for_boot) to wait until it is back.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, RebootMode};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// client.connect_device("device_id").await?;
+    /// client.reboot(RebootMode::Normal).await?;
+    /// client.wait_for_boot(Duration::from_secs(120)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn reboot(&mut self, mode: crate::device::RebootMode) -> Result<()> {
+        info!("Rebooting device ({:?})", mode);
+        match self.request_string(mode.command()).await {
+            Ok(_) | Err(HdcError::ChannelClosed { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Switch the selected device to TCP debugging on `port` (`tmode port`)
+    ///
+    /// Connect to it afterwards with [`tconn`](Self::tconn).
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn tmode_port(&mut self, port: u16) -> Result<String> {
+        info!("Switching device to TCP port {}", port);
+        self.request_string(&format!("tmode port {}", port)).await
+    }
+
+    /// Switch the selected device back to USB debugging (`tmode usb`)
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn tmode_usb(&mut self) -> Result<String> {
+        info!("Switching device to USB");
+        self.request_string("tmode usb").await
+    }
+
+    /// Ask the server to connect to a device over TCP (`tconn`)
+    ///
+    /// This is a server-level command; `address` is `ip:port`.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn tconn(&mut self, address: &str) -> Result<String> {
+        info!("Connecting to {}", address);
+        self.request_string(&format!("tconn {}", address)).await
+    }
+
+    /// Wait until the selected device reports a completed boot
+    ///
+    /// Polls `param get bootevent.boot.completed` every second, re-opening
+    /// the channel while the device is away, and fails with
+    /// [`HdcError::Timeout`] if the boot does not complete within `wait`.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn wait_for_boot(&mut self, wait: Duration) -> Result<()> {
+        info!("Waiting for boot to complete...");
+        let poll = async {
+            loop {
+                if self.boot_completed().await {
+                    return;
+                }
+                tokio::time::sleep(BOOT_POLL_INTERVAL).await;
+            }
+        };
+        timeout(wait, poll).await.map_err(|_| HdcError::Timeout)
+    }
+
+    async fn boot_completed(&mut self) -> bool {
+        if !self.is_connected() {
+            if let Err(e) = self.reconnect().await {
+                debug!("Device not reachable yet: {}", e);
+                return false;
+            }
+        }
+        match self.shell(BOOT_COMPLETED_COMMAND).await {
+            Ok(output) => output.trim() == "true",
+            Err(e) => {
+                debug!("Boot state not available yet: {}", e);
+                false
+            }
+        }
+    }
+
     /// Close the connection
+    ///
+    /// Like [`shutdown`](Self::shutdown) with a one second deadline, except
+    /// that failures are only logged: the channel is gone either way.
     pub async fn close(&mut self) -> Result<()> {
-        if let Some(stream) = self.stream.take() {
-            info!("Closing connection");
-            drop(stream);
-            self.handshake_ok = false;
+        if let Err(e) = self.shutdown(CLOSE_TIMEOUT).await {
+            debug!("Channel did not close cleanly: {}", e);
         }
         Ok(())
     }
 
+    /// Release the channel on the server, waiting at most `deadline`
+    ///
+    /// Sends the channel-close packet and drains pending output until the
+    /// server lets go, so it frees the channel immediately rather than on
+    /// TCP teardown. The channel is dropped even if this fails; a server
+    /// that does not answer in time yields [`HdcError::Timeout`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// client.shutdown(Duration::from_millis(500)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&mut self, deadline: Duration) -> Result<()> {
+        let Some(channel) = self.channel.take() else {
+            return Ok(());
+        };
+        info!("Closing connection");
+        match timeout(deadline, channel.close()).await {
+            Ok(result) => result,
+            Err(_) => Err(HdcError::Timeout),
+        }
+    }
+
     // ========== Forward Commands ==========
 
     /// Create a port forward (fport)
@@ -365,25 +1252,26 @@ impl HdcClient {
     /// # Ok(())
     /// # }
     /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn fport(
         &mut self,
         local: crate::forward::ForwardNode,
         remote: crate::forward::ForwardNode,
     ) -> Result<String> {
-        info!(
-            "Creating forward: {} -> {}",
-            local.as_protocol_string(),
-            remote.as_protocol_string()
-        );
+        local.validate()?;
+        remote.validate()?;
+
+        info!("Creating forward");
 
         let cmd = format!(
             "fport {} {}",
             local.as_protocol_string(),
             remote.as_protocol_string()
         );
-        self.send_command(&cmd).await?;
-
-        let response = self.read_response_string().await?;
+        let response = self.request_string(&cmd).await?;
         debug!("Forward response: {}", response);
         Ok(response)
     }
@@ -402,25 +1290,26 @@ impl HdcClient {
     /// # Ok(())
     /// # }
     /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn rport(
         &mut self,
         remote: crate::forward::ForwardNode,
         local: crate::forward::ForwardNode,
     ) -> Result<String> {
-        info!(
-            "Creating reverse forward: {} -> {}",
-            remote.as_protocol_string(),
-            local.as_protocol_string()
-        );
+        remote.validate()?;
+        local.validate()?;
+
+        info!("Creating reverse forward");
 
         let cmd = format!(
             "rport {} {}",
             remote.as_protocol_string(),
             local.as_protocol_string()
         );
-        self.send_command(&cmd).await?;
-
-        let response = self.read_response_string().await?;
+        let response = self.request_string(&cmd).await?;
         debug!("Reverse forward response: {}", response);
         Ok(response)
     }
@@ -429,22 +1318,20 @@ impl HdcClient {
     ///
     /// Note: This command does not require a device connection.
     /// It lists forwards across all devices.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn fport_list(&mut self) -> Result<Vec<String>> {
         info!("Listing forward tasks");
 
         // fport ls doesn't need connectKey, use a temporary connection
-        let mut temp_client = Self::new(&self.address);
+        let mut temp_client = self.sibling();
         temp_client.connect_internal().await?;
 
-        temp_client.send_command("fport ls").await?;
-        let response = temp_client.read_response_string().await?;
+        let response = temp_client.request_string("fport ls").await?;
         debug!("Forward list response: {}", response);
 
-        // Check for error messages
-        if response.starts_with("[Fail]") {
-            return Err(HdcError::Protocol(response));
-        }
-
         // Parse the response - each line is a forward task
         let tasks: Vec<String> = response
             .lines()
@@ -456,6 +1343,44 @@ impl HdcClient {
         Ok(tasks)
     }
 
+    /// List all forward/reverse tasks as typed [`ForwardEntry`](crate::forward::ForwardEntry)s
+    ///
+    /// Each entry carries the connect key of the device the task belongs to.
+    /// Lines that cannot be parsed are skipped.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn fport_list_tasks(&mut self) -> Result<Vec<crate::forward::ForwardEntry>> {
+        let lines = self.fport_list().await?;
+        let entries = lines
+            .iter()
+            .filter_map(|line| match crate::forward::ForwardEntry::parse(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    debug!("Skipping forward list line {:?}: {}", line, e);
+                    None
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// List forward/reverse tasks belonging to a specific device
+    ///
+    /// Unlike [`fport_list`](Self::fport_list), this ignores other devices'
+    /// tasks, so teardown logic in multi-device setups doesn't touch them.
+    pub async fn fport_list_for(
+        &mut self,
+        device_id: &str,
+    ) -> Result<Vec<crate::forward::ForwardEntry>> {
+        let entries = self.fport_list_tasks().await?;
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.belongs_to(device_id))
+            .collect())
+    }
+
     /// Remove a forward/reverse task by task string
     ///
     /// Note: This command does not require a device connection.
@@ -469,436 +1394,880 @@ impl HdcClient {
     /// # Ok(())
     /// # }
     /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
     pub async fn fport_remove(&mut self, task_str: &str) -> Result<String> {
-        info!("Removing forward task: {}", task_str);
+        info!("Removing forward task");
 
         // fport rm doesn't need connectKey, use a temporary connection
-        let mut temp_client = Self::new(&self.address);
+        let mut temp_client = self.sibling();
         temp_client.connect_internal().await?;
 
         let cmd = format!("fport rm {}", task_str);
-        temp_client.send_command(&cmd).await?;
-
-        let response = temp_client.read_response_string().await?;
+        let response = temp_client.request_string(&cmd).await?;
         debug!("Remove forward response: {}", response);
 
-        // Check for error messages
-        if response.starts_with("[Fail]") {
-            return Err(HdcError::Protocol(response));
-        }
-
         Ok(response)
     }
 
-    // ========== App Commands ==========
-
-    /// Install application package(s) to device
+    /// Remove all forward/reverse tasks
     ///
-    /// # Arguments
-    /// * `paths` - Single or multiple package paths (.hap, .hsp) or directories
-    /// * `options` - Install options (replace, shared)
+    /// If `device_id` is given, only tasks belonging to that device are removed.
+    /// Returns the number of tasks that were removed.
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::{HdcClient, InstallOptions};
+    /// # use hdc_rs::HdcClient;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// let opts = InstallOptions::new().replace(true);
-    /// client.install(&["app.hap"], opts).await?;
+    /// let removed = client.remove_all_forwards(None).await?;
+    /// println!("Removed {} forward(s)", removed);
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn install(
-        &mut self,
-        paths: &[&str],
-        options: crate::app::InstallOptions,
-    ) -> Result<String> {
-        info!("Installing app: {:?} with options: {:?}", paths, options);
-
-        let flags = options.to_flags();
-        let paths_str = paths.join(" ");
-
-        let cmd = if flags.is_empty() {
-            format!("install {}", paths_str)
-        } else {
-            format!("install {} {}", flags, paths_str)
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            device_id = device_id,
+        )
+    )]
+    pub async fn remove_all_forwards(&mut self, device_id: Option<&str>) -> Result<usize> {
+        info!("Removing all forward tasks");
+
+        let entries = match device_id {
+            Some(id) => self.fport_list_for(id).await?,
+            None => self.fport_list_tasks().await?,
         };
+        let mut removed = 0;
 
-        self.send_command(&cmd).await?;
+        for entry in &entries {
+            let task_str = entry.task.task_string();
+            match self.fport_remove(&task_str).await {
+                Ok(_) => removed += 1,
+                Err(e) => warn!("Failed to remove forward task {}: {}", task_str, e),
+            }
+        }
 
-        // Install may take time and send multiple responses
-        let mut output = String::new();
-        loop {
-            match timeout(Duration::from_secs(30), self.read_response_string()).await {
-                Ok(Ok(resp)) => {
-                    if resp.is_empty() {
-                        break;
-                    }
-                    output.push_str(&resp);
+        info!("Removed {} forward task(s)", removed);
+        Ok(removed)
+    }
 
-                    // Check if installation completed
-                    if resp.contains("Success")
-                        || resp.contains("success")
-                        || resp.contains("Fail")
-                        || resp.contains("fail")
-                    {
-                        break;
-                    }
-                }
-                Ok(Err(e)) => return Err(e),
-                Err(_) => {
-                    warn!("Timeout waiting for install response");
-                    break;
-                }
-            }
-        }
+    /// List debuggable (JDWP) processes on the device
+    ///
+    /// Equivalent to `hdc jpid`. The returned PIDs can be used with
+    /// [`ForwardNode::Jdwp`](crate::forward::ForwardNode::Jdwp) to set up a debugger forward.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// for process in client.jdwp_list().await? {
+    ///     println!("{} {:?}", process.pid, process.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn jdwp_list(&mut self) -> Result<Vec<crate::forward::JdwpProcess>> {
+        info!("Listing JDWP processes");
 
-        debug!("Install output: {} bytes", output.len());
-        Ok(output)
+        let response = self.request_string("jpid").await?;
+        debug!("JDWP list response: {}", response);
+
+        let processes = response
+            .lines()
+            .filter_map(crate::forward::JdwpProcess::parse)
+            .collect();
+
+        Ok(processes)
     }
 
-    /// Uninstall application package from device
+    /// Open a TCP stream to a device node through a temporary forward
     ///
-    /// # Arguments
-    /// * `package` - Package name to uninstall
-    /// * `options` - Uninstall options (keep_data, shared)
+    /// This picks a free local port, creates `fport tcp:<port> <remote>`,
+    /// and connects to it. The forward is removed when the returned stream
+    /// is dropped or closed.
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::{HdcClient, UninstallOptions};
+    /// # use hdc_rs::{HdcClient, ForwardNode};
+    /// # use tokio::io::AsyncWriteExt;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// let opts = UninstallOptions::new().keep_data(true);
-    /// client.uninstall("com.example.app", opts).await?;
+    /// # client.connect_device("device_id").await?;
+    /// let mut stream = client.open_forwarded(ForwardNode::Tcp(8080)).await?;
+    /// stream.write_all(b"ping").await?;
+    /// stream.close().await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn uninstall(
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            remote = %remote,
+        )
+    )]
+    pub async fn open_forwarded(
         &mut self,
-        package: &str,
-        options: crate::app::UninstallOptions,
-    ) -> Result<String> {
-        info!("Uninstalling app: {} with options: {:?}", package, options);
+        remote: crate::forward::ForwardNode,
+    ) -> Result<crate::forward::ForwardedStream> {
+        let device_id = self.connect_key.clone();
 
-        let flags = options.to_flags();
+        let port = crate::forward::free_local_port()?;
+        let task =
+            crate::forward::ForwardTask::forward(crate::forward::ForwardNode::Tcp(port), remote);
+        info!("Opening forwarded stream: {}", task.task_string());
 
-        let cmd = if flags.is_empty() {
-            format!("uninstall {}", package)
-        } else {
-            format!("uninstall {} {}", flags, package)
-        };
+        self.fport(task.local_node.clone(), task.remote_node.clone())
+            .await?;
 
-        self.send_command(&cmd).await?;
+        // The forward command consumes the channel - reconnect if we had a device
+        if let Some(device) = device_id {
+            if let Err(e) = self.connect_device(&device).await {
+                warn!("Failed to reconnect after fport: {}", e);
+            }
+        }
 
-        let response = self.read_response_string().await?;
-        debug!("Uninstall response: {}", response);
-        Ok(response)
+        let stream = match timeout(
+            self.config.timeouts.connect,
+            TcpStream::connect(("127.0.0.1", port)),
+        )
+        .await
+        {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                let _ = self.fport_remove(&task.task_string()).await;
+                return Err(HdcError::Io(e));
+            }
+            Err(_) => {
+                let _ = self.fport_remove(&task.task_string()).await;
+                return Err(HdcError::Timeout);
+            }
+        };
+
+        Ok(crate::forward::ForwardedStream::new(
+            stream,
+            task,
+            self.config.server.clone(),
+        ))
     }
 
-    /// Display device logs using hilog
-    ///
-    /// This method streams logs from the device. The log stream will continue until
-    /// the connection is closed or an error occurs.
+    /// Create a reverse forward that terminates at a host Unix socket
     ///
-    /// # Arguments
-    /// * `args` - Optional arguments for hilog command (e.g., "-h" for help, "-t app" for app logs)
+    /// Device connections to `remote` are relayed to the Unix domain socket
+    /// at `socket_path` on the host (a named pipe path such as
+    /// `\\.\pipe\name` on Windows). The forward is removed when the
+    /// returned bridge is dropped or closed.
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::{HdcClient, ForwardNode};
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
     /// # client.connect_device("device_id").await?;
-    /// // Display all logs
-    /// let logs = client.hilog(None).await?;
-    /// println!("{}", logs);
-    ///
-    /// // Display only app logs
-    /// let app_logs = client.hilog(Some("-t app")).await?;
-    /// println!("{}", app_logs);
+    /// let bridge = client
+    ///     .rport_unix(ForwardNode::Tcp(9090), "/run/my-service.sock")
+    ///     .await?;
+    /// // ... device connects to tcp:9090 ...
+    /// bridge.close().await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn hilog(&mut self, args: Option<&str>) -> Result<String> {
-        info!("Reading hilog: {:?}", args);
-
-        let cmd = if let Some(args) = args {
-            format!("hilog {}", args)
-        } else {
-            "hilog".to_string()
-        };
-
-        self.send_command(&cmd).await?;
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            remote = %remote,
+            socket_path = ?socket_path.as_ref(),
+        )
+    )]
+    pub async fn rport_unix(
+        &mut self,
+        remote: crate::forward::ForwardNode,
+        socket_path: impl AsRef<std::path::Path>,
+    ) -> Result<crate::forward::ReverseBridge> {
+        let device_id = self.connect_key.clone();
 
-        let mut output = String::new();
+        let (listener, port) = crate::forward::bind_bridge_listener().await?;
+        let task =
+            crate::forward::ForwardTask::reverse(remote, crate::forward::ForwardNode::Tcp(port));
+        info!("Bridging reverse forward {}", task.task_string());
 
-        // Read log stream with extended timeout
-        // Hilog streams continuously, we read for a reasonable amount of time
-        loop {
-            match timeout(Duration::from_secs(5), self.read_response_string()).await {
-                Ok(Ok(resp)) => {
-                    if resp.is_empty() {
-                        break;
-                    }
-                    output.push_str(&resp);
+        self.rport(task.remote_node.clone(), task.local_node.clone())
+            .await?;
 
-                    // For continuous log streaming, check if user wants to stop
-                    // In practice, you might want to use a callback or channel here
-                    // to allow real-time log streaming instead of buffering
-                }
-                Ok(Err(e)) => return Err(e),
-                Err(_) => {
-                    // Timeout - check if we got any data
-                    if output.is_empty() {
-                        warn!("Timeout waiting for hilog response");
-                        return Err(HdcError::Timeout);
-                    }
-                    // Otherwise, this might just be the end of the log stream
-                    break;
-                }
+        // The forward command consumes the channel - reconnect if we had a device
+        if let Some(device) = device_id {
+            if let Err(e) = self.connect_device(&device).await {
+                warn!("Failed to reconnect after rport: {}", e);
             }
         }
 
-        debug!("Hilog output: {} bytes", output.len());
-        Ok(output)
+        Ok(crate::forward::start_bridge(
+            listener,
+            task,
+            socket_path.as_ref().to_path_buf(),
+            Some(self.config.server.clone()),
+        ))
     }
 
-    /// Stream hilog output continuously with a callback
-    ///
-    /// This method streams logs from the device and calls the provided callback
-    /// for each log chunk received. The stream continues until an error occurs
-    /// or the callback returns false.
+    /// Copy a raw partition image to the host
     ///
-    /// # Arguments
-    /// * `args` - Optional arguments for hilog command
-    /// * `callback` - Function to call for each log chunk. Return false to stop streaming.
+    /// Resolves `name` under [`BY_NAME_DIR`](crate::partition::BY_NAME_DIR),
+    /// copies the block device in chunks with `dd` and pulls each chunk,
+    /// then checks the image size and, unless disabled, compares SHA-256
+    /// hashes with the device. Needs root on the device. Fails with
+    /// [`HdcError::CommandFailed`] if the partition cannot be read or the
+    /// image does not match; a mismatched image is left at `dest`.
     ///
     /// # Example
     /// ```no_run
     /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::partition::DumpOptions;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
     /// # client.connect_device("device_id").await?;
-    /// client.hilog_stream(None, |log_chunk| {
-    ///     print!("{}", log_chunk);
-    ///     true // Continue streaming
-    /// }).await?;
+    /// let dump = client.dump_partition("misc", "misc.img", DumpOptions::new()).await?;
+    /// println!("{} bytes, sha256 {:?}", dump.size, dump.sha256);
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn hilog_stream<F>(&mut self, args: Option<&str>, mut callback: F) -> Result<()>
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn dump_partition(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: crate::partition::DumpOptions,
+    ) -> Result<crate::partition::PartitionDump> {
+        self.dump_partition_inner(
+            name,
+            dest.as_ref(),
+            options,
+            None::<&mut fn(&TransferProgress)>,
+        )
+        .await
+    }
+
+    /// Copy a raw partition image, reporting progress to `progress`
+    ///
+    /// `progress` is called as each chunk arrives, with the bytes of the
+    /// partition copied so far, and once more with
+    /// [`finished`](TransferProgress::finished) set after verification.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn dump_partition_with_progress<F>(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: crate::partition::DumpOptions,
+        mut progress: F,
+    ) -> Result<crate::partition::PartitionDump>
     where
-        F: FnMut(&str) -> bool,
+        F: FnMut(&TransferProgress),
     {
-        info!("Starting hilog stream: {:?}", args);
+        self.dump_partition_inner(name, dest.as_ref(), options, Some(&mut progress))
+            .await
+    }
 
-        let cmd = if let Some(args) = args {
-            format!("hilog {}", args)
+    async fn dump_partition_inner<P: FnMut(&TransferProgress)>(
+        &mut self,
+        name: &str,
+        dest: &std::path::Path,
+        options: crate::partition::DumpOptions,
+        mut progress: Option<&mut P>,
+    ) -> Result<crate::partition::PartitionDump> {
+        use crate::partition::{is_valid_name, parse_sha256, parse_size, BY_NAME_DIR};
+
+        if !is_valid_name(name) {
+            return Err(HdcError::Config(format!(
+                "Invalid partition name: {:?}",
+                name
+            )));
+        }
+        let link = format!("{}/{}", BY_NAME_DIR, name);
+        let device = self.shell(&format!("readlink -f {}", link)).await?;
+        let device = device.trim().to_string();
+        if !device.starts_with("/dev/") {
+            return Err(HdcError::CommandFailed(format!(
+                "Partition {} not found: {}",
+                name, device
+            )));
+        }
+        let output = self
+            .shell(&format!("blockdev --getsize64 {}", device))
+            .await?;
+        let size = parse_size(&output)
+            .ok_or_else(|| HdcError::CommandFailed(output.trim().to_string()))?;
+        info!("Dumping {} ({}, {} bytes)", name, device, size);
+
+        let started = Instant::now();
+        let copied = self
+            .copy_partition(&device, name, size, dest, &options, started, &mut progress)
+            .await;
+        if let Err(e) = self
+            .shell(&format!("rm -f {}", options.chunk_path(name)))
+            .await
+        {
+            warn!("Failed to remove {}: {}", options.chunk_path(name), e);
+        }
+        let image_hash = copied?;
+
+        let sha256 = if options.verifies() {
+            let output = self
+                .shell_with_timeout(&format!("sha256sum {}", device), options.step_timeout())
+                .await?;
+            let device_hash = parse_sha256(&output)
+                .ok_or_else(|| HdcError::CommandFailed(output.trim().to_string()))?;
+            if device_hash != image_hash {
+                return Err(HdcError::CommandFailed(format!(
+                    "Image of {} does not match the device: sha256 {} on the device, {} in {}",
+                    name,
+                    device_hash,
+                    image_hash,
+                    dest.display()
+                )));
+            }
+            Some(image_hash)
         } else {
-            "hilog".to_string()
+            None
         };
 
-        self.send_command(&cmd).await?;
+        if let Some(progress) = progress {
+            progress(&TransferProgress {
+                direction: FileTransferDirection::Recv,
+                bytes: size,
+                total: Some(size),
+                elapsed: started.elapsed(),
+                finished: true,
+            });
+        }
+        Ok(crate::partition::PartitionDump {
+            name: name.to_string(),
+            device_path: device,
+            size,
+            sha256,
+            path: dest.to_path_buf(),
+        })
+    }
 
-        // Stream logs continuously
-        loop {
-            match timeout(Duration::from_secs(30), self.read_response_string()).await {
-                Ok(Ok(resp)) => {
-                    if resp.is_empty() {
-                        break;
-                    }
+    /// Copy `device` to `dest` chunk by chunk, returning the SHA-256 of
+    /// the image
+    #[allow(clippy::too_many_arguments)]
+    async fn copy_partition<P: FnMut(&TransferProgress)>(
+        &mut self,
+        device: &str,
+        name: &str,
+        size: u64,
+        dest: &std::path::Path,
+        options: &crate::partition::DumpOptions,
+        started: Instant,
+        progress: &mut Option<&mut P>,
+    ) -> Result<String> {
+        use sha2::{Digest, Sha256};
+        use std::io::Write;
+
+        let mut chunk_file = dest.as_os_str().to_owned();
+        chunk_file.push(".chunk");
+        let chunk_file = std::path::PathBuf::from(chunk_file);
+        let chunk_local = chunk_file.to_string_lossy().into_owned();
+        let remote = options.chunk_path(name);
+
+        let mut image = std::fs::File::create(dest)?;
+        let mut hasher = Sha256::new();
+        let mut copied = 0u64;
+        let mut index = 0;
+        while copied < size {
+            let output = self
+                .shell_with_timeout(
+                    &options.dd_command(device, name, index),
+                    options.step_timeout(),
+                )
+                .await?;
+            if crate::partition::is_dd_failure(&output) {
+                return Err(HdcError::CommandFailed(output.trim().to_string()));
+            }
 
-                    // Call user callback with log chunk
-                    if !callback(&resp) {
-                        info!("Hilog stream stopped by callback");
-                        break;
+            let transfer = self
+                .file_recv_with_progress(&remote, &chunk_local, FileTransferOptions::new(), |p| {
+                    if let Some(progress) = progress.as_mut() {
+                        progress(&TransferProgress {
+                            direction: FileTransferDirection::Recv,
+                            bytes: copied + p.bytes,
+                            total: Some(size),
+                            elapsed: started.elapsed(),
+                            finished: false,
+                        });
                     }
-                }
-                Ok(Err(e)) => {
-                    warn!("Error reading hilog stream: {:?}", e);
-                    return Err(e);
-                }
-                Err(_) => {
-                    warn!("Timeout reading hilog stream");
-                    break;
-                }
+                })
+                .await?;
+            if transfer.contains("[Fail]") {
+                return Err(HdcError::CommandFailed(transfer.trim().to_string()));
+            }
+            let data = std::fs::read(&chunk_file)?;
+            std::fs::remove_file(&chunk_file)?;
+            let expected = options.chunk_bytes().min(size - copied);
+            if data.len() as u64 != expected {
+                return Err(HdcError::CommandFailed(format!(
+                    "Chunk {} of {} has {} bytes, expected {}",
+                    index,
+                    name,
+                    data.len(),
+                    expected
+                )));
             }
+            hasher.update(&data);
+            image.write_all(&data)?;
+            copied += expected;
+            index += 1;
+            debug!("Copied {} of {} bytes", copied, size);
         }
-
-        Ok(())
+        image.flush()?;
+        Ok(crate::partition::hex(&hasher.finalize()))
     }
 
-    /// Wait for any device to connect
+    // ========== Flash Commands ==========
+
+    /// Run a firmware operation on a device in flashd mode
     ///
-    /// This command blocks until at least one device is connected.
-    /// If a device is already connected, it returns immediately.
+    /// Waits for the daemon to report the result, allowing up to
+    /// [`Timeouts::transfer`] between replies since writing a large image
+    /// can take minutes. Fails with [`HdcError::Remote`] if flashd reports
+    /// a failure.
     ///
     /// # Example
     /// ```no_run
     /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::flash::FlashOperation;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// // Wait for any device
-    /// let device = client.wait_for_device().await?;
-    /// println!("Device connected: {}", device);
+    /// # client.connect_device("device_id").await?;
+    /// client.flash(FlashOperation::flash("system", "out/system.img")).await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn wait_for_device(&mut self) -> Result<String> {
-        info!("Waiting for device...");
-
-        self.send_command("wait").await?;
-
-        let response = self.read_response_string().await?;
-        debug!("Wait for device response: {}", response);
-
-        // Response format: "Wait for connected target is <device_id>"
-        if let Some(device_id) = response.split("is ").nth(1) {
-            Ok(device_id.trim().to_string())
-        } else {
-            // Fallback: just return the whole response
-            Ok(response.trim().to_string())
-        }
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn flash(&mut self, operation: crate::flash::FlashOperation) -> Result<String> {
+        self.flash_inner(operation, None::<&mut fn(&FlashProgress)>)
+            .await
     }
 
-    /// Monitor device list changes with a callback
+    /// Run a firmware operation, reporting progress to `progress`
     ///
-    /// This function continuously polls the device list and calls the callback
-    /// when changes are detected. The polling interval can be configured.
+    /// `progress` is called for each stage or percentage the daemon
+    /// reports, and once more with
+    /// [`finished`](crate::flash::FlashProgress::finished) set on success.
     ///
-    /// Note: HDC doesn't have a native "track-devices" command like adb,
-    /// so this implementation uses polling to detect changes. Each poll creates
-    /// a new connection to ensure reliability.
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::flash::FlashOperation;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// client
+    ///     .flash_with_progress(FlashOperation::flash("system", "system.img"), |p| {
+    ///         println!("{} {:?}% after {:?}", p.stage, p.percent, p.elapsed);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn flash_with_progress<F>(
+        &mut self,
+        operation: crate::flash::FlashOperation,
+        mut progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&FlashProgress),
+    {
+        self.flash_inner(operation, Some(&mut progress)).await
+    }
+
+    async fn flash_inner<P: FnMut(&FlashProgress)>(
+        &mut self,
+        operation: crate::flash::FlashOperation,
+        progress: Option<&mut P>,
+    ) -> Result<String> {
+        operation.validate()?;
+        info!("Running flashd operation: {}", operation);
+        let started = Instant::now();
+        self.send_command(&operation.command()).await?;
+        self.follow_firmware_command(operation.verb(), started, progress)
+            .await
+    }
+
+    /// Install a package on a device in recovery mode (`sideload`)
     ///
-    /// # Arguments
-    /// * `interval` - Polling interval (recommended: 1-3 seconds)
-    /// * `callback` - Function called when device list changes. Return false to stop monitoring.
+    /// The server streams the file to the daemon with the `AppSideload`
+    /// command, where the updater verifies and applies it. Reboot with
+    /// [`RebootMode::Recovery`](crate::RebootMode::Recovery) first. Fails
+    /// with [`HdcError::Remote`] if the daemon reports a failure.
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::{HdcClient, RebootMode};
     /// # use std::time::Duration;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// client.monitor_devices(Duration::from_secs(2), |devices| {
-    ///     println!("Device list changed:");
-    ///     for device in devices {
-    ///         println!("  - {}", device);
-    ///     }
-    ///     true // Continue monitoring
-    /// }).await?;
+    /// # client.connect_device("device_id").await?;
+    /// client.reboot(RebootMode::Recovery).await?;
+    /// client.wait_for_device_timeout(Duration::from_secs(60)).await?;
+    /// client.sideload("updater.zip").await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn monitor_devices<F>(&mut self, interval: Duration, mut callback: F) -> Result<()>
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn sideload(&mut self, path: impl AsRef<std::path::Path>) -> Result<String> {
+        self.sideload_inner(path.as_ref(), None::<&mut fn(&FlashProgress)>)
+            .await
+    }
+
+    /// Install a package in recovery mode, reporting progress to `progress`
+    ///
+    /// See [`flash_with_progress`](Self::flash_with_progress) for when
+    /// `progress` is called.
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn sideload_with_progress<F>(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        mut progress: F,
+    ) -> Result<String>
     where
-        F: FnMut(&[String]) -> bool,
+        F: FnMut(&FlashProgress),
     {
-        info!("Starting device monitoring with interval: {:?}", interval);
+        self.sideload_inner(path.as_ref(), Some(&mut progress))
+            .await
+    }
 
-        let mut previous_devices: Vec<String> = Vec::new();
+    async fn sideload_inner<P: FnMut(&FlashProgress)>(
+        &mut self,
+        path: &std::path::Path,
+        progress: Option<&mut P>,
+    ) -> Result<String> {
+        if !path.is_file() {
+            return Err(HdcError::Config(format!(
+                "Package not found: {}",
+                path.display()
+            )));
+        }
+        let path = path.to_string_lossy();
+        if !crate::file::validate_path(&path) || path.contains(char::is_whitespace) {
+            return Err(HdcError::Config(format!("Invalid file path: {:?}", path)));
+        }
+        info!("Sideloading {}", path);
+        let started = Instant::now();
+        self.send_command(&format!("sideload {}", path)).await?;
+        self.follow_firmware_command("sideload", started, progress)
+            .await
+    }
 
-        loop {
-            // Reconnect for each poll to ensure fresh connection
-            // HDC server closes connection after each request
-            if let Err(e) = self.connect_internal().await {
-                warn!("Failed to reconnect during monitoring: {:?}", e);
-                tokio::time::sleep(interval).await;
-                continue;
+    /// Collect the replies to a flashd or sideload command until it ends
+    ///
+    /// Allows [`Timeouts::transfer`] between replies, as writing firmware
+    /// can take minutes, and reports each stage line to `progress`. The
+    /// command ends on the daemon's result line for `verb`, see
+    /// [`is_finished`](crate::flash::is_finished).
+    async fn follow_firmware_command<P: FnMut(&FlashProgress)>(
+        &mut self,
+        verb: &str,
+        started: Instant,
+        mut progress: Option<&mut P>,
+    ) -> Result<String> {
+        let mut output = String::new();
+        let mut last = FlashProgress::new("", None, Duration::ZERO);
+        loop {
+            match timeout(self.config.timeouts.transfer, self.read_response_string()).await {
+                Ok(Ok(resp)) => {
+                    if resp.is_empty() {
+                        break;
+                    }
+                    output.push_str(&resp);
+                    let mut finished = false;
+                    for line in resp.split(['\n', '\r']) {
+                        finished |= crate::flash::is_finished(verb, line);
+                        if let Some((stage, percent)) = parse_stage(line) {
+                            last = FlashProgress::new(stage, percent, started.elapsed());
+                            if let Some(progress) = progress.as_mut() {
+                                progress(&last);
+                            }
+                        }
+                    }
+                    if finished {
+                        break;
+                    }
+                }
+                Ok(Err(HdcError::ChannelClosed { .. })) if !output.is_empty() => break,
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(HdcError::Timeout),
             }
+        }
 
-            // Get current device list
-            match self.list_targets().await {
-                Ok(devices) => {
-                    // Check if device list has changed
-                    if devices != previous_devices {
-                        debug!(
-                            "Device list changed: {:?} -> {:?}",
-                            previous_devices, devices
-                        );
+        debug!("Firmware command output: {} bytes", output.len());
+        let output = HdcError::check_response(output)?;
+        if let Some(progress) = progress {
+            last.percent = Some(100);
+            last.elapsed = started.elapsed();
+            last.finished = true;
+            progress(&last);
+        }
+        Ok(output)
+    }
 
-                        // Call user callback
-                        if !callback(&devices) {
-                            info!("Device monitoring stopped by callback");
-                            break;
-                        }
+    /// Push an OTA update package and reboot into the updater
+    ///
+    /// Creates the package directory, sends `package_path` to
+    /// [`OtaOptions::package_path`](crate::ota::OtaOptions::package_path)
+    /// and triggers the updater. With
+    /// [`wait_for_boot`](crate::ota::OtaOptions::wait_for_boot) set, waits
+    /// for the device to go down and then for the updated system to finish
+    /// booting, failing with [`HdcError::Timeout`] if that takes too long.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::ota::OtaOptions;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let options = OtaOptions::new().wait_for_boot(Duration::from_secs(900));
+    /// client.apply_ota("updater.zip", options).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn apply_ota(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: crate::ota::OtaOptions,
+    ) -> Result<()> {
+        self.apply_ota_inner(
+            package_path.as_ref(),
+            options,
+            None::<&mut fn(&FlashProgress)>,
+        )
+        .await
+    }
 
-                        previous_devices = devices;
-                    }
-                }
-                Err(e) => {
-                    warn!("Error listing devices during monitoring: {:?}", e);
-                    // Continue monitoring even if there's an error
-                }
+    /// Apply an OTA update, reporting progress to `progress`
+    ///
+    /// The stages are the [`OTA_STAGES`](crate::ota::OTA_STAGES): the push
+    /// reports a percentage as the package is sent, the later ones only
+    /// that they started. `progress` is called once more with
+    /// [`finished`](crate::flash::FlashProgress::finished) set on success.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::ota::OtaOptions;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let options = OtaOptions::new().wait_for_boot(Duration::from_secs(900));
+    /// client
+    ///     .apply_ota_with_progress("updater.zip", options, |p| {
+    ///         println!("[{:?}] {} {:?}", p.elapsed, p.stage, p.percent);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn apply_ota_with_progress<F>(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: crate::ota::OtaOptions,
+        mut progress: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&FlashProgress),
+    {
+        self.apply_ota_inner(package_path.as_ref(), options, Some(&mut progress))
+            .await
+    }
+
+    async fn apply_ota_inner<P: FnMut(&FlashProgress)>(
+        &mut self,
+        package: &std::path::Path,
+        options: crate::ota::OtaOptions,
+        mut progress: Option<&mut P>,
+    ) -> Result<()> {
+        use crate::ota::{STAGE_BOOT, STAGE_INSTALL, STAGE_PUSH, STAGE_TRIGGER};
+
+        let started = Instant::now();
+        let mut report = |stage: &str, percent: Option<u8>, finished: bool| {
+            if let Some(progress) = progress.as_mut() {
+                let mut update = FlashProgress::new(stage, percent, started.elapsed());
+                update.finished = finished;
+                progress(&update);
             }
+        };
 
-            // Wait before next poll
-            tokio::time::sleep(interval).await;
+        if !package.is_file() {
+            return Err(HdcError::Config(format!(
+                "Update package not found: {}",
+                package.display()
+            )));
+        }
+        let remote = options.package_path();
+        let dir = match remote.rsplit_once('/') {
+            Some((dir, _)) if !dir.is_empty() => dir,
+            _ => crate::ota::UPDATER_DIR,
+        };
+        HdcError::check_response(self.shell(&format!("mkdir -p {}", dir)).await?)?;
+
+        info!("Pushing update package {} to {}", package.display(), remote);
+        report(STAGE_PUSH, Some(0), false);
+        let transfer = self
+            .file_send_with_progress(
+                &package.to_string_lossy(),
+                remote,
+                FileTransferOptions::new(),
+                |p| {
+                    if let Some(fraction) = p.fraction() {
+                        report(STAGE_PUSH, Some((fraction * 100.0) as u8), false);
+                    }
+                },
+            )
+            .await?;
+        if transfer.contains("[Fail]") {
+            return Err(HdcError::CommandFailed(transfer.trim().to_string()));
         }
 
+        // The device reboots at once, so a closed channel means it worked
+        // and any output is an error from `reboot`
+        report(STAGE_TRIGGER, None, false);
+        match self.shell(&options.trigger_command()).await {
+            Ok(output) if !output.trim().is_empty() => {
+                return Err(HdcError::CommandFailed(output.trim().to_string()))
+            }
+            Ok(_) | Err(HdcError::ChannelClosed { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        info!("Updater started");
+
+        if let Some(wait) = options.wait() {
+            report(STAGE_INSTALL, None, false);
+            let deadline = Instant::now() + wait;
+            // The old system can still answer for a moment
+            let going_down = async {
+                while self.boot_completed().await {
+                    tokio::time::sleep(BOOT_POLL_INTERVAL).await;
+                }
+            };
+            timeout(wait, going_down)
+                .await
+                .map_err(|_| HdcError::Timeout)?;
+            report(STAGE_BOOT, None, false);
+            self.wait_for_boot(deadline.saturating_duration_since(Instant::now()))
+                .await?;
+        }
+        report(
+            if options.wait().is_some() {
+                STAGE_BOOT
+            } else {
+                STAGE_TRIGGER
+            },
+            Some(100),
+            true,
+        );
         Ok(())
     }
 
-    /// Send file to device
-    ///
-    /// Transfer a file from local path to remote device path.
+    // ========== App Commands ==========
+
+    /// Install application package(s) to device
     ///
     /// # Arguments
-    /// * `local_path` - Local file path to send
-    /// * `remote_path` - Remote device path destination
-    /// * `options` - File transfer options (timestamp, sync, compress, etc.)
+    /// * `paths` - Single or multiple package paths (.hap, .hsp) or directories
+    /// * `options` - Install options (replace, shared)
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::{HdcClient, FileTransferOptions};
+    /// # use hdc_rs::{HdcClient, InstallOptions};
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// # client.connect_device("device_id").await?;
-    /// let opts = FileTransferOptions::new()
-    ///     .hold_timestamp(true)
-    ///     .compress(true);
-    /// client.file_send("test.txt", "/data/local/tmp/test.txt", opts).await?;
+    /// let opts = InstallOptions::new().replace(true);
+    /// client.install(&["app.hap"], opts).await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn file_send(
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn install(
         &mut self,
-        local_path: &str,
-        remote_path: &str,
-        options: crate::file::FileTransferOptions,
+        paths: &[&str],
+        options: crate::app::InstallOptions,
     ) -> Result<String> {
-        info!("Sending file: {} -> {}", local_path, remote_path);
-
-        // Validate paths
-        if !crate::file::validate_path(local_path) || !crate::file::validate_path(remote_path) {
-            return Err(HdcError::Protocol("Invalid file path".to_string()));
-        }
+        info!("Installing app with options: {:?}", options);
 
-        // Build command
         let flags = options.to_flags();
+        let paths_str = paths.join(" ");
+
         let cmd = if flags.is_empty() {
-            format!("file send {} {}", local_path, remote_path)
+            format!("install {}", paths_str)
         } else {
-            format!("file send {} {} {}", flags, local_path, remote_path)
+            format!("install {} {}", flags, paths_str)
         };
 
-        info!("File send command: {}", cmd);
         self.send_command(&cmd).await?;
 
-        // Read transfer responses
+        // Install may take time and send multiple responses
         let mut output = String::new();
         loop {
-            match timeout(Duration::from_secs(60), self.read_response_string()).await {
+            match timeout(self.config.timeouts.install, self.read_response_string()).await {
                 Ok(Ok(resp)) => {
                     if resp.is_empty() {
                         break;
                     }
                     output.push_str(&resp);
 
-                    // Check for completion indicators
-                    if resp.contains("FileTransfer finish")
-                        || resp.contains("Transfer finish")
-                        || resp.contains("[Fail]")
+                    // Check if installation completed
+                    if resp.contains("Success")
+                        || resp.contains("success")
+                        || resp.contains("Fail")
                         || resp.contains("fail")
                     {
                         break;
@@ -906,101 +2275,2009 @@ impl HdcClient {
                 }
                 Ok(Err(e)) => return Err(e),
                 Err(_) => {
-                    warn!("Timeout during file transfer");
-                    if output.is_empty() {
-                        return Err(HdcError::Timeout);
-                    }
+                    warn!("Timeout waiting for install response");
                     break;
                 }
             }
         }
 
-        debug!("File send output: {} bytes", output.len());
-        Ok(output)
+        debug!("Install output: {} bytes", output.len());
+        HdcError::check_response(output)
     }
 
-    /// Receive file from device
-    ///
-    /// Transfer a file from remote device path to local path.
+    /// Uninstall application package from device
     ///
     /// # Arguments
-    /// * `remote_path` - Remote device file path to receive
-    /// * `local_path` - Local destination path
-    /// * `options` - File transfer options (timestamp, sync, compress, etc.)
+    /// * `package` - Package name to uninstall
+    /// * `options` - Uninstall options (keep_data, shared)
     ///
     /// # Example
     /// ```no_run
-    /// # use hdc_rs::{HdcClient, FileTransferOptions};
+    /// # use hdc_rs::{HdcClient, UninstallOptions};
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
-    /// # client.connect_device("device_id").await?;
-    /// let opts = FileTransferOptions::new().hold_timestamp(true);
-    /// client.file_recv("/data/local/tmp/test.txt", "test.txt", opts).await?;
+    /// let opts = UninstallOptions::new().keep_data(true);
+    /// client.uninstall("com.example.app", opts).await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn file_recv(
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn uninstall(
         &mut self,
-        remote_path: &str,
-        local_path: &str,
-        options: crate::file::FileTransferOptions,
+        package: &str,
+        options: crate::app::UninstallOptions,
     ) -> Result<String> {
-        info!("Receiving file: {} -> {}", remote_path, local_path);
-
-        // Validate paths
-        if !crate::file::validate_path(local_path) || !crate::file::validate_path(remote_path) {
-            return Err(HdcError::Protocol("Invalid file path".to_string()));
-        }
+        info!("Uninstalling app with options: {:?}", options);
 
-        // Build command
         let flags = options.to_flags();
+
         let cmd = if flags.is_empty() {
-            format!("file recv {} {}", remote_path, local_path)
+            format!("uninstall {}", package)
         } else {
-            format!("file recv {} {} {}", flags, remote_path, local_path)
+            format!("uninstall {} {}", flags, package)
         };
 
-        info!("File recv command: {}", cmd);
+        let response = self.request_string(&cmd).await?;
+        debug!("Uninstall response: {}", response);
+        Ok(response)
+    }
+
+    /// Start an ability of an installed app (`aa start`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// client.start_app("com.example.app", "EntryAbility").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn start_app(&mut self, bundle: &str, ability: &str) -> Result<String> {
+        info!("Starting {}/{}", bundle, ability);
+        let output = self
+            .shell(&format!("aa start -a {} -b {}", ability, bundle))
+            .await?;
+        crate::app::check_tool_output(output)
+    }
+
+    /// Force-stop an app (`aa force-stop`)
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn stop_app(&mut self, bundle: &str) -> Result<String> {
+        info!("Stopping {}", bundle);
+        let output = self.shell(&format!("aa force-stop {}", bundle)).await?;
+        crate::app::check_tool_output(output)
+    }
+
+    /// Delete an app's data and cache (`bm clean -d`)
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn clear_app_data(&mut self, bundle: &str) -> Result<String> {
+        info!("Clearing data of {}", bundle);
+        let output = self.shell(&format!("bm clean -n {} -d", bundle)).await?;
+        crate::app::check_tool_output(output)
+    }
+
+    /// Display device logs using hilog
+    ///
+    /// This method streams logs from the device. The log stream will continue until
+    /// the connection is closed or an error occurs.
+    ///
+    /// # Arguments
+    /// * `args` - Optional arguments for hilog command (e.g., "-h" for help, "-t app" for app logs)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// // Display all logs
+    /// let logs = client.hilog(None).await?;
+    /// println!("{}", logs);
+    ///
+    /// // Display only app logs
+    /// let app_logs = client.hilog(Some("-t app")).await?;
+    /// println!("{}", app_logs);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hilog(&mut self, args: Option<&str>) -> Result<String> {
+        self.hilog_filtered(args).await
+    }
+
+    /// Read device logs matching a [`HilogFilter`](crate::hilog::HilogFilter)
+    ///
+    /// Like [`hilog`](Self::hilog), but the command is built from `filter`.
+    /// A filter that fails [`HilogFilter::validate`](crate::hilog::HilogFilter::validate)
+    /// is an [`HdcError::Config`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::hilog::{HilogFilter, LogLevel};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// // Display errors from one tag
+    /// let filter = HilogFilter::new().tag("MyTag").min_level(LogLevel::Error);
+    /// let errors = client.hilog_filtered(filter).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn hilog_filtered(
+        &mut self,
+        filter: impl Into<crate::hilog::HilogFilter>,
+    ) -> Result<String> {
+        let cmd = filter.into().to_command()?;
+        info!("Reading hilog");
+
         self.send_command(&cmd).await?;
 
-        // Read transfer responses
         let mut output = String::new();
+
+        // Read log stream with extended timeout
+        // Hilog streams continuously, we read for a reasonable amount of time
         loop {
-            match timeout(Duration::from_secs(60), self.read_response_string()).await {
+            match timeout(self.config.timeouts.read, self.read_response_string()).await {
                 Ok(Ok(resp)) => {
                     if resp.is_empty() {
                         break;
                     }
                     output.push_str(&resp);
 
-                    // Check for completion indicators
-                    if resp.contains("FileTransfer finish")
-                        || resp.contains("Transfer finish")
-                        || resp.contains("[Fail]")
-                        || resp.contains("fail")
-                    {
-                        break;
-                    }
+                    // For continuous log streaming, check if user wants to stop
+                    // In practice, you might want to use a callback or channel here
+                    // to allow real-time log streaming instead of buffering
                 }
                 Ok(Err(e)) => return Err(e),
                 Err(_) => {
-                    warn!("Timeout during file transfer");
+                    // Timeout - check if we got any data
                     if output.is_empty() {
+                        warn!("Timeout waiting for hilog response");
                         return Err(HdcError::Timeout);
                     }
+                    // Otherwise, this might just be the end of the log stream
                     break;
                 }
             }
         }
 
-        debug!("File recv output: {} bytes", output.len());
+        debug!("Hilog output: {} bytes", output.len());
         Ok(output)
     }
+
+    /// Stream hilog output continuously with a callback
+    ///
+    /// This method streams logs from the device and calls the provided callback
+    /// for each log chunk received. The stream continues until an error occurs
+    /// or the callback returns false.
+    ///
+    /// # Arguments
+    /// * `args` - Optional arguments for hilog command
+    /// * `callback` - Function to call for each log chunk. Return false to stop streaming.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// client.hilog_stream(None, |log_chunk| {
+    ///     print!("{}", log_chunk);
+    ///     true // Continue streaming
+    /// }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hilog_stream<F>(&mut self, args: Option<&str>, callback: F) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.hilog_stream_filtered(args, callback).await
+    }
+
+    /// Stream device logs matching a [`HilogFilter`](crate::hilog::HilogFilter)
+    ///
+    /// Like [`hilog_stream`](Self::hilog_stream), but the command is built
+    /// from `filter`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::hilog::HilogFilter;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// client.hilog_stream_filtered(HilogFilter::new().tag("MyTag"), |log_chunk| {
+    ///     print!("{}", log_chunk);
+    ///     true
+    /// }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn hilog_stream_filtered<F>(
+        &mut self,
+        filter: impl Into<crate::hilog::HilogFilter>,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let cmd = filter.into().to_command()?;
+        info!("Starting hilog stream");
+
+        self.send_command(&cmd).await?;
+        let heartbeat = self.supports_heartbeat();
+        if heartbeat {
+            self.send_control(HdcCommand::KernelEnableKeepalive).await?;
+        }
+
+        // Stream logs continuously, sending heartbeats through quiet periods
+        // when the server understands them (TCP keepalive covers the rest)
+        loop {
+            match timeout(self.config.keepalive_interval, self.read_response()).await {
+                Ok(Ok(data)) => {
+                    let resp = Response::parse(data).into_string()?;
+                    if resp.is_empty() {
+                        break;
+                    }
+
+                    // Call user callback with log chunk
+                    if !callback(&resp) {
+                        info!("Hilog stream stopped by callback");
+                        break;
+                    }
+                }
+                Ok(Err(e)) => {
+                    warn!("Error reading hilog stream: {:?}", e);
+                    return Err(e);
+                }
+                Err(_) if !heartbeat => debug!("Hilog stream idle"),
+                Err(_) => {
+                    debug!("Hilog stream idle, sending heartbeat");
+                    if let Err(e) = self.send_heartbeat().await {
+                        warn!("Hilog stream connection lost: {:?}", e);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read device logs and parse them into [`LogEntry`](crate::hilog::LogEntry) values
+    ///
+    /// Lines that are not in the standard hilog format are skipped.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// for entry in client.hilog_entries(Some("-x")).await? {
+    ///     println!("[{}] {}: {}", entry.level, entry.tag, entry.message);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hilog_entries(
+        &mut self,
+        args: Option<&str>,
+    ) -> Result<Vec<crate::hilog::LogEntry>> {
+        self.hilog_entries_filtered(args).await
+    }
+
+    /// Read device logs matching a [`HilogFilter`](crate::hilog::HilogFilter)
+    /// and parse them into [`LogEntry`](crate::hilog::LogEntry) values
+    pub async fn hilog_entries_filtered(
+        &mut self,
+        filter: impl Into<crate::hilog::HilogFilter>,
+    ) -> Result<Vec<crate::hilog::LogEntry>> {
+        let output = self.hilog_filtered(filter).await?;
+        Ok(output
+            .lines()
+            .filter_map(crate::hilog::LogEntry::parse)
+            .collect())
+    }
+
+    /// Stream device logs as parsed [`LogEntry`](crate::hilog::LogEntry) values
+    ///
+    /// Like [`hilog_stream`](Self::hilog_stream), but chunks are split into
+    /// lines and parsed before being passed to the callback. Return false from
+    /// the callback to stop streaming.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::hilog::LogLevel;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// client.hilog_entries_stream(None, |entry| {
+    ///     if entry.level >= LogLevel::Error {
+    ///         println!("{}", entry);
+    ///     }
+    ///     true
+    /// }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hilog_entries_stream<F>(&mut self, args: Option<&str>, callback: F) -> Result<()>
+    where
+        F: FnMut(crate::hilog::LogEntry) -> bool,
+    {
+        self.hilog_entries_stream_filtered(args, callback).await
+    }
+
+    /// Stream device logs matching a [`HilogFilter`](crate::hilog::HilogFilter)
+    /// as parsed [`LogEntry`](crate::hilog::LogEntry) values
+    pub async fn hilog_entries_stream_filtered<F>(
+        &mut self,
+        filter: impl Into<crate::hilog::HilogFilter>,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(crate::hilog::LogEntry) -> bool,
+    {
+        let mut lines = crate::hilog::LineBuffer::default();
+        let mut stopped = false;
+        self.hilog_stream_filtered(filter, |chunk| {
+            for line in lines.push(chunk) {
+                if let Some(entry) = crate::hilog::LogEntry::parse(&line) {
+                    if !callback(entry) {
+                        stopped = true;
+                        return false;
+                    }
+                }
+            }
+            true
+        })
+        .await?;
+
+        // Deliver a final line that wasn't newline-terminated
+        if !stopped {
+            if let Some(entry) = lines
+                .finish()
+                .and_then(|line| crate::hilog::LogEntry::parse(&line))
+            {
+                callback(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Watch device logs for lines matching a regular expression
+    ///
+    /// Opens a separate connection to the current device, so this client
+    /// stays usable while the returned stream is alive. The pattern is matched
+    /// against each full log line; matching lines that parse as hilog entries
+    /// are yielded. The stream ends when the log connection closes.
+    ///
+    /// Note that hilog replays its buffered history before following new
+    /// output, so recent lines can match too; see
+    /// [`wait_for_log`](Self::wait_for_log) to skip them. An invalid pattern
+    /// or filter is an [`HdcError::Config`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, HilogFilter};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let mut crashes = client
+    ///     .hilog_watch(HilogFilter::new(), r"(?i)fatal|crash")
+    ///     .await?;
+    /// while let Some(entry) = crashes.next().await {
+    ///     println!("{}", entry);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hilog_watch(
+        &self,
+        filter: impl Into<crate::hilog::HilogFilter>,
+        pattern: &str,
+    ) -> Result<crate::hilog::LogStream> {
+        let regex = compile_log_pattern(pattern)?;
+        self.watch_log_lines(filter.into(), regex, crate::hilog::LogCursor::new())
+            .await
+    }
+
+    /// Stream the lines of `filter` that `cursor` accepts and `regex` matches
+    async fn watch_log_lines(
+        &self,
+        filter: crate::hilog::HilogFilter,
+        regex: regex::Regex,
+        mut cursor: crate::hilog::LogCursor,
+    ) -> Result<crate::hilog::LogStream> {
+        filter.validate()?;
+        let mut client = self.sibling();
+        match &self.connect_key {
+            Some(key) => client.connect_device(key).await?,
+            None => client.connect_internal().await?,
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut lines = crate::hilog::LineBuffer::default();
+            let result = client
+                .hilog_stream_filtered(filter, |chunk| {
+                    for line in lines.push(chunk) {
+                        if !cursor.accept(&line) || !regex.is_match(&line) {
+                            continue;
+                        }
+                        if let Some(entry) = crate::hilog::LogEntry::parse(&line) {
+                            if tx.send(entry).is_err() {
+                                return false;
+                            }
+                        }
+                    }
+                    true
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("Hilog watch ended with error: {}", e);
+            }
+        });
+
+        Ok(crate::hilog::LogStream::new(rx, vec![task]))
+    }
+
+    /// Wait until a log line matching `pattern` appears
+    ///
+    /// Unlike [`hilog_watch`](Self::hilog_watch), only entries logged after
+    /// the call starts are matched: the newest buffered lines are read first
+    /// and hilog's replay of its history is skipped up to them. Returns the
+    /// matching entry, [`HdcError::Timeout`] if nothing matched within `wait`,
+    /// or [`HdcError::Protocol`] if the log stream ended first. An invalid
+    /// pattern is an [`HdcError::Config`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let entry = client
+    ///     .wait_for_log("MyService.*READY", Duration::from_secs(30))
+    ///     .await?;
+    /// println!("Service ready at {}", entry.timestamp);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_for_log(
+        &self,
+        pattern: &str,
+        wait: Duration,
+    ) -> Result<crate::hilog::LogEntry> {
+        let regex = compile_log_pattern(pattern)?;
+        let filter = crate::hilog::HilogFilter::new();
+
+        let mut probe = self.sibling();
+        match &self.connect_key {
+            Some(key) => probe.connect_device(key).await?,
+            None => probe.connect_internal().await?,
+        }
+        let history = probe
+            .shell(&format!(
+                "{} -x -z {}",
+                filter.to_command()?,
+                LOG_HISTORY_PROBE_LINES
+            ))
+            .await?;
+        let mut cursor = crate::hilog::LogCursor::new();
+        for line in history.lines() {
+            cursor.accept(line);
+        }
+        cursor.resume();
+
+        let mut stream = self.watch_log_lines(filter, regex, cursor).await?;
+
+        match timeout(wait, stream.next()).await {
+            Ok(Some(entry)) => Ok(entry),
+            Ok(None) => Err(HdcError::Protocol(
+                "Log stream ended before pattern matched".to_string(),
+            )),
+            Err(_) => Err(HdcError::Timeout),
+        }
+    }
+
+    /// Watch for crashes and freezes of one bundle
+    ///
+    /// Opens a separate connection to the current device, notes the reports
+    /// already present and then lists `/data/log/faultlog` every
+    /// [`CRASH_POLL_INTERVAL`](crate::faultlog::CRASH_POLL_INTERVAL). Each
+    /// new cppcrash, jscrash or appfreeze report of `bundle` (or one of its
+    /// `<bundle>:<name>` extension processes) is read, parsed and yielded.
+    /// The stream ends if the device goes away.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let mut crashes = client.watch_crashes("com.example.app").await?;
+    /// // ... drive the app ...
+    /// if let Some(crash) = crashes.next().await {
+    ///     panic!("{}", crash);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn watch_crashes(
+        &self,
+        bundle: &str,
+    ) -> Result<crate::hilog::LogStream<crate::faultlog::CrashEvent>> {
+        use crate::faultlog::{FaultLog, FaultLogName, CRASH_POLL_INTERVAL, FAULTLOG_DIR};
+
+        let mut client = self.sibling();
+        match &self.connect_key {
+            Some(key) => client.connect_device(key).await?,
+            None => client.connect_internal().await?,
+        }
+
+        let listing = format!("find {} -type f", FAULTLOG_DIR);
+        let mut seen: std::collections::HashSet<String> = client
+            .shell(&listing)
+            .await?
+            .lines()
+            .map(|path| path.trim().to_string())
+            .collect();
+        let bundle = bundle.to_string();
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CRASH_POLL_INTERVAL).await;
+                let output = match client
+                    .shell(&listing)
+                    .await
+                    .and_then(HdcError::check_response)
+                {
+                    Ok(output) => output,
+                    Err(e) => {
+                        warn!("Crash watch ended with error: {}", e);
+                        return;
+                    }
+                };
+
+                let mut new: Vec<(String, FaultLogName)> = Vec::new();
+                for path in output.lines().map(str::trim) {
+                    if path.is_empty() || !seen.insert(path.to_string()) {
+                        continue;
+                    }
+                    match FaultLogName::parse(path) {
+                        Some(name) if name.is_app_fault_of(&bundle) => {
+                            new.push((path.to_string(), name))
+                        }
+                        _ => {}
+                    }
+                }
+                new.sort_by_key(|(_, name)| name.timestamp);
+
+                for (path, _) in new {
+                    let content = match client.shell(&format!("cat {}", shell::quote(&path))).await
+                    {
+                        Ok(content) => content,
+                        Err(e) => {
+                            warn!("Failed to read {}: {}", path, e);
+                            continue;
+                        }
+                    };
+                    if let Some(log) = FaultLog::parse(&path, &content) {
+                        if tx.send(log.into()).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(crate::hilog::LogStream::new(rx, vec![task]))
+    }
+
+    /// Sample device metrics periodically
+    ///
+    /// Opens a separate connection to the current device and reads `kinds`
+    /// every `interval`, starting one interval from now. CPU usage is
+    /// measured over the interval before each sample. Samples are timed
+    /// from the end of the previous one, so a slow device stretches the
+    /// interval instead of queueing samples. The stream ends if the device
+    /// goes away.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::sampler::MetricKind;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let mut samples = client
+    ///     .sample_metrics(Duration::from_secs(5), &[MetricKind::Cpu, MetricKind::Battery])
+    ///     .await?;
+    /// while let Some(sample) = samples.next().await {
+    ///     if sample.battery.is_some_and(|b| b.temperature_c > 45.0) {
+    ///         println!("Device is overheating at {:?}% CPU", sample.cpu_percent);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sample_metrics(
+        &self,
+        interval: Duration,
+        kinds: &[crate::sampler::MetricKind],
+    ) -> Result<crate::hilog::LogStream<crate::sampler::MetricsSample>> {
+        if interval.is_zero() {
+            return Err(HdcError::Config(
+                "Sampling interval must be positive".to_string(),
+            ));
+        }
+        let mut sampler = crate::sampler::Sampler::new(kinds)?;
+
+        let mut client = self.sibling();
+        match &self.connect_key {
+            Some(key) => client.connect_device(key).await?,
+            None => client.connect_internal().await?,
+        }
+        sampler.prime(&mut client).await?;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                match sampler.sample(&mut client).await {
+                    Ok(sample) => {
+                        if tx.send(sample).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Metrics sampling ended with error: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(crate::hilog::LogStream::new(rx, vec![task]))
+    }
+
+    /// Collect crash and freeze reports from the device
+    ///
+    /// Lists `/data/log/faultlog` recursively and reads every cppcrash,
+    /// jscrash, appfreeze and sysfreeze report, parsing its header fields.
+    /// With `since`, only reports whose file name timestamp is at or after it
+    /// are returned; reports without a timestamp are skipped in that case.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// for log in client.collect_faultlogs(None).await? {
+    ///     println!("{} {} {:?}", log.kind, log.module_name(), log.reason());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            ?since,
+        )
+    )]
+    pub async fn collect_faultlogs(
+        &mut self,
+        since: Option<crate::hilog::LogTimestamp>,
+    ) -> Result<Vec<crate::faultlog::FaultLog>> {
+        use crate::faultlog::{FaultLog, FaultLogName, FAULTLOG_DIR};
+
+        info!("Collecting faultlogs");
+
+        let listing = self
+            .shell(&format!("find {} -type f", FAULTLOG_DIR))
+            .await?;
+
+        let mut paths: Vec<(String, FaultLogName)> = listing
+            .lines()
+            .map(str::trim)
+            .filter_map(|path| Some((path.to_string(), FaultLogName::parse(path)?)))
+            .filter(|(_, name)| match (since, name.timestamp) {
+                (Some(since), Some(ts)) => ts >= since,
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .collect();
+        paths.sort_by_key(|(_, name)| name.timestamp);
+
+        let mut logs = Vec::with_capacity(paths.len());
+        for (path, _) in paths {
+            let content = self.shell(&format!("cat {}", shell::quote(&path))).await?;
+            if let Some(log) = FaultLog::parse(&path, &content) {
+                logs.push(log);
+            }
+        }
+
+        debug!("Collected {} faultlogs", logs.len());
+        Ok(logs)
+    }
+
+    /// Collect a bug report into a timestamped directory under `dest_dir`
+    ///
+    /// Writes device properties, processes, a hilog snapshot, `hidumper`
+    /// output, storage and memory statistics and every faultlog; see
+    /// [`bugreport`](crate::bugreport) for the layout. Sections that fail
+    /// are listed in [`BugReport::failures`](crate::bugreport::BugReport::failures);
+    /// only local I/O errors abort the collection.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let report = client.bugreport("reports").await?;
+    /// println!("Bug report in {}", report.dir.display());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn bugreport(
+        &mut self,
+        dest_dir: impl AsRef<std::path::Path>,
+    ) -> Result<crate::bugreport::BugReport> {
+        use crate::bugreport::{dir_name, BugReport, DATE_COMMAND, FAULTLOG_SUBDIR, SECTIONS};
+
+        info!("Collecting bug report");
+
+        let date = self.shell(DATE_COMMAND).await.unwrap_or_default();
+        let fallback = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let name = dir_name(self.connect_key().unwrap_or("device"), &date, fallback);
+        let mut report = BugReport {
+            dir: dest_dir.as_ref().join(name),
+            ..BugReport::default()
+        };
+        std::fs::create_dir_all(&report.dir)?;
+
+        for (file, command) in SECTIONS {
+            match self.shell(command).await.and_then(HdcError::check_response) {
+                Ok(output) => {
+                    let path = report.dir.join(file);
+                    std::fs::write(&path, output)?;
+                    report.files.push(path);
+                }
+                Err(e) => {
+                    warn!("Bug report section {} failed: {}", file, e);
+                    report.failures.push((file.to_string(), e.to_string()));
+                }
+            }
+        }
+
+        match self.collect_faultlogs(None).await {
+            Ok(logs) => {
+                let dir = report.dir.join(FAULTLOG_SUBDIR);
+                std::fs::create_dir_all(&dir)?;
+                for log in logs {
+                    let file = log.path.rsplit('/').next().unwrap_or(&log.path);
+                    let path = dir.join(file);
+                    std::fs::write(&path, &log.content)?;
+                    report.files.push(path);
+                }
+            }
+            Err(e) => {
+                warn!("Bug report faultlogs failed: {}", e);
+                report
+                    .failures
+                    .push((FAULTLOG_SUBDIR.to_string(), e.to_string()));
+            }
+        }
+
+        debug!(
+            "Bug report: {} files, {} failures",
+            report.files.len(),
+            report.failures.len()
+        );
+        Ok(report)
+    }
+
+    /// Pull the coverage output of an instrumented test run
+    ///
+    /// Lists the files matching the options' patterns in the app's
+    /// sandbox, pulls each with a debug-dir transfer into the local
+    /// directory (keeping paths relative to the sandbox base) and merges
+    /// the LCOV tracefiles among them; see [`coverage`](crate::coverage).
+    /// The app must be debuggable.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::coverage::CoverageOptions;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let options = CoverageOptions::new("com.example.app").clear(true);
+    /// let coverage = client.collect_coverage(options).await?;
+    /// println!("Pulled {} coverage files", coverage.files.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            bundle = options.bundle_name(),
+        )
+    )]
+    pub async fn collect_coverage(
+        &mut self,
+        options: crate::coverage::CoverageOptions,
+    ) -> Result<crate::coverage::CoverageArtifacts> {
+        use crate::coverage::{merge_lcov, CoverageArtifacts, MERGED_LCOV, SANDBOX_BASE};
+
+        info!("Collecting coverage");
+
+        let listing = self.shell(&options.find_command()).await?;
+        let mut artifacts = CoverageArtifacts::default();
+        let mut pulled = Vec::new();
+        let mut tracefiles = Vec::new();
+        for device_path in listing.lines().map(str::trim) {
+            let Some(relative) = options.relative(device_path) else {
+                continue;
+            };
+            let local = options.local_path().join(relative);
+            if let Some(parent) = local.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let remote = format!("{}/{}", SANDBOX_BASE, relative);
+            let transfer = FileTransferOptions::new().bundle(options.bundle_name());
+            let output = self
+                .file_recv(&remote, &local.to_string_lossy(), transfer)
+                .await?;
+            if output.contains("[Fail]") {
+                return Err(HdcError::CommandFailed(output.trim().to_string()));
+            }
+
+            if relative.ends_with(".info") {
+                tracefiles.push(std::fs::read_to_string(&local)?);
+            }
+            pulled.push(device_path.to_string());
+            artifacts.files.push(local);
+        }
+
+        if !tracefiles.is_empty() {
+            let tracefiles: Vec<&str> = tracefiles.iter().map(String::as_str).collect();
+            let merged = options.local_path().join(MERGED_LCOV);
+            std::fs::write(&merged, merge_lcov(&tracefiles))?;
+            artifacts.merged = Some(merged);
+        }
+
+        if options.clear_after() {
+            for command in crate::coverage::remove_commands(&pulled) {
+                if let Err(e) = self.shell(&command).await {
+                    warn!("Failed to clear coverage files: {}", e);
+                }
+            }
+        }
+
+        debug!("Pulled {} coverage files", artifacts.files.len());
+        Ok(artifacts)
+    }
+
+    /// Capture the device screen as JPEG bytes
+    ///
+    /// Runs `snapshot_display` on the device, pulls the image and removes
+    /// both the remote and the local temporary file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let jpeg = client.screenshot().await?;
+    /// std::fs::write("screen.jpeg", jpeg)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn screenshot(&mut self) -> Result<Vec<u8>> {
+        let name = temp_name("screenshot", "jpeg");
+        let remote = format!("{}/{}", SCREENSHOT_DIR, name);
+
+        let output = self
+            .shell(&format!("snapshot_display -f {}", remote))
+            .await?;
+        if !output.contains("success") {
+            return Err(HdcError::CommandFailed(output.trim().to_string()));
+        }
+
+        self.pull_and_remove(&remote, &name).await
+    }
+
+    /// Record the device screen for `duration` as MP4 bytes
+    ///
+    /// Starts the device screen recorder (`uitest screenRecord`), stops it
+    /// after `duration`, pulls the video and removes both the remote and
+    /// the local temporary file. Fails with [`HdcError::CommandFailed`] right
+    /// away if the recorder reports that it could not start, and after
+    /// `duration` if it wrote no video.
+    ///
+    /// If the returned future is dropped while recording, the recorder is
+    /// stopped and the remote file removed on a separate connection in the
+    /// background.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let video = client.screen_record(Duration::from_secs(10)).await?;
+    /// std::fs::write("screen.mp4", video)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            ?duration,
+        )
+    )]
+    pub async fn screen_record(&mut self, duration: Duration) -> Result<Vec<u8>> {
+        let name = temp_name("screenrecord", "mp4");
+        let remote = format!("{}/{}", SCREENSHOT_DIR, name);
+        info!("Recording screen for {:?}", duration);
+
+        let started = self
+            .shell(&format!("{} start -p {}", SCREEN_RECORD_TOOL, remote))
+            .await?;
+        if screen_record_failed(&started) {
+            return Err(HdcError::CommandFailed(started.trim().to_string()));
+        }
+
+        let mut cleanup = RecordingCleanup {
+            client: Some(self.clone()),
+            remote: remote.clone(),
+            recording: true,
+        };
+        tokio::time::sleep(duration).await;
+        let stopped = self.shell(&format!("{} stop", SCREEN_RECORD_TOOL)).await;
+        cleanup.recording = false;
+        let stopped = stopped?;
+
+        let video = self.pull_and_remove(&remote, &name).await;
+        cleanup.client = None;
+        video.map_err(|e| {
+            debug!("Failed to pull screen recording: {}", e);
+            HdcError::CommandFailed(format!("{}{}", started, stopped).trim().to_string())
+        })
+    }
+
+    /// Record a system trace and return it in ftrace text format
+    ///
+    /// Runs `hitrace` (or `bytrace` on older devices) for `duration` with
+    /// the given categories, pulls the trace and removes both the remote
+    /// and the local temporary file. The result opens in Perfetto as is.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use hdc_rs::trace::TraceCategory;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let trace = client
+    ///     .capture_trace(&TraceCategory::app_defaults(), Duration::from_secs(10))
+    ///     .await?;
+    /// std::fs::write("app.ftrace", trace)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            ?duration,
+        )
+    )]
+    pub async fn capture_trace(
+        &mut self,
+        categories: &[crate::trace::TraceCategory],
+        duration: Duration,
+    ) -> Result<Vec<u8>> {
+        use crate::trace::{
+            is_failure, is_missing_tool, trace_command, BYTRACE, HITRACE, TRACE_DIR,
+        };
+
+        let name = temp_name("trace", "ftrace");
+        let remote = format!("{}/{}", TRACE_DIR, name);
+        info!("Capturing trace for {:?}", duration);
+
+        let mut output = String::new();
+        for tool in [HITRACE, BYTRACE] {
+            let cmd = trace_command(tool, categories, duration, &remote);
+            output = self
+                .shell_with_timeout(&cmd, duration + TRACE_GRACE)
+                .await?;
+            if !is_missing_tool(&output) {
+                break;
+            }
+            debug!("{} not available on the device", tool);
+        }
+        if is_missing_tool(&output) || is_failure(&output) {
+            return Err(HdcError::CommandFailed(output.trim().to_string()));
+        }
+
+        self.pull_and_remove(&remote, &name).await
+    }
+
+    /// Pull `remote` through a local temporary file named `name` and remove
+    /// both copies
+    async fn pull_and_remove(&mut self, remote: &str, name: &str) -> Result<Vec<u8>> {
+        let local = std::env::temp_dir().join(name);
+        let received = self
+            .file_recv(remote, &local.to_string_lossy(), FileTransferOptions::new())
+            .await;
+        if let Err(e) = self.shell(&format!("rm -f {}", remote)).await {
+            warn!("Failed to remove {}: {}", remote, e);
+        }
+        received?;
+
+        let data = std::fs::read(&local);
+        let _ = std::fs::remove_file(&local);
+        Ok(data?)
+    }
+
+    /// List processes running on the device (`ps -ef`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let running = client
+    ///     .processes()
+    ///     .await?
+    ///     .iter()
+    ///     .any(|p| p.command == "com.example.app");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn processes(&mut self) -> Result<Vec<crate::sysinfo::ProcessInfo>> {
+        let output = self.shell(crate::sysinfo::PS_COMMAND).await?;
+        Ok(crate::sysinfo::ProcessInfo::parse_list(&output))
+    }
+
+    /// Filesystem usage on the device (`df -k`)
+    pub async fn storage(&mut self) -> Result<Vec<crate::sysinfo::StorageInfo>> {
+        let output = self.shell(crate::sysinfo::DF_COMMAND).await?;
+        Ok(crate::sysinfo::StorageInfo::parse_list(&output))
+    }
+
+    /// Memory statistics of the device (`/proc/meminfo`)
+    pub async fn meminfo(&mut self) -> Result<crate::sysinfo::MemInfo> {
+        let output = self.shell(crate::sysinfo::MEMINFO_COMMAND).await?;
+        Ok(crate::sysinfo::MemInfo::parse(&output))
+    }
+
+    /// Battery level, charging state and temperature of the device
+    ///
+    /// Fails with [`HdcError::CommandFailed`] if the device reports no
+    /// battery.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let battery = client.battery().await?;
+    /// if battery.level < 30 && !battery.is_plugged() {
+    ///     println!("Charge the device before the soak test");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn battery(&mut self) -> Result<crate::sysinfo::BatteryInfo> {
+        let output = self.shell(crate::sysinfo::BATTERY_COMMAND).await?;
+        crate::sysinfo::BatteryInfo::parse(&output)
+            .ok_or_else(|| HdcError::CommandFailed(output.trim().to_string()))
+    }
+
+    /// Network interfaces with their addresses and counters (`ifconfig -a`)
+    pub async fn net_stats(&mut self) -> Result<Vec<crate::sysinfo::InterfaceStats>> {
+        let output = self.shell(crate::sysinfo::IFCONFIG_COMMAND).await?;
+        Ok(crate::sysinfo::InterfaceStats::parse_list(&output))
+    }
+
+    /// TCP and UDP sockets with their owning processes (`netstat -tuanp`)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let app_sockets: Vec<_> = client
+    ///     .connections()
+    ///     .await?
+    ///     .into_iter()
+    ///     .filter(|s| s.program.as_deref() == Some("com.example.app"))
+    ///     .collect();
+    /// assert!(app_sockets.iter().all(|s| s.remote_port() != Some(80)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connections(&mut self) -> Result<Vec<crate::sysinfo::SocketInfo>> {
+        let output = self.shell(crate::sysinfo::NETSTAT_COMMAND).await?;
+        Ok(crate::sysinfo::SocketInfo::parse_list(&output))
+    }
+
+    /// Wi-Fi state of the device: SSID, signal, link speed and IP address
+    ///
+    /// The address comes from the Wi-Fi service, or from `ifconfig` for
+    /// [`WIFI_INTERFACE`](crate::sysinfo::WIFI_INTERFACE) when the service
+    /// does not report it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let wifi = client.wifi_info().await?;
+    /// if let Some(addr) = wifi.tconn_addr(5555) {
+    ///     client.tmode_port(5555).await?;
+    ///     println!("hdc tconn {}", addr);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wifi_info(&mut self) -> Result<crate::sysinfo::WifiInfo> {
+        use crate::sysinfo::{WifiInfo, WIFI_COMMAND, WIFI_INTERFACE};
+
+        let output = self.shell(WIFI_COMMAND).await?;
+        let mut info = WifiInfo::parse(&HdcError::check_response(output)?);
+        if info.connected && info.ip.is_none() {
+            info.ip = self
+                .net_stats()
+                .await?
+                .into_iter()
+                .find(|interface| interface.name == WIFI_INTERFACE)
+                .and_then(|interface| interface.ipv4);
+        }
+        Ok(info)
+    }
+
+    /// SELinux enforcement mode of the device (`getenforce`)
+    pub async fn selinux_mode(&mut self) -> Result<crate::selinux::SelinuxMode> {
+        let output = self.shell(crate::selinux::GETENFORCE_COMMAND).await?;
+        HdcError::check_response(output)?.parse()
+    }
+
+    /// Recent SELinux `avc: denied` records from the kernel log and hilog
+    ///
+    /// A denial logged by both sources is returned once. Sources the shell
+    /// cannot read (e.g. `dmesg` without root) contribute nothing.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// # use hdc_rs::FileTransferOptions;
+    /// let options = FileTransferOptions::new();
+    /// if let Err(e) = client
+    ///     .file_send("config.json", "/data/local/tmp/config.json", options)
+    ///     .await
+    /// {
+    ///     for denial in client.avc_denials().await? {
+    ///         eprintln!("{} ({})", denial, denial.allow_rule());
+    ///     }
+    ///     return Err(e.into());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn avc_denials(&mut self) -> Result<Vec<crate::selinux::AvcDenial>> {
+        let mut output = String::new();
+        for command in crate::selinux::AVC_COMMANDS {
+            output.push_str(&self.shell(command).await?);
+            output.push('\n');
+        }
+        Ok(crate::selinux::AvcDenial::parse_list(&output))
+    }
+
+    /// Names of the bundles installed on the device (`bm dump -a`)
+    pub async fn list_bundles(&mut self) -> Result<Vec<String>> {
+        let output = self.shell(crate::sysinfo::BUNDLES_COMMAND).await?;
+        let output = HdcError::check_response(output)?;
+        Ok(crate::sysinfo::parse_bundles(&output))
+    }
+
+    /// Wait for any device to connect
+    ///
+    /// This command blocks until at least one device is connected.
+    /// If a device is already connected, it returns immediately.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// // Wait for any device
+    /// let device = client.wait_for_device().await?;
+    /// println!("Device connected: {}", device);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn wait_for_device(&mut self) -> Result<String> {
+        info!("Waiting for device...");
+        self.wait_for_device_within(None).await
+    }
+
+    /// Wait for any device to connect, giving up after `wait`
+    ///
+    /// Fails with [`HdcError::Timeout`] if no device shows up in time.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// let device = client.wait_for_device_timeout(Duration::from_secs(60)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn wait_for_device_timeout(&mut self, wait: Duration) -> Result<String> {
+        info!("Waiting for device...");
+        self.wait_for_device_within(Some(wait)).await
+    }
+
+    async fn wait_for_device_within(&mut self, wait: Option<Duration>) -> Result<String> {
+        let reply = Response::parse(self.request("wait", wait).await?).into_string()?;
+        let response = HdcError::check_response(reply)?;
+        debug!("Wait for device response: {}", response);
+
+        // Response format: "Wait for connected target is <device_id>"
+        if let Some(device_id) = response.split("is ").nth(1) {
+            Ok(device_id.trim().to_string())
+        } else {
+            // Fallback: just return the whole response
+            Ok(response.trim().to_string())
+        }
+    }
+
+    /// Monitor device list changes with a callback
+    ///
+    /// This function continuously polls the device list and calls the callback
+    /// when changes are detected. The polling interval can be configured.
+    ///
+    /// Note: HDC doesn't have a native "track-devices" command like adb,
+    /// so this implementation uses polling to detect changes. Each poll creates
+    /// a new connection to ensure reliability.
+    ///
+    /// # Arguments
+    /// * `interval` - Polling interval (recommended: 1-3 seconds)
+    /// * `callback` - Function called when device list changes. Return false to stop monitoring.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::HdcClient;
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// client.monitor_devices(Duration::from_secs(2), |devices| {
+    ///     println!("Device list changed:");
+    ///     for device in devices {
+    ///         println!("  - {}", device);
+    ///     }
+    ///     true // Continue monitoring
+    /// }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            connect_key = self.connect_key(),
+            channel_id = self.channel_id(),
+            command,
+            ?interval,
+        )
+    )]
+    pub async fn monitor_devices<F>(&mut self, interval: Duration, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&[String]) -> bool,
+    {
+        info!("Starting device monitoring");
+
+        let mut previous_devices: Vec<String> = Vec::new();
+
+        loop {
+            // Reconnect for each poll to ensure fresh connection
+            // HDC server closes connection after each request
+            if let Err(e) = self.connect_internal().await {
+                warn!("Failed to reconnect during monitoring: {:?}", e);
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+
+            // Get current device list
+            match self.list_targets().await {
+                Ok(devices) => {
+                    // Check if device list has changed
+                    if devices != previous_devices {
+                        debug!(
+                            "Device list changed: {:?} -> {:?}",
+                            previous_devices, devices
+                        );
+
+                        // Call user callback
+                        if !callback(&devices) {
+                            info!("Device monitoring stopped by callback");
+                            break;
+                        }
+
+                        previous_devices = devices;
+                    }
+                }
+                Err(e) => {
+                    warn!("Error listing devices during monitoring: {:?}", e);
+                    // Continue monitoring even if there's an error
+                }
+            }
+
+            // Wait before next poll
+            tokio::time::sleep(interval).await;
+        }
+
+        Ok(())
+    }
+
+    /// Stream device connects and disconnects
+    ///
+    /// Polls the device list every `interval` on a separate connection,
+    /// like [`monitor_devices`](Self::monitor_devices), and yields one
+    /// [`DeviceEvent`](crate::DeviceEvent) per change. Devices already
+    /// connected are reported first. Failed polls are skipped; dropping the
+    /// stream stops polling.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{DeviceEvent, HdcClient};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = HdcClient::lazy("127.0.0.1:8710");
+    /// let mut events = client.track_devices(Duration::from_secs(1));
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         DeviceEvent::Connected(id) => println!("+ {}", id),
+    ///         DeviceEvent::Disconnected(id) => println!("- {}", id),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn track_devices(&self, interval: Duration) -> crate::hilog::LogStream<crate::DeviceEvent> {
+        let mut client = self.sibling();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let mut previous = Vec::new();
+            let result = client
+                .monitor_devices(interval, |devices| {
+                    let events = crate::DeviceEvent::diff(&previous, devices);
+                    previous = devices.to_vec();
+                    events.into_iter().all(|event| tx.send(event).is_ok())
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("Device tracking ended with error: {}", e);
+            }
+        });
+        crate::hilog::LogStream::new(rx, vec![task])
+    }
+
+    /// Send file to device
+    ///
+    /// Transfer a file from local path to remote device path.
+    ///
+    /// # Arguments
+    /// * `local_path` - Local file path to send
+    /// * `remote_path` - Remote device path destination
+    /// * `options` - File transfer options (timestamp, sync, compress, etc.)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, FileTransferOptions};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let opts = FileTransferOptions::new()
+    ///     .hold_timestamp(true)
+    ///     .compress(true);
+    /// client.file_send("test.txt", "/data/local/tmp/test.txt", opts).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn file_send(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        info!("Sending file");
+        self.transfer(
+            FileTransferDirection::Send,
+            local_path,
+            remote_path,
+            options,
+            None::<&mut fn(&TransferProgress)>,
+        )
+        .await
+    }
+
+    /// Send file to device, reporting progress to `progress`
+    ///
+    /// `progress` is called every 250ms while the server works, and once
+    /// more with [`finished`](TransferProgress::finished) set on success.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, FileTransferOptions};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let opts = FileTransferOptions::new();
+    /// client
+    ///     .file_send_with_progress("big.bin", "/data/local/tmp/big.bin", opts, |p| {
+    ///         println!("{:?} after {:?}", p.fraction(), p.elapsed);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn file_send_with_progress<F>(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+        mut progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        info!("Sending file");
+        self.transfer(
+            FileTransferDirection::Send,
+            local_path,
+            remote_path,
+            options,
+            Some(&mut progress),
+        )
+        .await
+    }
+
+    /// Receive file from device
+    ///
+    /// Transfer a file from remote device path to local path.
+    ///
+    /// # Arguments
+    /// * `remote_path` - Remote device file path to receive
+    /// * `local_path` - Local destination path
+    /// * `options` - File transfer options (timestamp, sync, compress, etc.)
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, FileTransferOptions};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+    /// # client.connect_device("device_id").await?;
+    /// let opts = FileTransferOptions::new().hold_timestamp(true);
+    /// client.file_recv("/data/local/tmp/test.txt", "test.txt", opts).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn file_recv(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        info!("Receiving file");
+        self.transfer(
+            FileTransferDirection::Recv,
+            local_path,
+            remote_path,
+            options,
+            None::<&mut fn(&TransferProgress)>,
+        )
+        .await
+    }
+
+    /// Receive file from device, reporting progress to `progress`
+    ///
+    /// See [`file_send_with_progress`](Self::file_send_with_progress).
+    #[instrument(
+        skip_all,
+        fields(connect_key = self.connect_key(), channel_id = self.channel_id(), command)
+    )]
+    pub async fn file_recv_with_progress<F>(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+        mut progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        info!("Receiving file");
+        self.transfer(
+            FileTransferDirection::Recv,
+            local_path,
+            remote_path,
+            options,
+            Some(&mut progress),
+        )
+        .await
+    }
+
+    /// Run a file transfer and collect the server's messages about it
+    ///
+    /// Generic over the callback so transfers without one stay `Send`.
+    async fn transfer<P: FnMut(&TransferProgress)>(
+        &mut self,
+        direction: FileTransferDirection,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+        mut progress: Option<&mut P>,
+    ) -> Result<String> {
+        // Validate paths
+        if !crate::file::validate_path(local_path) || !crate::file::validate_path(remote_path) {
+            return Err(HdcError::Protocol("Invalid file path".to_string()));
+        }
+
+        // Build command
+        let (verb, from, to) = match direction {
+            FileTransferDirection::Send => ("send", local_path, remote_path),
+            FileTransferDirection::Recv => ("recv", remote_path, local_path),
+        };
+        let flags = options.for_server(self.features.as_ref()).to_flags();
+        let cmd = if flags.is_empty() {
+            format!("file {} {} {}", verb, from, to)
+        } else {
+            format!("file {} {} {} {}", verb, flags, from, to)
+        };
+
+        let started = Instant::now();
+        self.send_command(&cmd).await?;
+
+        // Read transfer responses
+        let mut output = String::new();
+        let mut last_reply = Instant::now();
+        loop {
+            let limit = self.config.timeouts.transfer;
+            let mut wait = limit.saturating_sub(last_reply.elapsed());
+            if progress.is_some() {
+                wait = wait.min(PROGRESS_INTERVAL);
+            }
+            match timeout(wait, self.read_response_string()).await {
+                Ok(Ok(resp)) => {
+                    if resp.is_empty() {
+                        break;
+                    }
+                    last_reply = Instant::now();
+                    output.push_str(&resp);
+
+                    // Check for completion indicators
+                    if resp.contains("FileTransfer finish")
+                        || resp.contains("Transfer finish")
+                        || resp.contains("[Fail]")
+                        || resp.contains("fail")
+                    {
+                        break;
+                    }
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) if last_reply.elapsed() < limit => {
+                    if let Some(progress) = progress.as_mut() {
+                        progress(&TransferProgress::measure(
+                            direction, local_path, started, false,
+                        ));
+                    }
+                }
+                Err(_) => {
+                    warn!("Timeout during file transfer");
+                    if output.is_empty() {
+                        return Err(HdcError::Timeout);
+                    }
+                    break;
+                }
+            }
+        }
+
+        debug!("File {} output: {} bytes", verb, output.len());
+        let output = HdcError::check_response(output)?;
+        if let Some(progress) = progress {
+            progress(&TransferProgress::measure(
+                direction, local_path, started, true,
+            ));
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::file_transferred(direction, local_path, started.elapsed());
+        Ok(output)
+    }
+}
+
+/// Extract the reason from a channel-close packet
+pub(crate) fn close_reason(message: &Response) -> String {
+    let text = message.text();
+    let reason = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if reason.is_empty() {
+        "no reason given".to_string()
+    } else {
+        reason.to_string()
+    }
+}
+
+/// Client with temporarily overridden time limits
+///
+/// Returned by [`HdcClient::with_timeout`]; use it like the client.
+pub struct TimeoutOverride<'a> {
+    client: &'a mut HdcClient,
+    saved: Timeouts,
+}
+
+impl std::ops::Deref for TimeoutOverride<'_> {
+    type Target = HdcClient;
+
+    fn deref(&self) -> &HdcClient {
+        self.client
+    }
+}
+
+impl std::ops::DerefMut for TimeoutOverride<'_> {
+    fn deref_mut(&mut self) -> &mut HdcClient {
+        self.client
+    }
+}
+
+impl Drop for TimeoutOverride<'_> {
+    fn drop(&mut self) {
+        self.client.config_mut().timeouts = self.saved;
+    }
+}
+
+/// Client with a temporarily overridden retry policy
+///
+/// Returned by [`HdcClient::with_retry`]; use it like the client.
+pub struct RetryOverride<'a> {
+    client: &'a mut HdcClient,
+    saved: Option<RetryPolicy>,
+}
+
+impl std::ops::Deref for RetryOverride<'_> {
+    type Target = HdcClient;
+
+    fn deref(&self) -> &HdcClient {
+        self.client
+    }
+}
+
+impl std::ops::DerefMut for RetryOverride<'_> {
+    fn deref_mut(&mut self) -> &mut HdcClient {
+        self.client
+    }
+}
+
+impl Drop for RetryOverride<'_> {
+    fn drop(&mut self) {
+        self.client.config_mut().retry_policy = self.saved;
+    }
+}
+
+impl Clone for HdcClient {
+    /// Handle with the same settings and device and its own channel
+    ///
+    /// The clone connects on first use and re-dials whenever its channel
+    /// has been consumed, like a [`DeviceClient`](crate::DeviceClient).
+    fn clone(&self) -> Self {
+        let mut client = self.sibling();
+        client.connect_key = self.connect_key.clone();
+        client.auto_reconnect = true;
+        client
+    }
+}
+
+/// Builder for [`HdcClient`], created with [`HdcClient::builder`]
+///
+/// Unset options keep the defaults of [`HdcClient::new`]; the address
+/// defaults to [`DEFAULT_SERVER_ADDR`].
+#[derive(Clone)]
+pub struct HdcClientBuilder {
+    config: ClientConfig,
+    device: Option<String>,
+    auto_reconnect: bool,
+}
+
+impl HdcClientBuilder {
+    /// Create a builder with default settings
+    pub fn new() -> Self {
+        Self {
+            config: ClientConfig::new(ServerAddr::from(DEFAULT_SERVER_ADDR)),
+            device: None,
+            auto_reconnect: false,
+        }
+    }
+
+    /// Server to connect to (`host:port`, `unix:` path or [`ServerAddr`])
+    pub fn address(mut self, address: impl Into<ServerAddr>) -> Self {
+        self.config.server = address.into();
+        self
+    }
+
+    /// Select a device, as with [`HdcClient::connect_device`]
+    pub fn device(mut self, device_id: impl Into<String>) -> Self {
+        self.device = Some(device_id.into());
+        self
+    }
+
+    /// Replace all time limits at once
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.config.timeouts = timeouts;
+        self
+    }
+
+    /// Time limit for opening a connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.connect = timeout;
+        self
+    }
+
+    /// Time limit for one-shot reads such as `hilog`
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.read = timeout;
+        self
+    }
+
+    /// Time limit for shell command output
+    pub fn shell_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.shell = timeout;
+        self
+    }
+
+    /// Time limit for progress during file transfers
+    pub fn transfer_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.transfer = timeout;
+        self
+    }
+
+    /// Time limit for progress during app installs
+    pub fn install_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.install = timeout;
+        self
+    }
+
+    /// Idle time before a heartbeat is sent on streaming commands
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.config.keepalive_interval = interval;
+        self
+    }
+
+    /// Notify `observer` of every packet sent or received
+    pub fn packet_observer(mut self, observer: Arc<dyn PacketObserver>) -> Self {
+        self.config.packet_observer = Some(observer);
+        self
+    }
+
+    /// Notify `observer` of every command, see [`HdcClient::set_command_observer`]
+    pub fn command_observer(mut self, observer: Arc<dyn CommandObserver>) -> Self {
+        self.config.command_observer = Some(observer);
+        self
+    }
+
+    /// Authenticate to secure daemons with the given host key
+    #[cfg(feature = "auth")]
+    pub fn auth(mut self, config: crate::auth::AuthConfig) -> Self {
+        self.config.auth = Some(config);
+        self
+    }
+
+    /// Select a device, or none for server-level commands
+    pub(crate) fn device_opt(mut self, device_id: Option<String>) -> Self {
+        self.device = device_id;
+        self
+    }
+
+    /// Re-open closed channels automatically, see [`HdcClient::set_auto_reconnect`]
+    pub fn auto_reconnect(mut self, enable: bool) -> Self {
+        self.auto_reconnect = enable;
+        self
+    }
+
+    /// Retry transient failures, see [`HdcClient::set_retry_policy`]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry_policy = Some(policy);
+        self
+    }
+
+    /// Connect and return the configured client
+    pub async fn build(self) -> Result<HdcClient> {
+        let mut client = HdcClient::with_config(Arc::new(self.config));
+        client.auto_reconnect = self.auto_reconnect;
+        match self.device {
+            Some(device) => client.connect_device(&device).await?,
+            None => client.connect_internal().await?,
+        }
+        Ok(client)
+    }
+
+    /// Return the configured client without connecting
+    ///
+    /// The client dials on first use and re-dials with the selected device
+    /// whenever its channel is closed, see [`HdcClient::lazy`].
+    pub fn build_lazy(self) -> HdcClient {
+        let mut client = HdcClient::with_config(Arc::new(self.config));
+        client.connect_key = self.device;
+        client.auto_reconnect = true;
+        client
+    }
+}
+
+impl Default for HdcClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compile the pattern of a log watch
+fn compile_log_pattern(pattern: &str) -> Result<regex::Regex> {
+    regex::Regex::new(pattern).map_err(|e| HdcError::Config(format!("Invalid pattern: {}", e)))
+}
+
+/// Whether `uitest screenRecord start` output shows the recorder did not start
+fn screen_record_failed(output: &str) -> bool {
+    let output = output.to_ascii_lowercase();
+    ["fail", "error", "not found", "no such file"]
+        .iter()
+        .any(|word| output.contains(word))
+}
+
+/// Stops a screen recording and removes its file if
+/// [`HdcClient::screen_record`] does not get to
+struct RecordingCleanup {
+    /// Client for the device, `None` once the recording was handled
+    client: Option<HdcClient>,
+    remote: String,
+    /// Whether the recorder may still be running
+    recording: bool,
+}
+
+impl Drop for RecordingCleanup {
+    fn drop(&mut self) {
+        let Some(mut client) = self.client.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            warn!(
+                "No runtime available, screen recording {} was not cleaned up",
+                self.remote
+            );
+            return;
+        };
+        let remote = std::mem::take(&mut self.remote);
+        let recording = self.recording;
+        debug!("Cleaning up screen recording: {}", remote);
+        handle.spawn(async move {
+            if recording {
+                if let Err(e) = client.shell(&format!("{} stop", SCREEN_RECORD_TOOL)).await {
+                    warn!("Failed to stop screen recording: {}", e);
+                }
+            }
+            if let Err(e) = client.shell(&format!("rm -f {}", remote)).await {
+                warn!("Failed to remove {}: {}", remote, e);
+            }
+        });
+    }
+}
+
+/// Unique file name for a temporary device file, e.g. a screenshot
+fn temp_name(kind: &str, extension: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "hdc_rs_{}_{}_{}.{}",
+        kind,
+        std::process::id(),
+        nanos,
+        extension
+    )
 }
 
 impl Drop for HdcClient {
     fn drop(&mut self) {
-        if self.stream.is_some() {
+        if self.channel.is_some() {
             debug!("HdcClient dropped, connection will be closed");
         }
     }
@@ -1013,7 +4290,1165 @@ mod tests {
     #[test]
     fn test_client_creation() {
         let client = HdcClient::new("127.0.0.1:8710");
-        assert_eq!(client.address, "127.0.0.1:8710");
+        assert_eq!(client.address().host_port(), "127.0.0.1:8710");
+        assert!(!client.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_screenshot() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when_prefix("snapshot_display -f ")
+            .reply("success: snapshot display 0, write to file as jpeg")
+            .when_command_prefix("file recv /data/local/tmp/hdc_rs_screenshot_")
+            .sends_file(b"\xff\xd8jpeg".to_vec());
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let jpeg = client.device("dev-1").screenshot().await.unwrap();
+        assert_eq!(jpeg, b"\xff\xd8jpeg");
+
+        let commands = phone.commands();
+        let remote = commands[0]
+            .strip_prefix("shell snapshot_display -f ")
+            .unwrap();
+        assert!(commands[1].starts_with(&format!("file recv {} ", remote)));
+        assert_eq!(commands[2], format!("shell rm -f {}", remote));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_screen_record() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when_prefix("uitest screenRecord ")
+            .reply("ScreenRecord done")
+            .when_command_prefix("file recv /data/local/tmp/hdc_rs_screenrecord_")
+            .sends_file(b"\0\0\0\x18ftypmp42".to_vec());
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let video = client
+            .device("dev-1")
+            .screen_record(Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(video, b"\0\0\0\x18ftypmp42");
+
+        let commands = phone.commands();
+        let remote = commands[0]
+            .strip_prefix("shell uitest screenRecord start -p ")
+            .unwrap();
+        assert!(remote.ends_with(".mp4"));
+        assert_eq!(commands[1], "shell uitest screenRecord stop");
+        assert!(commands[2].starts_with(&format!("file recv {} ", remote)));
+        assert_eq!(commands[3], format!("shell rm -f {}", remote));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_screen_record_failures() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when_prefix("uitest screenRecord start")
+            .reply("ScreenRecord start failed: recorder busy\n");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let result = timeout(
+            Duration::from_secs(1),
+            client
+                .device("dev-1")
+                .screen_record(Duration::from_secs(60)),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, Err(HdcError::CommandFailed(_))));
+        assert_eq!(phone.commands().len(), 1);
+
+        // Cancelled while recording: stop and remove in the background
+        let phone = FakeDevice::new("dev-1")
+            .when_prefix("uitest screenRecord ")
+            .reply("ScreenRecord done")
+            .when_prefix("rm -f ")
+            .reply("");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut device = client.device("dev-1");
+        let recording = timeout(
+            Duration::from_secs(1),
+            device.screen_record(Duration::from_secs(60)),
+        );
+        assert!(recording.await.is_err());
+        while phone.commands().len() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let commands = phone.commands();
+        let remote = commands[0]
+            .strip_prefix("shell uitest screenRecord start -p ")
+            .unwrap();
+        assert_eq!(commands[1], "shell uitest screenRecord stop");
+        assert_eq!(commands[2], format!("shell rm -f {}", remote));
+    }
+
+    #[tokio::test]
+    async fn test_dump_partition() {
+        use crate::partition::{DumpOptions, BLOCK_SIZE};
+        use crate::testing::{FakeDevice, MockServer};
+        use sha2::{Digest, Sha256};
+
+        let mut image = vec![0xaa; BLOCK_SIZE as usize];
+        image.extend_from_slice(b"tail bytes");
+        let hash = crate::partition::hex(&Sha256::digest(&image));
+
+        let chunk = BLOCK_SIZE as usize;
+        let phone = FakeDevice::new("dev-1")
+            .when("readlink -f /dev/block/by-name/misc")
+            .reply("/dev/block/sda5\n")
+            .when("blockdev --getsize64 /dev/block/sda5")
+            .reply(format!("{}\n", image.len()))
+            .when_prefix("dd ")
+            .reply("1+0 records in\n1+0 records out\n")
+            .when_command_prefix("file recv /data/local/tmp/misc.chunk ")
+            .sends_files([&image[..chunk], &image[chunk..]])
+            .when("sha256sum /dev/block/sda5")
+            .reply(format!("{}  /dev/block/sda5\n", hash));
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let dest = std::env::temp_dir().join(format!("hdc_rs_misc_{}.img", std::process::id()));
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut client = client.device("dev-1");
+        let options = DumpOptions::new().chunk_size(BLOCK_SIZE);
+        let mut finished = None;
+        let dump = client
+            .dump_partition_with_progress("misc", &dest, options, |p| {
+                if p.finished {
+                    finished = Some(p.bytes);
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(dump.device_path, "/dev/block/sda5");
+        assert_eq!(dump.size, image.len() as u64);
+        assert_eq!(dump.sha256.as_deref(), Some(hash.as_str()));
+        assert_eq!(finished, Some(image.len() as u64));
+        assert_eq!(std::fs::read(&dest).unwrap(), image);
+        std::fs::remove_file(&dest).unwrap();
+        let skips: Vec<String> = phone
+            .commands()
+            .iter()
+            .filter_map(|c| c.strip_prefix("shell dd "))
+            .filter_map(|dd| dd.split_whitespace().find(|arg| arg.starts_with("skip=")))
+            .map(str::to_string)
+            .collect();
+        assert_eq!(skips, ["skip=0", "skip=1"]);
+
+        assert!(matches!(
+            client
+                .dump_partition("../data", &dest, DumpOptions::new())
+                .await,
+            Err(HdcError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_capture_trace_falls_back_to_bytrace() {
+        use crate::testing::{FakeDevice, MockServer};
+        use crate::trace::TraceCategory;
+
+        let phone = FakeDevice::new("dev-1")
+            .when_prefix("hitrace ")
+            .reply("/bin/sh: hitrace: inaccessible or not found\n")
+            .when_prefix("bytrace ")
+            .reply("capture done\n")
+            .when_command_prefix("file recv /data/local/tmp/hdc_rs_trace_")
+            .sends_file("# tracer: nop\n");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let trace = client
+            .device("dev-1")
+            .capture_trace(
+                &[TraceCategory::Sched, TraceCategory::Ability],
+                Duration::from_secs(2),
+            )
+            .await
+            .unwrap();
+        assert_eq!(trace, b"# tracer: nop\n");
+
+        let commands = phone.commands();
+        let remote = commands[0]
+            .strip_prefix("shell hitrace -b 20480 -t 2 -o ")
+            .unwrap()
+            .strip_suffix(" sched ability")
+            .unwrap();
+        assert_eq!(
+            commands[1],
+            format!("shell bytrace -b 20480 -t 2 -o {} sched ability", remote)
+        );
+        assert!(commands[2].starts_with(&format!("file recv {} ", remote)));
+        assert_eq!(commands[3], format!("shell rm -f {}", remote));
+    }
+
+    #[tokio::test]
+    async fn test_collect_faultlogs() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let path = "/data/log/faultlog/faultlogger/cppcrash-com.example.app-20010043-20261017090000000.log";
+        let phone = FakeDevice::new("dev-1")
+            .when("find /data/log/faultlog -type f")
+            .reply(format!("{}\n/data/log/faultlog/hilog.000.gz\n", path))
+            .when(format!("cat '{}'", path))
+            .reply("Module name:com.example.app\nPid:4321\nReason:Signal:SIGSEGV\n");
+        let server = MockServer::new().device(phone).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let logs = client
+            .device("dev-1")
+            .collect_faultlogs(None)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].pid(), Some(4321));
+        assert_eq!(logs[0].reason(), Some("Signal:SIGSEGV"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watch_crashes() {
+        use crate::faultlog::FaultKind;
+        use crate::testing::{FakeDevice, MockServer};
+
+        let dir = "/data/log/faultlog/faultlogger";
+        let old = format!(
+            "{}/cppcrash-com.example.app-20010043-20261017090000000.log",
+            dir
+        );
+        let other = format!(
+            "{}/jscrash-com.other.app-20010044-20261017090100000.log",
+            dir
+        );
+        let freeze = format!(
+            "{}/appfreeze-com.example.app-20010043-20261017090200000.log",
+            dir
+        );
+        let phone = FakeDevice::new("dev-1")
+            .when("find /data/log/faultlog -type f")
+            .replies([
+                format!("{}\n", old),
+                format!("{}\n", old),
+                format!("{}\n{}\n{}\n", old, other, freeze),
+            ])
+            .when(format!("cat '{}'", freeze))
+            .reply("Module name:com.example.app\nPid:4321\nReason:THREAD_BLOCK_6S\n");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut crashes = client
+            .device("dev-1")
+            .watch_crashes("com.example.app")
+            .await
+            .unwrap();
+        let crash = crashes.next().await.unwrap();
+        assert_eq!(crash.kind, FaultKind::AppFreeze);
+        assert!(crash.is_freeze());
+        assert_eq!(crash.pid, Some(4321));
+        assert_eq!(crash.reason.as_deref(), Some("THREAD_BLOCK_6S"));
+        assert!(!phone
+            .commands()
+            .iter()
+            .any(|c| c.contains(&old) && c.starts_with("shell cat")));
+
+        phone.unplug();
+        assert!(crashes.next().await.is_none());
+    }
+
+    const OLD_READY: &str = "01-15 10:00:00.000  1  1 I A00001/Svc: READY";
+    const OLD_BUSY: &str = "01-15 10:00:01.000  1  1 I A00001/Svc: busy";
+    const NEW_READY: &str = "01-15 10:00:02.000  1  1 I A00001/Svc: READY again";
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hilog_watch() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when_command("hilog")
+            .reply(format!(
+                "{}\nnot a log line READY\n{}\n",
+                OLD_READY, OLD_BUSY
+            ));
+        let server = MockServer::new().device(phone).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let device = client.device("dev-1");
+        let mut stream = device
+            .hilog_watch(crate::hilog::HilogFilter::new(), "READY")
+            .await
+            .unwrap();
+        assert_eq!(stream.next().await.unwrap().message, "READY");
+        assert!(timeout(Duration::from_secs(5), stream.next())
+            .await
+            .is_err());
+
+        let result = device
+            .hilog_watch(crate::hilog::HilogFilter::new(), "(")
+            .await;
+        assert!(matches!(result, Err(HdcError::Config(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_log_skips_history() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when(format!("hilog -x -z {}", LOG_HISTORY_PROBE_LINES))
+            .reply(format!("{}\n{}\n", OLD_READY, OLD_BUSY))
+            .when_command("hilog")
+            .reply(format!("{}\n{}\n{}\n", OLD_READY, OLD_BUSY, NEW_READY));
+        let server = MockServer::new().device(phone).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let entry = client
+            .device("dev-1")
+            .wait_for_log("READY", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(entry.message, "READY again");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_log_timeout_and_stream_end() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let probe = format!("hilog -x -z {}", LOG_HISTORY_PROBE_LINES);
+        let quiet = FakeDevice::new("dev-1")
+            .when(&probe)
+            .reply(format!("{}\n", OLD_BUSY))
+            .when_command("hilog")
+            .reply(format!("{}\n{}\n", OLD_READY, OLD_BUSY));
+        let gone = FakeDevice::new("dev-2")
+            .when(&probe)
+            .reply("")
+            .when_command("hilog")
+            .reply("");
+        let server = MockServer::new()
+            .device(quiet)
+            .device(gone)
+            .spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let err = client
+            .device("dev-1")
+            .wait_for_log("READY", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Timeout));
+
+        let err = client
+            .device("dev-2")
+            .wait_for_log("READY", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Protocol(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sample_metrics() {
+        use crate::sampler::{MetricKind, CPU_STAT_COMMAND, RSS_COMMAND};
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when(CPU_STAT_COMMAND)
+            .replies([
+                "cpu  100 0 50 800 50 0 0 0 0 0\n",
+                "cpu  400 0 150 1300 150 0 0 0 0 0\n",
+                "cpu  400 0 150 2300 150 0 0 0 0 0\n",
+            ])
+            .when(RSS_COMMAND)
+            .replies([
+                "  RSS ARGS\n182040 com.example.app\n",
+                "  RSS ARGS\n 1824 init\n",
+            ]);
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let kinds = [
+            MetricKind::Cpu,
+            MetricKind::BundleRss("com.example.app".into()),
+        ];
+        let mut samples = client
+            .device("dev-1")
+            .sample_metrics(Duration::from_secs(1), &kinds)
+            .await
+            .unwrap();
+
+        let first = samples.next().await.unwrap();
+        assert_eq!(first.cpu_percent, Some(40.0));
+        assert_eq!(first.rss_kb["com.example.app"], 182040);
+        assert!(first.memory.is_none() && first.battery.is_none());
+
+        let second = samples.next().await.unwrap();
+        assert_eq!(second.cpu_percent, Some(0.0));
+        assert!(second.rss_kb.is_empty());
+
+        phone.unplug();
+        assert!(samples.next().await.is_none());
+        assert!(client
+            .device("dev-1")
+            .sample_metrics(Duration::ZERO, &kinds)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_on_devices() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1").when("uptime").reply("up 1 day\n");
+        let watch = FakeDevice::new("dev-2").when("uptime").reply("up 2 days\n");
+        let server = MockServer::new()
+            .device(phone.clone())
+            .device(watch)
+            .spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let results = client
+            .run_on_devices(
+                ["dev-2", "dev-1", "dev-3", "dev-2"],
+                0,
+                |mut device| async move {
+                    let output = HdcError::check_response(device.shell("uptime").await?)?;
+                    Ok(format!("{}: {}", device.device_id(), output.trim()))
+                },
+            )
+            .await;
+
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["dev-2", "dev-1", "dev-3"]);
+        assert_eq!(results[0].1.as_ref().unwrap(), "dev-2: up 2 days");
+        assert_eq!(results[1].1.as_ref().unwrap(), "dev-1: up 1 day");
+        assert!(results[2].1.is_err());
+        assert_eq!(phone.commands(), ["shell uptime"]);
+
+        let results: Vec<(String, Result<()>)> = client
+            .run_on_devices(["dev-1", "dev-2"], 2, |_| async { panic!("task bug") })
+            .await;
+        assert!(results
+            .iter()
+            .all(|(_, result)| matches!(result, Err(HdcError::CommandFailed(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_wifi_info_falls_back_to_ifconfig() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when(crate::sysinfo::WIFI_COMMAND)
+            .reply("WiFi active state: activated\nWiFi connection status: connected\n  Connection.ssid: lab\n")
+            .when(crate::sysinfo::IFCONFIG_COMMAND)
+            .reply("wlan0     Link encap:Ethernet  HWaddr 8c:aa:ce:01:02:03\n          inet addr:10.0.0.7  Mask:255.255.255.0\n");
+        let server = MockServer::new().device(phone).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let wifi = client.device("dev-1").wifi_info().await.unwrap();
+        assert_eq!(wifi.ssid.as_deref(), Some("lab"));
+        assert_eq!(wifi.tconn_addr(5555).as_deref(), Some("10.0.0.7:5555"));
+    }
+
+    #[tokio::test]
+    async fn test_avc_denials_without_dmesg_access() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when(crate::selinux::GETENFORCE_COMMAND)
+            .reply("Enforcing\n")
+            .when(crate::selinux::AVC_COMMANDS[0])
+            .reply("dmesg: klogctl: Operation not permitted\n")
+            .when(crate::selinux::AVC_COMMANDS[1])
+            .reply("10-17 09:30:12.120  612  612 W C02500/kernel: avc:  denied  { write } for  pid=612 comm=\"hdcd\" name=\"tmp\" scontext=u:r:hdcd:s0 tcontext=u:object_r:data_local_tmp:s0 tclass=dir permissive=0\n");
+        let server = MockServer::new().device(phone).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut device = client.device("dev-1");
+        assert_eq!(
+            device.selinux_mode().await.unwrap(),
+            crate::selinux::SelinuxMode::Enforcing
+        );
+        let denials = device.avc_denials().await.unwrap();
+        assert_eq!(denials.len(), 1);
+        assert_eq!(denials[0].comm.as_deref(), Some("hdcd"));
+        assert_eq!(denials[0].permissions, ["write"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_targets_verbose() {
+        let server = crate::testing::MockServer::new()
+            .respond(
+                "list targets -v",
+                "device-1\t\tUSB\tConnected\tNOH-AN00\nbad\n127.0.0.1:5555\t\tTCP\tOffline\t\n",
+            )
+            .spawn()
+            .await
+            .unwrap();
+
+        let mut client = HdcClient::connect(server.address()).await.unwrap();
+        let devices = client.list_targets_verbose().await.unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].connect_key, "device-1");
+        assert_eq!(devices[0].model, "NOH-AN00");
+        assert_eq!(devices[1].connection_type, "TCP");
+        assert_eq!(devices[1].state, "Offline");
+    }
+
+    #[tokio::test]
+    async fn test_app_lifecycle() {
+        let server = crate::testing::MockServer::new()
+            .respond(
+                "shell aa start -a EntryAbility -b com.example.app",
+                "start ability successfully.\n",
+            )
+            .respond(
+                "shell aa force-stop com.example.missing",
+                "error: failed to force stop application.\n",
+            )
+            .spawn()
+            .await
+            .unwrap();
+
+        let mut client = HdcClient::connect(server.address()).await.unwrap();
+        client
+            .start_app("com.example.app", "EntryAbility")
+            .await
+            .unwrap();
+
+        let mut client = HdcClient::connect(server.address()).await.unwrap();
+        let err = client.stop_app("com.example.missing").await.unwrap_err();
+        assert!(matches!(err, HdcError::CommandFailed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_reboot_and_wait_for_boot() {
+        let (listener, address) = mock_server().await;
+        let server = tokio::spawn(async move {
+            let mut conn = crate::testing::MockConnection::accept(&listener)
+                .await
+                .unwrap();
+            // The device goes away without answering
+            assert_eq!(conn.read_command().await.unwrap(), "target boot -recovery");
+            drop(conn);
+
+            // Each check re-dials the device
+            for completed in ["false", "true"] {
+                let mut conn = crate::testing::MockConnection::accept(&listener)
+                    .await
+                    .unwrap();
+                assert_eq!(conn.connect_key(), "dev-1");
+                assert_eq!(
+                    conn.read_command().await.unwrap(),
+                    "shell param get bootevent.boot.completed"
+                );
+                conn.send(completed.as_bytes()).await.unwrap();
+            }
+        });
+
+        let mut client = HdcClient::builder()
+            .address(address)
+            .device("dev-1")
+            .build()
+            .await
+            .unwrap();
+        client.reboot(crate::RebootMode::Recovery).await.unwrap();
+        client.wait_for_boot(Duration::from_secs(10)).await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flash() {
+        use crate::flash::FlashOperation;
+
+        let (listener, address) = mock_server().await;
+        let server = tokio::spawn(async move {
+            let mut conn = crate::testing::MockConnection::accept(&listener)
+                .await
+                .unwrap();
+            assert_eq!(
+                conn.read_command().await.unwrap(),
+                "flash -f boot_linux boot_linux.img"
+            );
+            conn.send(b"[Info]Progress: 40%\n").await.unwrap();
+            conn.send(b"[Info]Verify image success\n").await.unwrap();
+            conn.send(b"[Info]Flash partition boot_linux failsafe copy\n")
+                .await
+                .unwrap();
+            conn.send(b"Flash partition boot_linux success\n")
+                .await
+                .unwrap();
+
+            let mut conn = crate::testing::MockConnection::accept(&listener)
+                .await
+                .unwrap();
+            assert_eq!(conn.read_command().await.unwrap(), "erase -f vendor");
+            conn.send(b"[Fail]Device is not in flashd mode\n")
+                .await
+                .unwrap();
+        });
+
+        let mut client = HdcClient::builder()
+            .address(address.clone())
+            .device("dev-1")
+            .build()
+            .await
+            .unwrap();
+        let mut reports = Vec::new();
+        let output = client
+            .flash_with_progress(FlashOperation::flash("boot_linux", "boot_linux.img"), |p| {
+                reports.push((p.stage.clone(), p.percent, p.finished))
+            })
+            .await
+            .unwrap();
+        assert!(output.contains("Progress: 40%"));
+        assert_eq!(
+            reports,
+            [
+                ("Progress".to_string(), Some(40), false),
+                ("Verify image success".to_string(), None, false),
+                (
+                    "Flash partition boot_linux failsafe copy".to_string(),
+                    None,
+                    false
+                ),
+                (
+                    "Flash partition boot_linux success".to_string(),
+                    None,
+                    false
+                ),
+                (
+                    "Flash partition boot_linux success".to_string(),
+                    Some(100),
+                    true
+                ),
+            ]
+        );
+
+        let mut client = HdcClient::builder()
+            .address(address)
+            .device("dev-1")
+            .build()
+            .await
+            .unwrap();
+        let err = client
+            .flash(FlashOperation::erase("vendor"))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, HdcError::Remote { ref message, .. } if message == "Device is not in flashd mode")
+        );
+        assert!(matches!(
+            client.flash(FlashOperation::erase("")).await,
+            Err(HdcError::Config(_))
+        ));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sideload() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let package =
+            std::env::temp_dir().join(format!("hdc_rs_sideload_{}.zip", std::process::id()));
+        std::fs::write(&package, b"PK").unwrap();
+        let phone = FakeDevice::new("dev-1")
+            .when_command(format!("sideload {}", package.display()))
+            .reply("[Info]Sideload finish\n");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut device = client.device("dev-1");
+        let output = device.sideload(&package).await.unwrap();
+        assert!(output.contains("finish"));
+
+        std::fs::remove_file(&package).unwrap();
+        assert!(matches!(
+            device.sideload(&package).await,
+            Err(HdcError::Config(_))
+        ));
+        assert_eq!(phone.commands().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_apply_ota() {
+        use crate::ota::OtaOptions;
+        use crate::testing::{FakeDevice, MockServer};
+
+        let package = std::env::temp_dir().join(format!("hdc_rs_ota_{}.zip", std::process::id()));
+        std::fs::write(&package, b"PK").unwrap();
+        let options = OtaOptions::new().wait_for_boot(Duration::from_secs(60));
+        let phone = FakeDevice::new("dev-1")
+            .when("mkdir -p /data/updater")
+            .reply("")
+            .when_command(format!(
+                "file send {} /data/updater/updater.zip",
+                package.display()
+            ))
+            .reply("FileTransfer finish, Size:2, File count = 1, time:1ms rate:2kB/s\n")
+            .when(options.trigger_command())
+            .reply("")
+            .when(BOOT_COMPLETED_COMMAND)
+            .replies(["true\n", "false\n", "false\n", "true\n"]);
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut stages = Vec::new();
+        client
+            .device("dev-1")
+            .apply_ota_with_progress(&package, options, |p| {
+                if stages.last() != Some(&p.stage) {
+                    stages.push(p.stage.clone());
+                }
+                if p.finished {
+                    assert_eq!(p.fraction(), Some(1.0));
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(stages, crate::ota::OTA_STAGES);
+        let polls = phone
+            .commands()
+            .iter()
+            .filter(|c| c.ends_with(BOOT_COMPLETED_COMMAND))
+            .count();
+        assert_eq!(polls, 4);
+
+        std::fs::remove_file(&package).unwrap();
+        let err = client
+            .device("dev-1")
+            .apply_ota(&package, OtaOptions::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_builder() {
+        let server = crate::testing::MockServer::new()
+            .respond("list targets", "device-1\n")
+            .spawn()
+            .await
+            .unwrap();
+
+        let mut client = HdcClient::builder()
+            .address(server.address())
+            .shell_timeout(Duration::from_secs(42))
+            .auto_reconnect(true)
+            .build()
+            .await
+            .unwrap();
+        assert!(client.is_connected());
+        assert_eq!(client.timeouts().shell, Duration::from_secs(42));
+        assert_eq!(client.timeouts().connect, Timeouts::default().connect);
+
+        // A closed channel is re-opened by the next command
+        client.close().await.unwrap();
+        assert_eq!(client.list_targets().await.unwrap(), vec!["device-1"]);
+
+        client.set_auto_reconnect(false);
+        client.close().await.unwrap();
+        assert!(matches!(
+            client.list_targets().await,
+            Err(HdcError::NotConnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_sends_channel_close() {
+        use crate::testing::MockConnection;
+
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let close = HdcCommand::KernelChannelClose.as_u16().to_le_bytes();
+
+            // Pending output is drained until the server acknowledges
+            let mut conn = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(conn.read_packet().await.unwrap(), [close[0], close[1], 1]);
+            conn.send(b"late output").await.unwrap();
+            conn.send(&close).await.unwrap();
+
+            // A server that never answers
+            let mut conn = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(conn.read_packet().await.unwrap(), [close[0], close[1], 1]);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut client = HdcClient::connect(&address).await.unwrap();
+        client.shutdown(Duration::from_secs(5)).await.unwrap();
+        assert!(!client.is_connected());
+
+        let mut client = HdcClient::connect(&address).await.unwrap();
+        assert!(matches!(
+            client.shutdown(Duration::from_millis(50)).await,
+            Err(HdcError::Timeout)
+        ));
+        assert!(!client.is_connected());
+        // Nothing left to close
+        client.close().await.unwrap();
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_lazy_client() {
+        let (listener, address) = mock_server().await;
+
+        // Nothing is dialed until the first command
+        let mut client = HdcClient::builder()
+            .address(address.as_str())
+            .device("dev-1")
+            .build_lazy();
+        assert!(!client.is_connected());
+        assert_eq!(client.connect_key(), Some("dev-1"));
+
+        let server = tokio::spawn(async move {
+            // The server closes each channel after one command
+            for _ in 0..2 {
+                let mut conn = crate::testing::MockConnection::accept(&listener)
+                    .await
+                    .unwrap();
+                assert_eq!(conn.connect_key(), "dev-1");
+                assert_eq!(conn.read_command().await.unwrap(), "shell echo hi");
+                conn.send(b"hi\n").await.unwrap();
+            }
+        });
+        assert_eq!(client.shell("echo hi").await.unwrap(), "hi\n");
+        assert_eq!(client.shell("echo hi").await.unwrap(), "hi\n");
+        server.await.unwrap();
+
+        let mut client = HdcClient::lazy(address.as_str());
+        assert!(matches!(client.list_targets().await, Err(HdcError::Io(_))));
+    }
+
+    async fn read_frame(stream: &mut tokio::net::TcpStream) -> Vec<u8> {
+        HdcChannelCodec::new()
+            .read_packet(stream)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    /// Accept one client and complete the channel handshake
+    async fn accept_handshake(listener: tokio::net::TcpListener) -> tokio::net::TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut handshake = crate::protocol::ChannelHandShake::default();
+        handshake.banner[..8].copy_from_slice(b"OHOS HDC");
+        handshake.set_channel_id(1);
+        handshake.set_version("Ver: 3.1.0e");
+        HdcChannelCodec::new()
+            .write_packet(&mut stream, &handshake.to_bytes())
+            .await
+            .unwrap();
+        read_frame(&mut stream).await;
+        stream
+    }
+
+    async fn mock_server() -> (tokio::net::TcpListener, String) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        (listener, address)
+    }
+
+    #[tokio::test]
+    async fn test_hilog_stream_survives_idle() {
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept_handshake(listener).await;
+            let codec = HdcChannelCodec::new();
+
+            assert_eq!(read_frame(&mut stream).await, b"hilog");
+            let keepalive = HdcCommand::KernelEnableKeepalive.as_u16().to_le_bytes();
+            assert_eq!(read_frame(&mut stream).await, keepalive);
+
+            // Stay silent until the client sends a heartbeat
+            let heartbeat = HdcCommand::HeartbeatMsg.as_u16().to_le_bytes();
+            assert_eq!(read_frame(&mut stream).await, heartbeat);
+            codec.write_packet(&mut stream, &heartbeat).await.unwrap();
+            codec
+                .write_packet(&mut stream, b"late line\n")
+                .await
+                .unwrap();
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        client.set_keepalive_interval(Duration::from_millis(50));
+
+        let mut chunks = Vec::new();
+        let result = client
+            .hilog_stream(None, |chunk| {
+                chunks.push(chunk.to_string());
+                true
+            })
+            .await;
+
+        server.await.unwrap();
+        assert!(matches!(result, Err(HdcError::ChannelClosed { .. })));
+        assert_eq!(chunks, vec!["late line\n".to_string()]);
+        assert!(client.last_heartbeat().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_server_heartbeat_is_answered() {
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept_handshake(listener).await;
+            let codec = HdcChannelCodec::new();
+            let heartbeat = HdcCommand::HeartbeatMsg.as_u16().to_le_bytes();
+
+            assert_eq!(read_frame(&mut stream).await, b"version");
+            codec.write_packet(&mut stream, &heartbeat).await.unwrap();
+            assert_eq!(read_frame(&mut stream).await, heartbeat);
+            codec
+                .write_packet(&mut stream, b"Ver: 3.1.0a")
+                .await
+                .unwrap();
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        let features = client.server_features().unwrap();
+        assert_eq!(features.version_string.as_deref(), Some("Ver: 3.1.0e"));
+        assert!(features.heartbeat());
+        assert!(client.last_heartbeat().is_none());
+        client.send_command("version").await.unwrap();
+        let resp = client.read_response_string().await.unwrap();
+
+        server.await.unwrap();
+        assert_eq!(resp, "Ver: 3.1.0a");
+        assert!(client.last_heartbeat().is_some());
+        assert!(client.last_activity() >= client.last_heartbeat());
+    }
+
+    #[tokio::test]
+    async fn test_channel_close_packet() {
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept_handshake(listener).await;
+            let mut close = HdcCommand::KernelChannelClose
+                .as_u16()
+                .to_le_bytes()
+                .to_vec();
+            close.extend_from_slice(b"device offline\0");
+            read_frame(&mut stream).await;
+            HdcChannelCodec::new()
+                .write_packet(&mut stream, &close)
+                .await
+                .unwrap();
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        client.send_command("shell ls").await.unwrap();
+        let err = client.read_response().await.unwrap_err();
+        server.await.unwrap();
+
+        match err {
+            HdcError::ChannelClosed { reason } => assert_eq!(reason, "device offline"),
+            other => panic!("unexpected error: {:?}", other),
+        }
         assert!(!client.is_connected());
     }
+
+    #[tokio::test]
+    async fn test_shell_timeout_is_configurable() {
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept_handshake(listener).await;
+            assert_eq!(read_frame(&mut stream).await, b"shell sleep 100");
+            // Never answer; hold the connection until the client gives up
+            let _ = HdcChannelCodec::new().read_packet(&mut stream).await;
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        client.set_timeouts(Timeouts::default().shell(Duration::from_millis(50)));
+        let started = Instant::now();
+        let err = client.shell("sleep 100").await.unwrap_err();
+        assert!(matches!(err, HdcError::Timeout));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        drop(client);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auto_reconnect_retries_once() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            use crate::testing::MockConnection;
+            // Server dies after the handshake
+            let first = MockConnection::accept(&listener).await.unwrap();
+            drop(first);
+
+            // Same connect key on the new channel, query sent again
+            let mut second = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(second.connect_key(), "dev-1");
+            assert_eq!(second.read_command().await.unwrap(), "list targets");
+            second.send(b"dev-1").await.unwrap();
+
+            // A shell command that may have run is not sent again
+            assert_eq!(second.read_command().await.unwrap(), "shell id");
+            drop(second);
+
+            // Without auto-reconnect the error is surfaced
+            drop(MockConnection::accept(&listener).await.unwrap());
+        });
+
+        let mut client = HdcClient::new(address);
+        client.set_auto_reconnect(true);
+        client.connect_device("dev-1").await.unwrap();
+        assert_eq!(client.list_targets().await.unwrap(), ["dev-1"]);
+        let err = client.shell("id").await.unwrap_err();
+        assert!(
+            err.is_connection_lost() || matches!(err, HdcError::ChannelClosed { .. }),
+            "{:?}",
+            err
+        );
+
+        client.set_auto_reconnect(false);
+        client.connect_device("dev-1").await.unwrap();
+        let err = client.list_targets().await.unwrap_err();
+        assert!(
+            err.is_connection_lost() || matches!(err, HdcError::ChannelClosed { .. }),
+            "{:?}",
+            err
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_retries_timeouts() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            use crate::testing::MockConnection;
+            // First attempt never gets an answer
+            let mut first = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(first.read_command().await.unwrap(), "shell date");
+            let mut second = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(second.read_command().await.unwrap(), "shell date");
+            second.send(b"today").await.unwrap();
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        let policy = crate::RetryPolicy::new(2).backoff(crate::Backoff::NONE);
+        let output = client
+            .with_retry(policy)
+            .shell_with_timeout("date", Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert_eq!(output, "today");
+        assert!(client.retry_policy().is_none());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_settings() {
+        let server = crate::testing::MockServer::new()
+            .respond("shell echo hi", "hi\n")
+            .spawn()
+            .await
+            .unwrap();
+        let mut client = HdcClient::builder()
+            .address(server.address())
+            .device("dev-1")
+            .build()
+            .await
+            .unwrap();
+
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let mut client = client.clone();
+                tokio::spawn(async move { client.shell("echo hi").await })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), "hi\n");
+        }
+
+        let clone = client.clone();
+        assert!(Arc::ptr_eq(&client.config, &clone.config));
+        assert_eq!(clone.connect_key(), Some("dev-1"));
+        assert!(!clone.is_connected());
+
+        // Changing a setting detaches it from the clones
+        client.set_timeouts(Timeouts::uniform(Duration::from_secs(1)));
+        assert!(!Arc::ptr_eq(&client.config, &clone.config));
+        assert_eq!(*clone.timeouts(), Timeouts::default());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_override() {
+        let (listener, address) = mock_server().await;
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept_handshake(listener).await;
+            assert_eq!(read_frame(&mut stream).await, b"shell sleep 100");
+            let _ = HdcChannelCodec::new().read_packet(&mut stream).await;
+        });
+
+        let mut client = HdcClient::connect(address).await.unwrap();
+        let started = Instant::now();
+        let err = client
+            .shell_with_timeout("sleep 100", Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Timeout));
+        assert!(started.elapsed() < Timeouts::default().shell);
+        assert_eq!(*client.timeouts(), Timeouts::default());
+
+        drop(client);
+        server.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeouts_on_paused_clock() {
+        use crate::testing::MockServer;
+
+        let server = MockServer::new()
+            .ignore("shell sleep 100")
+            .ignore("file send Cargo.toml /data/local/tmp/x")
+            .spawn_in_memory();
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        client.set_timeouts(Timeouts::default().shell(Duration::from_secs(30)));
+
+        // Each attempt waits the full shell timeout, then backs off
+        client.set_retry_policy(Some(RetryPolicy::new(3)));
+        let started = Instant::now();
+        let err = client.shell("sleep 100").await.unwrap_err();
+        assert!(matches!(err, HdcError::Timeout));
+        assert_eq!(
+            started.elapsed(),
+            Duration::from_secs(90) + Duration::from_millis(200 + 400)
+        );
+
+        // Silence ends a transfer once the transfer timeout passes
+        client.set_retry_policy(None);
+        client.reconnect().await.unwrap();
+        let mut reports = 0;
+        let started = Instant::now();
+        let err = client
+            .file_send_with_progress(
+                "Cargo.toml",
+                "/data/local/tmp/x",
+                FileTransferOptions::new(),
+                |_| reports += 1,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::Timeout));
+        assert_eq!(started.elapsed(), Timeouts::default().transfer);
+        assert_eq!(reports, 239);
+    }
 }
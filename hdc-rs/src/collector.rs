@@ -0,0 +1,139 @@
+//! Aggregated log collection across several devices
+//!
+//! [`MultiLogCollector`] opens one hilog stream per device and merges them
+//! into a single [`LogStream`] of `(device_id, entry)` pairs, which makes it
+//! easy to correlate logs from companion devices (e.g. phone + wearable).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::collector::MultiLogCollector;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut logs = MultiLogCollector::new("127.0.0.1:8710")
+//!     .device("phone-serial")
+//!     .device("watch-serial")
+//!     .filter("-L E")
+//!     .start()
+//!     .await?;
+//!
+//! while let Some((device, entry)) = logs.next().await {
+//!     println!("[{}] {}", device, entry);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use tracing::{info, warn};
+
+use crate::client::HdcClient;
+use crate::error::Result;
+use crate::hilog::{HilogFilter, LogEntry, LogStream};
+use crate::transport::ServerAddr;
+
+/// Collects hilog entries from several devices into one stream
+#[derive(Debug, Clone)]
+pub struct MultiLogCollector {
+    address: ServerAddr,
+    devices: Vec<String>,
+    filter: HilogFilter,
+}
+
+impl MultiLogCollector {
+    /// Create a collector using the HDC server at `address`
+    pub fn new(address: impl Into<ServerAddr>) -> Self {
+        Self {
+            address: address.into(),
+            devices: Vec::new(),
+            filter: HilogFilter::new(),
+        }
+    }
+
+    /// Add a device to collect from
+    pub fn device(mut self, device_id: impl Into<String>) -> Self {
+        let device_id = device_id.into();
+        if !self.devices.contains(&device_id) {
+            self.devices.push(device_id);
+        }
+        self
+    }
+
+    /// Add several devices to collect from
+    pub fn devices<I, S>(self, device_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        device_ids.into_iter().fold(self, Self::device)
+    }
+
+    /// Set the hilog filter applied on every device
+    pub fn filter(mut self, filter: impl Into<HilogFilter>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Devices this collector reads from
+    pub fn device_ids(&self) -> &[String] {
+        &self.devices
+    }
+
+    /// Connect to every device and start streaming
+    ///
+    /// Fails if any device cannot be connected. Once started, a device whose
+    /// stream ends or errors simply stops contributing; the merged stream ends
+    /// when all devices have stopped. Must be called within a Tokio runtime.
+    pub async fn start(self) -> Result<LogStream<(String, LogEntry)>> {
+        info!("Starting log collection on {} devices", self.devices.len());
+
+        let mut clients = Vec::with_capacity(self.devices.len());
+        for device_id in &self.devices {
+            let mut client = HdcClient::connect(&self.address).await?;
+            client.connect_device(device_id).await?;
+            clients.push((device_id.clone(), client));
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let tasks = clients
+            .into_iter()
+            .map(|(device_id, mut client)| {
+                let tx = tx.clone();
+                let filter = self.filter.clone();
+                tokio::spawn(async move {
+                    let result = client
+                        .hilog_entries_stream_filtered(filter, |entry| {
+                            tx.send((device_id.clone(), entry)).is_ok()
+                        })
+                        .await;
+                    if let Err(e) = result {
+                        warn!("Log collection on {} ended with error: {}", device_id, e);
+                    }
+                })
+            })
+            .collect();
+
+        Ok(LogStream::new(rx, tasks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_dedup() {
+        let collector = MultiLogCollector::new("127.0.0.1:8710")
+            .device("phone")
+            .devices(["watch", "phone"]);
+        assert_eq!(collector.device_ids(), ["phone", "watch"]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_collector_ends() {
+        let mut logs = MultiLogCollector::new("127.0.0.1:8710")
+            .start()
+            .await
+            .unwrap();
+        assert!(logs.next().await.is_none());
+    }
+}
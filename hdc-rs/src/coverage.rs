@@ -0,0 +1,464 @@
+//! Coverage artifact collection
+//!
+//! Instrumented test runs leave their coverage output in the sandbox of the
+//! app under test. [`HdcClient::collect_coverage`](crate::HdcClient::collect_coverage)
+//! finds the files, pulls them with debug-dir transfers (`file recv -b`)
+//! into a local tree that mirrors the sandbox, and merges every LCOV
+//! tracefile into one `merged.info` with [`merge_lcov`].
+//!
+//! | Output | Default pattern | Handling |
+//! |--------|-----------------|----------|
+//! | gcov counters | `*.gcda` | pulled as is, for `gcov`/`llvm-cov` next to the `.gcno` files |
+//! | LCOV tracefiles | `*.info` | pulled and merged |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::coverage::CoverageOptions;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let coverage = client
+//!     .collect_coverage(CoverageOptions::new("com.example.app").local_dir("coverage"))
+//!     .await?;
+//! if let Some(merged) = coverage.merged {
+//!     println!("genhtml {}", merged.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::shell;
+
+/// Sandbox base directory as seen by the app and by `file recv -b`
+pub const SANDBOX_BASE: &str = "/data/storage/el2/base";
+
+/// File the merged LCOV tracefiles are written to, inside the local directory
+pub const MERGED_LCOV: &str = "merged.info";
+
+/// Longest `rm` command sent when clearing pulled files
+const MAX_RM_COMMAND_LEN: usize = 4096;
+
+/// Options for [`HdcClient::collect_coverage`](crate::HdcClient::collect_coverage)
+#[derive(Debug, Clone)]
+pub struct CoverageOptions {
+    bundle: String,
+    user: u32,
+    dir: String,
+    patterns: Vec<String>,
+    local_dir: PathBuf,
+    clear: bool,
+}
+
+impl CoverageOptions {
+    /// Collect the coverage output of a debuggable bundle
+    pub fn new(bundle: impl Into<String>) -> Self {
+        Self {
+            bundle: bundle.into(),
+            user: 100,
+            dir: String::new(),
+            patterns: Vec::new(),
+            local_dir: PathBuf::from("coverage"),
+            clear: false,
+        }
+    }
+
+    /// OS account the app runs as (default 100)
+    pub fn user(mut self, user: u32) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Only search this directory, relative to the sandbox base (e.g. `files`)
+    pub fn dir(mut self, dir: impl Into<String>) -> Self {
+        self.dir = dir.into().trim_matches('/').to_string();
+        self
+    }
+
+    /// File name pattern to collect, e.g. `*.json`; may be repeated and
+    /// replaces the defaults (`*.gcda`, `*.info`)
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Host directory to pull into (default `coverage`)
+    pub fn local_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.local_dir = dir.into();
+        self
+    }
+
+    /// Delete the files on the device once pulled, ready for the next run
+    pub fn clear(mut self, clear: bool) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    /// Bundle whose sandbox is searched
+    pub fn bundle_name(&self) -> &str {
+        &self.bundle
+    }
+
+    /// Whether pulled files are deleted from the device
+    pub fn clear_after(&self) -> bool {
+        self.clear
+    }
+
+    /// Host directory files are pulled into
+    pub fn local_path(&self) -> &std::path::Path {
+        &self.local_dir
+    }
+
+    /// Directory holding the bundle's sandbox base on the device
+    pub fn device_base(&self) -> String {
+        format!("/data/app/el2/{}/base/{}", self.user, self.bundle)
+    }
+
+    /// Shell command listing the coverage files on the device
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::coverage::CoverageOptions;
+    ///
+    /// let options = CoverageOptions::new("com.example.app").dir("files");
+    /// assert_eq!(
+    ///     options.find_command(),
+    ///     "find /data/app/el2/100/base/com.example.app/files -type f \
+    ///      \\( -name '*.gcda' -o -name '*.info' \\)"
+    /// );
+    /// ```
+    pub fn find_command(&self) -> String {
+        let mut root = self.device_base();
+        if !self.dir.is_empty() {
+            root = format!("{}/{}", root, self.dir);
+        }
+        let patterns: Vec<String> = if self.patterns.is_empty() {
+            vec!["*.gcda".to_string(), "*.info".to_string()]
+        } else {
+            self.patterns.clone()
+        };
+        let names: Vec<String> = patterns
+            .iter()
+            .map(|p| format!("-name {}", shell::quote(p)))
+            .collect();
+        format!("find {} -type f \\( {} \\)", root, names.join(" -o "))
+    }
+
+    /// Path of a listed device file relative to the sandbox base
+    pub(crate) fn relative<'a>(&self, device_path: &'a str) -> Option<&'a str> {
+        let rest = device_path.strip_prefix(&self.device_base())?;
+        let rest = rest.strip_prefix('/')?;
+        (!rest.is_empty() && !rest.split('/').any(|part| part == "..")).then_some(rest)
+    }
+}
+
+/// `rm -f` commands removing `paths`, each quoted, in batches
+pub(crate) fn remove_commands(paths: &[String]) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut command = String::new();
+    for path in paths {
+        let quoted = shell::quote(path);
+        if !command.is_empty() && command.len() + 1 + quoted.len() > MAX_RM_COMMAND_LEN {
+            commands.push(std::mem::take(&mut command));
+        }
+        if command.is_empty() {
+            command.push_str("rm -f");
+        }
+        command.push(' ');
+        command.push_str(&quoted);
+    }
+    if !command.is_empty() {
+        commands.push(command);
+    }
+    commands
+}
+
+/// Coverage files pulled by [`HdcClient::collect_coverage`](crate::HdcClient::collect_coverage)
+#[derive(Debug, Clone, Default)]
+pub struct CoverageArtifacts {
+    /// Local copies, in listing order
+    pub files: Vec<PathBuf>,
+    /// Merged LCOV tracefile, if any tracefiles were pulled
+    pub merged: Option<PathBuf>,
+}
+
+/// Coverage of one source file in an LCOV tracefile
+#[derive(Debug, Default)]
+struct SourceCoverage {
+    /// Function name to first line
+    functions: BTreeMap<String, u32>,
+    /// Function name to call count
+    function_hits: BTreeMap<String, u64>,
+    /// (line, block, branch) to times taken; `None` for `-` (never evaluated)
+    branches: BTreeMap<(u32, u32, u32), Option<u64>>,
+    /// Line to execution count
+    lines: BTreeMap<u32, u64>,
+}
+
+/// Merge LCOV tracefiles by summing the counters of each source file
+///
+/// Test names are dropped and the summary lines (`LF`, `LH`, ...) are
+/// recomputed; unknown lines are ignored.
+///
+/// # Example
+/// ```
+/// use hdc_rs::coverage::merge_lcov;
+///
+/// let a = "SF:src/main.c\nDA:1,1\nDA:2,0\nend_of_record\n";
+/// let b = "SF:src/main.c\nDA:2,3\nend_of_record\n";
+/// assert_eq!(
+///     merge_lcov(&[a, b]),
+///     "TN:\nSF:src/main.c\nDA:1,1\nDA:2,3\nLF:2\nLH:2\nend_of_record\n"
+/// );
+/// ```
+pub fn merge_lcov(tracefiles: &[&str]) -> String {
+    let mut sources: BTreeMap<String, SourceCoverage> = BTreeMap::new();
+    for tracefile in tracefiles {
+        let mut current: Option<&mut SourceCoverage> = None;
+        for line in tracefile.lines().map(str::trim) {
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            if key == "SF" {
+                current = Some(sources.entry(value.to_string()).or_default());
+                continue;
+            }
+            if key == "end_of_record" {
+                current = None;
+                continue;
+            }
+            let Some(source) = current.as_deref_mut() else {
+                continue;
+            };
+            let fields: Vec<&str> = value.split(',').collect();
+            match (key, fields.as_slice()) {
+                ("FN", [line, name, ..]) => {
+                    if let Ok(line) = line.parse() {
+                        source.functions.entry(name.to_string()).or_insert(line);
+                    }
+                }
+                ("FNDA", [count, name, ..]) => {
+                    if let Ok(count) = count.parse::<u64>() {
+                        *source.function_hits.entry(name.to_string()).or_default() += count;
+                    }
+                }
+                ("BRDA", [line, block, branch, taken]) => {
+                    let (Ok(line), Ok(block), Ok(branch)) =
+                        (line.parse(), block.parse(), branch.parse())
+                    else {
+                        continue;
+                    };
+                    let entry = source.branches.entry((line, block, branch)).or_default();
+                    if let Ok(taken) = taken.parse::<u64>() {
+                        *entry = Some(entry.unwrap_or_default() + taken);
+                    }
+                }
+                ("DA", [line, count, ..]) => {
+                    if let (Ok(line), Ok(count)) = (line.parse(), count.parse::<u64>()) {
+                        *source.lines.entry(line).or_default() += count;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (path, source) in &sources {
+        out.push_str(&format!("TN:\nSF:{}\n", path));
+
+        let mut functions: Vec<(&String, &u32)> = source.functions.iter().collect();
+        functions.sort_by_key(|(name, line)| (**line, name.as_str()));
+        for (name, line) in &functions {
+            out.push_str(&format!("FN:{},{}\n", line, name));
+        }
+        for (name, count) in &source.function_hits {
+            out.push_str(&format!("FNDA:{},{}\n", count, name));
+        }
+        if !functions.is_empty() {
+            let hit = source.function_hits.values().filter(|c| **c > 0).count();
+            out.push_str(&format!("FNF:{}\nFNH:{}\n", functions.len(), hit));
+        }
+
+        for ((line, block, branch), taken) in &source.branches {
+            let taken = taken.map_or("-".to_string(), |t| t.to_string());
+            out.push_str(&format!("BRDA:{},{},{},{}\n", line, block, branch, taken));
+        }
+        if !source.branches.is_empty() {
+            let hit = source
+                .branches
+                .values()
+                .filter(|t| t.is_some_and(|t| t > 0))
+                .count();
+            out.push_str(&format!("BRF:{}\nBRH:{}\n", source.branches.len(), hit));
+        }
+
+        for (line, count) in &source.lines {
+            out.push_str(&format!("DA:{},{}\n", line, count));
+        }
+        let hit = source.lines.values().filter(|c| **c > 0).count();
+        out.push_str(&format!(
+            "LF:{}\nLH:{}\nend_of_record\n",
+            source.lines.len(),
+            hit
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeDevice, MockServer};
+    use crate::HdcClient;
+
+    #[test]
+    fn test_merge_lcov() {
+        let a = "\
+TN:run1
+SF:src/a.c
+FN:3,main
+FN:10,helper
+FNDA:1,main
+FNDA:0,helper
+FNF:2
+FNH:1
+BRDA:4,0,0,1
+BRDA:4,0,1,-
+DA:3,1
+DA:4,1
+DA:10,0
+LF:3
+LH:2
+end_of_record
+";
+        let b = "\
+TN:run2
+SF:src/a.c
+FN:10,helper
+FNDA:2,helper
+BRDA:4,0,1,2
+DA:10,2
+end_of_record
+SF:src/b.c
+DA:1,0
+end_of_record
+";
+        assert_eq!(
+            merge_lcov(&[a, b]),
+            "\
+TN:
+SF:src/a.c
+FN:3,main
+FN:10,helper
+FNDA:2,helper
+FNDA:1,main
+FNF:2
+FNH:2
+BRDA:4,0,0,1
+BRDA:4,0,1,2
+BRF:2
+BRH:2
+DA:3,1
+DA:4,1
+DA:10,2
+LF:3
+LH:3
+end_of_record
+TN:
+SF:src/b.c
+DA:1,0
+LF:1
+LH:0
+end_of_record
+"
+        );
+    }
+
+    #[test]
+    fn test_relative() {
+        let options = CoverageOptions::new("com.example.app").user(101);
+        let base = "/data/app/el2/101/base/com.example.app";
+        assert_eq!(
+            options.relative(&format!("{}/files/cov/a.gcda", base)),
+            Some("files/cov/a.gcda")
+        );
+        assert_eq!(options.relative(&format!("{}/../x.gcda", base)), None);
+        assert_eq!(options.relative("/data/local/tmp/a.gcda"), None);
+    }
+
+    #[test]
+    fn test_remove_commands() {
+        let paths = vec!["/data/a b.gcda".to_string(), "/data/it's.info".to_string()];
+        assert_eq!(
+            remove_commands(&paths),
+            ["rm -f '/data/a b.gcda' '/data/it'\\''s.info'"]
+        );
+
+        let many: Vec<String> = (0..1000).map(|i| format!("/data/f{}.gcda", i)).collect();
+        let commands = remove_commands(&many);
+        assert!(commands.len() > 1);
+        assert!(commands.iter().all(|c| c.len() <= MAX_RM_COMMAND_LEN));
+        assert_eq!(
+            commands
+                .iter()
+                .map(|c| c.matches(".gcda").count())
+                .sum::<usize>(),
+            1000
+        );
+        assert!(remove_commands(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collect_coverage() {
+        let base = "/data/app/el2/100/base/com.example.app";
+        let options = CoverageOptions::new("com.example.app")
+            .dir("files")
+            .local_dir(std::env::temp_dir().join(format!("hdc_rs_cov_{}", std::process::id())))
+            .clear(true);
+        let local = options.local_path().to_path_buf();
+        let recv = |file: &str| {
+            format!(
+                "file recv -b com.example.app {}/files/{} {}",
+                SANDBOX_BASE,
+                file,
+                local.join("files").join(file).display()
+            )
+        };
+        let phone = FakeDevice::new("dev-1")
+            .when(options.find_command())
+            .reply(format!("{}/files/a.info\n{}/files/b.gcda\n", base, base))
+            .when_command(recv("a.info"))
+            .reply("FileTransfer finish, Size:0")
+            .when_command(recv("b.gcda"))
+            .reply("FileTransfer finish, Size:0")
+            .when(format!(
+                "rm -f '{}/files/a.info' '{}/files/b.gcda'",
+                base, base
+            ))
+            .reply("");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        // The fake device does not write files, so stage the pulled copies
+        std::fs::create_dir_all(local.join("files")).unwrap();
+        std::fs::write(
+            local.join("files/a.info"),
+            "SF:a.c\nDA:1,1\nend_of_record\n",
+        )
+        .unwrap();
+
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        client.connect_device("dev-1").await.unwrap();
+        let artifacts = client.collect_coverage(options).await.unwrap();
+        assert_eq!(artifacts.files.len(), 2);
+        let merged = artifacts.merged.unwrap();
+        assert_eq!(merged, local.join(MERGED_LCOV));
+        assert!(std::fs::read_to_string(&merged)
+            .unwrap()
+            .contains("SF:a.c\nDA:1,1\nLF:1\nLH:1\n"));
+        assert_eq!(phone.commands().len(), 4);
+        std::fs::remove_dir_all(&local).unwrap();
+    }
+}
@@ -0,0 +1,451 @@
+//! Direct connections to the device daemon (serverless mode)
+//!
+//! [`DaemonClient`] speaks the session protocol to `hdcd` listening on the
+//! device's TCP debug port (enabled with `hdc tmode port <port>`), so no
+//! local hdc server or toolchain is needed. This suits containers and CI
+//! runners that reach devices over the network.
+//!
+//! Only the commands implemented here are available; everything else still
+//! goes through [`HdcClient`](crate::HdcClient) and a server.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::DaemonClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut daemon = DaemonClient::connect("192.168.1.20:5555").await?;
+//! println!("{}", daemon.shell("uname -a").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use bytes::{Bytes, BytesMut};
+use tracing::{debug, info, warn};
+
+use crate::error::{HdcError, Result};
+use crate::protocol::session::{AuthType, SessionCodec, SessionHandShake, SessionPacket};
+use crate::protocol::HdcCommand;
+use crate::transport::{HdcStream, ServerAddr, Timeouts};
+
+/// Version announced to the daemon
+const HOST_VERSION: &str = "Ver: 3.1.0e";
+
+/// A session with a device daemon
+pub struct DaemonClient {
+    stream: HdcStream,
+    codec: SessionCodec,
+    session_id: u32,
+    next_channel_id: u32,
+    daemon_handshake: SessionHandShake,
+}
+
+impl DaemonClient {
+    /// Connect to a daemon that does not require authentication
+    pub async fn connect(address: impl Into<ServerAddr>) -> Result<Self> {
+        let address = address.into();
+        info!("Connecting to daemon at {}", address);
+        let stream = address.connect(Timeouts::default().connect).await?;
+        Self::handshake(
+            stream,
+            address.host_port(),
+            random_session_id(),
+            #[cfg(feature = "auth")]
+            None,
+        )
+        .await
+    }
+
+    /// Connect to a daemon over USB (requires the `usb` feature)
+    ///
+    /// Claims the device's hdc interface, so no hdc server may be using it.
+    #[cfg(feature = "usb")]
+    pub async fn connect_usb(device: &crate::usb::UsbDeviceInfo) -> Result<Self> {
+        let session_id = random_session_id();
+        let stream = crate::usb::UsbStream::open(device, session_id).await?;
+        Self::handshake(
+            stream,
+            &usb_connect_key(device),
+            session_id,
+            #[cfg(feature = "auth")]
+            None,
+        )
+        .await
+    }
+
+    /// Connect to a daemon over USB, authenticating with the host key
+    #[cfg(all(feature = "usb", feature = "auth"))]
+    pub async fn connect_usb_with_auth(
+        device: &crate::usb::UsbDeviceInfo,
+        config: &crate::auth::AuthConfig,
+    ) -> Result<Self> {
+        let key = load_host_key(config).await?;
+        let session_id = random_session_id();
+        let stream = crate::usb::UsbStream::open(device, session_id).await?;
+        Self::handshake(stream, &usb_connect_key(device), session_id, Some(&key)).await
+    }
+
+    /// Connect to a daemon, authenticating with the configured host key
+    ///
+    /// A daemon that doesn't know the key yet prompts on the device screen;
+    /// the handshake completes once the user accepts.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use hdc_rs::auth::AuthConfig;
+    /// use hdc_rs::DaemonClient;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut daemon =
+    ///     DaemonClient::connect_with_auth("192.168.1.20:5555", &AuthConfig::default()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "auth")]
+    pub async fn connect_with_auth(
+        address: impl Into<ServerAddr>,
+        config: &crate::auth::AuthConfig,
+    ) -> Result<Self> {
+        let address = address.into();
+        let key = load_host_key(config).await?;
+
+        info!("Connecting to daemon at {}", address);
+        let stream = address.connect(Timeouts::default().connect).await?;
+        Self::handshake(stream, address.host_port(), random_session_id(), Some(&key)).await
+    }
+
+    /// Perform the session handshake on an already connected stream
+    async fn handshake(
+        stream: impl Into<HdcStream>,
+        connect_key: &str,
+        session_id: u32,
+        #[cfg(feature = "auth")] host_key: Option<&crate::auth::HostKey>,
+    ) -> Result<Self> {
+        let mut client = Self {
+            stream: stream.into(),
+            codec: SessionCodec::new(),
+            session_id,
+            next_channel_id: 1,
+            daemon_handshake: SessionHandShake::new(0, "", ""),
+        };
+
+        let hello = SessionHandShake::new(client.session_id, connect_key, HOST_VERSION);
+        client.send_handshake(&hello).await?;
+        #[cfg(feature = "auth")]
+        let mut signed_last_token = false;
+
+        loop {
+            let packet = client.recv().await?;
+            if packet.command() != Some(HdcCommand::KernelHandshake) {
+                debug!("Ignoring packet during handshake: {:?}", packet.protect);
+                continue;
+            }
+
+            let reply = SessionHandShake::from_bytes(&packet.data)?;
+            match reply.auth() {
+                Some(AuthType::Ok) | Some(AuthType::None) => {
+                    info!("Daemon session {} established", client.session_id);
+                    client.daemon_handshake = reply;
+                    return Ok(client);
+                }
+                Some(AuthType::Fail) => {
+                    return Err(HdcError::Auth(
+                        String::from_utf8_lossy(&reply.buf).into_owned(),
+                    ))
+                }
+                Some(AuthType::Token) => {
+                    #[cfg(feature = "auth")]
+                    if let Some(key) = host_key {
+                        // A second token means the signature was not accepted:
+                        // offer the public key for approval on the device
+                        let response = if signed_last_token {
+                            info!("Host key unknown to daemon, requesting approval");
+                            hello.with_auth(AuthType::PublicKey, public_key_message(key)?)
+                        } else {
+                            hello.with_auth(AuthType::Signature, sign_token(key, &reply.buf)?)
+                        };
+                        signed_last_token = !signed_last_token;
+                        client.send_handshake(&response).await?;
+                        continue;
+                    }
+                    return Err(HdcError::Auth(
+                        "Daemon requires host key authentication".to_string(),
+                    ));
+                }
+                _ => {
+                    return Err(HdcError::Protocol(format!(
+                        "Unexpected auth type {} in daemon handshake",
+                        reply.auth_type
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Session ID chosen for this connection
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// Handshake the daemon accepted the session with
+    pub fn daemon_handshake(&self) -> &SessionHandShake {
+        &self.daemon_handshake
+    }
+
+    /// Version string reported by the daemon
+    pub fn daemon_version(&self) -> &str {
+        &self.daemon_handshake.version
+    }
+
+    /// Run a shell command on the device and return its output
+    pub async fn shell(&mut self, command: &str) -> Result<String> {
+        info!("Executing daemon shell command: {}", command);
+        let output = self
+            .execute(HdcCommand::UnityExecute, command.as_bytes())
+            .await?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Run a one-shot command on a new channel and collect its output
+    ///
+    /// Output packets are gathered until the daemon closes the channel.
+    pub async fn execute(&mut self, command: HdcCommand, payload: &[u8]) -> Result<Bytes> {
+        let channel_id = self.next_channel_id;
+        self.next_channel_id = self.next_channel_id.wrapping_add(1).max(1);
+
+        self.codec
+            .write_packet(&mut self.stream, channel_id, command, payload)
+            .await?;
+
+        let mut output = BytesMut::new();
+        loop {
+            let packet = self.recv().await?;
+            if packet.channel_id() != channel_id {
+                debug!("Ignoring packet for channel {}", packet.channel_id());
+                continue;
+            }
+
+            match packet.command() {
+                Some(HdcCommand::KernelEchoRaw) => output.extend_from_slice(&packet.data),
+                // Echo messages start with a severity byte
+                Some(HdcCommand::KernelEcho) => {
+                    output.extend_from_slice(packet.data.get(1..).unwrap_or_default())
+                }
+                Some(HdcCommand::KernelChannelClose) => {
+                    // A non-zero flag asks us to acknowledge the close
+                    if packet.data.first().is_some_and(|&flag| flag != 0) {
+                        self.codec
+                            .write_packet(
+                                &mut self.stream,
+                                channel_id,
+                                HdcCommand::KernelChannelClose,
+                                &[0],
+                            )
+                            .await?;
+                    }
+                    return Ok(output.freeze());
+                }
+                Some(HdcCommand::HeartbeatMsg) => {}
+                other => warn!("Unexpected daemon packet {:?}", other),
+            }
+        }
+    }
+
+    async fn send_handshake(&mut self, handshake: &SessionHandShake) -> Result<()> {
+        self.codec
+            .write_packet(
+                &mut self.stream,
+                0,
+                HdcCommand::KernelHandshake,
+                &handshake.to_bytes(),
+            )
+            .await
+    }
+
+    async fn recv(&mut self) -> Result<SessionPacket> {
+        match self.codec.read_packet(&mut self.stream).await {
+            Err(HdcError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(HdcError::ChannelClosed {
+                    reason: "daemon closed the session".to_string(),
+                })
+            }
+            result => result,
+        }
+    }
+}
+
+/// Pick a session ID unlikely to collide with other hosts
+fn random_session_id() -> u32 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    (hasher.finish() as u32).max(1)
+}
+
+/// Connect key identifying a USB device to the daemon
+#[cfg(feature = "usb")]
+fn usb_connect_key(device: &crate::usb::UsbDeviceInfo) -> String {
+    device
+        .serial
+        .clone()
+        .unwrap_or_else(|| format!("usb:{}:{}", device.bus, device.address))
+}
+
+/// Load the host key off the async workers, key generation takes a while
+#[cfg(feature = "auth")]
+async fn load_host_key(config: &crate::auth::AuthConfig) -> Result<crate::auth::HostKey> {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || config.load_key())
+        .await
+        .map_err(|e| HdcError::Auth(e.to_string()))?
+}
+
+/// Sign a daemon token; the signature travels base64-encoded
+#[cfg(feature = "auth")]
+fn sign_token(key: &crate::auth::HostKey, token: &[u8]) -> Result<Vec<u8>> {
+    use base64ct::{Base64, Encoding};
+    Ok(Base64::encode_string(&key.sign_token(token)?).into_bytes())
+}
+
+/// Public key followed by the host name shown in the device's prompt
+#[cfg(feature = "auth")]
+fn public_key_message(key: &crate::auth::HostKey) -> Result<Vec<u8>> {
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "hdc-rs".to_string());
+    Ok(format!("{}\t{}", key.public_key_pem()?, host).into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn mock_daemon() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        (listener, address)
+    }
+
+    async fn read_handshake(stream: &mut TcpStream) -> SessionHandShake {
+        let packet = SessionCodec::new().read_packet(stream).await.unwrap();
+        assert_eq!(packet.command(), Some(HdcCommand::KernelHandshake));
+        SessionHandShake::from_bytes(&packet.data).unwrap()
+    }
+
+    async fn send_handshake(stream: &mut TcpStream, hs: &SessionHandShake) {
+        SessionCodec::new()
+            .write_packet(stream, 0, HdcCommand::KernelHandshake, &hs.to_bytes())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_shell() {
+        let (listener, address) = mock_daemon().await;
+        let server = tokio::spawn(async move {
+            let codec = SessionCodec::new();
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let hello = read_handshake(&mut stream).await;
+            assert_eq!(hello.auth(), Some(AuthType::None));
+            assert_eq!(hello.version, HOST_VERSION);
+            let mut ok = hello.with_auth(AuthType::Ok, b"device".to_vec());
+            ok.version = "Ver: 3.1.0e".to_string();
+            send_handshake(&mut stream, &ok).await;
+
+            let cmd = codec.read_packet(&mut stream).await.unwrap();
+            assert_eq!(cmd.command(), Some(HdcCommand::UnityExecute));
+            assert_eq!(cmd.data, &b"echo hi"[..]);
+            let channel = cmd.channel_id();
+            codec
+                .write_packet(&mut stream, channel, HdcCommand::KernelEchoRaw, b"hi\n")
+                .await
+                .unwrap();
+            codec
+                .write_packet(&mut stream, channel, HdcCommand::KernelChannelClose, &[1])
+                .await
+                .unwrap();
+
+            let ack = codec.read_packet(&mut stream).await.unwrap();
+            assert_eq!(ack.command(), Some(HdcCommand::KernelChannelClose));
+            assert_eq!(ack.data, &[0][..]);
+        });
+
+        let mut daemon = DaemonClient::connect(address).await.unwrap();
+        assert_eq!(daemon.daemon_version(), "Ver: 3.1.0e");
+        assert_eq!(daemon.shell("echo hi").await.unwrap(), "hi\n");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auth_required_without_key() {
+        let (listener, address) = mock_daemon().await;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let hello = read_handshake(&mut stream).await;
+            send_handshake(
+                &mut stream,
+                &hello.with_auth(AuthType::Token, b"token".to_vec()),
+            )
+            .await;
+            // Keep the connection open until the client gives up
+            let _ = read_handshake(&mut stream).await;
+        });
+
+        let err = DaemonClient::connect(address).await.err().unwrap();
+        assert!(matches!(err, HdcError::Auth(_)));
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_token_signature() {
+        use base64ct::{Base64, Encoding};
+        use rsa::pkcs1::DecodeRsaPublicKey;
+
+        let path = std::env::temp_dir()
+            .join(format!("hdc-rs-daemon-{}", std::process::id()))
+            .join("hdckey");
+        crate::auth::HostKey::generate_with_bits(1024)
+            .unwrap()
+            .save(&path)
+            .unwrap();
+        let config = crate::auth::AuthConfig::new(&path).generate_if_missing(false);
+        let public = config.load_key().unwrap().public_key_pem().unwrap();
+
+        let (listener, address) = mock_daemon().await;
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let hello = read_handshake(&mut stream).await;
+            let token = b"01234567890123456789".to_vec();
+            send_handshake(
+                &mut stream,
+                &hello.with_auth(AuthType::Token, token.clone()),
+            )
+            .await;
+
+            let signature = read_handshake(&mut stream).await;
+            assert_eq!(signature.auth(), Some(AuthType::Signature));
+            let raw = Base64::decode_vec(std::str::from_utf8(&signature.buf).unwrap()).unwrap();
+            rsa::RsaPublicKey::from_pkcs1_pem(&public)
+                .unwrap()
+                .verify(rsa::Pkcs1v15Sign::new_unprefixed(), &token, &raw)
+                .unwrap();
+
+            // Pretend the key is unknown: the client should offer it
+            send_handshake(&mut stream, &hello.with_auth(AuthType::Token, token)).await;
+            let offer = read_handshake(&mut stream).await;
+            assert_eq!(offer.auth(), Some(AuthType::PublicKey));
+            assert!(String::from_utf8_lossy(&offer.buf).starts_with(&public));
+
+            send_handshake(&mut stream, &hello.with_auth(AuthType::Ok, Vec::new())).await;
+        });
+
+        DaemonClient::connect_with_auth(address, &config)
+            .await
+            .unwrap();
+        server.await.unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
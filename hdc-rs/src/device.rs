@@ -0,0 +1,707 @@
+//! Per-device client handles
+//!
+//! [`HdcClient::device`] returns a [`DeviceClient`] bound to one connect key.
+//! Each handle owns its channel, so programs driving several devices hold
+//! one handle per device instead of switching a shared client between them
+//! with [`connect_device`](HdcClient::connect_device).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::{FileTransferOptions, HdcClient};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = HdcClient::connect("127.0.0.1:8710").await?;
+//! let mut device = client.device("device-serial");
+//! device
+//!     .file_send("app.hap", "/data/local/tmp/app.hap", FileTransferOptions::new())
+//!     .await?;
+//! println!("{}", device.shell("ls /data/local/tmp").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::app::{InstallOptions, UninstallOptions};
+use crate::bugreport::BugReport;
+use crate::client::HdcClient;
+use crate::coverage::{CoverageArtifacts, CoverageOptions};
+use crate::error::{HdcError, Result};
+use crate::faultlog::{CrashEvent, FaultLog};
+use crate::file::{FileTransferOptions, TransferProgress};
+use crate::flash::{FlashOperation, FlashProgress};
+use crate::forward::{ForwardNode, ForwardTask, ForwardedStream, JdwpProcess, ReverseBridge};
+use crate::hilog::{HilogFilter, LogEntry, LogStream, LogTimestamp};
+use crate::ota::OtaOptions;
+use crate::partition::{DumpOptions, PartitionDump};
+use crate::perf::{PerfOptions, PerfSession};
+use crate::sampler::{MetricKind, MetricsSample};
+use crate::selinux::{AvcDenial, SelinuxMode};
+use crate::sysinfo::{
+    BatteryInfo, InterfaceStats, MemInfo, ProcessInfo, SocketInfo, StorageInfo, WifiInfo,
+};
+use crate::trace::TraceCategory;
+
+/// Client bound to one device, created with [`HdcClient::device`]
+///
+/// Methods mirror the device commands of [`HdcClient`]. The channel is
+/// opened on first use and re-opened whenever a command has consumed it.
+pub struct DeviceClient {
+    client: HdcClient,
+}
+
+impl DeviceClient {
+    /// Wrap a client whose connect key is already set
+    pub(crate) fn new(client: HdcClient) -> Self {
+        Self { client }
+    }
+
+    /// Connect key this handle is bound to
+    pub fn device_id(&self) -> &str {
+        self.client
+            .connect_key()
+            .expect("device clients always have a connect key")
+    }
+
+    /// Underlying client, e.g. for its settings and connection state
+    pub fn client(&self) -> &HdcClient {
+        &self.client
+    }
+
+    /// Take the underlying client, still bound to the device
+    pub fn into_inner(self) -> HdcClient {
+        self.client
+    }
+
+    /// Open the channel unless one is ready
+    pub async fn connect(&mut self) -> Result<()> {
+        if !self.client.is_connected() {
+            self.client.reconnect().await?;
+        }
+        Ok(())
+    }
+
+    /// Send a raw command and read the reply as a string
+    pub async fn command(&mut self, cmd: &str) -> Result<String> {
+        self.connect().await?;
+        self.client.send_command(cmd).await?;
+        self.client.read_response_string().await
+    }
+
+    /// See [`HdcClient::shell`]
+    pub async fn shell(&mut self, cmd: &str) -> Result<String> {
+        self.connect().await?;
+        self.client.shell(cmd).await
+    }
+
+    /// See [`HdcClient::shell_with_timeout`]
+    pub async fn shell_with_timeout(&mut self, cmd: &str, timeout: Duration) -> Result<String> {
+        self.connect().await?;
+        self.client.shell_with_timeout(cmd, timeout).await
+    }
+
+    /// See [`HdcClient::install`]
+    pub async fn install(&mut self, paths: &[&str], options: InstallOptions) -> Result<String> {
+        self.connect().await?;
+        self.client.install(paths, options).await
+    }
+
+    /// See [`HdcClient::flash`]
+    pub async fn flash(&mut self, operation: FlashOperation) -> Result<String> {
+        self.connect().await?;
+        self.client.flash(operation).await
+    }
+
+    /// See [`HdcClient::dump_partition`]
+    pub async fn dump_partition(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: DumpOptions,
+    ) -> Result<PartitionDump> {
+        self.connect().await?;
+        self.client.dump_partition(name, dest, options).await
+    }
+
+    /// See [`HdcClient::dump_partition_with_progress`]
+    pub async fn dump_partition_with_progress<F>(
+        &mut self,
+        name: &str,
+        dest: impl AsRef<std::path::Path>,
+        options: DumpOptions,
+        progress: F,
+    ) -> Result<PartitionDump>
+    where
+        F: FnMut(&TransferProgress),
+    {
+        self.connect().await?;
+        self.client
+            .dump_partition_with_progress(name, dest, options, progress)
+            .await
+    }
+
+    /// See [`HdcClient::flash_with_progress`]
+    pub async fn flash_with_progress<F>(
+        &mut self,
+        operation: FlashOperation,
+        progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&FlashProgress),
+    {
+        self.connect().await?;
+        self.client.flash_with_progress(operation, progress).await
+    }
+
+    /// See [`HdcClient::sideload`]
+    pub async fn sideload(&mut self, path: impl AsRef<std::path::Path>) -> Result<String> {
+        self.connect().await?;
+        self.client.sideload(path).await
+    }
+
+    /// See [`HdcClient::sideload_with_progress`]
+    pub async fn sideload_with_progress<F>(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        progress: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&FlashProgress),
+    {
+        self.connect().await?;
+        self.client.sideload_with_progress(path, progress).await
+    }
+
+    /// See [`HdcClient::apply_ota`]
+    pub async fn apply_ota(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: OtaOptions,
+    ) -> Result<()> {
+        self.connect().await?;
+        self.client.apply_ota(package_path, options).await
+    }
+
+    /// See [`HdcClient::apply_ota_with_progress`]
+    pub async fn apply_ota_with_progress<F>(
+        &mut self,
+        package_path: impl AsRef<std::path::Path>,
+        options: OtaOptions,
+        progress: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&FlashProgress),
+    {
+        self.connect().await?;
+        self.client
+            .apply_ota_with_progress(package_path, options, progress)
+            .await
+    }
+
+    /// See [`HdcClient::uninstall`]
+    pub async fn uninstall(&mut self, package: &str, options: UninstallOptions) -> Result<String> {
+        self.connect().await?;
+        self.client.uninstall(package, options).await
+    }
+
+    /// See [`HdcClient::start_app`]
+    pub async fn start_app(&mut self, bundle: &str, ability: &str) -> Result<String> {
+        self.connect().await?;
+        self.client.start_app(bundle, ability).await
+    }
+
+    /// See [`HdcClient::stop_app`]
+    pub async fn stop_app(&mut self, bundle: &str) -> Result<String> {
+        self.connect().await?;
+        self.client.stop_app(bundle).await
+    }
+
+    /// See [`HdcClient::clear_app_data`]
+    pub async fn clear_app_data(&mut self, bundle: &str) -> Result<String> {
+        self.connect().await?;
+        self.client.clear_app_data(bundle).await
+    }
+
+    /// See [`HdcClient::reboot`]
+    pub async fn reboot(&mut self, mode: RebootMode) -> Result<()> {
+        self.connect().await?;
+        self.client.reboot(mode).await
+    }
+
+    /// See [`HdcClient::tmode_port`]
+    pub async fn tmode_port(&mut self, port: u16) -> Result<String> {
+        self.connect().await?;
+        self.client.tmode_port(port).await
+    }
+
+    /// See [`HdcClient::tmode_usb`]
+    pub async fn tmode_usb(&mut self) -> Result<String> {
+        self.connect().await?;
+        self.client.tmode_usb().await
+    }
+
+    /// See [`HdcClient::wait_for_boot`]
+    pub async fn wait_for_boot(&mut self, wait: Duration) -> Result<()> {
+        self.connect().await?;
+        self.client.wait_for_boot(wait).await
+    }
+
+    /// See [`HdcClient::file_send`]
+    pub async fn file_send(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.connect().await?;
+        self.client
+            .file_send(local_path, remote_path, options)
+            .await
+    }
+
+    /// See [`HdcClient::file_recv`]
+    pub async fn file_recv(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.connect().await?;
+        self.client
+            .file_recv(remote_path, local_path, options)
+            .await
+    }
+
+    /// See [`HdcClient::fport`]
+    pub async fn fport(&mut self, local: ForwardNode, remote: ForwardNode) -> Result<String> {
+        self.connect().await?;
+        self.client.fport(local, remote).await
+    }
+
+    /// See [`HdcClient::rport`]
+    pub async fn rport(&mut self, remote: ForwardNode, local: ForwardNode) -> Result<String> {
+        self.connect().await?;
+        self.client.rport(remote, local).await
+    }
+
+    /// Forward tasks of this device
+    pub async fn fport_list(&mut self) -> Result<Vec<ForwardTask>> {
+        let device_id = self.device_id().to_string();
+        let entries = self.client.fport_list_for(&device_id).await?;
+        Ok(entries.into_iter().map(|entry| entry.task).collect())
+    }
+
+    /// Remove every forward task of this device
+    pub async fn remove_all_forwards(&mut self) -> Result<usize> {
+        let device_id = self.device_id().to_string();
+        self.client.remove_all_forwards(Some(&device_id)).await
+    }
+
+    /// See [`HdcClient::jdwp_list`]
+    pub async fn jdwp_list(&mut self) -> Result<Vec<JdwpProcess>> {
+        self.connect().await?;
+        self.client.jdwp_list().await
+    }
+
+    /// See [`HdcClient::open_forwarded`]
+    pub async fn open_forwarded(&mut self, remote: ForwardNode) -> Result<ForwardedStream> {
+        self.connect().await?;
+        self.client.open_forwarded(remote).await
+    }
+
+    /// See [`HdcClient::rport_unix`]
+    pub async fn rport_unix(
+        &mut self,
+        remote: ForwardNode,
+        socket_path: impl AsRef<std::path::Path>,
+    ) -> Result<ReverseBridge> {
+        self.connect().await?;
+        self.client.rport_unix(remote, socket_path).await
+    }
+
+    /// See [`HdcClient::hilog_filtered`]
+    pub async fn hilog(&mut self, filter: impl Into<HilogFilter>) -> Result<String> {
+        self.connect().await?;
+        self.client.hilog_filtered(filter).await
+    }
+
+    /// See [`HdcClient::hilog_stream_filtered`]
+    pub async fn hilog_stream<F>(
+        &mut self,
+        filter: impl Into<HilogFilter>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.connect().await?;
+        self.client.hilog_stream_filtered(filter, callback).await
+    }
+
+    /// See [`HdcClient::hilog_entries_filtered`]
+    pub async fn hilog_entries(&mut self, filter: impl Into<HilogFilter>) -> Result<Vec<LogEntry>> {
+        self.connect().await?;
+        self.client.hilog_entries_filtered(filter).await
+    }
+
+    /// See [`HdcClient::hilog_entries_stream_filtered`]
+    pub async fn hilog_entries_stream<F>(
+        &mut self,
+        filter: impl Into<HilogFilter>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(LogEntry) -> bool,
+    {
+        self.connect().await?;
+        self.client
+            .hilog_entries_stream_filtered(filter, callback)
+            .await
+    }
+
+    /// See [`HdcClient::hilog_watch`]
+    pub async fn hilog_watch(
+        &self,
+        filter: impl Into<HilogFilter>,
+        pattern: &str,
+    ) -> Result<LogStream> {
+        self.client.hilog_watch(filter, pattern).await
+    }
+
+    /// See [`HdcClient::wait_for_log`]
+    pub async fn wait_for_log(&self, pattern: &str, wait: Duration) -> Result<LogEntry> {
+        self.client.wait_for_log(pattern, wait).await
+    }
+
+    /// See [`HdcClient::watch_crashes`]
+    pub async fn watch_crashes(&self, bundle: &str) -> Result<LogStream<CrashEvent>> {
+        self.client.watch_crashes(bundle).await
+    }
+
+    /// See [`HdcClient::sample_metrics`]
+    pub async fn sample_metrics(
+        &self,
+        interval: Duration,
+        kinds: &[MetricKind],
+    ) -> Result<LogStream<MetricsSample>> {
+        self.client.sample_metrics(interval, kinds).await
+    }
+
+    /// See [`HdcClient::collect_faultlogs`]
+    pub async fn collect_faultlogs(
+        &mut self,
+        since: Option<LogTimestamp>,
+    ) -> Result<Vec<FaultLog>> {
+        self.connect().await?;
+        self.client.collect_faultlogs(since).await
+    }
+
+    /// See [`HdcClient::bugreport`]
+    pub async fn bugreport(&mut self, dest_dir: impl AsRef<std::path::Path>) -> Result<BugReport> {
+        self.connect().await?;
+        self.client.bugreport(dest_dir).await
+    }
+
+    /// See [`HdcClient::collect_coverage`]
+    pub async fn collect_coverage(
+        &mut self,
+        options: CoverageOptions,
+    ) -> Result<CoverageArtifacts> {
+        self.connect().await?;
+        self.client.collect_coverage(options).await
+    }
+
+    /// See [`HdcClient::screenshot`]
+    pub async fn screenshot(&mut self) -> Result<Vec<u8>> {
+        self.connect().await?;
+        self.client.screenshot().await
+    }
+
+    /// See [`HdcClient::screen_record`]
+    pub async fn screen_record(&mut self, duration: Duration) -> Result<Vec<u8>> {
+        self.connect().await?;
+        self.client.screen_record(duration).await
+    }
+
+    /// See [`HdcClient::capture_trace`]
+    pub async fn capture_trace(
+        &mut self,
+        categories: &[TraceCategory],
+        duration: Duration,
+    ) -> Result<Vec<u8>> {
+        self.connect().await?;
+        self.client.capture_trace(categories, duration).await
+    }
+
+    /// See [`PerfSession::record`]
+    pub async fn perf_record(&mut self, options: PerfOptions) -> Result<PerfSession> {
+        self.connect().await?;
+        PerfSession::record(&mut self.client, options).await
+    }
+
+    /// See [`HdcClient::processes`]
+    pub async fn processes(&mut self) -> Result<Vec<ProcessInfo>> {
+        self.connect().await?;
+        self.client.processes().await
+    }
+
+    /// See [`HdcClient::storage`]
+    pub async fn storage(&mut self) -> Result<Vec<StorageInfo>> {
+        self.connect().await?;
+        self.client.storage().await
+    }
+
+    /// See [`HdcClient::meminfo`]
+    pub async fn meminfo(&mut self) -> Result<MemInfo> {
+        self.connect().await?;
+        self.client.meminfo().await
+    }
+
+    /// See [`HdcClient::battery`]
+    pub async fn battery(&mut self) -> Result<BatteryInfo> {
+        self.connect().await?;
+        self.client.battery().await
+    }
+
+    /// See [`HdcClient::net_stats`]
+    pub async fn net_stats(&mut self) -> Result<Vec<InterfaceStats>> {
+        self.connect().await?;
+        self.client.net_stats().await
+    }
+
+    /// See [`HdcClient::connections`]
+    pub async fn connections(&mut self) -> Result<Vec<SocketInfo>> {
+        self.connect().await?;
+        self.client.connections().await
+    }
+
+    /// See [`HdcClient::wifi_info`]
+    pub async fn wifi_info(&mut self) -> Result<WifiInfo> {
+        self.connect().await?;
+        self.client.wifi_info().await
+    }
+
+    /// See [`HdcClient::selinux_mode`]
+    pub async fn selinux_mode(&mut self) -> Result<SelinuxMode> {
+        self.connect().await?;
+        self.client.selinux_mode().await
+    }
+
+    /// See [`HdcClient::avc_denials`]
+    pub async fn avc_denials(&mut self) -> Result<Vec<AvcDenial>> {
+        self.connect().await?;
+        self.client.avc_denials().await
+    }
+
+    /// See [`HdcClient::list_bundles`]
+    pub async fn list_bundles(&mut self) -> Result<Vec<String>> {
+        self.connect().await?;
+        self.client.list_bundles().await
+    }
+}
+
+/// Change in the list of connected devices
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceEvent {
+    /// A device appeared in the target list
+    Connected(String),
+    /// A device left the target list
+    Disconnected(String),
+}
+
+impl DeviceEvent {
+    /// Events that turn the `previous` target list into `current`
+    ///
+    /// Disconnects come first, each group in list order.
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::DeviceEvent;
+    ///
+    /// let before = ["a".to_string(), "b".to_string()];
+    /// let after = ["b".to_string(), "c".to_string()];
+    /// assert_eq!(
+    ///     DeviceEvent::diff(&before, &after),
+    ///     [
+    ///         DeviceEvent::Disconnected("a".to_string()),
+    ///         DeviceEvent::Connected("c".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff(previous: &[String], current: &[String]) -> Vec<DeviceEvent> {
+        let gone = previous
+            .iter()
+            .filter(|device| !current.contains(device))
+            .map(|device| DeviceEvent::Disconnected(device.clone()));
+        let new = current
+            .iter()
+            .filter(|device| !previous.contains(device))
+            .map(|device| DeviceEvent::Connected(device.clone()));
+        gone.chain(new).collect()
+    }
+
+    /// Device the event is about
+    pub fn device_id(&self) -> &str {
+        match self {
+            DeviceEvent::Connected(id) | DeviceEvent::Disconnected(id) => id,
+        }
+    }
+}
+
+/// One line of the verbose target list (`list targets -v`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Connect key (serial number or `ip:port`)
+    pub connect_key: String,
+    /// Transport the device is attached over, such as `USB` or `TCP`
+    pub connection_type: String,
+    /// Connection state, such as `Connected`, `Offline` or `Unauthorized`
+    pub state: String,
+    /// Device name reported by the daemon, usually its model
+    pub model: String,
+}
+
+impl DeviceInfo {
+    /// Parse a tab-separated `list targets -v` line
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::DeviceInfo;
+    ///
+    /// let info = DeviceInfo::parse("FMR0223C13000649\t\tUSB\tConnected\tlocalhost").unwrap();
+    /// assert_eq!(info.connection_type, "USB");
+    /// assert!(info.is_connected());
+    /// ```
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut fields = line
+            .split('\t')
+            .map(str::trim)
+            .filter(|field| !field.is_empty());
+        let (Some(connect_key), Some(connection_type), Some(state)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(HdcError::Protocol(format!("Invalid target line: {}", line)));
+        };
+        Ok(Self {
+            connect_key: connect_key.to_string(),
+            connection_type: connection_type.to_string(),
+            state: state.to_string(),
+            model: fields.next().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Whether the device is online and authorized
+    pub fn is_connected(&self) -> bool {
+        self.state.eq_ignore_ascii_case("Connected")
+    }
+}
+
+/// Mode to restart a device into, see [`HdcClient::reboot`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RebootMode {
+    /// Normal system boot
+    #[default]
+    Normal,
+    /// Bootloader (fastboot) mode
+    Bootloader,
+    /// Recovery mode
+    Recovery,
+    /// Updater's flashd mode, for [`HdcClient::flash`]
+    Flashd,
+}
+
+impl RebootMode {
+    /// `target boot` command for this mode
+    pub fn command(&self) -> &'static str {
+        match self {
+            RebootMode::Normal => "target boot",
+            RebootMode::Bootloader => "target boot -bootloader",
+            RebootMode::Recovery => "target boot -recovery",
+            RebootMode::Flashd => "target boot -flashd",
+        }
+    }
+}
+
+impl FromStr for RebootMode {
+    type Err = HdcError;
+
+    /// Parse `normal`, `bootloader`, `recovery` or `flashd`, ignoring case
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "normal" => Ok(RebootMode::Normal),
+            "bootloader" => Ok(RebootMode::Bootloader),
+            "recovery" => Ok(RebootMode::Recovery),
+            "flashd" => Ok(RebootMode::Flashd),
+            _ => Err(HdcError::Protocol(format!("Invalid reboot mode: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceInfo, RebootMode};
+    use crate::testing::MockConnection;
+    use crate::HdcClient;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_reboot_mode() {
+        assert_eq!("".parse::<RebootMode>().unwrap(), RebootMode::Normal);
+        assert_eq!(
+            "Recovery".parse::<RebootMode>().unwrap().command(),
+            "target boot -recovery"
+        );
+        assert_eq!(
+            "flashd".parse::<RebootMode>().unwrap().command(),
+            "target boot -flashd"
+        );
+        assert!("fastboot".parse::<RebootMode>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_device_client_keeps_its_key() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            // Parent client, server-level
+            let parent = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(parent.connect_key(), "");
+
+            for (command, output) in [("shell uname", "Linux"), ("shell id", "root")] {
+                let mut conn = MockConnection::accept(&listener).await.unwrap();
+                assert_eq!(conn.connect_key(), "dev-1");
+                assert_eq!(conn.read_command().await.unwrap(), command);
+                conn.send(output.as_bytes()).await.unwrap();
+            }
+            // Re-dial after the last shell command
+            let conn = MockConnection::accept(&listener).await.unwrap();
+            assert_eq!(conn.connect_key(), "dev-1");
+            parent
+        });
+
+        let client = HdcClient::connect(address).await.unwrap();
+        let mut device = client.device("dev-1");
+        assert_eq!(device.device_id(), "dev-1");
+        assert_eq!(device.shell("uname").await.unwrap(), "Linux");
+        assert_eq!(device.shell("id").await.unwrap(), "root");
+
+        assert_eq!(client.connect_key(), None);
+        assert!(client.is_connected());
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_device_info_parse() {
+        let info = DeviceInfo::parse("192.168.1.5:5555\t\tTCP\tOffline\t").unwrap();
+        assert_eq!(info.connect_key, "192.168.1.5:5555");
+        assert_eq!(info.connection_type, "TCP");
+        assert_eq!(info.state, "Offline");
+        assert_eq!(info.model, "");
+        assert!(!info.is_connected());
+
+        assert!(DeviceInfo::parse("device-1").is_err());
+    }
+}
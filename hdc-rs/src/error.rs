@@ -29,6 +29,10 @@ pub enum HdcError {
     #[error("Invalid banner: expected 'OHOS HDC', got {0:?}")]
     InvalidBanner(Vec<u8>),
 
+    /// The packet stream is out of sync and the connection is unusable
+    #[error("Stream out of sync: {0}")]
+    Desync(String),
+
     /// Buffer size error
     #[error("Buffer error: {0}")]
     BufferError(String),
@@ -41,6 +45,30 @@ pub enum HdcError {
     #[error("Operation timed out")]
     Timeout,
 
+    /// Server closed the channel
+    #[error("Channel closed by server: {reason}")]
+    ChannelClosed {
+        /// Reason given by the server, if any
+        reason: String,
+    },
+
+    /// Host authentication failed or no usable host key
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+
+    /// The server or device reported a failure (`[Fail]...` output)
+    #[error("{}", remote_display(.code.as_deref(), .message))]
+    Remote {
+        /// Error code such as `E001003`, when the server gives one
+        code: Option<String>,
+        /// Failure message without the `[Fail]` and code tags
+        message: String,
+    },
+
+    /// Invalid client configuration
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
     /// Device not found
     #[error("Device not found: {0}")]
     DeviceNotFound(String),
@@ -49,3 +77,280 @@ pub enum HdcError {
     #[error("UTF-8 error: {0}")]
     Utf8(#[from] std::string::FromUtf8Error),
 }
+
+/// What went wrong, independent of how the error was reported
+///
+/// Server failures arrive as free-form `[Fail]` text; [`HdcError::kind`]
+/// classifies the known messages so callers can branch on the reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// No device with the given connect key
+    DeviceNotFound,
+    /// The device is known but offline or unauthorized
+    DeviceOffline,
+    /// The server or device is busy with another request
+    Busy,
+    /// The device refused access
+    PermissionDenied,
+    /// A local or remote path does not exist
+    PathNotExist,
+    /// The package signature was rejected
+    InstallSignature,
+    /// Installing the package failed for another reason
+    InstallFailed,
+    /// A forward port is taken or could not be listened on
+    PortInUse,
+    /// The server does not know the command
+    UnknownCommand,
+    /// The operation timed out
+    Timeout,
+    /// The connection to the server dropped
+    ConnectionLost,
+    /// Anything not classified above
+    Other,
+}
+
+impl ErrorKind {
+    /// Known server messages, matched case-insensitively, in priority order
+    const MESSAGES: &'static [(&'static str, ErrorKind)] = &[
+        ("not match target founded", Self::DeviceNotFound),
+        ("device not found", Self::DeviceNotFound),
+        ("device not founded", Self::DeviceNotFound),
+        ("need connect-key", Self::DeviceNotFound),
+        ("offline", Self::DeviceOffline),
+        ("unauthorized", Self::DeviceOffline),
+        ("busy", Self::Busy),
+        ("permission denied", Self::PermissionDenied),
+        ("operation not permitted", Self::PermissionDenied),
+        ("no such file", Self::PathNotExist),
+        ("not exist", Self::PathNotExist),
+        ("signature", Self::InstallSignature),
+        ("install bundle failed", Self::InstallFailed),
+        ("failed to install", Self::InstallFailed),
+        ("address already in use", Self::PortInUse),
+        ("listen failed", Self::PortInUse),
+        ("port already", Self::PortInUse),
+        ("unknown command", Self::UnknownCommand),
+    ];
+
+    /// Classify a server failure message
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::ErrorKind;
+    ///
+    /// assert_eq!(
+    ///     ErrorKind::from_message("Error opening file: no such file or directory"),
+    ///     ErrorKind::PathNotExist
+    /// );
+    /// assert_eq!(ErrorKind::from_message("something new"), ErrorKind::Other);
+    /// ```
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        Self::MESSAGES
+            .iter()
+            .find(|(pattern, _)| message.contains(pattern))
+            .map_or(Self::Other, |&(_, kind)| kind)
+    }
+
+    /// Short snake_case name, e.g. for metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeviceNotFound => "device_not_found",
+            Self::DeviceOffline => "device_offline",
+            Self::Busy => "busy",
+            Self::PermissionDenied => "permission_denied",
+            Self::PathNotExist => "path_not_exist",
+            Self::InstallSignature => "install_signature",
+            Self::InstallFailed => "install_failed",
+            Self::PortInUse => "port_in_use",
+            Self::UnknownCommand => "unknown_command",
+            Self::Timeout => "timeout",
+            Self::ConnectionLost => "connection_lost",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Tag starting failure lines in server output
+const FAIL_TAG: &str = "[Fail]";
+
+fn remote_display(code: Option<&str>, message: &str) -> String {
+    match code {
+        Some(code) => format!("Remote failure [{}]: {}", code, message),
+        None => format!("Remote failure: {}", message),
+    }
+}
+
+impl HdcError {
+    /// Classify this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Remote { message, .. } | Self::CommandFailed(message) => {
+                ErrorKind::from_message(message)
+            }
+            Self::DeviceNotFound(_) => ErrorKind::DeviceNotFound,
+            Self::Timeout => ErrorKind::Timeout,
+            Self::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                ErrorKind::PermissionDenied
+            }
+            Self::Io(e) if e.kind() == io::ErrorKind::NotFound => ErrorKind::PathNotExist,
+            Self::Io(e) if e.kind() == io::ErrorKind::AddrInUse => ErrorKind::PortInUse,
+            e if e.is_connection_lost() => ErrorKind::ConnectionLost,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Failure reported in command output, if any
+    ///
+    /// Looks for a line starting with `[Fail]`, optionally followed by a
+    /// bracketed error code (`[Fail][E001003] ...`). The message is the rest
+    /// of that line plus any lines after it.
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::HdcError;
+    ///
+    /// let err = HdcError::from_response("[Fail][E001003] Device not found").unwrap();
+    /// assert!(matches!(
+    ///     err,
+    ///     HdcError::Remote { code: Some(ref c), ref message }
+    ///         if c == "E001003" && message == "Device not found"
+    /// ));
+    /// assert!(HdcError::from_response("[Info]Done").is_none());
+    /// ```
+    pub fn from_response(text: &str) -> Option<Self> {
+        let start = text
+            .lines()
+            .map(|line| line.trim_start_matches('\0').trim_start())
+            .find(|line| line.starts_with(FAIL_TAG))?;
+        // `start` borrows `text`, so the remainder runs to the end of the output
+        let offset = start.as_ptr() as usize - text.as_ptr() as usize;
+        let rest = text[offset + FAIL_TAG.len()..].trim_start();
+
+        let (code, message) = match rest
+            .strip_prefix('[')
+            .and_then(|r| r.split_once(']'))
+            .filter(|(code, _)| is_error_code(code))
+        {
+            Some((code, message)) => (Some(code.to_string()), message),
+            None => (None, rest),
+        };
+        Some(Self::Remote {
+            code,
+            message: message
+                .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                .to_string(),
+        })
+    }
+
+    /// Check command output for a reported failure
+    pub(crate) fn check_response(text: String) -> Result<String> {
+        match Self::from_response(&text) {
+            Some(e) => Err(e),
+            None => Ok(text),
+        }
+    }
+
+    /// Whether the connection to the server dropped
+    ///
+    /// True for resets, broken pipes and end-of-stream, but not for a
+    /// [`ChannelClosed`](Self::ChannelClosed) sent by the server.
+    pub fn is_connection_lost(&self) -> bool {
+        match self {
+            Self::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::NotConnected
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the failure may go away on its own, so retrying makes sense
+    ///
+    /// Covers timeouts, dropped or refused connections and the server
+    /// reporting itself busy.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::Io(e) => {
+                self.is_connection_lost()
+                    || matches!(
+                        e.kind(),
+                        io::ErrorKind::ConnectionRefused | io::ErrorKind::TimedOut
+                    )
+            }
+            Self::Protocol(message) => message.to_ascii_lowercase().contains("busy"),
+            _ => self.kind() == ErrorKind::Busy,
+        }
+    }
+}
+
+/// Whether `code` looks like a server error code such as `E001003`
+fn is_error_code(code: &str) -> bool {
+    code.len() > 1
+        && code.starts_with(|c: char| c.is_ascii_uppercase())
+        && code[1..].chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(text: &str) -> (Option<String>, String) {
+        match HdcError::from_response(text) {
+            Some(HdcError::Remote { code, message }) => (code, message),
+            other => panic!("not a remote failure: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_response() {
+        assert_eq!(
+            remote("[Fail]Unknown command"),
+            (None, "Unknown command".into())
+        );
+        assert_eq!(
+            remote("[Fail][E005004] Error opening file: not exist, path:/x\n"),
+            (
+                Some("E005004".into()),
+                "Error opening file: not exist, path:/x".into()
+            )
+        );
+        assert_eq!(
+            remote("[Info]App install path:/a.hap\n[Fail]install failed\ncode:9568322"),
+            (None, "install failed\ncode:9568322".into())
+        );
+        assert_eq!(
+            remote("[Fail][not a code] x"),
+            (None, "[not a code] x".into())
+        );
+        assert!(HdcError::from_response("shell output [Fail] inline").is_none());
+        assert_eq!(
+            HdcError::from_response("[Fail]Not match target founded, check connect-key please")
+                .unwrap()
+                .kind(),
+            ErrorKind::DeviceNotFound
+        );
+        assert_eq!(
+            HdcError::from_response("[Fail]The device is busy")
+                .unwrap()
+                .kind(),
+            ErrorKind::Busy
+        );
+        assert!(HdcError::from_response("[Fail]The device is busy")
+            .unwrap()
+            .is_transient());
+        assert_eq!(
+            HdcError::from_response("[Fail][E001003] gone")
+                .unwrap()
+                .to_string(),
+            "Remote failure [E001003]: gone"
+        );
+    }
+}
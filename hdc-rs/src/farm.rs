@@ -0,0 +1,528 @@
+//! Long-lived device handles with health checks
+//!
+//! A [`DeviceFarm`] keeps one [`DeviceClient`] per connect key for programs
+//! that drive a rack of devices for hours, such as test schedulers. In the
+//! background it runs a [`HealthProbe`] on every device each interval. A
+//! device whose probes keep failing is marked
+//! [`Unhealthy`](DeviceHealth::Unhealthy) and its channel dropped;
+//! [`get`](DeviceFarm::get) refuses it until a later probe gets through,
+//! at which point the device is attached again on a fresh channel.
+//!
+//! Devices checked out with [`get`](DeviceFarm::get) are skipped by the
+//! checks while in use.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::farm::{DeviceFarm, HealthProbe};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! let mut farm = DeviceFarm::new(&client)
+//!     .devices(["phone-1", "phone-2", "watch-1"])
+//!     .probe(HealthProbe::param("const.product.name"))
+//!     .check_interval(Duration::from_secs(30));
+//! farm.check().await;
+//! farm.start();
+//!
+//! for device_id in farm.healthy_devices() {
+//!     let mut device = farm.get(&device_id).await?;
+//!     println!("{}: {}", device_id, device.shell("uptime").await?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::OwnedMutexGuard;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+use crate::client::HdcClient;
+use crate::device::DeviceClient;
+use crate::error::{HdcError, Result};
+
+/// Text echoed back by [`HealthProbe::Echo`]
+pub const ECHO_TOKEN: &str = "hdc-rs-alive";
+
+/// Start of the error `param get` prints, e.g.
+/// `Get parameter "x" fail! errNum is:-1!`
+const PARAM_ERROR_PREFIX: &str = "Get parameter";
+
+/// Command run on a device to tell whether it responds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthProbe {
+    /// `echo` a token and expect it back
+    Echo,
+    /// `param get` a parameter and expect a value
+    Param(String),
+}
+
+impl HealthProbe {
+    /// Probe reading the system parameter `name`
+    pub fn param(name: impl Into<String>) -> Self {
+        HealthProbe::Param(name.into())
+    }
+
+    /// Shell command of the probe
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::farm::HealthProbe;
+    ///
+    /// assert_eq!(HealthProbe::Echo.command(), "echo hdc-rs-alive");
+    /// assert_eq!(
+    ///     HealthProbe::param("const.product.name").command(),
+    ///     "param get const.product.name"
+    /// );
+    /// ```
+    pub fn command(&self) -> String {
+        match self {
+            HealthProbe::Echo => format!("echo {}", ECHO_TOKEN),
+            HealthProbe::Param(name) => format!("param get {}", name),
+        }
+    }
+
+    /// Whether `output` of [`command`](Self::command) shows a working device
+    pub fn is_healthy(&self, output: &str) -> bool {
+        let output = output.trim();
+        match self {
+            HealthProbe::Echo => output == ECHO_TOKEN,
+            HealthProbe::Param(_) => {
+                let error = output.starts_with(PARAM_ERROR_PREFIX) && output.contains(" fail!");
+                !output.is_empty() && !error
+            }
+        }
+    }
+
+    async fn run(&self, device: &mut DeviceClient, timeout: Duration) -> Result<()> {
+        let output = device.shell_with_timeout(&self.command(), timeout).await?;
+        let output = HdcError::check_response(output)?;
+        if self.is_healthy(&output) {
+            Ok(())
+        } else {
+            Err(HdcError::CommandFailed(format!(
+                "Unexpected health check output: {:?}",
+                output.trim()
+            )))
+        }
+    }
+}
+
+/// Health of a device in a [`DeviceFarm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceHealth {
+    /// Not checked yet
+    Unknown,
+    /// The last probe succeeded
+    Healthy,
+    /// Probes failed too often in a row; the device is not handed out
+    Unhealthy,
+}
+
+/// Health check state of one device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceStatus {
+    /// Connect key
+    pub device_id: String,
+    /// Current health
+    pub health: DeviceHealth,
+    /// Probes failed in a row
+    pub failures: u32,
+    /// When the device was last probed
+    pub last_check: Option<SystemTime>,
+    /// Error of the last failed probe, until one succeeds
+    pub last_error: Option<String>,
+}
+
+impl DeviceStatus {
+    fn new(device_id: String) -> Self {
+        Self {
+            device_id,
+            health: DeviceHealth::Unknown,
+            failures: 0,
+            last_check: None,
+            last_error: None,
+        }
+    }
+
+    fn record(&mut self, result: Result<()>, unhealthy_after: u32) {
+        self.last_check = Some(SystemTime::now());
+        match result {
+            Ok(()) => {
+                if self.health == DeviceHealth::Unhealthy {
+                    info!("Device {} recovered, re-attached", self.device_id);
+                }
+                self.health = DeviceHealth::Healthy;
+                self.failures = 0;
+                self.last_error = None;
+            }
+            Err(e) => {
+                self.failures = self.failures.saturating_add(1);
+                if self.failures >= unhealthy_after && self.health != DeviceHealth::Unhealthy {
+                    warn!("Device {} marked unhealthy: {}", self.device_id, e);
+                    self.health = DeviceHealth::Unhealthy;
+                } else {
+                    debug!("Health check of {} failed: {}", self.device_id, e);
+                }
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// How devices are checked
+#[derive(Debug, Clone)]
+struct HealthSettings {
+    probe: HealthProbe,
+    interval: Duration,
+    timeout: Duration,
+    unhealthy_after: u32,
+}
+
+struct Member {
+    device: Arc<tokio::sync::Mutex<DeviceClient>>,
+    status: DeviceStatus,
+}
+
+type Members = Arc<Mutex<BTreeMap<String, Member>>>;
+
+/// Persistent device handles with periodic health checks
+///
+/// Stops checking when dropped.
+pub struct DeviceFarm {
+    client: HdcClient,
+    settings: HealthSettings,
+    members: Members,
+    checker: Option<JoinHandle<()>>,
+}
+
+impl DeviceFarm {
+    /// Farm whose devices share `client`'s settings
+    ///
+    /// Devices are checked every 10 seconds with [`HealthProbe::Echo`], a
+    /// 5 second limit, and marked unhealthy after 2 failures in a row.
+    pub fn new(client: &HdcClient) -> Self {
+        Self {
+            client: client.clone(),
+            settings: HealthSettings {
+                probe: HealthProbe::Echo,
+                interval: Duration::from_secs(10),
+                timeout: Duration::from_secs(5),
+                unhealthy_after: 2,
+            },
+            members: Arc::new(Mutex::new(BTreeMap::new())),
+            checker: None,
+        }
+    }
+
+    /// Add a device to the farm
+    pub fn device(self, device_id: impl Into<String>) -> Self {
+        self.add(device_id);
+        self
+    }
+
+    /// Add several devices to the farm
+    pub fn devices<I, S>(self, device_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        device_ids.into_iter().fold(self, Self::device)
+    }
+
+    /// Probe run on each device (default [`HealthProbe::Echo`])
+    pub fn probe(mut self, probe: HealthProbe) -> Self {
+        self.settings.probe = probe;
+        self
+    }
+
+    /// Time between checks started by [`start`](Self::start) (default 10
+    /// seconds)
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        self.settings.interval = interval;
+        self
+    }
+
+    /// Limit for one probe (default 5 seconds)
+    pub fn probe_timeout(mut self, timeout: Duration) -> Self {
+        self.settings.timeout = timeout;
+        self
+    }
+
+    /// Failures in a row before a device is marked unhealthy (default 2,
+    /// at least 1)
+    pub fn unhealthy_after(mut self, failures: u32) -> Self {
+        self.settings.unhealthy_after = failures.max(1);
+        self
+    }
+
+    /// Add a device, returning `false` if it is already in the farm
+    ///
+    /// Its health is [`Unknown`](DeviceHealth::Unknown) until checked.
+    pub fn add(&self, device_id: impl Into<String>) -> bool {
+        let device_id = device_id.into();
+        let mut members = lock(&self.members);
+        if members.contains_key(&device_id) {
+            return false;
+        }
+        let member = Member {
+            device: Arc::new(tokio::sync::Mutex::new(self.client.device(&device_id))),
+            status: DeviceStatus::new(device_id.clone()),
+        };
+        members.insert(device_id, member);
+        true
+    }
+
+    /// Remove a device, returning `false` if it was not in the farm
+    ///
+    /// A handle checked out with [`get`](Self::get) stays usable.
+    pub fn remove(&self, device_id: &str) -> bool {
+        lock(&self.members).remove(device_id).is_some()
+    }
+
+    /// Devices in the farm, sorted
+    pub fn device_ids(&self) -> Vec<String> {
+        lock(&self.members).keys().cloned().collect()
+    }
+
+    /// Devices whose last probe succeeded, sorted
+    pub fn healthy_devices(&self) -> Vec<String> {
+        lock(&self.members)
+            .values()
+            .filter(|member| member.status.health == DeviceHealth::Healthy)
+            .map(|member| member.status.device_id.clone())
+            .collect()
+    }
+
+    /// Health check state of `device_id`
+    pub fn status(&self, device_id: &str) -> Option<DeviceStatus> {
+        lock(&self.members)
+            .get(device_id)
+            .map(|member| member.status.clone())
+    }
+
+    /// Health check state of every device, sorted by id
+    pub fn statuses(&self) -> Vec<DeviceStatus> {
+        lock(&self.members)
+            .values()
+            .map(|member| member.status.clone())
+            .collect()
+    }
+
+    /// Check the health of every device not in use now
+    ///
+    /// Probes run concurrently. Returns the state of every device afterwards.
+    pub async fn check(&self) -> Vec<DeviceStatus> {
+        check_members(&self.client, &self.settings, &self.members).await;
+        self.statuses()
+    }
+
+    /// Check every [`check_interval`](Self::check_interval) in the
+    /// background, starting now
+    ///
+    /// Restarts the checks if they are running. Must be called within a
+    /// Tokio runtime.
+    pub fn start(&mut self) {
+        self.stop();
+        info!(
+            "Checking {} devices every {:?}",
+            lock(&self.members).len(),
+            self.settings.interval
+        );
+        let client = self.client.clone();
+        let settings = self.settings.clone();
+        let members = self.members.clone();
+        self.checker = Some(tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(settings.interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                check_members(&client, &settings, &members).await;
+            }
+        }));
+    }
+
+    /// Stop the background checks
+    pub fn stop(&mut self) {
+        if let Some(checker) = self.checker.take() {
+            checker.abort();
+        }
+    }
+
+    /// Check out the handle of `device_id`
+    ///
+    /// Waits while another caller holds it. Fails with
+    /// [`HdcError::DeviceNotFound`] for devices not in the farm and with
+    /// [`HdcError::CommandFailed`] for unhealthy ones.
+    pub async fn get(&self, device_id: &str) -> Result<FarmDevice> {
+        let device = {
+            let members = lock(&self.members);
+            let member = members
+                .get(device_id)
+                .ok_or_else(|| HdcError::DeviceNotFound(device_id.to_string()))?;
+            if member.status.health == DeviceHealth::Unhealthy {
+                return Err(HdcError::CommandFailed(format!(
+                    "Device {} is unhealthy: {}",
+                    device_id,
+                    member
+                        .status
+                        .last_error
+                        .as_deref()
+                        .unwrap_or("unknown error")
+                )));
+            }
+            member.device.clone()
+        };
+        Ok(FarmDevice {
+            device: device.lock_owned().await,
+        })
+    }
+}
+
+impl Drop for DeviceFarm {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A device handle checked out of a [`DeviceFarm`]
+///
+/// Dereferences to [`DeviceClient`]. Dropping it returns the handle.
+pub struct FarmDevice {
+    device: OwnedMutexGuard<DeviceClient>,
+}
+
+impl Deref for FarmDevice {
+    type Target = DeviceClient;
+
+    fn deref(&self) -> &DeviceClient {
+        &self.device
+    }
+}
+
+impl DerefMut for FarmDevice {
+    fn deref_mut(&mut self) -> &mut DeviceClient {
+        &mut self.device
+    }
+}
+
+fn lock(members: &Members) -> std::sync::MutexGuard<'_, BTreeMap<String, Member>> {
+    members.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Probe every idle member and record the results
+async fn check_members(client: &HdcClient, settings: &HealthSettings, members: &Members) {
+    let idle: Vec<_> = lock(members)
+        .iter()
+        .filter_map(
+            |(device_id, member)| match member.device.clone().try_lock_owned() {
+                Ok(device) => Some((device_id.clone(), device)),
+                Err(_) => {
+                    debug!("Skipping health check of {}: in use", device_id);
+                    None
+                }
+            },
+        )
+        .collect();
+
+    let mut probes = JoinSet::new();
+    for (device_id, mut device) in idle {
+        let client = client.clone();
+        let settings = settings.clone();
+        probes.spawn(async move {
+            let result = settings.probe.run(&mut device, settings.timeout).await;
+            if result.is_err() {
+                // Attach again on a fresh channel once the device is back
+                *device = client.device(&device_id);
+            }
+            (device_id, result)
+        });
+    }
+
+    while let Some(joined) = probes.join_next().await {
+        let Ok((device_id, result)) = joined else {
+            continue;
+        };
+        if let Some(member) = lock(members).get_mut(&device_id) {
+            member.status.record(result, settings.unhealthy_after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probes() {
+        assert!(HealthProbe::Echo.is_healthy("hdc-rs-alive\r\n"));
+        assert!(!HealthProbe::Echo.is_healthy(""));
+        let probe = HealthProbe::param("const.product.name");
+        assert!(probe.is_healthy("Mate 60\n"));
+        assert!(probe.is_healthy("failsafe\n"));
+        assert!(!probe.is_healthy("Get parameter \"x\" fail! errNum is:-1!\n"));
+    }
+
+    #[tokio::test]
+    async fn test_health_checks() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let phone = FakeDevice::new("dev-1")
+            .when(HealthProbe::Echo.command())
+            .reply("hdc-rs-alive\n");
+        let watch = FakeDevice::new("dev-2")
+            .when(HealthProbe::Echo.command())
+            .reply("hdc-rs-alive\n");
+        let server = MockServer::new()
+            .device(phone.clone())
+            .device(watch.clone())
+            .spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let farm = DeviceFarm::new(&client)
+            .devices(["dev-2", "dev-1", "dev-2"])
+            .unhealthy_after(2);
+        assert_eq!(farm.device_ids(), ["dev-1", "dev-2"]);
+        assert_eq!(farm.status("dev-1").unwrap().health, DeviceHealth::Unknown);
+
+        farm.check().await;
+        assert_eq!(farm.healthy_devices(), ["dev-1", "dev-2"]);
+
+        watch.unplug();
+        farm.check().await;
+        let status = farm.status("dev-2").unwrap();
+        assert_eq!((status.health, status.failures), (DeviceHealth::Healthy, 1));
+        farm.check().await;
+        assert_eq!(farm.healthy_devices(), ["dev-1"]);
+        assert!(farm.status("dev-2").unwrap().last_error.is_some());
+        assert!(matches!(
+            farm.get("dev-2").await,
+            Err(HdcError::CommandFailed(_))
+        ));
+        assert!(matches!(
+            farm.get("dev-3").await,
+            Err(HdcError::DeviceNotFound(_))
+        ));
+
+        // A checked-out device is left alone
+        let held = farm.get("dev-1").await.unwrap();
+        watch.plug();
+        farm.check().await;
+        assert_eq!(held.device_id(), "dev-1");
+        assert_eq!(phone.commands().len(), 3);
+        drop(held);
+        let status = farm.status("dev-2").unwrap();
+        assert_eq!((status.health, status.failures), (DeviceHealth::Healthy, 0));
+        let mut device = farm.get("dev-2").await.unwrap();
+        assert_eq!(
+            device.shell(&HealthProbe::Echo.command()).await.unwrap(),
+            "hdc-rs-alive\n"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Faultlog types for post-mortem crash triage
+//!
+//! HiviewDFX writes crash and freeze reports to `/data/log/faultlog`. File
+//! names follow `<kind>-<process>-<uid>-<YYYYMMDDHHMMSSmmm>.log` and each
+//! report starts with a block of `Key:Value` header lines.
+//!
+//! [`HdcClient::watch_crashes`](crate::HdcClient::watch_crashes) polls the
+//! directory and yields a [`CrashEvent`] for each new report of one bundle,
+//! so tests can fail as soon as the app under test crashes or freezes.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::hilog::LogTimestamp;
+
+/// Directory holding faultlogs on the device
+pub const FAULTLOG_DIR: &str = "/data/log/faultlog";
+
+/// How often [`HdcClient::watch_crashes`](crate::HdcClient::watch_crashes)
+/// lists the faultlog directory
+pub const CRASH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Kind of fault report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultKind {
+    /// Native (C/C++) crash
+    CppCrash,
+    /// ArkTS/JS crash
+    JsCrash,
+    /// Application freeze
+    AppFreeze,
+    /// System freeze
+    SysFreeze,
+}
+
+impl FaultKind {
+    /// Parse from the file name prefix (e.g. `cppcrash`)
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "cppcrash" => Some(Self::CppCrash),
+            "jscrash" => Some(Self::JsCrash),
+            "appfreeze" => Some(Self::AppFreeze),
+            "sysfreeze" => Some(Self::SysFreeze),
+            _ => None,
+        }
+    }
+
+    /// File name prefix for this kind
+    pub fn as_prefix(&self) -> &'static str {
+        match self {
+            Self::CppCrash => "cppcrash",
+            Self::JsCrash => "jscrash",
+            Self::AppFreeze => "appfreeze",
+            Self::SysFreeze => "sysfreeze",
+        }
+    }
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_prefix())
+    }
+}
+
+/// A fault report collected from the device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultLog {
+    /// Full path on the device
+    pub path: String,
+    /// Kind of fault
+    pub kind: FaultKind,
+    /// Process or bundle name from the file name
+    pub process: String,
+    /// Uid from the file name, if present
+    pub uid: Option<u32>,
+    /// Time of the fault from the file name, if present
+    pub timestamp: Option<LogTimestamp>,
+    /// Header fields (`Module name`, `Pid`, `Reason`, ...)
+    pub fields: BTreeMap<String, String>,
+    /// Full report text
+    pub content: String,
+}
+
+impl FaultLog {
+    /// Build a fault log from a device path and the report text
+    ///
+    /// Returns `None` if the file name is not a recognized faultlog.
+    pub fn parse(path: &str, content: &str) -> Option<Self> {
+        let name = FaultLogName::parse(path)?;
+        Some(Self {
+            path: path.to_string(),
+            kind: name.kind,
+            process: name.process,
+            uid: name.uid,
+            timestamp: name.timestamp,
+            fields: parse_header(content),
+            content: content.to_string(),
+        })
+    }
+
+    /// Look up a header field by name
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    /// Module (bundle) name, falling back to the process from the file name
+    pub fn module_name(&self) -> &str {
+        self.field("Module name").unwrap_or(&self.process)
+    }
+
+    /// Pid of the faulting process
+    pub fn pid(&self) -> Option<u32> {
+        self.field("Pid")?.parse().ok()
+    }
+
+    /// Fault reason (signal, error name or freeze reason)
+    pub fn reason(&self) -> Option<&str> {
+        self.field("Reason").or_else(|| self.field("Error name"))
+    }
+}
+
+/// New crash or freeze report of a watched bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashEvent {
+    /// Kind of fault
+    pub kind: FaultKind,
+    /// Bundle or process that faulted
+    pub process: String,
+    /// Pid of the faulting process
+    pub pid: Option<u32>,
+    /// Fault reason (signal, error name or freeze reason)
+    pub reason: Option<String>,
+    /// Time of the fault from the file name, if present
+    pub timestamp: Option<LogTimestamp>,
+    /// Full report
+    pub log: FaultLog,
+}
+
+impl CrashEvent {
+    /// Whether the process froze rather than crashed
+    pub fn is_freeze(&self) -> bool {
+        matches!(self.kind, FaultKind::AppFreeze | FaultKind::SysFreeze)
+    }
+}
+
+impl From<FaultLog> for CrashEvent {
+    fn from(log: FaultLog) -> Self {
+        Self {
+            kind: log.kind,
+            process: log.process.clone(),
+            pid: log.pid(),
+            reason: log.reason().map(str::to_string),
+            timestamp: log.timestamp,
+            log,
+        }
+    }
+}
+
+impl fmt::Display for CrashEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {}", self.kind, self.process)?;
+        if let Some(pid) = self.pid {
+            write!(f, " (pid {})", pid)?;
+        }
+        if let Some(reason) = &self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Metadata encoded in a faultlog file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FaultLogName {
+    pub kind: FaultKind,
+    pub process: String,
+    pub uid: Option<u32>,
+    pub timestamp: Option<LogTimestamp>,
+}
+
+impl FaultLogName {
+    /// Whether the report is an app fault of `bundle` or one of its
+    /// extension processes (`<bundle>:<name>`)
+    pub fn is_app_fault_of(&self, bundle: &str) -> bool {
+        self.kind != FaultKind::SysFreeze
+            && self
+                .process
+                .strip_prefix(bundle)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+    }
+
+    /// Parse a faultlog path or file name
+    pub fn parse(path: &str) -> Option<Self> {
+        let file = path.rsplit('/').next()?;
+        let stem = file.strip_suffix(".log").unwrap_or(file);
+
+        let (prefix, rest) = stem.split_once('-')?;
+        let kind = FaultKind::from_prefix(prefix)?;
+
+        // Process names may contain '-', so peel fields off the end
+        let mut parts = rest.rsplitn(3, '-');
+        let last = parts.next()?;
+        let timestamp = parse_file_time(last);
+        let (process, uid) = match (parts.next(), parts.next()) {
+            (Some(uid), Some(process)) if uid.parse::<u32>().is_ok() => {
+                (process.to_string(), uid.parse().ok())
+            }
+            (Some(middle), Some(process)) => (format!("{}-{}", process, middle), None),
+            (Some(process), None) => (process.to_string(), None),
+            _ => (rest.to_string(), None),
+        };
+
+        Some(Self {
+            kind,
+            process,
+            uid,
+            timestamp,
+        })
+    }
+}
+
+/// Parse a `YYYYMMDDHHMMSSmmm` file name timestamp
+fn parse_file_time(s: &str) -> Option<LogTimestamp> {
+    if s.len() != 17 || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| s[range].parse::<u32>().ok();
+    Some(LogTimestamp {
+        year: Some(num(0..4)? as u16),
+        month: num(4..6)? as u8,
+        day: num(6..8)? as u8,
+        hour: num(8..10)? as u8,
+        minute: num(10..12)? as u8,
+        second: num(12..14)? as u8,
+        nanos: num(14..17)? * 1_000_000,
+    })
+}
+
+/// Collect `Key:Value` header lines up to the first blank line after them
+fn parse_header(content: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if fields.is_empty() {
+                continue;
+            }
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.len() > 32 {
+            continue;
+        }
+        fields
+            .entry(key.to_string())
+            .or_insert_with(|| value.trim().to_string());
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPPCRASH: &str = "Generated by HiviewDFX@OpenHarmony
+================================================================
+Device info:OpenHarmony 4.0
+Build info:OpenHarmony 4.0.10.5
+Module name:com.example.app
+Version:1.0.0
+Pid:1234
+Uid:20010043
+Reason:Signal:SIGSEGV(SEGV_MAPERR)@0x00000000
+Fault thread info:
+Tid:1234, Name:example.app
+
+#00 pc 0001a2b4 /system/lib/libc.so
+";
+
+    #[test]
+    fn test_parse_name() {
+        let name = FaultLogName::parse(
+            "/data/log/faultlog/faultlogger/cppcrash-com.example.app-20010043-20240102030405678.log",
+        )
+        .unwrap();
+        assert_eq!(name.kind, FaultKind::CppCrash);
+        assert_eq!(name.process, "com.example.app");
+        assert_eq!(name.uid, Some(20010043));
+        let ts = name.timestamp.unwrap();
+        assert_eq!(ts.year, Some(2024));
+        assert_eq!(
+            (ts.month, ts.day, ts.hour, ts.minute, ts.second),
+            (1, 2, 3, 4, 5)
+        );
+        assert_eq!(ts.nanos, 678_000_000);
+
+        let name = FaultLogName::parse("appfreeze-my-svc-0-20240102030405678.log").unwrap();
+        assert_eq!(name.process, "my-svc");
+        assert_eq!(name.uid, Some(0));
+
+        assert!(FaultLogName::parse("hilog.000.gz").is_none());
+
+        let name =
+            FaultLogName::parse("jscrash-com.example.app:widget-20010043-20240102030405678.log")
+                .unwrap();
+        assert!(name.is_app_fault_of("com.example.app"));
+        assert!(!name.is_app_fault_of("com.example"));
+        let name =
+            FaultLogName::parse("sysfreeze-com.example.app-0-20240102030405678.log").unwrap();
+        assert!(!name.is_app_fault_of("com.example.app"));
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let log = FaultLog::parse(
+            "/data/log/faultlog/faultlogger/cppcrash-com.example.app-20010043-20240102030405678.log",
+            CPPCRASH,
+        )
+        .unwrap();
+        assert_eq!(log.module_name(), "com.example.app");
+        assert_eq!(log.pid(), Some(1234));
+        assert_eq!(log.reason(), Some("Signal:SIGSEGV(SEGV_MAPERR)@0x00000000"));
+        assert_eq!(log.field("Build info"), Some("OpenHarmony 4.0.10.5"));
+        assert!(log.field("#00 pc 0001a2b4 /system/lib/libc.so").is_none());
+
+        let event = CrashEvent::from(log);
+        assert!(!event.is_freeze());
+        assert_eq!(
+            event.to_string(),
+            "cppcrash in com.example.app (pid 1234): Signal:SIGSEGV(SEGV_MAPERR)@0x00000000"
+        );
+    }
+}
@@ -1,5 +1,9 @@
 //! File transfer types and options for HDC
 
+use std::time::Duration;
+
+use tokio::time::Instant;
+
 /// File transfer options for send/recv operations
 #[derive(Debug, Clone, Default)]
 pub struct FileTransferOptions {
@@ -14,6 +18,8 @@ pub struct FileTransferOptions {
     mode_sync: bool,
     /// Send/receive file to debug application directory (-b)
     debug_dir: bool,
+    /// Debuggable bundle whose sandbox paths are addressed (-b <bundle>)
+    bundle: Option<String>,
 }
 
 impl FileTransferOptions {
@@ -52,9 +58,26 @@ impl FileTransferOptions {
         self
     }
 
+    /// Address paths inside the sandbox of a debuggable bundle, such as
+    /// `/data/storage/el2/base/files/a.txt`; implies [`debug_dir`](Self::debug_dir)
+    pub fn bundle(mut self, bundle: impl Into<String>) -> Self {
+        self.debug_dir = true;
+        self.bundle = Some(bundle.into());
+        self
+    }
+
+    /// Drop options the server cannot handle
+    pub(crate) fn for_server(mut self, features: Option<&crate::protocol::ServerFeatures>) -> Self {
+        if self.compress && !features.is_some_and(|f| f.compressed_transfer()) {
+            tracing::warn!("Server does not support compressed transfer, sending uncompressed");
+            self.compress = false;
+        }
+        self
+    }
+
     /// Convert options to command flags string
     pub(crate) fn to_flags(&self) -> String {
-        let mut flags = Vec::new();
+        let mut flags: Vec<&str> = Vec::new();
 
         if self.hold_timestamp {
             flags.push("-a");
@@ -70,6 +93,9 @@ impl FileTransferOptions {
         }
         if self.debug_dir {
             flags.push("-b");
+            if let Some(bundle) = &self.bundle {
+                flags.push(bundle);
+            }
         }
 
         flags.join(" ")
@@ -85,6 +111,58 @@ pub enum FileTransferDirection {
     Recv,
 }
 
+/// Progress of a file transfer, passed to progress callbacks
+///
+/// The server moves the data itself, so progress is read off the local
+/// file: receives report it growing, sends only know its size until the
+/// server reports completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// Which way the file moves
+    pub direction: FileTransferDirection,
+    /// Bytes known to have been transferred
+    pub bytes: u64,
+    /// Size of the whole file, if known yet
+    pub total: Option<u64>,
+    /// Time since the transfer started
+    pub elapsed: Duration,
+    /// Set on the last report, after the server confirmed success
+    pub finished: bool,
+}
+
+impl TransferProgress {
+    /// Inspect the local file of a transfer started at `started`
+    pub(crate) fn measure(
+        direction: FileTransferDirection,
+        local_path: &str,
+        started: Instant,
+        finished: bool,
+    ) -> Self {
+        let size = std::fs::metadata(local_path).map(|m| m.len()).ok();
+        let (bytes, total) = match (direction, finished) {
+            (_, true) => (size.unwrap_or(0), size),
+            (FileTransferDirection::Send, false) => (0, size),
+            (FileTransferDirection::Recv, false) => (size.unwrap_or(0), None),
+        };
+        Self {
+            direction,
+            bytes,
+            total,
+            elapsed: started.elapsed(),
+            finished,
+        }
+    }
+
+    /// Completed share between 0 and 1, if the total size is known
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            _ if self.finished => Some(1.0),
+            Some(total) if total > 0 => Some(self.bytes as f64 / total as f64),
+            _ => None,
+        }
+    }
+}
+
 /// Validate file path for transfer
 pub(crate) fn validate_path(path: &str) -> bool {
     !path.is_empty() && !path.contains('\0')
@@ -103,6 +181,32 @@ mod tests {
 
         let opts = FileTransferOptions::new().sync_mode(true).mode_sync(true);
         assert_eq!(opts.to_flags(), "-sync -m");
+
+        let opts = FileTransferOptions::new().debug_dir(true);
+        assert_eq!(opts.to_flags(), "-b");
+        let opts = FileTransferOptions::new().bundle("com.example.app");
+        assert_eq!(opts.to_flags(), "-b com.example.app");
+    }
+
+    #[test]
+    fn test_transfer_progress() {
+        let started = Instant::now();
+        let size = std::fs::metadata("Cargo.toml").unwrap().len();
+
+        let send =
+            TransferProgress::measure(FileTransferDirection::Send, "Cargo.toml", started, false);
+        assert_eq!((send.bytes, send.total), (0, Some(size)));
+        assert_eq!(send.fraction(), Some(0.0));
+
+        let recv =
+            TransferProgress::measure(FileTransferDirection::Recv, "Cargo.toml", started, false);
+        assert_eq!((recv.bytes, recv.total), (size, None));
+        assert_eq!(recv.fraction(), None);
+
+        let missing =
+            TransferProgress::measure(FileTransferDirection::Recv, "missing", started, true);
+        assert_eq!((missing.bytes, missing.total), (0, None));
+        assert_eq!(missing.fraction(), Some(1.0));
     }
 
     #[test]
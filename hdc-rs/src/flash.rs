@@ -0,0 +1,304 @@
+//! Firmware operations against a device in flashd mode
+//!
+//! A device rebooted with [`RebootMode::Flashd`](crate::RebootMode::Flashd)
+//! runs the updater's `flashd` daemon instead of the normal system. It
+//! accepts the operations of [`FlashOperation`], which
+//! [`HdcClient::flash`](crate::HdcClient::flash) sends and follows until
+//! the daemon reports the result.
+//! [`flash_with_progress`](crate::HdcClient::flash_with_progress) also
+//! reports the stages and percentages flashd prints along the way as
+//! [`FlashProgress`] values, since writing a partition can take minutes.
+//!
+//! Erasing and formatting destroy data without asking; the hdc command
+//! line tool confirms them interactively, so callers should do the same
+//! where it matters.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::flash::FlashOperation;
+//! use hdc_rs::RebootMode;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! client.reboot(RebootMode::Flashd).await?;
+//! client.wait_for_device_timeout(Duration::from_secs(60)).await?;
+//! client.flash(FlashOperation::flash("boot_linux", "out/boot_linux.img")).await?;
+//! client.flash(FlashOperation::erase("userdata")).await?;
+//! client.reboot(RebootMode::Normal).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::{HdcError, Result};
+
+/// Operation accepted by flashd
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlashOperation {
+    /// Apply an update package (`update <package>`)
+    Update {
+        /// Local path of the package
+        package: String,
+    },
+    /// Write an image to a partition (`flash -f <partition> <image>`)
+    Flash {
+        /// Partition name, e.g. `boot_linux`
+        partition: String,
+        /// Local path of the image
+        image: String,
+    },
+    /// Erase a partition (`erase -f <partition>`)
+    Erase {
+        /// Partition name
+        partition: String,
+    },
+    /// Format a partition (`format -f <partition>`)
+    Format {
+        /// Partition name
+        partition: String,
+    },
+}
+
+impl FlashOperation {
+    /// Apply the update package at `package`
+    pub fn update(package: impl Into<String>) -> Self {
+        FlashOperation::Update {
+            package: package.into(),
+        }
+    }
+
+    /// Write the image at `image` to `partition`
+    pub fn flash(partition: impl Into<String>, image: impl Into<String>) -> Self {
+        FlashOperation::Flash {
+            partition: partition.into(),
+            image: image.into(),
+        }
+    }
+
+    /// Erase `partition`
+    pub fn erase(partition: impl Into<String>) -> Self {
+        FlashOperation::Erase {
+            partition: partition.into(),
+        }
+    }
+
+    /// Format `partition`
+    pub fn format(partition: impl Into<String>) -> Self {
+        FlashOperation::Format {
+            partition: partition.into(),
+        }
+    }
+
+    /// Name of the operation, as used on the command line
+    pub fn verb(&self) -> &'static str {
+        match self {
+            FlashOperation::Update { .. } => "update",
+            FlashOperation::Flash { .. } => "flash",
+            FlashOperation::Erase { .. } => "erase",
+            FlashOperation::Format { .. } => "format",
+        }
+    }
+
+    /// Command sent to the server
+    ///
+    /// `-f` skips the confirmation the hdc command line tool would ask for.
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::flash::FlashOperation;
+    ///
+    /// let op = FlashOperation::flash("system", "system.img");
+    /// assert_eq!(op.command(), "flash -f system system.img");
+    /// assert_eq!(FlashOperation::update("ota.zip").command(), "update ota.zip");
+    /// ```
+    pub fn command(&self) -> String {
+        match self {
+            FlashOperation::Update { package } => format!("update {}", package),
+            FlashOperation::Flash { partition, image } => {
+                format!("flash -f {} {}", partition, image)
+            }
+            FlashOperation::Erase { partition } | FlashOperation::Format { partition } => {
+                format!("{} -f {}", self.verb(), partition)
+            }
+        }
+    }
+
+    /// Check the partition name and local path before anything is sent
+    pub(crate) fn validate(&self) -> Result<()> {
+        let (partition, path) = match self {
+            FlashOperation::Update { package } => (None, Some(package)),
+            FlashOperation::Flash { partition, image } => (Some(partition), Some(image)),
+            FlashOperation::Erase { partition } | FlashOperation::Format { partition } => {
+                (Some(partition), None)
+            }
+        };
+        if let Some(partition) = partition {
+            if !crate::partition::is_valid_name(partition) {
+                return Err(HdcError::Config(format!(
+                    "Invalid partition name: {:?}",
+                    partition
+                )));
+            }
+        }
+        if let Some(path) = path {
+            if !crate::file::validate_path(path) || path.contains(char::is_whitespace) {
+                return Err(HdcError::Config(format!("Invalid file path: {:?}", path)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FlashOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command())
+    }
+}
+
+/// Progress of a firmware operation, see
+/// [`HdcClient::flash_with_progress`](crate::HdcClient::flash_with_progress)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashProgress {
+    /// What is happening, e.g. `Flash partition system` or a stage of an
+    /// OTA update
+    pub stage: String,
+    /// Completed percentage, if reported
+    pub percent: Option<u8>,
+    /// Time since the operation started
+    pub elapsed: Duration,
+    /// Set on the last report, after the operation succeeded
+    pub finished: bool,
+}
+
+impl FlashProgress {
+    pub(crate) fn new(stage: impl Into<String>, percent: Option<u8>, elapsed: Duration) -> Self {
+        Self {
+            stage: stage.into(),
+            percent,
+            elapsed,
+            finished: false,
+        }
+    }
+
+    /// Completed share between 0 and 1, if known
+    pub fn fraction(&self) -> Option<f64> {
+        match self.percent {
+            _ if self.finished => Some(1.0),
+            Some(percent) => Some(f64::from(percent) / 100.0),
+            None => None,
+        }
+    }
+}
+
+/// Stage and percentage in one line of daemon output
+///
+/// Leading tags such as `[Info]` are dropped and a `NN%` figure is taken
+/// out of the text, so `[Info]Flash system: 45%` becomes
+/// `("Flash system", Some(45))`.
+pub(crate) fn parse_stage(line: &str) -> Option<(String, Option<u8>)> {
+    let mut line = line.trim();
+    if line.starts_with('[') {
+        if let Some(end) = line.find(']') {
+            line = line[end + 1..].trim_start();
+        }
+    }
+    if line.is_empty() {
+        return None;
+    }
+
+    let Some(sign) = line.find('%') else {
+        return Some((line.to_string(), None));
+    };
+    let start = line[..sign]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    let percent = match line[start..sign].parse::<u32>() {
+        Ok(percent) => percent.min(100) as u8,
+        Err(_) => return Some((line.to_string(), None)),
+    };
+    let words: Vec<&str> = line[..start]
+        .split_whitespace()
+        .chain(line[sign + 1..].split_whitespace())
+        .collect();
+    let stage = words.join(" ");
+    Some((stage.trim_end_matches(':').to_string(), Some(percent)))
+}
+
+/// Words ending the line that reports a successful operation
+const SUCCESS_WORDS: &[&str] = &["success", "successfully", "finish", "finished"];
+
+/// Whether a line of flashd or sideload output ends the operation `verb`
+///
+/// The daemon ends with a `[Fail]` line on failure, and on success with a
+/// `[Success]` line or one naming the operation and ending in one of
+/// [`SUCCESS_WORDS`], e.g. `Flash partition boot_linux success` or
+/// `[Info]Sideload finish`. Stage lines such as `Verify package success`
+/// don't name the operation and are not terminal.
+pub(crate) fn is_finished(verb: &str, line: &str) -> bool {
+    let line = line.trim().trim_start_matches('\0');
+    if line.starts_with("[Fail]") || line.starts_with("[Success]") {
+        return true;
+    }
+    let Some((stage, None)) = parse_stage(line) else {
+        return false;
+    };
+    let stage = stage.to_ascii_lowercase();
+    let mut words = stage.split_whitespace();
+    words.next() == Some(verb)
+        && words
+            .last()
+            .is_some_and(|word| SUCCESS_WORDS.contains(&word.trim_end_matches(['.', '!'])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(
+            FlashOperation::erase("userdata").command(),
+            "erase -f userdata"
+        );
+        assert_eq!(FlashOperation::format("data").to_string(), "format -f data");
+        assert!(FlashOperation::erase("userdata").validate().is_ok());
+        assert!(FlashOperation::erase("").validate().is_err());
+        assert!(FlashOperation::erase("boot; reboot").validate().is_err());
+        assert!(FlashOperation::update("my ota.zip").validate().is_err());
+        assert!(is_finished("flash", "Flash partition boot_linux success\n"));
+        assert!(is_finished("sideload", "[Info]Sideload finish"));
+        assert!(is_finished("update", "[Fail]Verify package failed"));
+        assert!(is_finished("erase", "[Success]Erase userdata"));
+        assert!(!is_finished("flash", "[Info]Progress: 40%\n"));
+        assert!(!is_finished("update", "[Info]Verify package success"));
+        assert!(!is_finished("update", "[Info]Stage finished: unpack"));
+        assert!(!is_finished(
+            "flash",
+            "[Info]Flash partition boot failsafe copy"
+        ));
+        assert!(!is_finished("sideload", "[Info]Sideload success 40%"));
+    }
+
+    #[test]
+    fn test_parse_stage() {
+        assert_eq!(
+            parse_stage("[Info]Flash partition system: 45%"),
+            Some(("Flash partition system".to_string(), Some(45)))
+        );
+        assert_eq!(
+            parse_stage("update progress 100% done"),
+            Some(("update progress done".to_string(), Some(100)))
+        );
+        assert_eq!(
+            parse_stage("Verifying package"),
+            Some(("Verifying package".to_string(), None))
+        );
+        assert_eq!(parse_stage("[Info]"), None);
+        assert_eq!(parse_stage("  "), None);
+    }
+}
@@ -1,5 +1,34 @@
 //! Port forwarding functionality
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+use crate::error::{HdcError, Result};
+use crate::retry::Backoff;
+use crate::transport::ServerAddr;
+
+/// Maximum length of a Unix domain socket name (`sun_path` minus the terminator)
+pub const MAX_SOCKET_NAME_LEN: usize = 107;
+
+/// Pause after failed accepts on a reverse bridge, growing with each
+/// failure in a row, e.g. while out of file descriptors
+const ACCEPT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_millis(10),
+    max: Duration::from_secs(1),
+    multiplier: 2.0,
+};
+
 /// Forward node type
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ForwardNode {
@@ -34,49 +63,84 @@ impl ForwardNode {
     /// - `dev:device_name`
     /// - `jdwp:1234`
     /// - `ark:1234@5678@Debugger`
-    pub fn parse(s: &str) -> crate::error::Result<Self> {
-        if let Some(port_str) = s.strip_prefix("tcp:") {
-            let port = port_str.parse::<u16>().map_err(|_| {
-                crate::error::HdcError::Protocol(format!("Invalid TCP port: {}", port_str))
-            })?;
-            Ok(Self::Tcp(port))
+    ///
+    /// The parsed node is checked with [`ForwardNode::validate`].
+    pub fn parse(s: &str) -> Result<Self> {
+        let node = if let Some(port_str) = s.strip_prefix("tcp:") {
+            let port = port_str
+                .parse::<u16>()
+                .map_err(|_| HdcError::Protocol(format!("Invalid TCP port: {}", port_str)))?;
+            Self::Tcp(port)
         } else if let Some(name) = s.strip_prefix("localfilesystem:") {
-            Ok(Self::LocalFilesystem(name.to_string()))
+            Self::LocalFilesystem(name.to_string())
         } else if let Some(name) = s.strip_prefix("localreserved:") {
-            Ok(Self::LocalReserved(name.to_string()))
+            Self::LocalReserved(name.to_string())
         } else if let Some(name) = s.strip_prefix("localabstract:") {
-            Ok(Self::LocalAbstract(name.to_string()))
+            Self::LocalAbstract(name.to_string())
         } else if let Some(name) = s.strip_prefix("dev:") {
-            Ok(Self::Dev(name.to_string()))
+            Self::Dev(name.to_string())
         } else if let Some(pid_str) = s.strip_prefix("jdwp:") {
-            let pid = pid_str.parse::<u32>().map_err(|_| {
-                crate::error::HdcError::Protocol(format!("Invalid JDWP pid: {}", pid_str))
-            })?;
-            Ok(Self::Jdwp(pid))
+            let pid = pid_str
+                .parse::<u32>()
+                .map_err(|_| HdcError::Protocol(format!("Invalid JDWP pid: {}", pid_str)))?;
+            Self::Jdwp(pid)
         } else if let Some(ark_str) = s.strip_prefix("ark:") {
             let parts: Vec<&str> = ark_str.split('@').collect();
             if parts.len() != 3 {
-                return Err(crate::error::HdcError::Protocol(format!(
+                return Err(HdcError::Protocol(format!(
                     "Invalid ark format: expected pid@tid@debugger, got {}",
                     ark_str
                 )));
             }
-            let pid = parts[0].parse::<u32>().map_err(|_| {
-                crate::error::HdcError::Protocol(format!("Invalid pid in ark: {}", parts[0]))
-            })?;
-            let tid = parts[1].parse::<u32>().map_err(|_| {
-                crate::error::HdcError::Protocol(format!("Invalid tid in ark: {}", parts[1]))
-            })?;
-            Ok(Self::Ark {
+            let pid = parts[0]
+                .parse::<u32>()
+                .map_err(|_| HdcError::Protocol(format!("Invalid pid in ark: {}", parts[0])))?;
+            let tid = parts[1]
+                .parse::<u32>()
+                .map_err(|_| HdcError::Protocol(format!("Invalid tid in ark: {}", parts[1])))?;
+            Self::Ark {
                 pid,
                 tid,
                 debugger: parts[2].to_string(),
-            })
+            }
         } else {
-            Err(crate::error::HdcError::Protocol(format!(
+            return Err(HdcError::Protocol(format!(
                 "Invalid forward node format: {}",
                 s
-            )))
+            )));
+        };
+
+        node.validate()?;
+        Ok(node)
+    }
+
+    /// Check that the node can be used in a forward task
+    ///
+    /// Rejects TCP port 0, empty names, and socket names longer than
+    /// [`MAX_SOCKET_NAME_LEN`].
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Tcp(0) => Err(HdcError::Protocol("Invalid TCP port: 0".to_string())),
+            Self::LocalFilesystem(name) | Self::LocalReserved(name) | Self::LocalAbstract(name) => {
+                if name.is_empty() {
+                    Err(HdcError::Protocol("Socket name is empty".to_string()))
+                } else if name.len() > MAX_SOCKET_NAME_LEN {
+                    Err(HdcError::Protocol(format!(
+                        "Socket name too long: {} bytes (max {})",
+                        name.len(),
+                        MAX_SOCKET_NAME_LEN
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Dev(name) if name.is_empty() => {
+                Err(HdcError::Protocol("Device name is empty".to_string()))
+            }
+            Self::Ark { debugger, .. } if debugger.is_empty() => {
+                Err(HdcError::Protocol("Ark debugger name is empty".to_string()))
+            }
+            _ => Ok(()),
         }
     }
 
@@ -94,8 +158,22 @@ impl ForwardNode {
     }
 }
 
+impl fmt::Display for ForwardNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_protocol_string())
+    }
+}
+
+impl FromStr for ForwardNode {
+    type Err = HdcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
 /// Forward task information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ForwardTask {
     pub local_node: ForwardNode,
     pub remote_node: ForwardNode,
@@ -139,13 +217,585 @@ impl ForwardTask {
     }
 
     /// Get task string (for removal)
+    ///
+    /// Nodes are listed in command order, so reverse tasks start with the
+    /// remote node, matching the server's `fport ls` output.
     pub fn task_string(&self) -> String {
-        format!(
-            "{} {}",
-            self.local_node.as_protocol_string(),
-            self.remote_node.as_protocol_string()
-        )
+        if self.is_forward {
+            format!(
+                "{} {}",
+                self.local_node.as_protocol_string(),
+                self.remote_node.as_protocol_string()
+            )
+        } else {
+            format!(
+                "{} {}",
+                self.remote_node.as_protocol_string(),
+                self.local_node.as_protocol_string()
+            )
+        }
     }
+
+    /// Parse a task from the server's task-string format
+    ///
+    /// Accepts a bare task string (`tcp:8080 tcp:8081`) or a full `fport ls`
+    /// line (`<connect_key>    tcp:8080 tcp:8081    [Forward]`). Lines without
+    /// a `[Reverse]` marker are treated as forward tasks. The connect key, if
+    /// present, is dropped; [`ForwardEntry::parse`] keeps it.
+    pub fn parse(s: &str) -> Result<Self> {
+        Self::parse_line(s).map(|(_, task)| task)
+    }
+
+    /// Parse a task string or `fport ls` line along with its connect key
+    fn parse_line(s: &str) -> Result<(Option<&str>, Self)> {
+        let mut parts: Vec<&str> = s.split_whitespace().collect();
+
+        let is_forward = match parts.last() {
+            Some(&"[Reverse]") => {
+                parts.pop();
+                false
+            }
+            Some(&"[Forward]") => {
+                parts.pop();
+                true
+            }
+            _ => true,
+        };
+
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(HdcError::Protocol(format!("Invalid forward task: {}", s)));
+        }
+
+        let first = ForwardNode::parse(parts[parts.len() - 2])?;
+        let second = ForwardNode::parse(parts[parts.len() - 1])?;
+
+        let task = if is_forward {
+            Self::forward(first, second)
+        } else {
+            Self::reverse(first, second)
+        };
+        let connect_key = (parts.len() == 3).then(|| parts[0]);
+        Ok((connect_key, task))
+    }
+}
+
+/// Forward task listed by the server, with the device it belongs to
+///
+/// Returned by [`crate::HdcClient::fport_list_tasks`] and
+/// [`crate::HdcClient::fport_list_for`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardEntry {
+    /// Connect key of the device the task belongs to
+    pub connect_key: String,
+    /// The forward or reverse task
+    pub task: ForwardTask,
+}
+
+impl ForwardEntry {
+    /// Parse a `fport ls` line (`<connect_key>    tcp:8080 tcp:8081    [Forward]`)
+    pub fn parse(line: &str) -> Result<Self> {
+        match ForwardTask::parse_line(line)? {
+            (Some(connect_key), task) => Ok(Self {
+                connect_key: connect_key.to_string(),
+                task,
+            }),
+            (None, _) => Err(HdcError::Protocol(format!(
+                "Forward list line without a connect key: {}",
+                line
+            ))),
+        }
+    }
+
+    /// Check whether the task belongs to the given device
+    pub fn belongs_to(&self, device_id: &str) -> bool {
+        self.connect_key == device_id
+    }
+}
+
+/// Keeps forward mappings alive across device reconnects
+///
+/// The manager remembers the desired forward/reverse tasks per device and
+/// re-creates them whenever [`ForwardManager::run`] sees the device come back
+/// online (e.g. after a reboot). Clones share the same mapping table, so one
+/// clone can run the watch loop in a spawned task while another adds mappings.
+///
+/// # Example
+/// ```no_run
+/// # use hdc_rs::forward::{ForwardManager, ForwardNode, ForwardTask};
+/// # use std::time::Duration;
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = ForwardManager::new("127.0.0.1:8710");
+/// manager.add(
+///     "FMR0223C13000649",
+///     ForwardTask::forward(ForwardNode::Tcp(8080), ForwardNode::Tcp(8080)),
+/// );
+///
+/// let watcher = manager.clone();
+/// tokio::spawn(async move { watcher.run(Duration::from_secs(2)).await });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForwardManager {
+    address: ServerAddr,
+    mappings: Arc<Mutex<Vec<(String, ForwardTask)>>>,
+}
+
+impl ForwardManager {
+    /// Create a manager for the given HDC server address
+    pub fn new(address: impl Into<ServerAddr>) -> Self {
+        Self {
+            address: address.into(),
+            mappings: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Remember a forward task for a device
+    ///
+    /// The task is created the next time the device is seen coming online,
+    /// or immediately via [`ForwardManager::apply`].
+    pub fn add(&self, device_id: &str, task: ForwardTask) {
+        let mut mappings = self.mappings.lock().unwrap();
+        if !mappings
+            .iter()
+            .any(|(id, existing)| id == device_id && *existing == task)
+        {
+            mappings.push((device_id.to_string(), task));
+        }
+    }
+
+    /// Forget a forward task for a device
+    ///
+    /// Returns `true` if the mapping was known. This does not remove the
+    /// forward from the server; use [`crate::HdcClient::fport_remove`] for that.
+    pub fn remove(&self, device_id: &str, task: &ForwardTask) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        let before = mappings.len();
+        mappings.retain(|(id, existing)| !(id == device_id && existing == task));
+        mappings.len() != before
+    }
+
+    /// Get a snapshot of all remembered mappings
+    pub fn mappings(&self) -> Vec<(String, ForwardTask)> {
+        self.mappings.lock().unwrap().clone()
+    }
+
+    fn tasks_for(&self, device_id: &str) -> Vec<ForwardTask> {
+        self.mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| id == device_id)
+            .map(|(_, task)| task.clone())
+            .collect()
+    }
+
+    /// Create all remembered tasks for a device
+    ///
+    /// Returns the number of tasks that were created successfully. A task
+    /// that fails is logged and doesn't stop the others.
+    pub async fn apply(&self, device_id: &str) -> Result<usize> {
+        let tasks = self.tasks_for(device_id);
+        let failed = self.create(device_id, &tasks).await;
+        Ok(tasks.len() - failed.len())
+    }
+
+    /// Create `tasks` on a device, returning the ones that failed
+    async fn create(&self, device_id: &str, tasks: &[ForwardTask]) -> Vec<ForwardTask> {
+        let mut failed = Vec::new();
+        for task in tasks {
+            match self.create_one(device_id, task).await {
+                Ok(_) => debug!("Created {} for {}", task.to_command_string(), device_id),
+                Err(e) => {
+                    warn!("Failed to create {}: {}", task.to_command_string(), e);
+                    failed.push(task.clone());
+                }
+            }
+        }
+        failed
+    }
+
+    async fn create_one(&self, device_id: &str, task: &ForwardTask) -> Result<String> {
+        let mut client = crate::HdcClient::connect(&self.address).await?;
+        client.connect_device(device_id).await?;
+        let result = if task.is_forward {
+            client
+                .fport(task.local_node.clone(), task.remote_node.clone())
+                .await
+        } else {
+            client
+                .rport(task.remote_node.clone(), task.local_node.clone())
+                .await
+        };
+        result.and_then(HdcError::check_response)
+    }
+
+    /// Remembered tasks of `device_id` missing from the server's task list
+    ///
+    /// The server drops a device's forwards when its session ends, so this
+    /// also catches reboots too quick for the device list to show.
+    async fn missing(&self, device_id: &str) -> Result<Vec<ForwardTask>> {
+        let mut client = crate::HdcClient::connect(&self.address).await?;
+        let existing: HashSet<(bool, String)> = client
+            .fport_list_for(device_id)
+            .await?
+            .iter()
+            .map(|entry| (entry.task.is_forward, entry.task.task_string()))
+            .collect();
+        Ok(self
+            .tasks_for(device_id)
+            .into_iter()
+            .filter(|task| !existing.contains(&(task.is_forward, task.task_string())))
+            .collect())
+    }
+
+    /// Re-create forwards when devices reconnect
+    ///
+    /// Follows [`HdcClient::track_devices`](crate::HdcClient::track_devices)
+    /// and creates a device's remembered forwards when it comes online,
+    /// including devices already online when the loop starts. Every
+    /// `interval` the server's task list is checked for forwards that went
+    /// missing, e.g. across a reboot between polls, and tasks that failed
+    /// are retried. This runs until the future is dropped or the task is
+    /// aborted.
+    pub async fn run(&self, interval: Duration) -> Result<()> {
+        info!("Starting forward manager with interval: {:?}", interval);
+
+        let mut events = crate::HdcClient::lazy(&self.address).track_devices(interval);
+        let mut online: HashSet<String> = HashSet::new();
+        let mut pending: HashMap<String, Vec<ForwardTask>> = HashMap::new();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(crate::DeviceEvent::Connected(device_id)) => {
+                        let tasks = self.tasks_for(&device_id);
+                        if !tasks.is_empty() {
+                            info!("Device {} came online, restoring forwards", device_id);
+                            self.restore(&device_id, &tasks, &mut pending).await;
+                        }
+                        online.insert(device_id);
+                    }
+                    Some(crate::DeviceEvent::Disconnected(device_id)) => {
+                        online.remove(&device_id);
+                        pending.remove(&device_id);
+                    }
+                    None => {
+                        return Err(HdcError::Protocol("Device tracking ended".to_string()));
+                    }
+                },
+                _ = ticker.tick() => {
+                    for device_id in &online {
+                        let tasks = match pending.remove(device_id) {
+                            Some(failed) => failed,
+                            None => match self.missing(device_id).await {
+                                Ok(missing) => missing,
+                                Err(e) => {
+                                    warn!("Failed to list forwards of {}: {}", device_id, e);
+                                    continue;
+                                }
+                            },
+                        };
+                        if !tasks.is_empty() {
+                            debug!("Restoring {} forward(s) for {}", tasks.len(), device_id);
+                            self.restore(device_id, &tasks, &mut pending).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Create `tasks`, keeping the ones that fail in `pending` for a retry
+    async fn restore(
+        &self,
+        device_id: &str,
+        tasks: &[ForwardTask],
+        pending: &mut HashMap<String, Vec<ForwardTask>>,
+    ) {
+        let failed = self.create(device_id, tasks).await;
+        if !failed.is_empty() {
+            pending.insert(device_id.to_string(), failed);
+        }
+    }
+}
+
+/// Debuggable process reported by `jpid`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JdwpProcess {
+    /// Process ID, usable with [`ForwardNode::Jdwp`]
+    pub pid: u32,
+    /// Process or bundle name, if reported by the daemon
+    pub name: Option<String>,
+}
+
+impl JdwpProcess {
+    /// Parse one line of `jpid` output (`pid` or `pid name`)
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let pid = parts.next()?.parse::<u32>().ok()?;
+        let name = parts.collect::<Vec<_>>().join(" ");
+        Some(Self {
+            pid,
+            name: if name.is_empty() { None } else { Some(name) },
+        })
+    }
+
+    /// Forward node targeting this process
+    pub fn forward_node(&self) -> ForwardNode {
+        ForwardNode::Jdwp(self.pid)
+    }
+}
+
+/// TCP stream connected through a temporary forward
+///
+/// Returned by [`crate::HdcClient::open_forwarded`]. The stream derefs to the
+/// underlying [`TcpStream`] and implements `AsyncRead`/`AsyncWrite`. When it is
+/// dropped, the forward task is removed in the background; use
+/// [`ForwardedStream::close`] to remove it and wait for the result.
+pub struct ForwardedStream {
+    stream: Option<TcpStream>,
+    task: ForwardTask,
+    /// Server to remove the forward from; `None` once removed
+    server_address: Option<ServerAddr>,
+}
+
+impl ForwardedStream {
+    pub(crate) fn new(stream: TcpStream, task: ForwardTask, server_address: ServerAddr) -> Self {
+        Self {
+            stream: Some(stream),
+            task,
+            server_address: Some(server_address),
+        }
+    }
+
+    /// Get the forward task backing this stream
+    pub fn task(&self) -> &ForwardTask {
+        &self.task
+    }
+
+    /// Get the local TCP port used by the forward
+    pub fn local_port(&self) -> Option<u16> {
+        match self.task.local_node {
+            ForwardNode::Tcp(port) => Some(port),
+            _ => None,
+        }
+    }
+
+    /// Close the stream and remove the forward task
+    pub async fn close(mut self) -> Result<()> {
+        self.stream = None;
+        let task_str = self.task.task_string();
+        // Taking the address prevents Drop from removing the task again
+        if let Some(address) = self.server_address.take() {
+            let mut client = crate::HdcClient::connect(address).await?;
+            client.fport_remove(&task_str).await?;
+        }
+        Ok(())
+    }
+
+    fn stream_mut(&mut self) -> &mut TcpStream {
+        self.stream.as_mut().expect("stream is only taken on close")
+    }
+}
+
+impl Deref for ForwardedStream {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        self.stream.as_ref().expect("stream is only taken on close")
+    }
+}
+
+impl DerefMut for ForwardedStream {
+    fn deref_mut(&mut self) -> &mut TcpStream {
+        self.stream_mut()
+    }
+}
+
+impl AsyncRead for ForwardedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().stream_mut()).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ForwardedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(self.get_mut().stream_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().stream_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().stream_mut()).poll_shutdown(cx)
+    }
+}
+
+impl Drop for ForwardedStream {
+    fn drop(&mut self) {
+        if let Some(address) = self.server_address.take() {
+            spawn_remove(address, self.task.task_string());
+        }
+    }
+}
+
+/// Reverse forward bridged into a host-side Unix socket
+///
+/// Returned by [`crate::HdcClient::rport_unix`]. The server only terminates
+/// reverse forwards at host TCP ports, so the bridge listens on a loopback
+/// port and relays each accepted connection to the host socket (a named pipe
+/// on Windows). Dropping the bridge stops relaying and removes the forward in
+/// the background; use [`ReverseBridge::close`] to wait for the removal.
+pub struct ReverseBridge {
+    task: ForwardTask,
+    /// Server to remove the forward from; `None` once removed
+    server_address: Option<ServerAddr>,
+    accept_task: tokio::task::JoinHandle<()>,
+}
+
+impl ReverseBridge {
+    /// Get the reverse forward task backing this bridge
+    pub fn task(&self) -> &ForwardTask {
+        &self.task
+    }
+
+    /// Get the loopback TCP port the bridge listens on
+    pub fn local_port(&self) -> Option<u16> {
+        match self.task.local_node {
+            ForwardNode::Tcp(port) => Some(port),
+            _ => None,
+        }
+    }
+
+    /// Stop the bridge and remove the reverse forward
+    pub async fn close(mut self) -> Result<()> {
+        self.accept_task.abort();
+        let task_str = self.task.task_string();
+        // Taking the address prevents Drop from removing the task again
+        if let Some(address) = self.server_address.take() {
+            let mut client = crate::HdcClient::connect(address).await?;
+            client.fport_remove(&task_str).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ReverseBridge {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        if let Some(address) = self.server_address.take() {
+            spawn_remove(address, self.task.task_string());
+        }
+    }
+}
+
+/// Bind a loopback listener for a reverse bridge
+pub(crate) async fn bind_bridge_listener() -> Result<(tokio::net::TcpListener, u16)> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+    let port = listener.local_addr()?.port();
+    Ok((listener, port))
+}
+
+/// Start relaying connections from `listener` into the host socket at `path`
+pub(crate) fn start_bridge(
+    listener: tokio::net::TcpListener,
+    task: ForwardTask,
+    path: std::path::PathBuf,
+    server_address: Option<ServerAddr>,
+) -> ReverseBridge {
+    let accept_task = tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            let (mut inbound, peer) = match listener.accept().await {
+                Ok(conn) => {
+                    failures = 0;
+                    conn
+                }
+                Err(e) => {
+                    failures += 1;
+                    let delay = ACCEPT_BACKOFF.delay(failures);
+                    warn!(
+                        "Reverse bridge accept failed, retrying in {:?}: {}",
+                        delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+            debug!("Reverse bridge accepted connection from {}", peer);
+
+            let path = path.clone();
+            tokio::spawn(async move {
+                let result = match connect_host_socket(&path).await {
+                    Ok(mut outbound) => tokio::io::copy_bidirectional(&mut inbound, &mut outbound)
+                        .await
+                        .map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    debug!("Reverse bridge connection to {:?} ended: {}", path, e);
+                }
+            });
+        }
+    });
+
+    ReverseBridge {
+        task,
+        server_address,
+        accept_task,
+    }
+}
+
+#[cfg(unix)]
+async fn connect_host_socket(path: &std::path::Path) -> io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(windows)]
+async fn connect_host_socket(
+    path: &std::path::Path,
+) -> io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(path)
+}
+
+/// Remove a forward task in the background on the current runtime
+fn spawn_remove(address: ServerAddr, task_str: String) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            debug!("Removing temporary forward: {}", task_str);
+            handle.spawn(async move {
+                let result = match crate::HdcClient::connect(address).await {
+                    Ok(mut client) => client.fport_remove(&task_str).await.map(|_| ()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    warn!("Failed to remove temporary forward {}: {}", task_str, e);
+                }
+            });
+        }
+        Err(_) => warn!(
+            "No runtime available, temporary forward {} was not removed",
+            task_str
+        ),
+    }
+}
+
+/// Find a free local TCP port by binding to port 0
+pub(crate) fn free_local_port() -> io::Result<u16> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+    Ok(listener.local_addr()?.port())
 }
 
 #[cfg(test)]
@@ -184,4 +834,188 @@ mod tests {
         assert_eq!(task.to_command_string(), "fport tcp:8080 tcp:8081");
         assert_eq!(task.task_string(), "tcp:8080 tcp:8081");
     }
+
+    #[test]
+    fn test_jdwp_process_parse() {
+        let proc = JdwpProcess::parse("1234").unwrap();
+        assert_eq!(proc.pid, 1234);
+        assert_eq!(proc.name, None);
+
+        let proc = JdwpProcess::parse("5678 com.example.app").unwrap();
+        assert_eq!(proc.name.as_deref(), Some("com.example.app"));
+        assert_eq!(proc.forward_node(), ForwardNode::Jdwp(5678));
+
+        assert!(JdwpProcess::parse("[Empty]").is_none());
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let node: ForwardNode = "localabstract:my_socket".parse().unwrap();
+        assert_eq!(node, ForwardNode::LocalAbstract("my_socket".to_string()));
+        assert_eq!(node.to_string(), "localabstract:my_socket");
+        assert_eq!(ForwardNode::Tcp(8080).to_string(), "tcp:8080");
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(ForwardNode::parse("tcp:0").is_err());
+        assert!(ForwardNode::parse("localabstract:").is_err());
+        let long_name = format!("localfilesystem:{}", "a".repeat(MAX_SOCKET_NAME_LEN + 1));
+        assert!(ForwardNode::parse(&long_name).is_err());
+        assert!(ForwardNode::Tcp(0).validate().is_err());
+        assert!(ForwardNode::Tcp(1).validate().is_ok());
+    }
+
+    #[test]
+    fn test_forward_task_parse() {
+        let task = ForwardTask::parse("tcp:8080 tcp:8081").unwrap();
+        assert_eq!(
+            task,
+            ForwardTask::forward(ForwardNode::Tcp(8080), ForwardNode::Tcp(8081))
+        );
+
+        let task =
+            ForwardTask::parse("FMR0223C13000649    tcp:9090 tcp:9091    [Reverse]").unwrap();
+        assert!(!task.is_forward);
+        assert_eq!(task.remote_node, ForwardNode::Tcp(9090));
+        assert_eq!(task.local_node, ForwardNode::Tcp(9091));
+        assert_eq!(task.task_string(), "tcp:9090 tcp:9091");
+        assert_eq!(task.to_command_string(), "rport tcp:9090 tcp:9091");
+
+        let entry =
+            ForwardEntry::parse("FMR0223C13000649    tcp:9090 tcp:9091    [Reverse]").unwrap();
+        assert_eq!(entry.connect_key, "FMR0223C13000649");
+        assert_eq!(entry.task, task);
+        assert!(entry.belongs_to("FMR0223C13000649"));
+        assert!(!entry.belongs_to("other"));
+        assert!(ForwardEntry::parse("tcp:9090 tcp:9091").is_err());
+
+        assert!(ForwardTask::parse("[Empty]").is_err());
+    }
+
+    #[test]
+    fn test_forward_manager_mappings() {
+        let manager = ForwardManager::new("127.0.0.1:8710");
+        let task = ForwardTask::forward(ForwardNode::Tcp(8080), ForwardNode::Tcp(8081));
+
+        manager.add("device-1", task.clone());
+        manager.add("device-1", task.clone());
+        manager.clone().add("device-2", task.clone());
+        assert_eq!(manager.mappings().len(), 2);
+
+        assert!(manager.remove("device-1", &task));
+        assert!(!manager.remove("device-1", &task));
+        assert_eq!(manager.mappings(), vec![("device-2".to_string(), task)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_forward_manager_retries_and_restores() {
+        use crate::testing::{FakeDevice, MockServer};
+
+        let retried = FakeDevice::new("dev-1")
+            .when_command("fport tcp:8080 tcp:8080")
+            .replies(["[Fail]Forward parameter failed", "Forwardport result:OK"]);
+        let dropped = FakeDevice::new("dev-2")
+            .when_command("fport tcp:9090 tcp:9090")
+            .reply("Forwardport result:OK");
+        // The server only lists dev-1's forward, as if dev-2 had rebooted
+        let server = MockServer::new()
+            .respond("fport ls", "dev-1    tcp:8080 tcp:8080    [Forward]\n")
+            .device(retried.clone())
+            .device(dropped.clone())
+            .spawn_in_memory();
+
+        let manager = ForwardManager::new(server.server_addr());
+        manager.add(
+            "dev-1",
+            ForwardTask::forward(ForwardNode::Tcp(8080), ForwardNode::Tcp(8080)),
+        );
+        manager.add(
+            "dev-2",
+            ForwardTask::forward(ForwardNode::Tcp(9090), ForwardNode::Tcp(9090)),
+        );
+        let interval = Duration::from_secs(1);
+        let watcher = manager.clone();
+        let task = tokio::spawn(async move { watcher.run(interval).await });
+        tokio::time::sleep(interval * 5).await;
+        task.abort();
+
+        assert_eq!(
+            retried.commands(),
+            ["fport tcp:8080 tcp:8080", "fport tcp:8080 tcp:8080"]
+        );
+        assert!(dropped.commands().len() > 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reverse_bridge_relays_to_unix_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = std::env::temp_dir().join(format!("hdc-rs-bridge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("echo.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let uds = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut conn, _) = uds.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            conn.read_exact(&mut buf).await.unwrap();
+            conn.write_all(&buf).await.unwrap();
+        });
+
+        let (listener, port) = bind_bridge_listener().await.unwrap();
+        let task = ForwardTask::reverse(ForwardNode::Tcp(9000), ForwardNode::Tcp(port));
+        // No server address: nothing to remove on drop
+        let bridge = start_bridge(listener, task, path.clone(), None);
+        assert_eq!(bridge.local_port(), Some(port));
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(bridge);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_free_local_port() {
+        let port = free_local_port().unwrap();
+        assert_ne!(port, 0);
+    }
+
+    /// Nodes that survive validation
+    fn valid_node() -> impl proptest::strategy::Strategy<Value = ForwardNode> {
+        use proptest::prelude::*;
+        let name = "[^\\x00]{1,107}"
+            .prop_filter("fits sun_path", |n: &String| n.len() <= MAX_SOCKET_NAME_LEN);
+        prop_oneof![
+            (1u16..).prop_map(ForwardNode::Tcp),
+            name.clone().prop_map(ForwardNode::LocalFilesystem),
+            name.clone().prop_map(ForwardNode::LocalReserved),
+            name.prop_map(ForwardNode::LocalAbstract),
+            ".+".prop_map(ForwardNode::Dev),
+            any::<u32>().prop_map(ForwardNode::Jdwp),
+            (any::<u32>(), any::<u32>(), "[^@]+")
+                .prop_map(|(pid, tid, debugger)| { ForwardNode::Ark { pid, tid, debugger } }),
+        ]
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_forward_node_roundtrip(node in valid_node()) {
+            proptest::prop_assert_eq!(ForwardNode::parse(&node.to_string()).unwrap(), node);
+        }
+
+        #[test]
+        fn test_forward_node_parse_any(text in "(tcp|localfilesystem|localreserved|localabstract|dev|jdwp|ark)?:?\\PC*") {
+            if let Ok(node) = ForwardNode::parse(&text) {
+                proptest::prop_assert!(node.validate().is_ok());
+                proptest::prop_assert_eq!(ForwardNode::parse(&node.to_string()).unwrap(), node);
+            }
+        }
+    }
 }
@@ -0,0 +1,805 @@
+//! Device log (hilog) types and parsing
+
+use std::collections::HashSet;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::error::{HdcError, Result};
+use crate::shell;
+
+/// Log level of a hilog entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Debug (D)
+    Debug,
+    /// Info (I)
+    Info,
+    /// Warning (W)
+    Warn,
+    /// Error (E)
+    Error,
+    /// Fatal (F)
+    Fatal,
+}
+
+impl LogLevel {
+    /// Parse a level from its single-letter form (`D`, `I`, `W`, `E`, `F`)
+    pub fn from_letter(s: &str) -> Option<Self> {
+        match s {
+            "D" => Some(Self::Debug),
+            "I" => Some(Self::Info),
+            "W" => Some(Self::Warn),
+            "E" => Some(Self::Error),
+            "F" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Get the single-letter form of the level
+    pub fn as_letter(&self) -> &'static str {
+        match self {
+            Self::Debug => "D",
+            Self::Info => "I",
+            Self::Warn => "W",
+            Self::Error => "E",
+            Self::Fatal => "F",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_letter())
+    }
+}
+
+/// Timestamp of a hilog entry
+///
+/// hilog prints `MM-DD HH:MM:SS.fff` by default and prefixes the year when
+/// run with `-v year`. Timestamps of the same kind compare chronologically;
+/// one without a year sorts before any with a year, whatever the date, so
+/// don't compare entries read with and without `-v year`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct LogTimestamp {
+    /// Year, if printed
+    pub year: Option<u16>,
+    /// Month (1-12)
+    pub month: u8,
+    /// Day of month (1-31)
+    pub day: u8,
+    /// Hour (0-23)
+    pub hour: u8,
+    /// Minute (0-59)
+    pub minute: u8,
+    /// Second (0-59)
+    pub second: u8,
+    /// Fraction of the second in nanoseconds
+    pub nanos: u32,
+}
+
+impl LogTimestamp {
+    /// Parse a timestamp from its date and time parts
+    ///
+    /// `date` is `MM-DD` or `YYYY-MM-DD`, `time` is `HH:MM:SS[.fraction]`.
+    pub fn parse(date: &str, time: &str) -> Option<Self> {
+        let date_parts: Vec<&str> = date.split('-').collect();
+        let (year, month, day) = match date_parts.as_slice() {
+            [m, d] => (None, m.parse().ok()?, d.parse().ok()?),
+            [y, m, d] => (Some(y.parse().ok()?), m.parse().ok()?, d.parse().ok()?),
+            _ => return None,
+        };
+
+        let (hms, fraction) = match time.split_once('.') {
+            Some((hms, fraction)) => (hms, fraction),
+            None => (time, ""),
+        };
+        let time_parts: Vec<&str> = hms.split(':').collect();
+        let [hour, minute, second] = time_parts.as_slice() else {
+            return None;
+        };
+
+        let nanos = if fraction.is_empty() {
+            0
+        } else {
+            if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let value: u32 = fraction.parse().ok()?;
+            value * 10u32.pow(9 - fraction.len() as u32)
+        };
+
+        Some(Self {
+            year,
+            month,
+            day,
+            hour: hour.parse().ok()?,
+            minute: minute.parse().ok()?,
+            second: second.parse().ok()?,
+            nanos,
+        })
+    }
+}
+
+impl fmt::Display for LogTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(year) = self.year {
+            write!(f, "{:04}-", year)?;
+        }
+        write!(
+            f,
+            "{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.nanos / 1_000_000
+        )
+    }
+}
+
+/// A parsed hilog entry
+///
+/// Parsed from the standard hilog line format:
+/// ```text
+/// 01-15 10:23:45.123  1234  5678 I A03200/MyTag: message text
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Time the entry was logged
+    pub timestamp: LogTimestamp,
+    /// Process ID
+    pub pid: u32,
+    /// Thread ID
+    pub tid: u32,
+    /// Log level
+    pub level: LogLevel,
+    /// Log type and domain as printed (e.g. `A03200`)
+    pub domain: String,
+    /// Log tag
+    pub tag: String,
+    /// Message text
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Parse a single hilog line
+    ///
+    /// Returns `None` for lines that are not log entries (banners, blank
+    /// lines, or partial output).
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut rest = line.trim_start();
+
+        let mut fields = [""; 5];
+        for field in fields.iter_mut() {
+            let (value, tail) = rest.split_once(char::is_whitespace)?;
+            *field = value;
+            rest = tail.trim_start();
+        }
+        let [date, time, pid, tid, level] = fields;
+
+        let (domain, tail) = rest.split_once('/')?;
+        let (tag, message) = match tail.split_once(": ") {
+            Some((tag, message)) => (tag, message),
+            None => (tail.strip_suffix(':')?, ""),
+        };
+        if domain.is_empty() || domain.contains(char::is_whitespace) {
+            return None;
+        }
+
+        Some(Self {
+            timestamp: LogTimestamp::parse(date, time)?,
+            pid: pid.parse().ok()?,
+            tid: tid.parse().ok()?,
+            level: LogLevel::from_letter(level)?,
+            domain: domain.to_string(),
+            tag: tag.to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:>5} {:>5} {} {}/{}: {}",
+            self.timestamp, self.pid, self.tid, self.level, self.domain, self.tag, self.message
+        )
+    }
+}
+
+/// hilog log type (buffer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogType {
+    /// Application logs
+    App,
+    /// System core logs
+    Core,
+    /// Init process logs
+    Init,
+    /// Kernel logs
+    Kmsg,
+}
+
+impl LogType {
+    /// Get the name used on the hilog command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Core => "core",
+            Self::Init => "init",
+            Self::Kmsg => "kmsg",
+        }
+    }
+}
+
+/// Filter for hilog commands
+///
+/// Serializes to the matching hilog flags (`-T`, `-L`, `-P`, `-D`, `-t`,
+/// `-e`), with tag, domain and regex values single-quoted for the device
+/// shell. Raw arguments can still be passed with [`HilogFilter::raw`] or by
+/// converting from a string, for flags the builder doesn't cover; they are
+/// passed to the shell unchanged.
+///
+/// # Example
+/// ```
+/// use hdc_rs::hilog::{HilogFilter, LogLevel, LogType};
+///
+/// let filter = HilogFilter::new()
+///     .tag("MyTag")
+///     .min_level(LogLevel::Warn)
+///     .log_type(LogType::App);
+/// assert_eq!(filter.to_args(), "-T 'MyTag' -L W,E,F -t app");
+///
+/// // Raw arguments still work
+/// let filter: HilogFilter = "-x".into();
+/// assert_eq!(filter.to_args(), "-x");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HilogFilter {
+    tags: Vec<String>,
+    min_level: Option<LogLevel>,
+    pids: Vec<u32>,
+    domains: Vec<String>,
+    log_types: Vec<LogType>,
+    regex: Option<String>,
+    raw: Option<String>,
+}
+
+impl HilogFilter {
+    /// Create an empty filter (all logs)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only show logs with this tag (can be called multiple times)
+    ///
+    /// Tags may only contain ASCII letters, digits, `_`, `.` and `-`; see
+    /// [`validate`](Self::validate).
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Only show logs at or above this level
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Only show logs from this process (can be called multiple times)
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.pids.push(pid);
+        self
+    }
+
+    /// Only show logs from this domain, e.g. `0xD003200` (can be called multiple times)
+    ///
+    /// Domains are hex numbers with an optional `0x` prefix; see
+    /// [`validate`](Self::validate).
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domains.push(domain.into());
+        self
+    }
+
+    /// Only show logs of this type (can be called multiple times)
+    pub fn log_type(mut self, log_type: LogType) -> Self {
+        self.log_types.push(log_type);
+        self
+    }
+
+    /// Only show logs matching this regular expression
+    pub fn regex(mut self, pattern: impl Into<String>) -> Self {
+        self.regex = Some(pattern.into());
+        self
+    }
+
+    /// Append raw hilog arguments
+    pub fn raw(mut self, args: impl Into<String>) -> Self {
+        self.raw = Some(args.into());
+        self
+    }
+
+    /// Check whether an entry passes the tag, level, pid and domain filters
+    ///
+    /// Useful when filtering already-captured entries on the client side.
+    /// The regex and raw arguments are not evaluated.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        (self.tags.is_empty() || self.tags.contains(&entry.tag))
+            && self.min_level.map_or(true, |level| entry.level >= level)
+            && (self.pids.is_empty() || self.pids.contains(&entry.pid))
+            && (self.domains.is_empty()
+                || self
+                    .domains
+                    .iter()
+                    .any(|domain| domain_matches(domain, &entry.domain)))
+    }
+
+    /// Check that every tag and domain is a plain name
+    ///
+    /// Tags may only contain ASCII letters, digits, `_`, `.` and `-`, and
+    /// domains must be hex numbers with an optional `0x` prefix. Anything
+    /// else is an [`HdcError::Config`]. The hilog methods of the clients
+    /// check this before sending the command.
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::hilog::HilogFilter;
+    ///
+    /// assert!(HilogFilter::new().tag("MyTag").validate().is_ok());
+    /// assert!(HilogFilter::new().tag("x;reboot").validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if let Some(tag) = self.tags.iter().find(|tag| !is_plain_tag(tag)) {
+            return Err(HdcError::Config(format!("Invalid hilog tag: {:?}", tag)));
+        }
+        if let Some(domain) = self.domains.iter().find(|domain| !is_hex_domain(domain)) {
+            return Err(HdcError::Config(format!(
+                "Invalid hilog domain: {:?}",
+                domain
+            )));
+        }
+        Ok(())
+    }
+
+    /// Convert the filter to hilog command-line arguments
+    pub fn to_args(&self) -> String {
+        let mut args = Vec::new();
+
+        if !self.tags.is_empty() {
+            args.push(format!("-T {}", shell::quote(&self.tags.join(","))));
+        }
+        if let Some(min_level) = self.min_level {
+            let levels: Vec<&str> = [
+                LogLevel::Debug,
+                LogLevel::Info,
+                LogLevel::Warn,
+                LogLevel::Error,
+                LogLevel::Fatal,
+            ]
+            .iter()
+            .filter(|level| **level >= min_level)
+            .map(|level| level.as_letter())
+            .collect();
+            args.push(format!("-L {}", levels.join(",")));
+        }
+        if !self.pids.is_empty() {
+            let pids: Vec<String> = self.pids.iter().map(|pid| pid.to_string()).collect();
+            args.push(format!("-P {}", pids.join(",")));
+        }
+        if !self.domains.is_empty() {
+            args.push(format!("-D {}", shell::quote(&self.domains.join(","))));
+        }
+        if !self.log_types.is_empty() {
+            let types: Vec<&str> = self.log_types.iter().map(|t| t.as_str()).collect();
+            args.push(format!("-t {}", types.join(",")));
+        }
+        if let Some(regex) = &self.regex {
+            args.push(format!("-e {}", shell::quote(regex)));
+        }
+        if let Some(raw) = &self.raw {
+            if !raw.trim().is_empty() {
+                args.push(raw.trim().to_string());
+            }
+        }
+
+        args.join(" ")
+    }
+
+    /// Build the full hilog command string, after [`validate`](Self::validate)
+    pub(crate) fn to_command(&self) -> Result<String> {
+        self.validate()?;
+        let args = self.to_args();
+        if args.is_empty() {
+            Ok("hilog".to_string())
+        } else {
+            Ok(format!("hilog {}", args))
+        }
+    }
+}
+
+impl From<&str> for HilogFilter {
+    fn from(args: &str) -> Self {
+        Self::new().raw(args)
+    }
+}
+
+impl From<String> for HilogFilter {
+    fn from(args: String) -> Self {
+        Self::new().raw(args)
+    }
+}
+
+impl From<Option<&str>> for HilogFilter {
+    fn from(args: Option<&str>) -> Self {
+        args.map(Self::from).unwrap_or_default()
+    }
+}
+
+/// Compare a filter domain (`0xD003200`, `3200`) with a printed domain (`A03200`)
+fn domain_matches(filter: &str, printed: &str) -> bool {
+    let parse_hex = |s: &str| u32::from_str_radix(s, 16).ok();
+    let filter_value = filter
+        .strip_prefix("0x")
+        .or_else(|| filter.strip_prefix("0X"))
+        .unwrap_or(filter);
+    let printed_value = printed.get(1..).unwrap_or("");
+
+    match (parse_hex(filter_value), parse_hex(printed_value)) {
+        // The printed form only shows the low 16 bits of the domain
+        (Some(f), Some(p)) => f & 0xFFFF == p & 0xFFFF,
+        _ => filter == printed,
+    }
+}
+
+/// Whether `tag` is safe to pass to `hilog -T`
+fn is_plain_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Whether `domain` is a hex number, optionally prefixed with `0x`
+fn is_hex_domain(domain: &str) -> bool {
+    let digits = domain
+        .strip_prefix("0x")
+        .or_else(|| domain.strip_prefix("0X"))
+        .unwrap_or(domain);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Stream of log entries produced by background hilog connections
+///
+/// Returned by [`crate::HdcClient::hilog_watch`] and
+/// [`MultiLogCollector::start`](crate::collector::MultiLogCollector::start).
+/// Implements [`Stream`] and also offers an inherent [`LogStream::next`] for
+/// use without extra crates. The background connections are closed when the
+/// stream is dropped.
+pub struct LogStream<T = LogEntry> {
+    rx: mpsc::UnboundedReceiver<T>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<T> LogStream<T> {
+    pub(crate) fn new(rx: mpsc::UnboundedReceiver<T>, tasks: Vec<JoinHandle<()>>) -> Self {
+        Self { rx, tasks }
+    }
+
+    /// Receive the next item, or `None` once all log streams have ended
+    pub async fn next(&mut self) -> Option<T> {
+        self.rx.recv().await
+    }
+}
+
+impl<T> Stream for LogStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for LogStream<T> {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Tracks the position in a log stream so a reconnect can resume from it
+///
+/// hilog replays its buffer whenever a stream is (re)opened. After
+/// [`resume`](LogCursor::resume), [`LogCursor::accept`] drops the replayed
+/// lines older than the last entry accepted, as well as exact repeats of
+/// lines sharing its timestamp, so nothing is duplicated across reconnects.
+/// The first entry newer than that ends the replay, and from then on every
+/// line is accepted, so lines out of order between hilog buffers or stamped
+/// by a clock that went back, e.g. after a reboot, are kept. Lines without
+/// a hilog header follow the decision made for the entry before them.
+#[derive(Debug, Clone, Default)]
+pub struct LogCursor {
+    last: Option<LogTimestamp>,
+    seen_at_last: HashSet<String>,
+    replaying: bool,
+    accepting: bool,
+}
+
+impl LogCursor {
+    /// Create a cursor that accepts everything
+    pub fn new() -> Self {
+        Self {
+            accepting: true,
+            ..Self::default()
+        }
+    }
+
+    /// Create a cursor that skips the entries before `timestamp`, until
+    /// the first one after it
+    pub fn since(timestamp: LogTimestamp) -> Self {
+        Self {
+            last: Some(timestamp),
+            replaying: true,
+            ..Self::new()
+        }
+    }
+
+    /// Timestamp of the last accepted entry
+    pub fn last_timestamp(&self) -> Option<LogTimestamp> {
+        self.last
+    }
+
+    /// Note that the stream was reopened and hilog is replaying its buffer
+    pub fn resume(&mut self) {
+        self.replaying = self.last.is_some();
+    }
+
+    /// Decide whether a line is new, advancing the cursor if it is
+    pub fn accept(&mut self, line: &str) -> bool {
+        let Some(entry) = LogEntry::parse(line) else {
+            return self.accepting;
+        };
+
+        if self.replaying {
+            match self.last {
+                Some(last) if entry.timestamp < last => {
+                    self.accepting = false;
+                    return false;
+                }
+                Some(last) if entry.timestamp == last => {
+                    self.accepting = self.seen_at_last.insert(line.to_string());
+                    return self.accepting;
+                }
+                _ => self.replaying = false,
+            }
+        }
+
+        if self.last != Some(entry.timestamp) {
+            self.last = Some(entry.timestamp);
+            self.seen_at_last.clear();
+        }
+        self.seen_at_last.insert(line.to_string());
+        self.accepting = true;
+        true
+    }
+}
+
+/// Splits streamed log chunks into complete lines
+///
+/// hilog output arrives in arbitrary chunks; a line may span several packets.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    /// Append a chunk and return all lines completed by it
+    pub(crate) fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(chunk);
+        let Some(last_newline) = self.pending.rfind('\n') else {
+            return Vec::new();
+        };
+
+        let remainder = self.pending.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.pending, remainder);
+        complete
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect()
+    }
+
+    /// Take any incomplete trailing line
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.pending))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_resume() {
+        let a = "01-15 10:00:00.000  1  1 I A00001/T: a";
+        let b = "01-15 10:00:01.000  1  1 I A00001/T: b";
+        let c = "01-15 10:00:01.000  1  1 I A00001/T: c";
+        let d = "01-15 10:00:02.000  1  1 I A00001/T: d";
+
+        let mut cursor = LogCursor::new();
+        assert!(cursor.accept(a));
+        assert!(cursor.accept(b));
+        assert!(cursor.accept("  continuation"));
+
+        // Reconnect replays the buffer
+        cursor.resume();
+        assert!(!cursor.accept(a));
+        assert!(!cursor.accept("  continuation"));
+        assert!(!cursor.accept(b));
+        assert!(cursor.accept(c));
+        assert!(cursor.accept(d));
+        assert_eq!(cursor.last_timestamp().unwrap().second, 2);
+
+        let mut cursor = LogCursor::since(LogEntry::parse(b).unwrap().timestamp);
+        assert!(!cursor.accept(a));
+        assert!(cursor.accept(c));
+    }
+
+    #[test]
+    fn test_cursor_keeps_live_lines() {
+        let a = "01-15 10:00:05.000  1  1 I A00001/T: a";
+        let b = "01-15 10:00:06.000  1  1 I A00001/T: b";
+        // Kernel buffer line interleaved behind the app buffer
+        let kernel = "01-15 10:00:05.500  0  0 I C02D00/kernel: late";
+        // Clock back at its default after a reboot
+        let booted = "01-01 08:00:01.000  1  1 I A00001/T: booted";
+        let later = "01-01 08:00:02.000  1  1 I A00001/T: later";
+
+        let mut cursor = LogCursor::new();
+        assert!(cursor.accept(a));
+        assert!(cursor.accept(b));
+        assert!(cursor.accept(kernel));
+        assert!(cursor.accept(booted));
+        assert!(cursor.accept(later));
+
+        // Resuming after the reboot only skips what was already seen
+        cursor.resume();
+        assert!(!cursor.accept(booted));
+        assert!(!cursor.accept(later));
+        let next = "01-01 08:00:03.000  1  1 I A00001/T: next";
+        assert!(cursor.accept(next));
+        assert!(cursor.accept(a));
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        let entry =
+            LogEntry::parse("01-15 10:23:45.123  1234  5678 I A03200/MyTag: hello: world").unwrap();
+        assert_eq!(entry.timestamp.month, 1);
+        assert_eq!(entry.timestamp.day, 15);
+        assert_eq!(entry.timestamp.nanos, 123_000_000);
+        assert_eq!(entry.pid, 1234);
+        assert_eq!(entry.tid, 5678);
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.domain, "A03200");
+        assert_eq!(entry.tag, "MyTag");
+        assert_eq!(entry.message, "hello: world");
+    }
+
+    #[test]
+    fn test_parse_entry_with_year() {
+        let entry =
+            LogEntry::parse("2024-01-15 10:23:45.123456   42   43 E C01406/render_service: oops")
+                .unwrap();
+        assert_eq!(entry.timestamp.year, Some(2024));
+        assert_eq!(entry.timestamp.nanos, 123_456_000);
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.tag, "render_service");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(LogEntry::parse("").is_none());
+        assert!(LogEntry::parse("----beginning of main").is_none());
+        assert!(LogEntry::parse("01-15 10:23:45.123 1234").is_none());
+    }
+
+    #[test]
+    fn test_level_order() {
+        assert!(LogLevel::Error > LogLevel::Warn);
+        assert!(LogLevel::Debug < LogLevel::Info);
+    }
+
+    #[test]
+    fn test_timestamp_order_and_display() {
+        let a = LogTimestamp::parse("01-15", "10:23:45.123").unwrap();
+        let b = LogTimestamp::parse("01-15", "10:23:45.124").unwrap();
+        assert!(a < b);
+        assert_eq!(a.to_string(), "01-15 10:23:45.123");
+
+        let dated = LogTimestamp::parse("2024-01-15", "10:23:45.123").unwrap();
+        let later = LogTimestamp::parse("12-31", "23:59:59.999").unwrap();
+        assert!(later < dated);
+        assert!(a < dated);
+        assert_ne!(a, dated);
+        assert!(dated < LogTimestamp::parse("2025-01-01", "00:00:00").unwrap());
+    }
+
+    #[test]
+    fn test_filter_args() {
+        assert_eq!(HilogFilter::new().to_command().unwrap(), "hilog");
+
+        let filter = HilogFilter::new()
+            .tag("A")
+            .tag("B")
+            .min_level(LogLevel::Error)
+            .pid(42)
+            .domain("0xD003200")
+            .log_type(LogType::Core)
+            .regex("crash at")
+            .raw("-x");
+        assert_eq!(
+            filter.to_args(),
+            "-T 'A,B' -L E,F -P 42 -D '0xD003200' -t core -e 'crash at' -x"
+        );
+
+        let filter: HilogFilter = None.into();
+        assert_eq!(filter, HilogFilter::new());
+        let filter: HilogFilter = Some("-t app").into();
+        assert_eq!(filter.to_command().unwrap(), "hilog -t app");
+    }
+
+    #[test]
+    fn test_filter_quotes_values() {
+        let filter = HilogFilter::new().regex("foo|bar(baz)");
+        assert_eq!(filter.to_command().unwrap(), "hilog -e 'foo|bar(baz)'");
+        let filter = HilogFilter::new().regex("it's $(id)");
+        assert_eq!(filter.to_args(), "-e 'it'\\''s $(id)'");
+
+        let filter = HilogFilter::new().tag("x;reboot");
+        assert!(matches!(filter.validate(), Err(HdcError::Config(_))));
+        assert!(matches!(filter.to_command(), Err(HdcError::Config(_))));
+        assert_eq!(filter.to_args(), "-T 'x;reboot'");
+
+        assert!(HilogFilter::new().tag("").validate().is_err());
+        assert!(HilogFilter::new().tag("My.Tag_1-a").validate().is_ok());
+        assert!(HilogFilter::new().domain("D003200").validate().is_ok());
+        assert!(HilogFilter::new().domain("0xD0032 ").validate().is_err());
+        assert!(HilogFilter::new().domain("0x").validate().is_err());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let entry = LogEntry::parse("01-15 10:23:45.123  1234  5678 W A03200/MyTag: hi").unwrap();
+        assert!(HilogFilter::new().matches(&entry));
+        assert!(HilogFilter::new()
+            .tag("MyTag")
+            .min_level(LogLevel::Warn)
+            .matches(&entry));
+        assert!(!HilogFilter::new()
+            .min_level(LogLevel::Error)
+            .matches(&entry));
+        assert!(HilogFilter::new().domain("0xD003200").matches(&entry));
+        assert!(!HilogFilter::new().pid(1).matches(&entry));
+    }
+
+    #[test]
+    fn test_line_buffer() {
+        let mut buf = LineBuffer::default();
+        assert!(buf.push("first li").is_empty());
+        assert_eq!(buf.push("ne\r\nsecond\nthi"), vec!["first line", "second"]);
+        assert_eq!(buf.finish().as_deref(), Some("thi"));
+        assert!(buf.finish().is_none());
+    }
+}
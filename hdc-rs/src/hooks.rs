@@ -0,0 +1,136 @@
+//! Command-level hooks
+//!
+//! A [`CommandObserver`] registered with
+//! [`HdcClient::set_command_observer`](crate::HdcClient::set_command_observer)
+//! sees every command string before it is sent, every reply read for it and
+//! every error, which is enough for audit logs, metrics or rewriting
+//! commands without forking [`send_command`](crate::HdcClient::send_command).
+//! For packet-level hooks see [`PacketObserver`](crate::protocol::PacketObserver).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::hooks::{CommandContext, CommandObserver};
+//! use hdc_rs::{HdcClient, HdcError};
+//! use std::sync::Arc;
+//!
+//! struct AuditLog;
+//!
+//! impl CommandObserver for AuditLog {
+//!     fn before_send(&self, connect_key: Option<&str>, command: &mut String) {
+//!         println!("{:?} <- {}", connect_key, command);
+//!     }
+//!
+//!     fn on_error(&self, ctx: &CommandContext<'_>, error: &HdcError) {
+//!         println!("{} failed after {:?}: {}", ctx.command, ctx.elapsed, error);
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+//! client.set_command_observer(Some(Arc::new(AuditLog)));
+//! client.list_targets().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use crate::error::HdcError;
+
+/// The command a reply or error belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct CommandContext<'a> {
+    /// Device the channel is bound to, `None` for server-level commands
+    pub connect_key: Option<&'a str>,
+    /// Command as sent, after any rewriting in [`CommandObserver::before_send`]
+    pub command: &'a str,
+    /// Time since the command was sent
+    pub elapsed: Duration,
+}
+
+/// Receives a callback for every command a client sends
+///
+/// Callbacks run inline and should return quickly. Streaming commands such
+/// as `hilog` report each reply chunk through
+/// [`after_response`](Self::after_response); retried commands report each
+/// failed attempt through [`on_error`](Self::on_error).
+pub trait CommandObserver: Send + Sync {
+    /// Called before a command is written; may rewrite `command`
+    fn before_send(&self, connect_key: Option<&str>, command: &mut String) {
+        let _ = (connect_key, command);
+    }
+
+    /// Called for every reply read after the command
+    fn after_response(&self, ctx: &CommandContext<'_>, response: &[u8]) {
+        let _ = (ctx, response);
+    }
+
+    /// Called when sending the command or reading its reply fails
+    fn on_error(&self, ctx: &CommandContext<'_>, error: &HdcError) {
+        let _ = (ctx, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+    use crate::HdcClient;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl CommandObserver for Recorder {
+        fn before_send(&self, _connect_key: Option<&str>, command: &mut String) {
+            if command == "list targets" {
+                command.push_str(" -v");
+            }
+        }
+
+        fn after_response(&self, ctx: &CommandContext<'_>, response: &[u8]) {
+            let response = String::from_utf8_lossy(response);
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} => {}", ctx.command, response.trim()));
+        }
+
+        fn on_error(&self, ctx: &CommandContext<'_>, error: &HdcError) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} !! {}", ctx.command, error));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_command_observer() {
+        let server = MockServer::new()
+            .respond("list targets -v", "device-1\tUSB\tConnected\n")
+            .spawn()
+            .await
+            .unwrap();
+        let recorder = Arc::new(Recorder::default());
+
+        let mut client = HdcClient::connect(server.address()).await.unwrap();
+        client.set_command_observer(Some(recorder.clone()));
+        client.list_targets().await.unwrap();
+
+        let mut offline = HdcClient::new(server.address());
+        offline.set_command_observer(Some(recorder.clone()));
+        assert!(offline.check_server().await.is_err());
+
+        let events = recorder.events.lock().unwrap();
+        assert_eq!(
+            *events,
+            [
+                "list targets -v => device-1\tUSB\tConnected",
+                "checkserver !! Not connected to HDC server",
+            ]
+        );
+    }
+}
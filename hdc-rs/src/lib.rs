@@ -47,9 +47,37 @@
 //! - [`client`] - Main HDC client implementation
 //! - [`blocking`] - Synchronous/blocking API (requires `blocking` feature)
 //! - [`app`] - Application management types and options
+//! - `auth` - Host key authentication (requires `auth` feature)
+//! - [`bugreport`] - One-call bug report collection
+//! - [`capture`] - Packet capture and replay of server sessions
+//! - [`collector`] - Merged log streams across devices
+//! - [`coverage`] - Coverage artifact collection from app sandboxes
+//! - [`daemon`] - Direct daemon sessions without a local server
+//! - [`device`] - Client handles bound to one device
+//! - [`farm`] - Persistent device handles with health checks
 //! - [`file`] - File transfer types and options
+//! - [`flash`] - Firmware operations in flashd mode
 //! - [`forward`] - Port forwarding types
+//! - [`hilog`] - Device log parsing types
+//! - [`hooks`] - Command observers for auditing and rewriting
+//! - `metrics` - Health counters and histograms (requires `metrics` feature)
+//! - `native` - Synchronous client on `std::net` without a runtime (requires `blocking-native` feature)
+//! - [`ota`] - OTA update packages through the updater
+//! - [`partition`] - Raw partition image dumps
+//! - [`perf`] - CPU profiling with `hiperf`
+//! - [`pool`] - Pooled channels for concurrent operations
 //! - [`protocol`] - HDC protocol implementation
+//! - [`recorder`] - Log capture to files with rotation
+//! - [`retry`] - Retry policies for transient failures
+//! - [`sampler`] - Periodic CPU, memory, battery and bundle RSS samples
+//! - [`selinux`] - SELinux mode and AVC denials
+//! - [`shared`] - Client handle shareable across tasks
+//! - [`sysinfo`] - Process, storage, memory and bundle listings
+//! - [`faultlog`] - Crash and freeze report types
+//! - `testing` - Mock server and frame builders (requires `testing` feature)
+//! - [`trace`] - System trace capture with `hitrace`
+//! - [`transport`] - Server addresses (TCP, TLS with the `tls` feature)
+//! - `usb` - Direct USB device access (requires `usb` feature)
 //! - [`error`] - Error types
 //!
 //! ## Blocking API
@@ -79,16 +107,66 @@
 //! - `comprehensive` - All features
 
 pub mod app;
+#[cfg(feature = "auth")]
+pub mod auth;
 #[cfg(feature = "blocking")]
 pub mod blocking;
+pub mod bugreport;
+pub mod capture;
 pub mod client;
+pub mod collector;
+pub mod coverage;
+pub mod daemon;
+pub mod device;
 pub mod error;
+pub mod farm;
+pub mod faultlog;
 pub mod file;
+pub mod flash;
 pub mod forward;
+pub mod hilog;
+pub mod hooks;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "blocking-native")]
+pub mod native;
+pub mod ota;
+pub mod partition;
+pub mod perf;
+pub mod pool;
 pub mod protocol;
+pub mod recorder;
+pub mod retry;
+pub mod sampler;
+pub mod selinux;
+pub mod shared;
+mod shell;
+pub mod sysinfo;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod trace;
+pub mod transport;
+#[cfg(feature = "usb")]
+pub mod usb;
 
 pub use app::{InstallOptions, UninstallOptions};
-pub use client::HdcClient;
-pub use error::{HdcError, Result};
-pub use file::{FileTransferDirection, FileTransferOptions};
-pub use forward::{ForwardNode, ForwardTask};
+pub use client::{HdcClient, HdcClientBuilder, RetryOverride, TimeoutOverride};
+pub use collector::MultiLogCollector;
+pub use daemon::DaemonClient;
+pub use device::{DeviceClient, DeviceEvent, DeviceInfo, RebootMode};
+pub use error::{ErrorKind, HdcError, Result};
+pub use farm::DeviceFarm;
+pub use faultlog::{CrashEvent, FaultKind, FaultLog};
+pub use file::{FileTransferDirection, FileTransferOptions, TransferProgress};
+pub use forward::{
+    ForwardEntry, ForwardManager, ForwardNode, ForwardTask, ForwardedStream, JdwpProcess,
+    ReverseBridge,
+};
+pub use hilog::{HilogFilter, LogCursor, LogEntry, LogLevel, LogStream, LogType};
+pub use hooks::CommandObserver;
+pub use perf::{PerfOptions, PerfSession};
+pub use pool::{HdcPool, PooledClient};
+pub use retry::{Backoff, RetryPolicy};
+pub use shared::SharedHdcClient;
+pub use trace::TraceCategory;
+pub use transport::{HdcStream, ServerAddr, Timeouts, DEFAULT_SERVER_ADDR, SERVER_PORT_ENV};
@@ -0,0 +1,177 @@
+//! Health metrics (requires `metrics` feature)
+//!
+//! Clients record counters and histograms through the
+//! [`metrics`](https://docs.rs/metrics) facade. Nothing is kept unless the
+//! application installs a recorder, such as `metrics-exporter-prometheus`,
+//! so device farms can export link health with the rest of their data.
+//!
+//! | Metric | Type | Labels |
+//! |---|---|---|
+//! | [`COMMANDS_SENT`] | counter | `command` (first word) |
+//! | [`BYTES_SENT`], [`BYTES_RECEIVED`] | counter | |
+//! | [`TRANSFER_BYTES`] | counter | `direction` |
+//! | [`TRANSFER_THROUGHPUT`] | histogram | `direction` |
+//! | [`RECONNECTS`] | counter | |
+//! | [`ERRORS`] | counter | `kind` ([`ErrorKind::as_str`](crate::ErrorKind::as_str)) |
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Install a recorder of your choice first, then:
+//! hdc_rs::metrics::describe();
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.list_targets().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+use std::time::Duration;
+
+use ::metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use crate::error::HdcError;
+use crate::file::FileTransferDirection;
+
+/// Commands sent to the server
+pub const COMMANDS_SENT: &str = "hdc_commands_sent_total";
+/// Payload bytes written to server channels
+pub const BYTES_SENT: &str = "hdc_bytes_sent_total";
+/// Payload bytes read from server channels
+pub const BYTES_RECEIVED: &str = "hdc_bytes_received_total";
+/// Size of files sent or received successfully
+pub const TRANSFER_BYTES: &str = "hdc_file_transfer_bytes_total";
+/// Throughput of successful file transfers
+pub const TRANSFER_THROUGHPUT: &str = "hdc_file_transfer_throughput_bytes_per_second";
+/// Channels re-opened after being consumed or dropped
+pub const RECONNECTS: &str = "hdc_reconnects_total";
+/// Failed commands
+pub const ERRORS: &str = "hdc_errors_total";
+
+/// Register units and descriptions with the installed recorder
+pub fn describe() {
+    describe_counter!(COMMANDS_SENT, "Commands sent to the HDC server");
+    describe_counter!(
+        BYTES_SENT,
+        Unit::Bytes,
+        "Payload bytes sent to the HDC server"
+    );
+    describe_counter!(
+        BYTES_RECEIVED,
+        Unit::Bytes,
+        "Payload bytes received from the HDC server"
+    );
+    describe_counter!(TRANSFER_BYTES, Unit::Bytes, "Bytes moved by file transfers");
+    describe_histogram!(
+        TRANSFER_THROUGHPUT,
+        "File transfer throughput in bytes per second"
+    );
+    describe_counter!(RECONNECTS, "Channels re-opened to the HDC server");
+    describe_counter!(ERRORS, "Failed HDC commands by error kind");
+}
+
+pub(crate) fn command_sent(command: &str) {
+    let name = command.split_whitespace().next().unwrap_or_default();
+    counter!(COMMANDS_SENT, "command" => name.to_string()).increment(1);
+}
+
+pub(crate) fn bytes_sent(len: usize) {
+    counter!(BYTES_SENT).increment(len as u64);
+}
+
+pub(crate) fn bytes_received(len: usize) {
+    counter!(BYTES_RECEIVED).increment(len as u64);
+}
+
+pub(crate) fn reconnected() {
+    counter!(RECONNECTS).increment(1);
+}
+
+pub(crate) fn error(error: &HdcError) {
+    counter!(ERRORS, "kind" => error.kind().as_str()).increment(1);
+}
+
+/// Record a finished transfer of the file at `local_path`
+pub(crate) fn file_transferred(direction: FileTransferDirection, local_path: &str, took: Duration) {
+    let Ok(size) = Path::new(local_path).metadata().map(|m| m.len()) else {
+        return;
+    };
+    let direction = match direction {
+        FileTransferDirection::Send => "send",
+        FileTransferDirection::Recv => "recv",
+    };
+    counter!(TRANSFER_BYTES, "direction" => direction).increment(size);
+    let secs = took.as_secs_f64();
+    if secs > 0.0 {
+        histogram!(TRANSFER_THROUGHPUT, "direction" => direction).record(size as f64 / secs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString};
+    use std::sync::Mutex;
+
+    /// Lists every metric registration as `name{label=value,...}`
+    #[derive(Default)]
+    struct Registrations(Mutex<Vec<String>>);
+
+    impl Registrations {
+        fn push(&self, key: &Key) {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let entry = format!("{}{{{}}}", key.name(), labels.join(","));
+            self.0.lock().unwrap().push(entry);
+        }
+    }
+
+    impl Recorder for Registrations {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.push(key);
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.push(key);
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.push(key);
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_metric_labels() {
+        let recorder = Registrations::default();
+        ::metrics::with_local_recorder(&recorder, || {
+            command_sent("shell ls -l");
+            error(&HdcError::Timeout);
+            error(&HdcError::from_response("[Fail]Device not found").unwrap());
+            file_transferred(
+                FileTransferDirection::Send,
+                "Cargo.toml",
+                Duration::from_millis(10),
+            );
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "hdc_commands_sent_total{command=shell}",
+                "hdc_errors_total{kind=timeout}",
+                "hdc_errors_total{kind=device_not_found}",
+                "hdc_file_transfer_bytes_total{direction=send}",
+                "hdc_file_transfer_throughput_bytes_per_second{direction=send}",
+            ]
+        );
+    }
+}
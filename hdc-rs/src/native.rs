@@ -0,0 +1,523 @@
+//! Synchronous client on `std::net` (requires `blocking-native` feature)
+//!
+//! [`blocking::HdcClient`](crate::blocking) drives the async client on its
+//! own multi-threaded Tokio runtime, which costs a set of worker threads per
+//! client. This client speaks the protocol directly on a
+//! [`std::net::TcpStream`] instead: no runtime is started and no threads are
+//! spawned, which suits short-lived CLI tools and PyO3 extensions.
+//!
+//! Each command runs on its own channel, since the server closes channels
+//! after most commands. Only TCP server addresses are supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::native::HdcClient;
+//!
+//! let mut client = HdcClient::connect("127.0.0.1:8710")?;
+//! for device in client.list_targets()? {
+//!     client.connect_device(&device)?;
+//!     println!("{}: {}", device, client.shell("uname -a")?);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tracing::{debug, info, warn};
+
+use crate::app::{InstallOptions, UninstallOptions};
+use crate::client::close_reason;
+use crate::error::{HdcError, Result};
+use crate::file::FileTransferOptions;
+use crate::forward::ForwardNode;
+use crate::hilog::HilogFilter;
+use crate::protocol::channel::answer_handshake;
+use crate::protocol::{HdcChannelCodec, HdcCommand, Response, ServerFeatures};
+use crate::transport::Timeouts;
+
+/// Idle time before a heartbeat is sent on `hilog_stream`
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// HDC client using blocking `std` sockets
+pub struct HdcClient {
+    /// Resolved server addresses, tried in order
+    addrs: Vec<SocketAddr>,
+    /// Channel opened by the last handshake and not yet used
+    channel: Option<NativeChannel>,
+    /// Current connect key (device identifier)
+    connect_key: Option<String>,
+    /// Features from the most recent handshake
+    features: Option<ServerFeatures>,
+    /// Time limits for connecting and waiting on commands
+    timeouts: Timeouts,
+}
+
+/// One handshaken connection
+struct NativeChannel {
+    stream: TcpStream,
+    codec: HdcChannelCodec,
+    id: u32,
+    /// Whether a command was sent on it; the server closes it afterwards
+    used: bool,
+}
+
+impl HdcClient {
+    /// Connect to the HDC server at `addr`
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        if addrs.is_empty() {
+            return Err(HdcError::Config("address resolved to nothing".to_string()));
+        }
+        let mut client = Self {
+            addrs,
+            channel: None,
+            connect_key: None,
+            features: None,
+            timeouts: Timeouts::default(),
+        };
+        client.open()?;
+        Ok(client)
+    }
+
+    /// Set the time limits for connecting and waiting on commands
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Time limits in use
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Device selected with [`connect_device`](Self::connect_device), if any
+    pub fn connect_key(&self) -> Option<&str> {
+        self.connect_key.as_deref()
+    }
+
+    /// Features the server advertised in the most recent handshake
+    pub fn server_features(&self) -> Option<&ServerFeatures> {
+        self.features.as_ref()
+    }
+
+    /// Get the channel ID, 0 before the first handshake
+    pub fn channel_id(&self) -> u32 {
+        self.channel.as_ref().map_or(0, |channel| channel.id)
+    }
+
+    /// Select the device later commands run on
+    pub fn connect_device(&mut self, device_id: &str) -> Result<()> {
+        info!("Connecting to device: {}", device_id);
+        self.connect_key = Some(device_id.to_string());
+        self.open()
+    }
+
+    /// Open a new channel with the current connect key
+    fn open(&mut self) -> Result<()> {
+        self.channel = None;
+        let mut stream = connect_any(&self.addrs, self.timeouts.connect)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(self.timeouts.connect))?;
+
+        let mut codec = HdcChannelCodec::new();
+        let data = codec.read_packet_blocking(&mut stream)?;
+        let (server, reply) = answer_handshake(&data, self.connect_key.as_deref())?;
+        codec.set_max_packet_size(server.max_packet_size());
+        codec.write_packet_blocking(&mut stream, &reply)?;
+
+        self.features = Some(ServerFeatures::from_handshake(&server));
+        self.channel = Some(NativeChannel {
+            stream,
+            codec,
+            id: server.get_channel_id(),
+            used: false,
+        });
+        Ok(())
+    }
+
+    /// Send raw command string to server, on a fresh channel if needed
+    pub fn send_command(&mut self, command: &str) -> Result<()> {
+        if self.channel.as_ref().map_or(true, |channel| channel.used) {
+            self.open()?;
+        }
+        debug!("Sending command: {}", command);
+        let channel = self.channel()?;
+        channel.used = true;
+        channel
+            .codec
+            .write_packet_blocking(&mut channel.stream, command.as_bytes())
+    }
+
+    /// Read the next reply, waiting at most `wait` (`None` waits forever)
+    ///
+    /// Server heartbeats are answered on the way; a close packet ends the
+    /// channel with [`HdcError::ChannelClosed`].
+    pub fn read_response(&mut self, wait: Option<Duration>) -> Result<Bytes> {
+        loop {
+            let channel = self.channel()?;
+            channel.stream.set_read_timeout(wait)?;
+            let data = match channel.codec.read_packet_blocking(&mut channel.stream) {
+                Ok(data) => data,
+                Err(HdcError::Io(e)) if is_timeout(&e) => return Err(HdcError::Timeout),
+                Err(HdcError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.channel = None;
+                    return Err(HdcError::ChannelClosed {
+                        reason: e.to_string(),
+                    });
+                }
+                Err(e) => {
+                    self.channel = None;
+                    return Err(e);
+                }
+            };
+
+            let message = Response::parse(data.clone());
+            match message.command() {
+                Some(HdcCommand::KernelChannelClose) => {
+                    self.channel = None;
+                    return Err(HdcError::ChannelClosed {
+                        reason: close_reason(&message),
+                    });
+                }
+                Some(HdcCommand::HeartbeatMsg) => {
+                    debug!("Answering server heartbeat");
+                    self.send_control(HdcCommand::HeartbeatMsg)?;
+                }
+                _ => return Ok(data),
+            }
+        }
+    }
+
+    /// Read the next reply as a string
+    pub fn read_response_string(&mut self, wait: Option<Duration>) -> Result<String> {
+        Response::parse(self.read_response(wait)?).into_string()
+    }
+
+    fn channel(&mut self) -> Result<&mut NativeChannel> {
+        self.channel.as_mut().ok_or(HdcError::NotConnected)
+    }
+
+    fn send_control(&mut self, command: HdcCommand) -> Result<()> {
+        let channel = self.channel()?;
+        let payload = command.as_u16().to_le_bytes();
+        channel
+            .codec
+            .write_packet_blocking(&mut channel.stream, &payload)
+    }
+
+    /// Send `command` and read one reply; `[Fail]` becomes [`HdcError::Remote`]
+    fn request_string(&mut self, command: &str, wait: Option<Duration>) -> Result<String> {
+        self.send_command(command)?;
+        HdcError::check_response(self.read_response_string(wait)?)
+    }
+
+    /// [`request_string`](Self::request_string) on a channel without connect key
+    fn server_request_string(&mut self, command: &str) -> Result<String> {
+        let connect_key = self.connect_key.take();
+        self.channel = None;
+        let result = self.request_string(command, Some(self.timeouts.read));
+        self.connect_key = connect_key;
+        self.channel = None;
+        result
+    }
+
+    /// Send `command` and collect replies until `done` matches one, the
+    /// server sends an empty reply or `wait` passes without progress
+    fn collect(
+        &mut self,
+        command: &str,
+        wait: Duration,
+        done: impl Fn(&str) -> bool,
+    ) -> Result<String> {
+        self.send_command(command)?;
+        let mut output = String::new();
+        loop {
+            match self.read_response_string(Some(wait)) {
+                Ok(resp) if resp.is_empty() => break,
+                Ok(resp) => {
+                    output.push_str(&resp);
+                    if done(&resp) {
+                        break;
+                    }
+                }
+                Err(HdcError::Timeout) if !output.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        HdcError::check_response(output)
+    }
+
+    /// List connected devices/targets
+    pub fn list_targets(&mut self) -> Result<Vec<String>> {
+        let response = self.server_request_string("list targets")?;
+        Ok(response
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Check server version
+    pub fn check_server(&mut self) -> Result<String> {
+        self.server_request_string("checkserver")
+    }
+
+    /// Execute a shell command on the selected device
+    pub fn shell(&mut self, cmd: &str) -> Result<String> {
+        info!("Executing shell command: {}", cmd);
+        self.send_command(&format!("shell {}", cmd))?;
+        let data = self.read_response(Some(self.timeouts.shell))?;
+        let output = String::from_utf8_lossy(&data).to_string();
+
+        // Only a leading tag is the server's; later lines are command output
+        if output.trim_start().starts_with("[Fail]") {
+            return HdcError::check_response(output);
+        }
+        Ok(output)
+    }
+
+    /// Create a port forward (local -> device)
+    pub fn fport(&mut self, local: ForwardNode, remote: ForwardNode) -> Result<String> {
+        local.validate()?;
+        remote.validate()?;
+        let cmd = format!(
+            "fport {} {}",
+            local.as_protocol_string(),
+            remote.as_protocol_string()
+        );
+        self.request_string(&cmd, Some(self.timeouts.read))
+    }
+
+    /// Create a reverse port forward (device -> local)
+    pub fn rport(&mut self, remote: ForwardNode, local: ForwardNode) -> Result<String> {
+        remote.validate()?;
+        local.validate()?;
+        let cmd = format!(
+            "rport {} {}",
+            remote.as_protocol_string(),
+            local.as_protocol_string()
+        );
+        self.request_string(&cmd, Some(self.timeouts.read))
+    }
+
+    /// Remove a forward task, e.g. `"tcp:8080 tcp:8081"`
+    pub fn fport_remove(&mut self, task_str: &str) -> Result<String> {
+        self.server_request_string(&format!("fport rm {}", task_str))
+    }
+
+    /// Install application packages on the selected device
+    pub fn install(&mut self, paths: &[&str], options: InstallOptions) -> Result<String> {
+        let flags = options.to_flags();
+        let paths_str = paths.join(" ");
+        let cmd = if flags.is_empty() {
+            format!("install {}", paths_str)
+        } else {
+            format!("install {} {}", flags, paths_str)
+        };
+        self.collect(&cmd, self.timeouts.install, |resp| {
+            resp.contains("Success")
+                || resp.contains("success")
+                || resp.contains("Fail")
+                || resp.contains("fail")
+        })
+    }
+
+    /// Uninstall a package from the selected device
+    pub fn uninstall(&mut self, package: &str, options: UninstallOptions) -> Result<String> {
+        let flags = options.to_flags();
+        let cmd = if flags.is_empty() {
+            format!("uninstall {}", package)
+        } else {
+            format!("uninstall {} {}", flags, package)
+        };
+        self.request_string(&cmd, Some(self.timeouts.install))
+    }
+
+    /// Send a file to the selected device
+    pub fn file_send(
+        &mut self,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.file_transfer("send", local_path, remote_path, options)
+    }
+
+    /// Receive a file from the selected device
+    pub fn file_recv(
+        &mut self,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.file_transfer("recv", remote_path, local_path, options)
+    }
+
+    fn file_transfer(
+        &mut self,
+        direction: &str,
+        from: &str,
+        to: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        if !crate::file::validate_path(from) || !crate::file::validate_path(to) {
+            return Err(HdcError::Protocol("Invalid file path".to_string()));
+        }
+        let flags = options.for_server(self.features.as_ref()).to_flags();
+        let cmd = if flags.is_empty() {
+            format!("file {} {} {}", direction, from, to)
+        } else {
+            format!("file {} {} {} {}", direction, flags, from, to)
+        };
+        info!("File transfer: {}", cmd);
+        self.collect(&cmd, self.timeouts.transfer, |resp| {
+            resp.contains("FileTransfer finish")
+                || resp.contains("Transfer finish")
+                || resp.contains("[Fail]")
+                || resp.contains("fail")
+        })
+    }
+
+    /// Read buffered device logs
+    pub fn hilog(&mut self, filter: impl Into<HilogFilter>) -> Result<String> {
+        let cmd = filter.into().to_command()?;
+        self.collect(&cmd, self.timeouts.read, |_| false)
+    }
+
+    /// Stream device logs until `callback` returns false or the stream ends
+    pub fn hilog_stream<F>(&mut self, filter: impl Into<HilogFilter>, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let cmd = filter.into().to_command()?;
+        self.send_command(&cmd)?;
+        let heartbeat = self
+            .features
+            .as_ref()
+            .is_some_and(ServerFeatures::heartbeat);
+        if heartbeat {
+            self.send_control(HdcCommand::KernelEnableKeepalive)?;
+        }
+
+        loop {
+            match self.read_response_string(Some(KEEPALIVE_INTERVAL)) {
+                Ok(resp) if resp.is_empty() => return Ok(()),
+                Ok(resp) => {
+                    if !callback(&resp) {
+                        info!("Hilog stream stopped by callback");
+                        return Ok(());
+                    }
+                }
+                Err(HdcError::Timeout) if heartbeat => {
+                    debug!("Hilog stream idle, sending heartbeat");
+                    self.send_control(HdcCommand::HeartbeatMsg)?;
+                }
+                Err(HdcError::Timeout) => debug!("Hilog stream idle"),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait until a device connects and return its connect key
+    pub fn wait_for_device(&mut self) -> Result<String> {
+        self.wait_for_device_within(None)
+    }
+
+    /// Wait until a device connects, failing with
+    /// [`HdcError::Timeout`] after `wait`
+    pub fn wait_for_device_timeout(&mut self, wait: Duration) -> Result<String> {
+        self.wait_for_device_within(Some(wait))
+    }
+
+    fn wait_for_device_within(&mut self, wait: Option<Duration>) -> Result<String> {
+        let response = self.request_string("wait", wait)?;
+        // Response format: "Wait for connected target is <device_id>"
+        Ok(match response.split("is ").nth(1) {
+            Some(device_id) => device_id.trim().to_string(),
+            None => response.trim().to_string(),
+        })
+    }
+
+    /// Poll the device list every `interval`, calling `callback` on changes
+    ///
+    /// Stops when `callback` returns false.
+    pub fn monitor_devices<F>(&mut self, interval: Duration, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&[String]) -> bool,
+    {
+        let mut previous: Option<Vec<String>> = None;
+        loop {
+            match self.list_targets() {
+                Ok(devices) if previous.as_ref() != Some(&devices) => {
+                    if !callback(&devices) {
+                        return Ok(());
+                    }
+                    previous = Some(devices);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Error listing devices during monitoring: {}", e),
+            }
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+/// Connect to the first of `addrs` that accepts within `timeout`
+fn connect_any(addrs: &[SocketAddr], timeout: Duration) -> Result<TcpStream> {
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) if is_timeout(&e) => HdcError::Timeout,
+        Some(e) => HdcError::Io(e),
+        None => HdcError::NotConnected,
+    })
+}
+
+/// Whether a socket error means a read or connect timeout expired
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_native_client() {
+        let server = MockServer::new()
+            .respond("list targets", "device-1\ndevice-2\n")
+            .respond("shell echo hi", "hi\n")
+            .spawn()
+            .await
+            .unwrap();
+        let address = server.address().to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut client = HdcClient::connect(address).unwrap();
+            assert_eq!(client.list_targets().unwrap(), ["device-1", "device-2"]);
+
+            client.connect_device("device-1").unwrap();
+            assert_eq!(client.shell("echo hi").unwrap(), "hi\n");
+            assert_eq!(client.shell("echo hi").unwrap(), "hi\n");
+            assert_eq!(client.connect_key(), Some("device-1"));
+
+            let err = client.check_server().unwrap_err();
+            assert!(matches!(err, HdcError::Remote { .. }));
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! OTA updates from a running system
+//!
+//! [`HdcClient::apply_ota`](crate::HdcClient::apply_ota) pushes an update
+//! package to [`DEFAULT_PACKAGE_PATH`], where the updater looks for it,
+//! and reboots into the updater with `reboot updater:--update_package=...`.
+//! The updater verifies and installs the package, then boots the system
+//! again. Unlike [`flash`](crate::flash), this needs no flashd mode.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::ota::OtaOptions;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let options = OtaOptions::new().wait_for_boot(Duration::from_secs(900));
+//! client.apply_ota("out/updater.zip", options).await?;
+//! println!("{}", client.shell("param get const.ohos.fullname").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+/// Device directory the updater reads packages from
+pub const UPDATER_DIR: &str = "/data/updater";
+
+/// Device path packages are pushed to unless configured otherwise
+pub const DEFAULT_PACKAGE_PATH: &str = "/data/updater/updater.zip";
+
+/// Stage reported while the package is sent
+pub const STAGE_PUSH: &str = "Pushing package";
+/// Stage reported while rebooting into the updater
+pub const STAGE_TRIGGER: &str = "Starting updater";
+/// Stage reported while the updater installs the package
+pub const STAGE_INSTALL: &str = "Installing update";
+/// Stage reported while the updated system boots
+pub const STAGE_BOOT: &str = "Booting updated system";
+
+/// Stages reported by
+/// [`HdcClient::apply_ota_with_progress`](crate::HdcClient::apply_ota_with_progress),
+/// in order; the last two only when waiting for the boot
+pub const OTA_STAGES: &[&str] = &[STAGE_PUSH, STAGE_TRIGGER, STAGE_INSTALL, STAGE_BOOT];
+
+/// Options for [`HdcClient::apply_ota`](crate::HdcClient::apply_ota)
+#[derive(Debug, Clone)]
+pub struct OtaOptions {
+    remote_path: String,
+    wait: Option<Duration>,
+}
+
+impl Default for OtaOptions {
+    fn default() -> Self {
+        Self {
+            remote_path: DEFAULT_PACKAGE_PATH.to_string(),
+            wait: None,
+        }
+    }
+}
+
+impl OtaOptions {
+    /// Push to [`DEFAULT_PACKAGE_PATH`] and return once the update starts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Device path to push the package to (default [`DEFAULT_PACKAGE_PATH`])
+    pub fn remote_path(mut self, path: impl Into<String>) -> Self {
+        self.remote_path = path.into();
+        self
+    }
+
+    /// Wait up to `timeout` for the updated system to finish booting
+    ///
+    /// Installing a full package can take ten minutes or more.
+    pub fn wait_for_boot(mut self, timeout: Duration) -> Self {
+        self.wait = Some(timeout);
+        self
+    }
+
+    /// Device path the package is pushed to
+    pub fn package_path(&self) -> &str {
+        &self.remote_path
+    }
+
+    /// How long to wait for the updated system, if at all
+    pub fn wait(&self) -> Option<Duration> {
+        self.wait
+    }
+
+    /// Command rebooting into the updater with the package
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::ota::OtaOptions;
+    ///
+    /// assert_eq!(
+    ///     OtaOptions::new().trigger_command(),
+    ///     "reboot updater:--update_package=/data/updater/updater.zip"
+    /// );
+    /// ```
+    pub fn trigger_command(&self) -> String {
+        format!("reboot updater:--update_package={}", self.remote_path)
+    }
+}
@@ -0,0 +1,204 @@
+//! Raw partition images
+//!
+//! [`HdcClient::dump_partition`](crate::HdcClient::dump_partition) copies a
+//! block device to the host, e.g. for forensics or to compare the A and B
+//! slots. The device reads it with `dd` in chunks of
+//! [`DumpOptions::chunk_size`] into a temporary file that is pulled and
+//! appended to the image, so the device never needs room for the whole
+//! partition. The image is then checked against the partition's size and
+//! SHA-256 hash.
+//!
+//! Reading block devices needs root, e.g. after `hdc smode` on a
+//! development build.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::partition::DumpOptions;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let a = client.dump_partition("boot_a", "boot_a.img", DumpOptions::new()).await?;
+//! let b = client.dump_partition("boot_b", "boot_b.img", DumpOptions::new()).await?;
+//! println!("slots identical: {}", a.sha256 == b.sha256);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Directory of the named block device links
+pub const BY_NAME_DIR: &str = "/dev/block/by-name";
+
+/// Unit `dd` copies in; chunk sizes are whole multiples of it
+pub const BLOCK_SIZE: u64 = 1024 * 1024;
+
+/// Options for [`HdcClient::dump_partition`](crate::HdcClient::dump_partition)
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    chunk_size: u64,
+    remote_dir: String,
+    verify: bool,
+    timeout: Duration,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64 * BLOCK_SIZE,
+            remote_dir: "/data/local/tmp".to_string(),
+            verify: true,
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl DumpOptions {
+    /// 64 MiB chunks through `/data/local/tmp`, verified by hash
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes copied per chunk, rounded up to whole MiB (default 64 MiB)
+    pub fn chunk_size(mut self, bytes: u64) -> Self {
+        self.chunk_size = (bytes.saturating_add(BLOCK_SIZE - 1) / BLOCK_SIZE).max(1) * BLOCK_SIZE;
+        self
+    }
+
+    /// Device directory for the chunk file (default `/data/local/tmp`)
+    pub fn remote_dir(mut self, dir: impl Into<String>) -> Self {
+        self.remote_dir = dir.into();
+        self
+    }
+
+    /// Compare the SHA-256 hash of the image with the partition's
+    /// (default on)
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Limit for each step on the device: copying one chunk, or hashing
+    /// the whole partition (default 5 minutes)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Bytes copied per chunk
+    pub fn chunk_bytes(&self) -> u64 {
+        self.chunk_size
+    }
+
+    /// Whether the hash is compared
+    pub fn verifies(&self) -> bool {
+        self.verify
+    }
+
+    /// Limit for each step on the device
+    pub fn step_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Device path of the chunk file for partition `name`
+    pub fn chunk_path(&self, name: &str) -> String {
+        format!("{}/{}.chunk", self.remote_dir.trim_end_matches('/'), name)
+    }
+
+    /// Command copying chunk `index` of `device` to [`chunk_path`](Self::chunk_path)
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::partition::DumpOptions;
+    ///
+    /// let options = DumpOptions::new().chunk_size(8 << 20);
+    /// assert_eq!(
+    ///     options.dd_command("/dev/block/mmcblk0p5", "boot", 2),
+    ///     "dd if=/dev/block/mmcblk0p5 of=/data/local/tmp/boot.chunk bs=1048576 skip=16 count=8"
+    /// );
+    /// ```
+    pub fn dd_command(&self, device: &str, name: &str, index: u64) -> String {
+        let blocks = self.chunk_size / BLOCK_SIZE;
+        format!(
+            "dd if={} of={} bs={} skip={} count={}",
+            device,
+            self.chunk_path(name),
+            BLOCK_SIZE,
+            index * blocks,
+            blocks
+        )
+    }
+}
+
+/// Image written by [`HdcClient::dump_partition`](crate::HdcClient::dump_partition)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionDump {
+    /// Partition name
+    pub name: String,
+    /// Block device the partition was read from
+    pub device_path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 of the image as lowercase hex, if verified
+    pub sha256: Option<String>,
+    /// Host path of the image
+    pub path: PathBuf,
+}
+
+/// Whether `name` is safe to use as a partition name in shell commands
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Size printed by `blockdev --getsize64`
+pub(crate) fn parse_size(output: &str) -> Option<u64> {
+    output.trim().parse().ok()
+}
+
+/// Hash printed by `sha256sum`
+pub(crate) fn parse_sha256(output: &str) -> Option<String> {
+    let hash = output.split_whitespace().next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| hash.to_ascii_lowercase())
+}
+
+/// Whether `dd` output reports an error
+pub(crate) fn is_dd_failure(output: &str) -> bool {
+    output
+        .lines()
+        .any(|line| line.trim_start().starts_with("dd:"))
+}
+
+/// Lowercase hex of `bytes`
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let options = DumpOptions::new().chunk_size(1).remote_dir("/data/tmp/");
+        assert_eq!(options.chunk_bytes(), BLOCK_SIZE);
+        assert_eq!(options.chunk_path("misc"), "/data/tmp/misc.chunk");
+        assert!(is_valid_name("boot_a"));
+        assert!(!is_valid_name("../userdata"));
+        assert_eq!(parse_size("67108864\n"), Some(67108864));
+        assert_eq!(parse_size("blockdev: /dev/x: No such file\n"), None);
+        assert!(is_dd_failure("dd: /dev/block/sda1: Permission denied\n"));
+        assert!(!is_dd_failure("8+0 records in\n8+0 records out\n"));
+        assert_eq!(
+            parse_sha256(&format!("{}  /dev/block/sda1\n", "AB".repeat(32))),
+            Some("ab".repeat(32))
+        );
+        assert_eq!(parse_sha256("sha256sum: Permission denied"), None);
+        assert_eq!(hex(&[0, 15, 255]), "000fff");
+    }
+}
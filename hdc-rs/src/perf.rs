@@ -0,0 +1,374 @@
+//! CPU profiling with `hiperf`
+//!
+//! [`PerfSession::record`] runs `hiperf record` on the device for a process,
+//! an app or the whole system, pulls the resulting `perf.data` to the host
+//! and optionally keeps the text of `hiperf report`. The recording stops by
+//! itself once the configured duration has elapsed.
+//!
+//! | Option | `hiperf record` flag |
+//! |--------|----------------------|
+//! | [`PerfOptions::pid`] | `-p <pid>` |
+//! | [`PerfOptions::app`] | `--app <bundle>` (debuggable apps only) |
+//! | [`PerfOptions::system`] | `-a` |
+//! | [`duration`](PerfOptions::duration) | `-d <seconds>` |
+//! | [`frequency`](PerfOptions::frequency) | `-f <hz>` |
+//! | [`call_graph`](PerfOptions::call_graph) | `-s fp` or `-s dwarf` |
+//! | [`event`](PerfOptions::event) | `-e <event,...>` |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::perf::{CallGraph, PerfOptions, PerfSession};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let options = PerfOptions::app("com.example.app")
+//!     .duration(Duration::from_secs(10))
+//!     .call_graph(CallGraph::Dwarf)
+//!     .local_path("app.perf.data")
+//!     .report(true);
+//! let session = PerfSession::record(&mut client, options).await?;
+//! println!("{}", session.report.unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::client::HdcClient;
+use crate::error::{HdcError, Result};
+use crate::file::FileTransferOptions;
+
+/// Device path recordings are written to unless configured otherwise
+pub const DEFAULT_REMOTE_PATH: &str = "/data/local/tmp/perf.data";
+
+/// Time allowed on top of the duration for `hiperf` to save its data
+const RECORD_GRACE: Duration = Duration::from_secs(30);
+
+/// What `hiperf record` samples
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PerfTarget {
+    /// One process by ID
+    Pid(u32),
+    /// A debuggable app by bundle name
+    App(String),
+    /// Every process on the device
+    System,
+}
+
+/// How `hiperf` unwinds call stacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallGraph {
+    /// Frame pointers; cheap but needs `-fno-omit-frame-pointer` builds
+    Fp,
+    /// DWARF unwinding from copied stacks
+    Dwarf,
+}
+
+impl CallGraph {
+    fn as_arg(self) -> &'static str {
+        match self {
+            CallGraph::Fp => "fp",
+            CallGraph::Dwarf => "dwarf",
+        }
+    }
+}
+
+/// Options for [`PerfSession::record`]
+#[derive(Debug, Clone)]
+pub struct PerfOptions {
+    target: PerfTarget,
+    duration: Duration,
+    frequency: Option<u32>,
+    call_graph: Option<CallGraph>,
+    events: Vec<String>,
+    remote_path: String,
+    local_path: PathBuf,
+    report: bool,
+    keep_remote: bool,
+}
+
+impl PerfOptions {
+    /// Profile the process with the given ID
+    pub fn pid(pid: u32) -> Self {
+        Self::new(PerfTarget::Pid(pid))
+    }
+
+    /// Profile a running debuggable app
+    pub fn app(bundle: impl Into<String>) -> Self {
+        Self::new(PerfTarget::App(bundle.into()))
+    }
+
+    /// Profile the whole system
+    pub fn system() -> Self {
+        Self::new(PerfTarget::System)
+    }
+
+    fn new(target: PerfTarget) -> Self {
+        Self {
+            target,
+            duration: Duration::from_secs(10),
+            frequency: None,
+            call_graph: None,
+            events: Vec::new(),
+            remote_path: DEFAULT_REMOTE_PATH.to_string(),
+            local_path: PathBuf::from("perf.data"),
+            report: false,
+            keep_remote: false,
+        }
+    }
+
+    /// How long to record (default 10 seconds)
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sampling frequency in Hz (`hiperf` default when unset)
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.frequency = Some(hz);
+        self
+    }
+
+    /// Record call stacks
+    pub fn call_graph(mut self, call_graph: CallGraph) -> Self {
+        self.call_graph = Some(call_graph);
+        self
+    }
+
+    /// Sample this event, e.g. `hw-cpu-cycles`; may be repeated
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.events.push(event.into());
+        self
+    }
+
+    /// Device path to record to (default [`DEFAULT_REMOTE_PATH`])
+    pub fn remote_path(mut self, path: impl Into<String>) -> Self {
+        self.remote_path = path.into();
+        self
+    }
+
+    /// Host path to pull the recording to (default `perf.data`)
+    pub fn local_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.local_path = path.into();
+        self
+    }
+
+    /// Also run `hiperf report` on the device and keep its output
+    pub fn report(mut self, report: bool) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Leave the recording on the device after pulling it
+    pub fn keep_remote(mut self, keep: bool) -> Self {
+        self.keep_remote = keep;
+        self
+    }
+
+    /// Profiled target
+    pub fn target(&self) -> &PerfTarget {
+        &self.target
+    }
+
+    /// `hiperf record` command line for these options
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use hdc_rs::perf::{CallGraph, PerfOptions};
+    ///
+    /// let options = PerfOptions::pid(4321)
+    ///     .duration(Duration::from_millis(2500))
+    ///     .frequency(4000)
+    ///     .call_graph(CallGraph::Fp);
+    /// assert_eq!(
+    ///     options.record_command(),
+    ///     "hiperf record -p 4321 -d 2.5 -f 4000 -s fp -o /data/local/tmp/perf.data"
+    /// );
+    /// ```
+    pub fn record_command(&self) -> String {
+        let mut cmd = String::from("hiperf record");
+        match &self.target {
+            PerfTarget::Pid(pid) => cmd.push_str(&format!(" -p {}", pid)),
+            PerfTarget::App(bundle) => cmd.push_str(&format!(" --app {}", bundle)),
+            PerfTarget::System => cmd.push_str(" -a"),
+        }
+        cmd.push_str(&format!(" -d {}", self.duration.as_secs_f64()));
+        if let Some(hz) = self.frequency {
+            cmd.push_str(&format!(" -f {}", hz));
+        }
+        if let Some(call_graph) = self.call_graph {
+            cmd.push_str(&format!(" -s {}", call_graph.as_arg()));
+        }
+        if !self.events.is_empty() {
+            cmd.push_str(&format!(" -e {}", self.events.join(",")));
+        }
+        cmd.push_str(&format!(" -o {}", self.remote_path));
+        cmd
+    }
+
+    /// `hiperf report` command line for the recording
+    pub fn report_command(&self) -> String {
+        format!("hiperf report -i {}", self.remote_path)
+    }
+}
+
+/// Finished `hiperf` recording pulled to the host
+#[derive(Debug, Clone)]
+pub struct PerfSession {
+    /// Where the recording was written on the device
+    pub remote_path: String,
+    /// Where the recording was pulled to
+    pub local_path: PathBuf,
+    /// What `hiperf record` printed
+    pub output: String,
+    /// What `hiperf report` printed, if requested
+    pub report: Option<String>,
+}
+
+impl PerfSession {
+    /// Record a profile, pull it and optionally report on it
+    ///
+    /// Blocks for the configured duration; the shell timeout is raised to
+    /// cover it. Fails with [`HdcError::CommandFailed`] when `hiperf`
+    /// reports an error, e.g. because the target is not running.
+    pub async fn record(client: &mut HdcClient, options: PerfOptions) -> Result<PerfSession> {
+        let command = options.record_command();
+        info!("Recording profile: {}", command);
+
+        let output = client
+            .shell_with_timeout(&command, options.duration + RECORD_GRACE)
+            .await?;
+        if is_failure(&output) {
+            return Err(HdcError::CommandFailed(output.trim().to_string()));
+        }
+
+        let local = options.local_path.to_string_lossy();
+        let pulled = client
+            .file_recv(&options.remote_path, &local, FileTransferOptions::new())
+            .await;
+        let report = match (&pulled, options.report) {
+            (Ok(_), true) => Some(client.shell(&options.report_command()).await),
+            _ => None,
+        };
+        if !options.keep_remote {
+            if let Err(e) = client
+                .shell(&format!("rm -f {}", options.remote_path))
+                .await
+            {
+                warn!("Failed to remove {}: {}", options.remote_path, e);
+            }
+        }
+        let transfer = pulled?;
+        if transfer.contains("[Fail]") {
+            return Err(HdcError::CommandFailed(transfer.trim().to_string()));
+        }
+
+        debug!("Pulled profile to {}", options.local_path.display());
+        Ok(PerfSession {
+            remote_path: options.remote_path,
+            local_path: options.local_path,
+            output,
+            report: report.transpose()?,
+        })
+    }
+
+    /// Host path of the recording
+    pub fn path(&self) -> &Path {
+        &self.local_path
+    }
+}
+
+/// Whether `hiperf record` output reports an error
+fn is_failure(output: &str) -> bool {
+    output.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.contains("fail") || line.starts_with("error") || line.contains("not exist")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeDevice, MockServer};
+
+    #[test]
+    fn test_record_command() {
+        let options = PerfOptions::app("com.example.app")
+            .call_graph(CallGraph::Dwarf)
+            .event("hw-cpu-cycles")
+            .event("sw-task-clock")
+            .remote_path("/data/local/tmp/app.data");
+        assert_eq!(
+            options.record_command(),
+            "hiperf record --app com.example.app -d 10 -s dwarf \
+             -e hw-cpu-cycles,sw-task-clock -o /data/local/tmp/app.data"
+        );
+        assert_eq!(
+            options.report_command(),
+            "hiperf report -i /data/local/tmp/app.data"
+        );
+        assert_eq!(
+            PerfOptions::system().record_command(),
+            "hiperf record -a -d 10 -o /data/local/tmp/perf.data"
+        );
+
+        assert!(is_failure("RecordCommand failed: pid 99 not exist\n"));
+        assert!(is_failure("Error: --app needs a debuggable app\n"));
+        assert!(!is_failure(
+            "Profiling duration is 1.000 seconds.\nProcess and Saving data...\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let local = std::env::temp_dir().join(format!("hdc_rs_perf_{}.data", std::process::id()));
+        let recv = format!("file recv {} {}", DEFAULT_REMOTE_PATH, local.display());
+        let phone = FakeDevice::new("dev-1")
+            .when("hiperf record -p 4321 -d 1 -o /data/local/tmp/perf.data")
+            .reply("Profiling duration is 1.000 seconds.\nProcess and Saving data...\n")
+            .when_command(recv.clone())
+            .reply("FileTransfer finish, Size:4096")
+            .when("hiperf report -i /data/local/tmp/perf.data")
+            .reply("Overhead  Command  Symbol\n42.00%  app  main\n")
+            .when("rm -f /data/local/tmp/perf.data")
+            .reply("");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        client.connect_device("dev-1").await.unwrap();
+        let options = PerfOptions::pid(4321)
+            .duration(Duration::from_secs(1))
+            .local_path(&local)
+            .report(true);
+        let session = PerfSession::record(&mut client, options).await.unwrap();
+        assert_eq!(session.path(), local);
+        assert!(session.report.unwrap().contains("42.00%"));
+        assert_eq!(
+            phone.commands()[1..],
+            [
+                recv,
+                "shell hiperf report -i /data/local/tmp/perf.data".to_string(),
+                "shell rm -f /data/local/tmp/perf.data".to_string(),
+            ]
+        );
+
+        let phone = FakeDevice::new("dev-1")
+            .when("hiperf record -p 99 -d 10 -o /data/local/tmp/perf.data")
+            .reply("RecordCommand failed: pid 99 not exist\n");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        client.connect_device("dev-1").await.unwrap();
+        let err = PerfSession::record(&mut client, PerfOptions::pid(99))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HdcError::CommandFailed(_)));
+        assert_eq!(phone.commands().len(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+//! Pool of handshaken channels for concurrent operations
+//!
+//! An [`HdcClient`] owns one channel, so everything done through it runs one
+//! command at a time. [`HdcPool`] keeps up to N clients per device, each with
+//! its own channel, and hands them out so a shell command, a file transfer
+//! and a hilog stream can run side by side.
+//!
+//! Most hdc commands consume the channel they run on. Clients handed back in
+//! that state are re-handshaken in the background, so the next checkout
+//! usually finds a ready channel.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::pool::HdcPool;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let pool = HdcPool::new("127.0.0.1:8710", 4);
+//! pool.warm("device-serial").await?;
+//!
+//! let (a, b) = tokio::join!(
+//!     async { pool.get("device-serial").await?.shell("uptime").await },
+//!     async { pool.get("device-serial").await?.shell("df -h").await },
+//! );
+//! println!("{}\n{}", a?, b?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+use crate::client::{HdcClient, HdcClientBuilder};
+use crate::error::Result;
+use crate::transport::ServerAddr;
+
+/// Device a pooled channel is bound to; `None` for server-level commands
+type PoolKey = Option<String>;
+
+/// Pool of up to `size` handshaken clients per device
+///
+/// Cloning is cheap; clones share the same channels.
+#[derive(Clone)]
+pub struct HdcPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    builder: HdcClientBuilder,
+    size: usize,
+    slots: Mutex<HashMap<PoolKey, Slot>>,
+}
+
+/// Per-device state: checkout limit and idle clients
+struct Slot {
+    permits: Arc<Semaphore>,
+    idle: Vec<HdcClient>,
+}
+
+impl Slot {
+    fn new(size: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(size)),
+            idle: Vec::new(),
+        }
+    }
+}
+
+impl HdcPool {
+    /// Pool of up to `size` channels per device to the server at `address`
+    pub fn new(address: impl Into<ServerAddr>, size: usize) -> Self {
+        Self::from_builder(HdcClient::builder().address(address), size)
+    }
+
+    /// Pool whose clients are configured by `builder`
+    ///
+    /// The builder's device, if any, is ignored; each checkout names its own.
+    pub fn from_builder(builder: HdcClientBuilder, size: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                builder,
+                size: size.max(1),
+                slots: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Maximum number of channels per device
+    pub fn size(&self) -> usize {
+        self.inner.size
+    }
+
+    /// Check out a client bound to `device_id`
+    ///
+    /// Waits while all of the device's channels are in use.
+    pub async fn get(&self, device_id: &str) -> Result<PooledClient> {
+        self.checkout(Some(device_id.to_string())).await
+    }
+
+    /// Check out a client for server-level commands such as `list targets`
+    pub async fn get_server(&self) -> Result<PooledClient> {
+        self.checkout(None).await
+    }
+
+    /// Open channels to `device_id` until the pool is full
+    pub async fn warm(&self, device_id: &str) -> Result<()> {
+        let key = Some(device_id.to_string());
+        let mut clients = Vec::new();
+        while self.inner.idle_count(&key) + clients.len() < self.inner.size {
+            clients.push(self.inner.open(key.clone()).await?);
+        }
+        for client in clients {
+            self.inner.put_back(key.clone(), client);
+        }
+        Ok(())
+    }
+
+    /// Number of ready channels not checked out for `device_id`
+    pub fn idle(&self, device_id: &str) -> usize {
+        self.inner.idle_count(&Some(device_id.to_string()))
+    }
+
+    /// Close all idle channels
+    pub fn clear(&self) {
+        for slot in self.inner.lock().values_mut() {
+            slot.idle.clear();
+        }
+    }
+
+    async fn checkout(&self, key: PoolKey) -> Result<PooledClient> {
+        let permits = self.inner.slot_permits(&key);
+        let permit = permits
+            .acquire_owned()
+            .await
+            .expect("pool semaphores are never closed");
+
+        let idle = self.inner.take_idle(&key);
+        let client = match idle {
+            Some(client) if client.is_connected() => client,
+            Some(mut client) => {
+                client.reconnect().await?;
+                client
+            }
+            None => self.inner.open(key.clone()).await?,
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            key,
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl PoolInner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PoolKey, Slot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn slot_permits(&self, key: &PoolKey) -> Arc<Semaphore> {
+        self.lock()
+            .entry(key.clone())
+            .or_insert_with(|| Slot::new(self.size))
+            .permits
+            .clone()
+    }
+
+    fn idle_count(&self, key: &PoolKey) -> usize {
+        self.lock().get(key).map_or(0, |slot| slot.idle.len())
+    }
+
+    fn take_idle(&self, key: &PoolKey) -> Option<HdcClient> {
+        self.lock().get_mut(key).and_then(|slot| slot.idle.pop())
+    }
+
+    fn put_back(&self, key: PoolKey, client: HdcClient) {
+        let mut slots = self.lock();
+        let slot = slots.entry(key).or_insert_with(|| Slot::new(self.size));
+        if slot.idle.len() < self.size {
+            slot.idle.push(client);
+        }
+    }
+
+    async fn open(&self, key: PoolKey) -> Result<HdcClient> {
+        debug!("Opening pooled channel for {:?}", key);
+        self.builder.clone().device_opt(key).build().await
+    }
+}
+
+/// A client checked out of an [`HdcPool`]
+///
+/// Dereferences to [`HdcClient`]. Dropping it returns the client to the
+/// pool; a consumed channel is re-handshaken in the background first.
+pub struct PooledClient {
+    client: Option<HdcClient>,
+    key: PoolKey,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledClient {
+    /// Take the client out of the pool for good
+    ///
+    /// Frees its place, so the pool may open another channel in its stead.
+    pub fn detach(mut self) -> HdcClient {
+        self.client.take().expect("client present until drop")
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = HdcClient;
+
+    fn deref(&self) -> &HdcClient {
+        self.client.as_ref().expect("client present until drop")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut HdcClient {
+        self.client.as_mut().expect("client present until drop")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        let Some(mut client) = self.client.take() else {
+            return;
+        };
+        // A device change through the client would poison the slot
+        if client.connect_key() != self.key.as_deref() {
+            return;
+        }
+        if client.is_connected() {
+            self.pool.put_back(self.key.take(), client);
+            return;
+        }
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let pool = self.pool.clone();
+        let key = self.key.take();
+        runtime.spawn(async move {
+            match client.reconnect().await {
+                Ok(()) => pool.put_back(key, client),
+                Err(e) => debug!("Dropping pooled channel for {:?}: {}", key, e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pool_limits_and_reuses() {
+        let server = MockServer::new()
+            .respond("list targets", "device-1\n")
+            .spawn()
+            .await
+            .unwrap();
+        let pool = HdcPool::new(server.address(), 2);
+
+        pool.warm("device-1").await.unwrap();
+        assert_eq!(pool.idle("device-1"), 2);
+
+        let a = pool.get("device-1").await.unwrap();
+        let b = pool.get("device-1").await.unwrap();
+        assert_eq!(a.connect_key(), Some("device-1"));
+        assert_eq!(pool.idle("device-1"), 0);
+
+        // Both channels are out; a third checkout waits
+        let third = tokio::time::timeout(Duration::from_millis(50), pool.get("device-1")).await;
+        assert!(third.is_err());
+
+        drop(a);
+        let c = pool.get("device-1").await.unwrap();
+        assert!(c.is_connected());
+        drop((b, c));
+        assert_eq!(pool.idle("device-1"), 2);
+
+        let mut server_client = pool.get_server().await.unwrap();
+        assert_eq!(
+            server_client.list_targets().await.unwrap(),
+            vec!["device-1"]
+        );
+    }
+}
@@ -1,9 +1,15 @@
-//! Channel handshake protocol
+//! Channel handshake protocol and raw channel access
 
-use tracing::debug;
+use bytes::Bytes;
+use tracing::{debug, info};
+use zerocopy::network_endian::U32;
+use zerocopy::{FromBytes, FromZeros, Immutable, IntoBytes, KnownLayout};
 
-use super::HANDSHAKE_BANNER;
+use super::{
+    HdcChannelCodec, HdcCommand, Response, HANDSHAKE_BANNER, MAX_PACKET_SIZE, STABLE_PACKET_SIZE,
+};
 use crate::error::{HdcError, Result};
+use crate::transport::{HdcStream, ServerAddr};
 
 /// Channel handshake structure
 ///
@@ -19,8 +25,12 @@ use crate::error::{HdcError, Result};
 ///     char version[64];
 /// }
 /// ```
+///
+/// The struct has that exact byte layout (checked at compile time), so it is
+/// parsed and serialized as a whole via [`zerocopy`] rather than field by
+/// field. Fields added to the C++ struct must be added here in wire order.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, FromBytes, IntoBytes, Immutable, KnownLayout)]
 pub struct ChannelHandShake {
     /// Banner: "OHOS HDC" + feature flags (12 bytes)
     pub banner: [u8; 12],
@@ -37,11 +47,11 @@ impl ChannelHandShake {
     /// Size of the handshake without version field
     pub const SIZE_WITHOUT_VERSION: usize = 12 + 32;
 
-    /// Offset of feature tag in banner
-    const BANNER_FEATURE_TAG_OFFSET: usize = 7;
+    /// Offset of feature tag in banner (after "OHOS HDC" and padding)
+    const BANNER_FEATURE_TAG_OFFSET: usize = 11;
 
     /// Tag indicating huge buffer support
-    const HUGE_BUF_TAG: u8 = b'1';
+    const HUGE_BUF_TAG: u8 = b'H';
 
     /// Create a new handshake from raw bytes
     ///
@@ -60,43 +70,30 @@ impl ChannelHandShake {
             )));
         }
 
-        let mut handshake = ChannelHandShake {
-            banner: [0; 12],
-            channel_id_or_key: [0; 32],
-            version: [0; 64],
-        };
-
-        handshake.banner.copy_from_slice(&data[0..12]);
-        handshake.channel_id_or_key.copy_from_slice(&data[12..44]);
-
         // Version field is optional
-        if data.len() >= Self::SIZE {
-            handshake.version.copy_from_slice(&data[44..108]);
+        let size = if data.len() >= Self::SIZE {
+            Self::SIZE
         } else {
             debug!(
                 "Received handshake without version field ({} bytes)",
                 data.len()
             );
-        }
+            Self::SIZE_WITHOUT_VERSION
+        };
 
+        let mut handshake = Self::new_zeroed();
+        handshake.as_mut_bytes()[..size].copy_from_slice(&data[..size]);
         Ok(handshake)
     }
 
     /// Convert handshake to bytes (full format with version)
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(Self::SIZE);
-        bytes.extend_from_slice(&self.banner);
-        bytes.extend_from_slice(&self.channel_id_or_key);
-        bytes.extend_from_slice(&self.version);
-        bytes
+        self.as_bytes().to_vec()
     }
 
     /// Convert handshake to bytes without version field
     pub fn to_bytes_without_version(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(Self::SIZE_WITHOUT_VERSION);
-        bytes.extend_from_slice(&self.banner);
-        bytes.extend_from_slice(&self.channel_id_or_key);
-        bytes
+        self.as_bytes()[..Self::SIZE_WITHOUT_VERSION].to_vec()
     }
 
     /// Verify the banner is valid
@@ -109,19 +106,25 @@ impl ChannelHandShake {
 
     /// Get channel ID from the handshake (server -> client)
     pub fn get_channel_id(&self) -> u32 {
-        // Channel ID is stored in first 4 bytes of channel_id_or_key, network byte order
-        u32::from_be_bytes([
-            self.channel_id_or_key[0],
-            self.channel_id_or_key[1],
-            self.channel_id_or_key[2],
-            self.channel_id_or_key[3],
-        ])
+        self.channel_id().get()
     }
 
     /// Set channel ID in the handshake
     pub fn set_channel_id(&mut self, channel_id: u32) {
-        let bytes = channel_id.to_be_bytes();
-        self.channel_id_or_key[0..4].copy_from_slice(&bytes);
+        self.channel_id_mut().set(channel_id);
+    }
+
+    /// Channel ID view of the union: first 4 bytes, network byte order
+    fn channel_id(&self) -> &U32 {
+        U32::ref_from_prefix(&self.channel_id_or_key)
+            .map(|(id, _)| id)
+            .expect("channel id fits the union")
+    }
+
+    fn channel_id_mut(&mut self) -> &mut U32 {
+        U32::mut_from_prefix(&mut self.channel_id_or_key)
+            .map(|(id, _)| id)
+            .expect("channel id fits the union")
     }
 
     /// Set connect key in the handshake (client -> server)
@@ -143,12 +146,22 @@ impl ChannelHandShake {
         String::from_utf8_lossy(&self.channel_id_or_key[0..end]).to_string()
     }
 
-    /// Check if server supports stable buffer mode
+    /// Check if the peer is limited to stable (small) buffers
     pub fn is_stable_buf(&self) -> bool {
-        if self.banner.len() > Self::BANNER_FEATURE_TAG_OFFSET {
-            self.banner[Self::BANNER_FEATURE_TAG_OFFSET] != Self::HUGE_BUF_TAG
+        self.banner[Self::BANNER_FEATURE_TAG_OFFSET] != Self::HUGE_BUF_TAG
+    }
+
+    /// Advertise (or stop advertising) huge buffer support
+    pub fn set_huge_buf(&mut self, enable: bool) {
+        self.banner[Self::BANNER_FEATURE_TAG_OFFSET] = if enable { Self::HUGE_BUF_TAG } else { 0 };
+    }
+
+    /// Largest packet both sides can exchange given the advertised features
+    pub fn max_packet_size(&self) -> usize {
+        if self.is_stable_buf() {
+            STABLE_PACKET_SIZE
         } else {
-            true
+            MAX_PACKET_SIZE
         }
     }
 
@@ -169,12 +182,219 @@ impl ChannelHandShake {
 
 impl Default for ChannelHandShake {
     fn default() -> Self {
-        Self {
-            banner: [0; 12],
-            channel_id_or_key: [0; 32],
-            version: [0; 64],
+        Self::new_zeroed()
+    }
+}
+
+const _: () = assert!(std::mem::size_of::<ChannelHandShake>() == ChannelHandShake::SIZE);
+
+/// Check the server's handshake and build the reply for `connect_key`
+///
+/// Returns the handshake as received along with the reply to send.
+pub(crate) fn answer_handshake(
+    data: &[u8],
+    connect_key: Option<&str>,
+) -> Result<(ChannelHandShake, Vec<u8>)> {
+    let received_size = data.len();
+    debug!("Received handshake data: {} bytes", received_size);
+
+    // Step 1: Parse and verify banner
+    let mut handshake = ChannelHandShake::from_bytes(data)?;
+    handshake.verify_banner()?;
+    info!("Banner verified: {:?}", &handshake.banner[..8]);
+    let server_handshake = handshake.clone();
+
+    // Step 2: Extract channel ID
+    info!("Assigned channel ID: {}", handshake.get_channel_id());
+
+    // Step 3: Check features. The banner is echoed back, so a huge-buffer
+    // tag from the server is acknowledged.
+    debug!(
+        "Server stable buffer mode: {}, max packet size {}",
+        handshake.is_stable_buf(),
+        handshake.max_packet_size()
+    );
+
+    // Step 4: Set connect key
+    if let Some(key) = connect_key {
+        handshake.set_connect_key(key);
+        info!("Using connect key: {}", key);
+    } else {
+        // Empty connect key for initial connection
+        handshake.set_connect_key("");
+    }
+
+    // Reply with the same format as received
+    // If server sent 44 bytes (without version), respond with 44 bytes
+    // If server sent 108 bytes (with version), respond with 108 bytes
+    let response = if received_size >= ChannelHandShake::SIZE {
+        debug!("Sending full handshake response (108 bytes)");
+        handshake.to_bytes()
+    } else {
+        debug!("Sending handshake response without version (44 bytes)");
+        handshake.to_bytes_without_version()
+    };
+    Ok((server_handshake, response))
+}
+
+/// A handshaken channel to the HDC server
+///
+/// This is the transport [`HdcClient`](crate::HdcClient) is built on. Use it
+/// directly to implement hdc commands the crate doesn't wrap yet: send the
+/// command string with [`send`](Self::send) and read packets with
+/// [`recv`](Self::recv) or [`recv_message`](Self::recv_message). Unlike the
+/// client, a channel does not reconnect, answer heartbeats or interpret
+/// close packets; most server commands consume the channel they run on.
+///
+/// # Example
+/// ```no_run
+/// use hdc_rs::protocol::Channel;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut channel = Channel::connect("127.0.0.1:8710", Some("device_id")).await?;
+/// channel.send(b"shell getprop const.product.model").await?;
+/// let message = channel.recv_message().await?;
+/// println!("{}", message.text());
+/// # Ok(())
+/// # }
+/// ```
+pub struct Channel {
+    stream: HdcStream,
+    codec: HdcChannelCodec,
+    id: u32,
+    server_handshake: ChannelHandShake,
+}
+
+impl Channel {
+    /// Connect to the server at `address` and perform the handshake
+    pub async fn connect(
+        address: impl Into<ServerAddr>,
+        connect_key: Option<&str>,
+    ) -> Result<Self> {
+        let stream = address
+            .into()
+            .connect(crate::transport::Timeouts::default().connect)
+            .await?;
+        Self::handshake(stream, connect_key).await
+    }
+
+    /// Perform the channel handshake on an already connected stream
+    ///
+    /// `connect_key` selects the target device; `None` opens a channel to the
+    /// server itself (for `list targets`, `fport ls`, ...).
+    pub async fn handshake(
+        stream: impl Into<HdcStream>,
+        connect_key: Option<&str>,
+    ) -> Result<Self> {
+        Self::handshake_with_codec(stream, connect_key, HdcChannelCodec::new()).await
+    }
+
+    /// Perform the handshake using a preconfigured codec
+    ///
+    /// Lets a [`PacketObserver`](super::PacketObserver) attached to `codec`
+    /// see the handshake packets too.
+    pub async fn handshake_with_codec(
+        stream: impl Into<HdcStream>,
+        connect_key: Option<&str>,
+        mut codec: HdcChannelCodec,
+    ) -> Result<Self> {
+        let mut stream = stream.into();
+
+        info!("Starting channel handshake");
+
+        let handshake_data = codec.read_packet(&mut stream).await?;
+        let (server_handshake, response) = answer_handshake(&handshake_data, connect_key)?;
+        let id = server_handshake.get_channel_id();
+        codec.set_max_packet_size(server_handshake.max_packet_size());
+
+        codec.write_packet(&mut stream, &response).await?;
+        info!("Channel handshake completed successfully");
+
+        Ok(Self {
+            stream,
+            codec,
+            id,
+            server_handshake,
+        })
+    }
+
+    /// Channel ID assigned by the server
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Handshake as received from the server (banner, features, version)
+    pub fn server_handshake(&self) -> &ChannelHandShake {
+        &self.server_handshake
+    }
+
+    /// Largest packet that may be sent on this channel
+    pub fn max_packet_size(&self) -> usize {
+        self.codec.max_packet_size()
+    }
+
+    /// Notify `observer` of every packet sent or received on this channel
+    pub fn set_observer(&mut self, observer: Option<std::sync::Arc<dyn super::PacketObserver>>) {
+        self.codec.set_observer(observer);
+    }
+
+    /// Send one packet
+    pub async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.codec.write_packet(&mut self.stream, data).await
+    }
+
+    /// Send one packet prefixed with a command code
+    pub async fn send_command(&mut self, command: HdcCommand, payload: &[u8]) -> Result<()> {
+        let mut data = Vec::with_capacity(2 + payload.len());
+        data.extend_from_slice(&command.as_u16().to_le_bytes());
+        data.extend_from_slice(payload);
+        self.send(&data).await
+    }
+
+    /// Receive one raw packet
+    pub async fn recv(&mut self) -> Result<Bytes> {
+        self.codec.read_packet(&mut self.stream).await
+    }
+
+    /// Receive one packet decoded into command code and payload
+    pub async fn recv_message(&mut self) -> Result<Response> {
+        Ok(Response::parse(self.recv().await?))
+    }
+
+    /// Ask the server to release the channel
+    ///
+    /// Sends [`HdcCommand::KernelChannelClose`] and discards pending output
+    /// until the server acknowledges or hangs up, so the channel is freed at
+    /// once instead of when the server notices the TCP teardown. Wrap the
+    /// call in a timeout if the server may not answer.
+    pub async fn close(mut self) -> Result<()> {
+        debug!("Closing channel {}", self.id);
+        self.send_command(HdcCommand::KernelChannelClose, &[1])
+            .await?;
+        loop {
+            match self.recv().await {
+                Ok(data) => {
+                    if Response::parse(data).command() == Some(HdcCommand::KernelChannelClose) {
+                        return Ok(());
+                    }
+                }
+                Err(HdcError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
+
+    /// Convert into a framed stream for split/concurrent use
+    pub fn into_framed(self) -> tokio_util::codec::Framed<HdcStream, HdcChannelCodec> {
+        tokio_util::codec::Framed::new(self.stream, self.codec)
+    }
+
+    /// Take the underlying stream
+    pub fn into_inner(self) -> HdcStream {
+        self.stream
+    }
 }
 
 #[cfg(test)]
@@ -188,6 +408,32 @@ mod tests {
         assert_eq!(std::mem::size_of::<ChannelHandShake>(), 108);
     }
 
+    #[test]
+    fn test_handshake_round_trip() {
+        let mut hs = ChannelHandShake::default();
+        hs.banner[..8].copy_from_slice(HANDSHAKE_BANNER);
+        hs.set_huge_buf(true);
+        hs.set_channel_id(7);
+        hs.set_version("Ver: 3.1.0e");
+
+        let bytes = hs.to_bytes();
+        assert_eq!(&bytes[12..16], &[0, 0, 0, 7]);
+        assert_eq!(&bytes[44..55], b"Ver: 3.1.0e");
+        assert_eq!(
+            ChannelHandShake::from_bytes(&bytes).unwrap().to_bytes(),
+            bytes
+        );
+        assert_eq!(&hs.to_bytes_without_version()[..], &bytes[..44]);
+
+        // Extra trailing bytes are ignored
+        let mut long = bytes.clone();
+        long.extend_from_slice(b"extra");
+        assert_eq!(
+            ChannelHandShake::from_bytes(&long).unwrap().to_bytes(),
+            bytes
+        );
+    }
+
     #[test]
     fn test_handshake_from_bytes_short() {
         // Test 44-byte handshake (without version)
@@ -234,6 +480,20 @@ mod tests {
         assert_eq!(hs.get_version(), "3.2.0");
     }
 
+    #[test]
+    fn test_huge_buf_feature() {
+        let mut hs = ChannelHandShake::default();
+        hs.banner[..8].copy_from_slice(b"OHOS HDC");
+        assert!(hs.is_stable_buf());
+        assert_eq!(hs.max_packet_size(), STABLE_PACKET_SIZE);
+
+        hs.set_huge_buf(true);
+        assert_eq!(hs.banner[11], b'H');
+        assert!(!hs.is_stable_buf());
+        assert_eq!(hs.max_packet_size(), MAX_PACKET_SIZE);
+        assert!(hs.verify_banner().is_ok());
+    }
+
     #[test]
     fn test_to_bytes_without_version() {
         let mut hs = ChannelHandShake::default();
@@ -244,4 +504,63 @@ mod tests {
         assert_eq!(bytes.len(), 44);
         assert_eq!(&bytes[..8], b"OHOS HDC");
     }
+
+    #[tokio::test]
+    async fn test_channel_roundtrip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut codec = HdcChannelCodec::new();
+            let mut hs = ChannelHandShake::default();
+            hs.banner[..8].copy_from_slice(b"OHOS HDC");
+            hs.set_channel_id(7);
+            hs.set_version("Ver: 3.1.0e");
+            codec
+                .write_packet(&mut stream, &hs.to_bytes())
+                .await
+                .unwrap();
+
+            let reply = codec.read_packet(&mut stream).await.unwrap();
+            let reply = ChannelHandShake::from_bytes(&reply).unwrap();
+            assert_eq!(reply.get_connect_key(), "serial");
+
+            assert_eq!(
+                codec.read_packet(&mut stream).await.unwrap(),
+                &b"custom"[..]
+            );
+            let mut echo = HdcCommand::KernelEcho.as_u16().to_le_bytes().to_vec();
+            echo.extend_from_slice(b"pong");
+            codec.write_packet(&mut stream, &echo).await.unwrap();
+        });
+
+        let mut channel = Channel::connect(address, Some("serial")).await.unwrap();
+        assert_eq!(channel.id(), 7);
+        assert_eq!(channel.server_handshake().get_version(), "Ver: 3.1.0e");
+
+        channel.send(b"custom").await.unwrap();
+        let message = channel.recv_message().await.unwrap();
+        assert!(message.is(HdcCommand::KernelEcho));
+        assert_eq!(message.text(), "pong");
+        server.await.unwrap();
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_handshake_from_any_bytes(data in proptest::collection::vec(0u8.., 0..160)) {
+            match ChannelHandShake::from_bytes(&data) {
+                Ok(hs) => {
+                    let len = if data.len() >= ChannelHandShake::SIZE {
+                        ChannelHandShake::SIZE
+                    } else {
+                        ChannelHandShake::SIZE_WITHOUT_VERSION
+                    };
+                    proptest::prop_assert_eq!(&hs.to_bytes()[..len], &data[..len]);
+                    let _ = (hs.get_connect_key(), hs.get_version(), hs.max_packet_size());
+                }
+                Err(_) => proptest::prop_assert!(data.len() < ChannelHandShake::SIZE_WITHOUT_VERSION),
+            }
+        }
+    }
 }
@@ -92,16 +92,48 @@ impl HdcCommand {
             8 => Some(Self::KernelTargetDisconnect),
             9 => Some(Self::KernelEcho),
             10 => Some(Self::KernelEchoRaw),
+            11 => Some(Self::KernelEnableKeepalive),
+            12 => Some(Self::KernelWakeupSlavetask),
             13 => Some(Self::CheckServer),
             14 => Some(Self::CheckDevice),
+            15 => Some(Self::WaitFor),
+            16 => Some(Self::ServerKill),
+            17 => Some(Self::ServiceStart),
             1001 => Some(Self::UnityExecute),
             1002 => Some(Self::UnityRemount),
             1003 => Some(Self::UnityReboot),
+            1004 => Some(Self::UnityRunmode),
+            1005 => Some(Self::UnityHilog),
+            1007 => Some(Self::UnityRootrun),
+            1008 => Some(Self::JdwpList),
+            1009 => Some(Self::JdwpTrack),
             2000 => Some(Self::ShellInit),
             2001 => Some(Self::ShellData),
+            2500 => Some(Self::ForwardInit),
+            2501 => Some(Self::ForwardCheck),
+            2502 => Some(Self::ForwardCheckResult),
+            2503 => Some(Self::ForwardActiveSlave),
+            2504 => Some(Self::ForwardActiveMaster),
+            2505 => Some(Self::ForwardData),
+            2506 => Some(Self::ForwardFreeContext),
+            2507 => Some(Self::ForwardList),
+            2508 => Some(Self::ForwardRemove),
+            2509 => Some(Self::ForwardSuccess),
             3000 => Some(Self::FileInit),
+            3001 => Some(Self::FileCheck),
+            3002 => Some(Self::FileBegin),
             3003 => Some(Self::FileData),
             3004 => Some(Self::FileFinish),
+            3005 => Some(Self::AppSideload),
+            3006 => Some(Self::FileMode),
+            3007 => Some(Self::DirMode),
+            3500 => Some(Self::AppInit),
+            3501 => Some(Self::AppCheck),
+            3502 => Some(Self::AppBegin),
+            3503 => Some(Self::AppData),
+            3504 => Some(Self::AppFinish),
+            3506 => Some(Self::AppUninstall),
+            5000 => Some(Self::HeartbeatMsg),
             _ => None,
         }
     }
@@ -118,3 +150,24 @@ impl HdcCommand {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_u16_roundtrip() {
+        for cmd in [
+            HdcCommand::KernelChannelClose,
+            HdcCommand::KernelEnableKeepalive,
+            HdcCommand::WaitFor,
+            HdcCommand::JdwpList,
+            HdcCommand::ForwardSuccess,
+            HdcCommand::AppUninstall,
+            HdcCommand::HeartbeatMsg,
+        ] {
+            assert_eq!(HdcCommand::from_u16(cmd.as_u16()), Some(cmd));
+        }
+        assert_eq!(HdcCommand::from_u16(3), None);
+    }
+}
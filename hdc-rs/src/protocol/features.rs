@@ -0,0 +1,159 @@
+//! Server version and feature detection
+
+use std::fmt;
+
+use super::ChannelHandShake;
+
+/// Parsed hdc version, e.g. `3.1.0e`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    /// Major version
+    pub major: u16,
+    /// Minor version
+    pub minor: u16,
+    /// Patch version
+    pub patch: u16,
+    /// Build letter suffix (`e` in `3.1.0e`)
+    pub suffix: Option<char>,
+}
+
+impl ServerVersion {
+    /// Create a version without suffix
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            suffix: None,
+        }
+    }
+
+    /// Parse `3.1.0e`, `Ver: 3.1.0e` or `ver.3.1.0e`
+    pub fn parse(s: &str) -> Option<Self> {
+        let start = s.find(|c: char| c.is_ascii_digit())?;
+        let mut parts = s[start..].trim_end().splitn(3, '.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+
+        let rest = parts.next()?;
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let patch = rest[..digits].parse().ok()?;
+        let suffix = rest[digits..]
+            .chars()
+            .next()
+            .filter(char::is_ascii_alphabetic);
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+            suffix,
+        })
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(suffix) = self.suffix {
+            write!(f, "{}", suffix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Capabilities advertised by the server during the channel handshake
+///
+/// Servers that send the short (44-byte) handshake carry no version and are
+/// treated as the oldest protocol: no heartbeats and no compressed transfers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerFeatures {
+    /// Raw version string from the handshake, if any
+    pub version_string: Option<String>,
+    /// Parsed version, if the string was understood
+    pub version: Option<ServerVersion>,
+    /// Raw banner bytes
+    pub banner: [u8; 12],
+    /// Whether the server accepts 511KB packets
+    pub huge_buffers: bool,
+}
+
+impl ServerFeatures {
+    /// First version that answers heartbeats and keepalive requests
+    pub const MIN_HEARTBEAT_VERSION: ServerVersion = ServerVersion::new(3, 1, 0);
+
+    /// First version that supports compressed file transfer (`-z`)
+    pub const MIN_COMPRESS_VERSION: ServerVersion = ServerVersion::new(3, 1, 0);
+
+    /// Derive features from the handshake the server sent
+    pub fn from_handshake(handshake: &ChannelHandShake) -> Self {
+        let version_string = Some(handshake.get_version()).filter(|v| !v.is_empty());
+        let version = version_string.as_deref().and_then(ServerVersion::parse);
+        Self {
+            version_string,
+            version,
+            banner: handshake.banner,
+            huge_buffers: !handshake.is_stable_buf(),
+        }
+    }
+
+    /// Check whether the server version is at least `min`
+    pub fn at_least(&self, min: ServerVersion) -> bool {
+        self.version.is_some_and(|v| v >= min)
+    }
+
+    /// Whether heartbeats and `KernelEnableKeepalive` are understood
+    pub fn heartbeat(&self) -> bool {
+        self.at_least(Self::MIN_HEARTBEAT_VERSION)
+    }
+
+    /// Whether compressed file transfer is supported
+    pub fn compressed_transfer(&self) -> bool {
+        self.at_least(Self::MIN_COMPRESS_VERSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let v = ServerVersion::parse("Ver: 3.1.0e").unwrap();
+        assert_eq!(
+            v,
+            ServerVersion {
+                suffix: Some('e'),
+                ..ServerVersion::new(3, 1, 0)
+            }
+        );
+        assert_eq!(v.to_string(), "3.1.0e");
+        assert_eq!(
+            ServerVersion::parse("ver.2.0.0").unwrap(),
+            ServerVersion::new(2, 0, 0)
+        );
+        assert!(ServerVersion::parse("unknown").is_none());
+
+        assert!(ServerVersion::parse("3.1.0a").unwrap() > ServerVersion::new(3, 1, 0));
+        assert!(ServerVersion::new(3, 0, 9) < ServerVersion::new(3, 1, 0));
+    }
+
+    #[test]
+    fn test_features_from_handshake() {
+        let mut hs = ChannelHandShake::default();
+        hs.banner[..8].copy_from_slice(b"OHOS HDC");
+        let features = ServerFeatures::from_handshake(&hs);
+        assert_eq!(features.version, None);
+        assert!(!features.heartbeat());
+        assert!(!features.huge_buffers);
+
+        hs.set_version("Ver: 3.1.0e");
+        hs.set_huge_buf(true);
+        let features = ServerFeatures::from_handshake(&hs);
+        assert!(features.heartbeat());
+        assert!(features.compressed_transfer());
+        assert!(features.huge_buffers);
+    }
+}
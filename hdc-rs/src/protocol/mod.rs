@@ -2,18 +2,32 @@
 
 pub mod channel;
 pub mod command;
+pub mod features;
+pub mod observer;
 pub mod packet;
+pub mod response;
+pub mod session;
 
-pub use channel::ChannelHandShake;
+pub use channel::{Channel, ChannelHandShake};
 pub use command::HdcCommand;
-pub use packet::PacketCodec;
+pub use features::{ServerFeatures, ServerVersion};
+pub use observer::{PacketDirection, PacketEvent, PacketObserver, TracingObserver};
+pub use packet::{HdcChannelCodec, PacketCodec};
+pub use response::Response;
+pub use session::{SessionCodec, SessionHandShake, SessionPacket};
 
 /// HDC handshake banner
 pub const HANDSHAKE_BANNER: &[u8] = b"OHOS HDC";
 
 /// Maximum packet size (511KB for large transfers)
+///
+/// Only servers advertising the huge-buffer feature accept packets this
+/// large; see [`STABLE_PACKET_SIZE`].
 pub const MAX_PACKET_SIZE: usize = 511 * 1024;
 
+/// Maximum packet size for servers without huge-buffer support (60KB)
+pub const STABLE_PACKET_SIZE: usize = 60 * 1024;
+
 /// Default buffer size
 pub const DEFAULT_BUF_SIZE: usize = 1024;
 
@@ -0,0 +1,88 @@
+//! Packet-level observation hooks
+
+use std::fmt;
+
+use tracing::debug;
+
+/// Number of leading payload bytes passed to observers
+pub const PACKET_PREVIEW_LEN: usize = 16;
+
+/// Which way a packet travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketDirection {
+    /// Client to server
+    Sent,
+    /// Server to client
+    Received,
+}
+
+impl fmt::Display for PacketDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sent => "sent",
+            Self::Received => "received",
+        })
+    }
+}
+
+/// A packet seen by the codec
+#[derive(Debug, Clone, Copy)]
+pub struct PacketEvent<'a> {
+    /// Which way the packet travelled
+    pub direction: PacketDirection,
+    /// Payload length, without the length prefix
+    pub length: usize,
+    /// Up to [`PACKET_PREVIEW_LEN`] leading payload bytes
+    pub head: &'a [u8],
+}
+
+impl<'a> PacketEvent<'a> {
+    pub(crate) fn new(direction: PacketDirection, data: &'a [u8]) -> Self {
+        Self {
+            direction,
+            length: data.len(),
+            head: &data[..data.len().min(PACKET_PREVIEW_LEN)],
+        }
+    }
+}
+
+/// Receives a callback for every packet a codec sends or receives
+///
+/// Attach one with [`HdcChannelCodec::set_observer`](super::HdcChannelCodec::set_observer)
+/// or [`HdcClient::set_packet_observer`](crate::HdcClient::set_packet_observer)
+/// to debug protocol issues without trace-logging whole payloads. Callbacks
+/// run inline on the I/O path and should return quickly.
+pub trait PacketObserver: Send + Sync {
+    /// Called after a packet has been written
+    fn on_packet_sent(&self, event: &PacketEvent<'_>) {
+        let _ = event;
+    }
+
+    /// Called after a packet has been read
+    fn on_packet_received(&self, event: &PacketEvent<'_>) {
+        let _ = event;
+    }
+}
+
+/// Observer logging every packet at debug level
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingObserver;
+
+impl TracingObserver {
+    fn log(event: &PacketEvent<'_>) {
+        debug!(
+            "Packet {}: {} bytes, head {:02x?}",
+            event.direction, event.length, event.head
+        );
+    }
+}
+
+impl PacketObserver for TracingObserver {
+    fn on_packet_sent(&self, event: &PacketEvent<'_>) {
+        Self::log(event);
+    }
+
+    fn on_packet_received(&self, event: &PacketEvent<'_>) {
+        Self::log(event);
+    }
+}
@@ -1,10 +1,14 @@
 //! Packet encoding and decoding
 
-use bytes::{BufMut, BytesMut};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::sync::Arc;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder};
 use tracing::{debug, trace};
 
-use super::{MAX_PACKET_SIZE, PACKET_LENGTH_SIZE};
+use super::observer::{PacketDirection, PacketEvent, PacketObserver};
+use super::{MAX_PACKET_SIZE, PACKET_LENGTH_SIZE, STABLE_PACKET_SIZE};
 use crate::error::{HdcError, Result};
 
 /// Codec for HDC packet protocol
@@ -17,69 +21,135 @@ use crate::error::{HdcError, Result};
 /// | N bytes: data    |
 /// +------------------+
 /// ```
-pub struct PacketCodec {
-    #[allow(dead_code)]
+///
+/// Besides the sequential [`read_packet`](Self::read_packet) and
+/// [`write_packet`](Self::write_packet) helpers, the codec implements
+/// [`Decoder`] and [`Encoder`], so it can drive a
+/// [`Framed`](tokio_util::codec::Framed) stream whose halves are polled
+/// independently.
+///
+/// # Example
+/// ```no_run
+/// use hdc_rs::protocol::HdcChannelCodec;
+/// use tokio::net::TcpStream;
+/// use tokio_util::codec::Framed;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let stream = TcpStream::connect("127.0.0.1:8710").await?;
+/// let framed = Framed::new(stream, HdcChannelCodec::new());
+/// # Ok(())
+/// # }
+/// ```
+pub struct HdcChannelCodec {
+    /// Reusable buffer for sequential reads
     read_buf: BytesMut,
+    /// Largest packet this codec will encode
+    max_packet_size: usize,
+    /// Notified of every packet sent or received
+    observer: Option<Arc<dyn PacketObserver>>,
+    /// Progress of the packet currently being read
+    read_state: ReadState,
+}
+
+/// Progress of a sequential packet read
+///
+/// Kept in the codec so a read cancelled part-way (e.g. by a timeout)
+/// resumes where it stopped instead of misreading payload bytes as a length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadState {
+    /// Reading the length prefix, `filled` bytes so far
+    Length {
+        buf: [u8; PACKET_LENGTH_SIZE],
+        filled: usize,
+    },
+    /// Reading a payload of `len` bytes into `read_buf`, `filled` so far
+    Body { len: usize, filled: usize },
+    /// An invalid length was read; the stream position is unknown
+    Desynced,
+}
+
+impl ReadState {
+    const START: Self = Self::Length {
+        buf: [0; PACKET_LENGTH_SIZE],
+        filled: 0,
+    };
 }
 
-impl PacketCodec {
+/// Former name of [`HdcChannelCodec`]
+pub type PacketCodec = HdcChannelCodec;
+
+impl HdcChannelCodec {
     /// Create a new packet codec
     pub fn new() -> Self {
         Self {
-            read_buf: BytesMut::with_capacity(MAX_PACKET_SIZE),
+            read_buf: BytesMut::with_capacity(STABLE_PACKET_SIZE),
+            max_packet_size: MAX_PACKET_SIZE,
+            observer: None,
+            read_state: ReadState::START,
         }
     }
 
-    /// Encode data into a packet with length prefix
-    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
-        if data.len() > MAX_PACKET_SIZE {
-            return Err(HdcError::BufferError(format!(
-                "Data size {} exceeds maximum packet size {}",
-                data.len(),
-                MAX_PACKET_SIZE
-            )));
-        }
-
-        let size = data.len() as u32;
-        let mut buf = Vec::with_capacity(PACKET_LENGTH_SIZE + data.len());
+    /// Whether a sequential read stopped part-way through a packet
+    ///
+    /// The next [`read_packet`](Self::read_packet) resumes that packet.
+    pub fn has_partial_packet(&self) -> bool {
+        !matches!(
+            self.read_state,
+            ReadState::Length { filled: 0, .. } | ReadState::Desynced
+        )
+    }
 
-        // Write length as big-endian
-        buf.put_u32(size);
-        buf.extend_from_slice(data);
+    /// Whether the stream was found to be out of sync
+    ///
+    /// Once set, every read fails with [`HdcError::Desync`]; the connection
+    /// has to be replaced.
+    pub fn is_desynced(&self) -> bool {
+        self.read_state == ReadState::Desynced
+    }
 
-        trace!("Encoded packet: size={}, data_len={}", size, data.len());
-        Ok(buf)
+    /// Notify `observer` of every packet this codec sends or receives
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn PacketObserver>>) {
+        self.observer = observer;
     }
 
-    /// Read and decode a packet from a stream
-    pub async fn decode<S>(&mut self, stream: &mut S) -> Result<Vec<u8>>
-    where
-        S: AsyncReadExt + Unpin,
-    {
-        // Read length prefix (4 bytes, big-endian)
-        let mut len_buf = [0u8; PACKET_LENGTH_SIZE];
-        stream.read_exact(&mut len_buf).await?;
-        let packet_len = u32::from_be_bytes(len_buf) as usize;
+    /// Currently attached observer
+    pub fn observer(&self) -> Option<&Arc<dyn PacketObserver>> {
+        self.observer.as_ref()
+    }
 
-        if packet_len == 0 {
-            // Empty packet - return empty vec instead of error
-            debug!("Received zero-length packet");
-            return Ok(Vec::new());
+    fn notify(&self, direction: PacketDirection, data: &[u8]) {
+        #[cfg(feature = "metrics")]
+        match direction {
+            PacketDirection::Sent => crate::metrics::bytes_sent(data.len()),
+            PacketDirection::Received => crate::metrics::bytes_received(data.len()),
         }
-
-        if packet_len > MAX_PACKET_SIZE {
-            return Err(HdcError::Protocol(format!(
-                "Packet size {} exceeds maximum {}",
-                packet_len, MAX_PACKET_SIZE
-            )));
+        if let Some(observer) = &self.observer {
+            let event = PacketEvent::new(direction, data);
+            match direction {
+                PacketDirection::Sent => observer.on_packet_sent(&event),
+                PacketDirection::Received => observer.on_packet_received(&event),
+            }
         }
+    }
 
-        // Read packet data
-        let mut data = vec![0u8; packet_len];
-        stream.read_exact(&mut data).await?;
+    /// Largest packet this codec will encode
+    pub fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
 
-        debug!("Decoded packet: size={}", packet_len);
-        Ok(data)
+    /// Limit outgoing packets to `size` bytes (capped at [`MAX_PACKET_SIZE`])
+    ///
+    /// Set from the handshake's huge-buffer feature. Incoming packets are
+    /// always accepted up to [`MAX_PACKET_SIZE`].
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.max_packet_size = size.min(MAX_PACKET_SIZE);
+    }
+
+    /// Encode data into a packet with length prefix
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(PACKET_LENGTH_SIZE + data.len());
+        encode_into(data, self.max_packet_size, &mut buf)?;
+        Ok(buf)
     }
 
     /// Write an encoded packet to a stream
@@ -90,6 +160,7 @@ impl PacketCodec {
         let packet = self.encode(data)?;
         stream.write_all(&packet).await?;
         stream.flush().await?;
+        self.notify(PacketDirection::Sent, data);
         debug!(
             "Wrote packet: {} bytes (data: {} bytes)",
             packet.len(),
@@ -98,21 +169,238 @@ impl PacketCodec {
         Ok(())
     }
 
-    /// Read a raw packet (already has length prefix)
-    pub async fn read_packet<S>(&mut self, stream: &mut S) -> Result<Vec<u8>>
+    /// Read and decode one packet from a stream
+    ///
+    /// Reads exactly one packet, never more, so it can be mixed with other
+    /// direct reads of the same stream. The payload is read into the codec's
+    /// internal buffer and handed out as a [`Bytes`] view of it; the buffer's
+    /// allocation is reused once earlier packets have been dropped.
+    ///
+    /// This method is cancel safe: if the future is dropped part-way through
+    /// a packet, the bytes already read are kept and the next call continues
+    /// the same packet. A length above [`MAX_PACKET_SIZE`] means the stream
+    /// is out of sync and fails this and all later reads with
+    /// [`HdcError::Desync`].
+    pub async fn read_packet<S>(&mut self, stream: &mut S) -> Result<Bytes>
     where
-        S: AsyncReadExt + Unpin,
+        S: AsyncRead + Unpin,
     {
-        self.decode(stream).await
+        loop {
+            if let Some(packet) = self.advance_read()? {
+                return Ok(packet);
+            }
+            let n = stream.read(self.unfilled()).await?;
+            self.filled(n)?;
+        }
+    }
+
+    /// Write an encoded packet to a blocking stream
+    pub fn write_packet_blocking<S: std::io::Write>(
+        &self,
+        stream: &mut S,
+        data: &[u8],
+    ) -> Result<()> {
+        let packet = self.encode(data)?;
+        stream.write_all(&packet)?;
+        stream.flush()?;
+        self.notify(PacketDirection::Sent, data);
+        debug!(
+            "Wrote packet: {} bytes (data: {} bytes)",
+            packet.len(),
+            data.len()
+        );
+        Ok(())
+    }
+
+    /// Read one packet from a blocking stream
+    ///
+    /// Behaves like [`read_packet`](Self::read_packet): a read that fails
+    /// part-way, e.g. on a socket read timeout, is resumed by the next call.
+    pub fn read_packet_blocking<S: std::io::Read>(&mut self, stream: &mut S) -> Result<Bytes> {
+        loop {
+            if let Some(packet) = self.advance_read()? {
+                return Ok(packet);
+            }
+            let n = stream.read(self.unfilled())?;
+            self.filled(n)?;
+        }
+    }
+
+    /// Move the read state forward as far as possible without input
+    ///
+    /// Returns the packet once it is complete.
+    fn advance_read(&mut self) -> Result<Option<Bytes>> {
+        match self.read_state {
+            ReadState::Length { buf, filled } if filled == PACKET_LENGTH_SIZE => {
+                let packet_len = u32::from_be_bytes(buf) as usize;
+                if let Err(e) = check_length(packet_len) {
+                    self.read_state = ReadState::Desynced;
+                    return Err(e);
+                }
+                if packet_len == 0 {
+                    // Empty packet - return empty bytes instead of error
+                    debug!("Received zero-length packet");
+                    self.read_state = ReadState::START;
+                    self.notify(PacketDirection::Received, &[]);
+                    return Ok(Some(Bytes::new()));
+                }
+
+                self.read_buf.clear();
+                self.read_buf.resize(packet_len, 0);
+                self.read_state = ReadState::Body {
+                    len: packet_len,
+                    filled: 0,
+                };
+                Ok(None)
+            }
+            ReadState::Body { len, filled } if filled == len => {
+                debug!("Decoded packet: size={}", len);
+                self.read_state = ReadState::START;
+                self.notify(PacketDirection::Received, &self.read_buf);
+                Ok(Some(self.read_buf.split().freeze()))
+            }
+            ReadState::Desynced => Err(desync_error()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Buffer the next read of the current packet goes into
+    fn unfilled(&mut self) -> &mut [u8] {
+        match &mut self.read_state {
+            ReadState::Length { buf, filled } => &mut buf[*filled..],
+            ReadState::Body { len, filled } => &mut self.read_buf[*filled..*len],
+            ReadState::Desynced => &mut [],
+        }
+    }
+
+    /// Account for `n` bytes read into [`unfilled`](Self::unfilled)
+    fn filled(&mut self, n: usize) -> Result<()> {
+        match &mut self.read_state {
+            ReadState::Length { filled, .. } => {
+                check_progress(n, *filled, PACKET_LENGTH_SIZE)?;
+                *filled += n;
+            }
+            ReadState::Body { len, filled } => {
+                check_progress(n, *filled, *len)?;
+                *filled += n;
+            }
+            ReadState::Desynced => {}
+        }
+        Ok(())
     }
 }
 
-impl Default for PacketCodec {
+/// Turn a zero-byte read into an end-of-stream error
+fn check_progress(n: usize, filled: usize, want: usize) -> Result<()> {
+    if n > 0 {
+        return Ok(());
+    }
+    let message = if filled == 0 && want == PACKET_LENGTH_SIZE {
+        "connection closed".to_string()
+    } else {
+        format!(
+            "connection closed mid-packet ({} of {} bytes)",
+            filled, want
+        )
+    };
+    Err(HdcError::Io(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        message,
+    )))
+}
+
+impl Default for HdcChannelCodec {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl Decoder for HdcChannelCodec {
+    type Item = BytesMut;
+    type Error = HdcError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        if self.read_state == ReadState::Desynced {
+            return Err(desync_error());
+        }
+        if src.len() < PACKET_LENGTH_SIZE {
+            return Ok(None);
+        }
+
+        let mut len_buf = [0u8; PACKET_LENGTH_SIZE];
+        len_buf.copy_from_slice(&src[..PACKET_LENGTH_SIZE]);
+        let packet_len = u32::from_be_bytes(len_buf) as usize;
+        if let Err(e) = check_length(packet_len) {
+            self.read_state = ReadState::Desynced;
+            return Err(e);
+        }
+
+        if src.len() < PACKET_LENGTH_SIZE + packet_len {
+            src.reserve(PACKET_LENGTH_SIZE + packet_len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(PACKET_LENGTH_SIZE);
+        trace!("Decoded framed packet: size={}", packet_len);
+        let packet = src.split_to(packet_len);
+        self.notify(PacketDirection::Received, &packet);
+        Ok(Some(packet))
+    }
+}
+
+impl Encoder<&[u8]> for HdcChannelCodec {
+    type Error = HdcError;
+
+    fn encode(&mut self, item: &[u8], dst: &mut BytesMut) -> Result<()> {
+        encode_into(item, self.max_packet_size, dst)?;
+        self.notify(PacketDirection::Sent, item);
+        Ok(())
+    }
+}
+
+impl Encoder<Bytes> for HdcChannelCodec {
+    type Error = HdcError;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<()> {
+        Encoder::<&[u8]>::encode(self, &item, dst)
+    }
+}
+
+/// Append a length-prefixed packet to `dst`
+fn encode_into<B: BufMut>(data: &[u8], max_size: usize, dst: &mut B) -> Result<()> {
+    if data.len() > max_size {
+        return Err(HdcError::BufferError(format!(
+            "Data size {} exceeds maximum packet size {}",
+            data.len(),
+            max_size
+        )));
+    }
+
+    // Write length as big-endian
+    dst.put_u32(data.len() as u32);
+    dst.put_slice(data);
+
+    trace!("Encoded packet: data_len={}", data.len());
+    Ok(())
+}
+
+/// Reject packet lengths above the protocol maximum
+///
+/// Such a length means the prefix was not read at a packet boundary.
+fn check_length(packet_len: usize) -> Result<()> {
+    if packet_len > MAX_PACKET_SIZE {
+        return Err(HdcError::Desync(format!(
+            "packet length {} exceeds maximum {}",
+            packet_len, MAX_PACKET_SIZE
+        )));
+    }
+    Ok(())
+}
+
+fn desync_error() -> HdcError {
+    HdcError::Desync("stream position lost after an invalid packet".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,4 +429,211 @@ mod tests {
         let len = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
         assert_eq!(len, 0);
     }
+
+    #[tokio::test]
+    async fn test_read_packet_reuses_buffer() {
+        let codec = HdcChannelCodec::new();
+        let mut wire = Vec::new();
+        for data in [&b"one"[..], b"", b"three"] {
+            wire.extend(codec.encode(data).unwrap());
+        }
+
+        let mut codec = HdcChannelCodec::new();
+        let mut reader = &wire[..];
+        assert_eq!(codec.read_packet(&mut reader).await.unwrap(), &b"one"[..]);
+        assert!(codec.read_packet(&mut reader).await.unwrap().is_empty());
+        assert_eq!(codec.read_packet(&mut reader).await.unwrap(), &b"three"[..]);
+        assert!(codec.read_packet(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn test_decoder_partial_frames() {
+        let mut codec = HdcChannelCodec::new();
+        let mut buf = BytesMut::new();
+        Encoder::<&[u8]>::encode(&mut codec, b"first", &mut buf).unwrap();
+        Encoder::<Bytes>::encode(&mut codec, Bytes::from_static(b""), &mut buf).unwrap();
+        Encoder::<&[u8]>::encode(&mut codec, b"second", &mut buf).unwrap();
+
+        // Feed the bytes in small pieces
+        let mut src = BytesMut::new();
+        let mut frames = Vec::new();
+        for chunk in buf.chunks(3) {
+            src.extend_from_slice(chunk);
+            while let Some(frame) = Decoder::decode(&mut codec, &mut src).unwrap() {
+                frames.push(frame.to_vec());
+            }
+        }
+        assert_eq!(
+            frames,
+            vec![b"first".to_vec(), Vec::new(), b"second".to_vec()]
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_encode_respects_negotiated_size() {
+        let mut codec = HdcChannelCodec::new();
+        let data = vec![0u8; STABLE_PACKET_SIZE + 1];
+        assert!(codec.encode(&data).is_ok());
+
+        codec.set_max_packet_size(STABLE_PACKET_SIZE);
+        assert!(codec.encode(&data).is_err());
+        assert!(codec.encode(&data[..STABLE_PACKET_SIZE]).is_ok());
+    }
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<(PacketDirection, usize, Vec<u8>)>>);
+
+    impl PacketObserver for Recorder {
+        fn on_packet_sent(&self, event: &PacketEvent<'_>) {
+            let record = (event.direction, event.length, event.head.to_vec());
+            self.0.lock().unwrap().push(record);
+        }
+
+        fn on_packet_received(&self, event: &PacketEvent<'_>) {
+            let record = (event.direction, event.length, event.head.to_vec());
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer() {
+        let recorder = Arc::new(Recorder::default());
+        let mut codec = HdcChannelCodec::new();
+        codec.set_observer(Some(recorder.clone()));
+
+        let long = [7u8; 40];
+        let mut wire = Vec::new();
+        codec.write_packet(&mut wire, b"shell ls").await.unwrap();
+        codec.write_packet(&mut wire, &long).await.unwrap();
+        let mut reader = &wire[..];
+        codec.read_packet(&mut reader).await.unwrap();
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events[0], (PacketDirection::Sent, 8, b"shell ls".to_vec()));
+        assert_eq!(events[1], (PacketDirection::Sent, 40, vec![7u8; 16]));
+        assert_eq!(
+            events[2],
+            (PacketDirection::Received, 8, b"shell ls".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_read_resumes() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let mut codec = HdcChannelCodec::new();
+        let wire = codec.encode(b"hello world").unwrap();
+
+        // Length prefix and part of the body, then a timeout
+        server.write_all(&wire[..6]).await.unwrap();
+        let interrupted = tokio::time::timeout(
+            std::time::Duration::from_millis(20),
+            codec.read_packet(&mut client),
+        )
+        .await;
+        assert!(interrupted.is_err());
+        assert!(codec.has_partial_packet());
+
+        server.write_all(&wire[6..]).await.unwrap();
+        assert_eq!(
+            codec.read_packet(&mut client).await.unwrap(),
+            &b"hello world"[..]
+        );
+        assert!(!codec.has_partial_packet());
+    }
+
+    #[tokio::test]
+    async fn test_desync_is_sticky() {
+        let mut wire = Vec::new();
+        wire.put_u32(MAX_PACKET_SIZE as u32 + 1);
+        wire.extend(HdcChannelCodec::new().encode(b"valid").unwrap());
+
+        let mut codec = HdcChannelCodec::new();
+        let mut reader = &wire[..];
+        assert!(matches!(
+            codec.read_packet(&mut reader).await,
+            Err(HdcError::Desync(_))
+        ));
+        assert!(codec.is_desynced());
+        assert!(matches!(
+            codec.read_packet(&mut reader).await,
+            Err(HdcError::Desync(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_eof_mid_packet() {
+        let wire = HdcChannelCodec::new().encode(b"truncated").unwrap();
+        let mut reader = &wire[..7];
+        let err = HdcChannelCodec::new()
+            .read_packet(&mut reader)
+            .await
+            .unwrap_err();
+        match err {
+            HdcError::Io(e) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+                assert!(e.to_string().contains("3 of 9"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decoder_rejects_oversized() {
+        let mut codec = HdcChannelCodec::new();
+        let mut src = BytesMut::new();
+        src.put_u32(MAX_PACKET_SIZE as u32 + 1);
+        assert!(Decoder::decode(&mut codec, &mut src).is_err());
+        assert!(codec.is_desynced());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_decode_any_stream(data in proptest::collection::vec(0u8.., 0..2048), chunk in 1usize..64) {
+            let mut codec = HdcChannelCodec::new();
+            let mut src = BytesMut::new();
+            let mut consumed = 0;
+            for read in data.chunks(chunk) {
+                src.extend_from_slice(read);
+                loop {
+                    match codec.decode(&mut src) {
+                        Ok(Some(packet)) => {
+                            let start = consumed + PACKET_LENGTH_SIZE;
+                            proptest::prop_assert_eq!(&packet[..], &data[start..start + packet.len()]);
+                            consumed = start + packet.len();
+                        }
+                        Ok(None) => break,
+                        Err(_) => {
+                            proptest::prop_assert!(codec.decode(&mut src).is_err());
+                            return Ok(());
+                        }
+                    }
+                }
+                proptest::prop_assert!(src.capacity() <= 2 * (PACKET_LENGTH_SIZE + MAX_PACKET_SIZE) + chunk);
+            }
+        }
+
+        #[test]
+        fn test_decode_split_packets(
+            packets in proptest::collection::vec(proptest::collection::vec(0u8.., 0..300), 0..8),
+            chunk in 1usize..64,
+        ) {
+            let mut wire = BytesMut::new();
+            for packet in &packets {
+                encode_into(packet, MAX_PACKET_SIZE, &mut wire).unwrap();
+            }
+
+            let mut codec = HdcChannelCodec::new();
+            let mut src = BytesMut::new();
+            let mut decoded = Vec::new();
+            for read in wire.chunks(chunk) {
+                src.extend_from_slice(read);
+                while let Some(packet) = codec.decode(&mut src).unwrap() {
+                    decoded.push(packet.to_vec());
+                }
+            }
+            proptest::prop_assert_eq!(decoded, packets);
+            proptest::prop_assert!(src.is_empty());
+        }
+    }
 }
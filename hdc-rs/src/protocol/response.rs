@@ -0,0 +1,105 @@
+//! Typed server responses
+
+use std::borrow::Cow;
+
+use bytes::Bytes;
+
+use super::HdcCommand;
+use crate::error::Result;
+
+/// A packet received from the server, split into command code and payload
+///
+/// Control and data messages start with a little-endian `u16` command code
+/// (e.g. [`HdcCommand::ShellData`], [`HdcCommand::FileFinish`]); plain
+/// command output has no prefix. Dispatch on [`Response::command`] instead
+/// of inspecting raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    command: Option<HdcCommand>,
+    payload: Bytes,
+}
+
+impl Response {
+    /// Decode a raw packet
+    pub fn parse(data: Bytes) -> Self {
+        if data.len() >= 2 {
+            if let Some(command) = HdcCommand::from_u16(u16::from_le_bytes([data[0], data[1]])) {
+                return Self {
+                    command: Some(command),
+                    payload: data.slice(2..),
+                };
+            }
+        }
+        Self {
+            command: None,
+            payload: data,
+        }
+    }
+
+    /// Command code, or `None` for plain output
+    pub fn command(&self) -> Option<HdcCommand> {
+        self.command
+    }
+
+    /// Check whether this response carries the given command code
+    pub fn is(&self, command: HdcCommand) -> bool {
+        self.command == Some(command)
+    }
+
+    /// Payload after the command code
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Take the payload
+    pub fn into_payload(self) -> Bytes {
+        self.payload
+    }
+
+    /// Check whether both the command code and the payload are absent
+    pub fn is_empty(&self) -> bool {
+        self.command.is_none() && self.payload.is_empty()
+    }
+
+    /// Payload as text, replacing invalid UTF-8
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.payload)
+    }
+
+    /// Payload as a UTF-8 string
+    pub fn into_string(self) -> Result<String> {
+        Ok(String::from_utf8(self.payload.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_prefix(command: HdcCommand, payload: &[u8]) -> Bytes {
+        let mut data = command.as_u16().to_le_bytes().to_vec();
+        data.extend_from_slice(payload);
+        Bytes::from(data)
+    }
+
+    #[test]
+    fn test_parse_prefixed() {
+        let resp = Response::parse(with_prefix(HdcCommand::FileFinish, b"done"));
+        assert!(resp.is(HdcCommand::FileFinish));
+        assert_eq!(resp.payload(), &b"done"[..]);
+        assert_eq!(resp.into_string().unwrap(), "done");
+
+        let resp = Response::parse(with_prefix(HdcCommand::HeartbeatMsg, b""));
+        assert_eq!(resp.command(), Some(HdcCommand::HeartbeatMsg));
+        assert!(!resp.is_empty());
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        let resp = Response::parse(Bytes::from_static(b"[Info]Done"));
+        assert_eq!(resp.command(), None);
+        assert_eq!(resp.text(), "[Info]Done");
+
+        assert!(Response::parse(Bytes::new()).is_empty());
+    }
+}
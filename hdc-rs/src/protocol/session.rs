@@ -0,0 +1,476 @@
+//! Session protocol spoken between hdc hosts and the device daemon
+//!
+//! The hdc server normally speaks this protocol to `hdcd` on our behalf. A
+//! session packet is a fixed header followed by a serialized
+//! [`PayloadProtect`] and the payload itself:
+//! ```text
+//! +---------------------------------------------+
+//! | 2 bytes: "HW"                               |
+//! | 2 bytes: reserved                           |
+//! | 1 byte:  protocol version (0x01)            |
+//! | 2 bytes: protect size (big-endian u16)      |
+//! | 4 bytes: payload size (big-endian u32)      |
+//! +---------------------------------------------+
+//! | protect: channel id, command, checksum, ... |
+//! +---------------------------------------------+
+//! | payload                                     |
+//! +---------------------------------------------+
+//! ```
+//! Structured fields use the protobuf wire format, as the reference
+//! implementation's `serial_struct` does.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, trace};
+
+use super::{HdcCommand, HANDSHAKE_BANNER, MAX_PACKET_SIZE};
+use crate::error::{HdcError, Result};
+
+/// Magic at the start of every session packet
+const PACKET_FLAG: &[u8; 2] = b"HW";
+
+/// Session protocol version
+const PROTOCOL_VERSION: u8 = 0x01;
+
+/// Size of the fixed packet header
+pub const PAYLOAD_HEAD_SIZE: usize = 11;
+
+/// Verification code carried in every [`PayloadProtect`]
+const PAYLOAD_VCODE: u8 = 0x09;
+
+/// Largest serialized [`PayloadProtect`] accepted
+const MAX_PROTECT_SIZE: usize = 64;
+
+/// Per-packet routing information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadProtect {
+    /// Channel the packet belongs to (0 for session-level packets)
+    pub channel_id: u32,
+    /// Raw command code, see [`HdcCommand`]
+    pub command_flag: u32,
+    /// Optional payload checksum (0 when disabled)
+    pub check_sum: u8,
+    /// Verification code, always `0x09`
+    pub v_code: u8,
+}
+
+impl PayloadProtect {
+    /// Routing information for `command` on `channel_id`
+    pub fn new(channel_id: u32, command: HdcCommand) -> Self {
+        Self {
+            channel_id,
+            command_flag: command.as_u16() as u32,
+            check_sum: 0,
+            v_code: PAYLOAD_VCODE,
+        }
+    }
+
+    fn encode(&self, dst: &mut BytesMut) {
+        wire::put_varint_field(dst, 1, self.channel_id as u64);
+        wire::put_varint_field(dst, 2, self.command_flag as u64);
+        wire::put_varint_field(dst, 3, self.check_sum as u64);
+        wire::put_varint_field(dst, 4, self.v_code as u64);
+    }
+
+    fn decode(mut src: &[u8]) -> Result<Self> {
+        let mut protect = Self {
+            channel_id: 0,
+            command_flag: 0,
+            check_sum: 0,
+            v_code: 0,
+        };
+        while let Some((tag, value)) = wire::next_field(&mut src)? {
+            match (tag, value) {
+                (1, wire::Value::Varint(v)) => protect.channel_id = v as u32,
+                (2, wire::Value::Varint(v)) => protect.command_flag = v as u32,
+                (3, wire::Value::Varint(v)) => protect.check_sum = v as u8,
+                (4, wire::Value::Varint(v)) => protect.v_code = v as u8,
+                _ => {}
+            }
+        }
+        if protect.v_code != PAYLOAD_VCODE {
+            return Err(HdcError::Protocol(format!(
+                "Invalid session packet verification code {:#04x}",
+                protect.v_code
+            )));
+        }
+        Ok(protect)
+    }
+}
+
+/// A decoded session packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionPacket {
+    /// Routing information
+    pub protect: PayloadProtect,
+    /// Payload
+    pub data: Bytes,
+}
+
+impl SessionPacket {
+    /// Channel the packet belongs to
+    pub fn channel_id(&self) -> u32 {
+        self.protect.channel_id
+    }
+
+    /// Command code, or `None` for codes this crate doesn't know
+    pub fn command(&self) -> Option<HdcCommand> {
+        u16::try_from(self.protect.command_flag)
+            .ok()
+            .and_then(HdcCommand::from_u16)
+    }
+}
+
+/// Codec for session packets
+#[derive(Debug, Default)]
+pub struct SessionCodec;
+
+impl SessionCodec {
+    /// Create a new session codec
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Encode a packet for `command` on `channel_id`
+    pub fn encode(&self, channel_id: u32, command: HdcCommand, data: &[u8]) -> Result<BytesMut> {
+        if data.len() > MAX_PACKET_SIZE {
+            return Err(HdcError::BufferError(format!(
+                "Data size {} exceeds maximum packet size {}",
+                data.len(),
+                MAX_PACKET_SIZE
+            )));
+        }
+
+        let mut protect = BytesMut::new();
+        PayloadProtect::new(channel_id, command).encode(&mut protect);
+
+        let mut buf = BytesMut::with_capacity(PAYLOAD_HEAD_SIZE + protect.len() + data.len());
+        buf.put_slice(PACKET_FLAG);
+        buf.put_bytes(0, 2);
+        buf.put_u8(PROTOCOL_VERSION);
+        buf.put_u16(protect.len() as u16);
+        buf.put_u32(data.len() as u32);
+        buf.put_slice(&protect);
+        buf.put_slice(data);
+        trace!(
+            "Encoded session packet: channel={} command={:?} data_len={}",
+            channel_id,
+            command,
+            data.len()
+        );
+        Ok(buf)
+    }
+
+    /// Write a packet to a stream
+    pub async fn write_packet<S>(
+        &self,
+        stream: &mut S,
+        channel_id: u32,
+        command: HdcCommand,
+        data: &[u8],
+    ) -> Result<()>
+    where
+        S: AsyncWriteExt + Unpin,
+    {
+        let packet = self.encode(channel_id, command, data)?;
+        stream.write_all(&packet).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    /// Read and decode one packet from a stream
+    pub async fn read_packet<S>(&self, stream: &mut S) -> Result<SessionPacket>
+    where
+        S: AsyncReadExt + Unpin,
+    {
+        let mut head = [0u8; PAYLOAD_HEAD_SIZE];
+        stream.read_exact(&mut head).await?;
+        if &head[..2] != PACKET_FLAG {
+            return Err(HdcError::Protocol(format!(
+                "Invalid session packet flag {:02x?}",
+                &head[..2]
+            )));
+        }
+
+        let mut sizes = &head[5..];
+        let protect_len = sizes.get_u16() as usize;
+        let data_len = sizes.get_u32() as usize;
+        if protect_len > MAX_PROTECT_SIZE || data_len > MAX_PACKET_SIZE {
+            return Err(HdcError::Protocol(format!(
+                "Session packet too large: protect {} bytes, data {} bytes",
+                protect_len, data_len
+            )));
+        }
+
+        let mut protect = [0u8; MAX_PROTECT_SIZE];
+        stream.read_exact(&mut protect[..protect_len]).await?;
+        let protect = PayloadProtect::decode(&protect[..protect_len])?;
+
+        let mut data = BytesMut::zeroed(data_len);
+        stream.read_exact(&mut data).await?;
+        debug!(
+            "Received session packet: channel={} command={} size={}",
+            protect.channel_id, protect.command_flag, data_len
+        );
+        Ok(SessionPacket {
+            protect,
+            data: data.freeze(),
+        })
+    }
+}
+
+/// Authentication step carried in a [`SessionHandShake`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AuthType {
+    /// No authentication (host hello)
+    None = 0,
+    /// Daemon sends a token to sign
+    Token = 1,
+    /// Host sends the token signature
+    Signature = 2,
+    /// Host sends its public key for approval
+    PublicKey = 3,
+    /// Daemon accepted the host
+    Ok = 4,
+    /// Daemon rejected the host
+    Fail = 5,
+}
+
+impl AuthType {
+    /// Convert a raw value (if valid)
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Token),
+            2 => Some(Self::Signature),
+            3 => Some(Self::PublicKey),
+            4 => Some(Self::Ok),
+            5 => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// Session handshake, exchanged in [`HdcCommand::KernelHandshake`] packets
+///
+/// Unlike the server's [`ChannelHandShake`](super::ChannelHandShake) this is
+/// a variable-length structure; `buf` carries the auth token, signature or
+/// public key depending on `auth_type`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionHandShake {
+    /// Always `OHOS HDC`
+    pub banner: String,
+    /// Raw [`AuthType`]
+    pub auth_type: u8,
+    /// Session ID chosen by the host
+    pub session_id: u32,
+    /// Connect key of the target (e.g. `ip:port`)
+    pub connect_key: String,
+    /// Auth payload
+    pub buf: Vec<u8>,
+    /// Version string
+    pub version: String,
+}
+
+impl SessionHandShake {
+    /// Host hello for a new session
+    pub fn new(
+        session_id: u32,
+        connect_key: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            banner: String::from_utf8_lossy(HANDSHAKE_BANNER).into_owned(),
+            auth_type: AuthType::None as u8,
+            session_id,
+            connect_key: connect_key.into(),
+            buf: Vec::new(),
+            version: version.into(),
+        }
+    }
+
+    /// Decoded auth type, if known
+    pub fn auth(&self) -> Option<AuthType> {
+        AuthType::from_u8(self.auth_type)
+    }
+
+    /// Copy of this handshake carrying another auth step
+    pub fn with_auth(&self, auth: AuthType, buf: impl Into<Vec<u8>>) -> Self {
+        Self {
+            auth_type: auth as u8,
+            buf: buf.into(),
+            ..self.clone()
+        }
+    }
+
+    /// Serialize to the wire format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dst = BytesMut::new();
+        wire::put_bytes_field(&mut dst, 1, self.banner.as_bytes());
+        wire::put_varint_field(&mut dst, 2, self.auth_type as u64);
+        wire::put_varint_field(&mut dst, 3, self.session_id as u64);
+        wire::put_bytes_field(&mut dst, 4, self.connect_key.as_bytes());
+        wire::put_bytes_field(&mut dst, 5, &self.buf);
+        wire::put_bytes_field(&mut dst, 6, self.version.as_bytes());
+        dst.to_vec()
+    }
+
+    /// Parse the wire format
+    pub fn from_bytes(mut src: &[u8]) -> Result<Self> {
+        let mut hs = Self::new(0, "", "");
+        hs.banner.clear();
+        while let Some((tag, value)) = wire::next_field(&mut src)? {
+            match (tag, value) {
+                (1, wire::Value::Bytes(b)) => hs.banner = String::from_utf8_lossy(b).into_owned(),
+                (2, wire::Value::Varint(v)) => hs.auth_type = v as u8,
+                (3, wire::Value::Varint(v)) => hs.session_id = v as u32,
+                (4, wire::Value::Bytes(b)) => {
+                    hs.connect_key = String::from_utf8_lossy(b).into_owned()
+                }
+                (5, wire::Value::Bytes(b)) => hs.buf = b.to_vec(),
+                (6, wire::Value::Bytes(b)) => hs.version = String::from_utf8_lossy(b).into_owned(),
+                _ => {}
+            }
+        }
+        if !hs.banner.as_bytes().starts_with(HANDSHAKE_BANNER) {
+            return Err(HdcError::InvalidBanner(hs.banner.into_bytes()));
+        }
+        Ok(hs)
+    }
+}
+
+/// Minimal protobuf wire format support
+mod wire {
+    use bytes::BufMut;
+
+    use crate::error::{HdcError, Result};
+
+    const WIRE_VARINT: u8 = 0;
+    const WIRE_FIXED64: u8 = 1;
+    const WIRE_BYTES: u8 = 2;
+    const WIRE_FIXED32: u8 = 5;
+
+    pub(super) enum Value<'a> {
+        Varint(u64),
+        Bytes(&'a [u8]),
+        Fixed,
+    }
+
+    fn put_varint<B: BufMut>(dst: &mut B, mut value: u64) {
+        while value >= 0x80 {
+            dst.put_u8(value as u8 | 0x80);
+            value >>= 7;
+        }
+        dst.put_u8(value as u8);
+    }
+
+    pub(super) fn put_varint_field<B: BufMut>(dst: &mut B, tag: u32, value: u64) {
+        put_varint(dst, ((tag as u64) << 3) | WIRE_VARINT as u64);
+        put_varint(dst, value);
+    }
+
+    pub(super) fn put_bytes_field<B: BufMut>(dst: &mut B, tag: u32, value: &[u8]) {
+        put_varint(dst, ((tag as u64) << 3) | WIRE_BYTES as u64);
+        put_varint(dst, value.len() as u64);
+        dst.put_slice(value);
+    }
+
+    fn get_varint(src: &mut &[u8]) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = src
+                .split_first()
+                .ok_or_else(|| HdcError::Protocol("Truncated varint".to_string()))?;
+            *src = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(HdcError::Protocol("Varint too long".to_string()))
+    }
+
+    fn take<'a>(src: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+        if src.len() < len {
+            return Err(HdcError::Protocol("Truncated field".to_string()));
+        }
+        let (value, rest) = src.split_at(len);
+        *src = rest;
+        Ok(value)
+    }
+
+    /// Read the next `(tag, value)` pair, or `None` at the end
+    pub(super) fn next_field<'a>(src: &mut &'a [u8]) -> Result<Option<(u32, Value<'a>)>> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        let key = get_varint(src)?;
+        let tag = (key >> 3) as u32;
+        let value = match (key & 0x7) as u8 {
+            WIRE_VARINT => Value::Varint(get_varint(src)?),
+            WIRE_BYTES => {
+                let len = get_varint(src)? as usize;
+                Value::Bytes(take(src, len)?)
+            }
+            WIRE_FIXED64 => {
+                take(src, 8)?;
+                Value::Fixed
+            }
+            WIRE_FIXED32 => {
+                take(src, 4)?;
+                Value::Fixed
+            }
+            other => {
+                return Err(HdcError::Protocol(format!(
+                    "Unsupported wire type {}",
+                    other
+                )))
+            }
+        };
+        Ok(Some((tag, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_packet_roundtrip() {
+        let codec = SessionCodec::new();
+        let wire = codec
+            .encode(300, HdcCommand::UnityExecute, b"ls /data")
+            .unwrap();
+        assert_eq!(&wire[..2], b"HW");
+        assert_eq!(wire[4], PROTOCOL_VERSION);
+
+        let packet = codec.read_packet(&mut &wire[..]).await.unwrap();
+        assert_eq!(packet.channel_id(), 300);
+        assert_eq!(packet.command(), Some(HdcCommand::UnityExecute));
+        assert_eq!(packet.data, &b"ls /data"[..]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_flag() {
+        let codec = SessionCodec::new();
+        let mut wire = codec.encode(1, HdcCommand::KernelEcho, b"").unwrap();
+        wire[0] = b'X';
+        assert!(codec.read_packet(&mut &wire[..]).await.is_err());
+    }
+
+    #[test]
+    fn test_handshake_roundtrip() {
+        let hs = SessionHandShake::new(0xdead_beef, "192.168.1.2:5555", "Ver: 3.1.0e")
+            .with_auth(AuthType::Signature, b"sig".to_vec());
+        let parsed = SessionHandShake::from_bytes(&hs.to_bytes()).unwrap();
+        assert_eq!(parsed, hs);
+        assert_eq!(parsed.auth(), Some(AuthType::Signature));
+
+        let bad = SessionHandShake {
+            banner: "NOPE".to_string(),
+            ..hs
+        };
+        assert!(SessionHandShake::from_bytes(&bad.to_bytes()).is_err());
+    }
+}
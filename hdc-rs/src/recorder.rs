@@ -0,0 +1,497 @@
+//! Log capture to local files
+//!
+//! [`LogRecorder`] streams hilog output from a device into a local file,
+//! rotating it by size and/or age and optionally gzip-compressing rotated
+//! files (requires the `gzip` feature). Dropped streams can be reconnected
+//! and resumed from the last recorded timestamp.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::recorder::LogRecorder;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let handle = LogRecorder::new("logs/device.log")
+//!     .max_file_size(10 * 1024 * 1024)
+//!     .max_file_age(Duration::from_secs(3600))
+//!     .max_files(5)
+//!     .reconnect_delay(Duration::from_secs(2))
+//!     .start("127.0.0.1:8710", "FMR0223C13000649");
+//!
+//! // ... run tests ...
+//!
+//! let stats = handle.stop().await?;
+//! println!("Captured {} lines", stats.lines_written);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::error::{HdcError, Result};
+use crate::hilog::{HilogFilter, LineBuffer, LogCursor, LogTimestamp};
+use crate::transport::ServerAddr;
+
+/// Statistics about a finished recording
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecorderStats {
+    /// Total bytes written across all files
+    pub bytes_written: u64,
+    /// Total lines written across all files
+    pub lines_written: u64,
+    /// Number of times the output file was rotated
+    pub rotations: u64,
+}
+
+/// Records device logs to a file with rotation
+#[derive(Debug, Clone)]
+pub struct LogRecorder {
+    path: PathBuf,
+    filter: HilogFilter,
+    max_file_size: Option<u64>,
+    max_file_age: Option<Duration>,
+    max_files: Option<usize>,
+    reconnect_delay: Option<Duration>,
+    resume_from: Option<LogTimestamp>,
+    #[cfg(feature = "gzip")]
+    compress: bool,
+}
+
+impl LogRecorder {
+    /// Create a recorder writing to `path`
+    ///
+    /// Rotated files are stored next to it as `<path>.1`, `<path>.2`, ...
+    /// with higher numbers being newer.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            filter: HilogFilter::new(),
+            max_file_size: None,
+            max_file_age: None,
+            max_files: None,
+            reconnect_delay: None,
+            resume_from: None,
+            #[cfg(feature = "gzip")]
+            compress: false,
+        }
+    }
+
+    /// Set the hilog filter used for the capture
+    pub fn filter(mut self, filter: impl Into<HilogFilter>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Rotate when the current file reaches this many bytes
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Rotate when the current file has been open this long
+    pub fn max_file_age(mut self, age: Duration) -> Self {
+        self.max_file_age = Some(age);
+        self
+    }
+
+    /// Keep at most this many rotated files, deleting the oldest
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.max_files = Some(count);
+        self
+    }
+
+    /// Reconnect after this delay when the log stream drops
+    ///
+    /// On reconnect, lines already recorded are skipped by timestamp (see
+    /// [`LogCursor`]), so the file has no gaps or duplicates from the replayed
+    /// hilog buffer. Without this, a dropped stream ends the recording.
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = Some(delay);
+        self
+    }
+
+    /// Skip the replayed entries before `timestamp`, recording from the
+    /// first one at or after it
+    ///
+    /// Useful to continue an earlier recording without repeating its lines.
+    pub fn resume_from(mut self, timestamp: LogTimestamp) -> Self {
+        self.resume_from = Some(timestamp);
+        self
+    }
+
+    /// Gzip-compress rotated files (`<path>.N.gz`)
+    #[cfg(feature = "gzip")]
+    pub fn compress(mut self, enable: bool) -> Self {
+        self.compress = enable;
+        self
+    }
+
+    /// Start recording logs from a device in a background task
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(
+        self,
+        address: impl Into<ServerAddr>,
+        device_id: impl Into<String>,
+    ) -> RecorderHandle {
+        let address = address.into();
+        let device_id = device_id.into();
+        let (stop_tx, stop_rx) = oneshot::channel();
+
+        let task = tokio::spawn(async move { self.run(address, device_id, stop_rx).await });
+
+        RecorderHandle {
+            stop_tx: Some(stop_tx),
+            task,
+        }
+    }
+
+    async fn run(
+        self,
+        address: ServerAddr,
+        device_id: String,
+        stop_rx: oneshot::Receiver<()>,
+    ) -> Result<RecorderStats> {
+        info!("Recording logs from {} to {:?}", device_id, self.path);
+
+        let mut writer = RotatingWriter::open(&self)?;
+        let mut cursor = self
+            .resume_from
+            .map_or_else(LogCursor::new, LogCursor::since);
+        let mut stop_rx = stop_rx;
+
+        let result = loop {
+            cursor.resume();
+            let mut lines = LineBuffer::default();
+            let mut write_error: Option<io::Error> = None;
+
+            let (result, stopped) = tokio::select! {
+                result = Self::stream_once(
+                    &address,
+                    &device_id,
+                    self.filter.clone(),
+                    &mut lines,
+                    &mut cursor,
+                    &mut writer,
+                    &mut write_error,
+                ) => (result, false),
+                _ = &mut stop_rx => {
+                    debug!("Log recorder stopped");
+                    (Ok(()), true)
+                }
+            };
+
+            if let Some(line) = lines.finish() {
+                if cursor.accept(&line) {
+                    writer.write_line(&line)?;
+                }
+            }
+            if let Some(e) = write_error {
+                writer.finish()?;
+                return Err(HdcError::Io(e));
+            }
+
+            let Some(delay) = self.reconnect_delay.filter(|_| !stopped) else {
+                break result;
+            };
+            match &result {
+                Ok(()) => info!("Log stream from {} ended, reconnecting", device_id),
+                Err(e) => warn!("Log stream from {} failed: {}, reconnecting", device_id, e),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut stop_rx => break Ok(()),
+            }
+        };
+
+        let stats = writer.finish()?;
+        result?;
+
+        info!(
+            "Log recording finished: {} lines, {} bytes",
+            stats.lines_written, stats.bytes_written
+        );
+        Ok(stats)
+    }
+
+    /// Open one hilog stream and write new lines until it ends
+    async fn stream_once(
+        address: &ServerAddr,
+        device_id: &str,
+        filter: HilogFilter,
+        lines: &mut LineBuffer,
+        cursor: &mut LogCursor,
+        writer: &mut RotatingWriter,
+        write_error: &mut Option<io::Error>,
+    ) -> Result<()> {
+        let mut client = crate::HdcClient::connect(address).await?;
+        client.connect_device(device_id).await?;
+
+        client
+            .hilog_stream_filtered(filter, |chunk| {
+                for line in lines.push(chunk) {
+                    if !cursor.accept(&line) {
+                        continue;
+                    }
+                    if let Err(e) = writer.write_line(&line) {
+                        *write_error = Some(e);
+                        return false;
+                    }
+                }
+                true
+            })
+            .await
+    }
+}
+
+/// Handle to a running [`LogRecorder`]
+pub struct RecorderHandle {
+    stop_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<Result<RecorderStats>>,
+}
+
+impl RecorderHandle {
+    /// Check whether the recording has ended on its own
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop recording, flush the file, and return statistics
+    pub async fn stop(mut self) -> Result<RecorderStats> {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+        self.wait().await
+    }
+
+    /// Wait for the recording to end without stopping it
+    pub async fn wait(self) -> Result<RecorderStats> {
+        self.task
+            .await
+            .map_err(|e| HdcError::Io(io::Error::new(io::ErrorKind::Other, e)))?
+    }
+}
+
+/// File writer that rotates by size and age
+pub(crate) struct RotatingWriter {
+    path: PathBuf,
+    file: File,
+    opened_at: Instant,
+    current_size: u64,
+    next_index: u64,
+    max_file_size: Option<u64>,
+    max_file_age: Option<Duration>,
+    max_files: Option<usize>,
+    compress: bool,
+    stats: RecorderStats,
+}
+
+impl RotatingWriter {
+    pub(crate) fn open(config: &LogRecorder) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let current_size = file.metadata()?.len();
+        let next_index = rotated_indices(&config.path)?
+            .last()
+            .map_or(1, |(index, _)| index + 1);
+
+        #[cfg(feature = "gzip")]
+        let compress = config.compress;
+        #[cfg(not(feature = "gzip"))]
+        let compress = false;
+
+        Ok(Self {
+            path: config.path.clone(),
+            file,
+            opened_at: Instant::now(),
+            current_size,
+            next_index,
+            max_file_size: config.max_file_size,
+            max_file_age: config.max_file_age,
+            max_files: config.max_files,
+            compress,
+            stats: RecorderStats::default(),
+        })
+    }
+
+    /// Write one line, rotating first if the current file is full or too old
+    pub(crate) fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let too_big = self
+            .max_file_size
+            .is_some_and(|max| self.current_size > 0 && self.current_size + len > max);
+        let too_old = self
+            .max_file_age
+            .is_some_and(|max| self.current_size > 0 && self.opened_at.elapsed() >= max);
+        if too_big || too_old {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.current_size += len;
+        self.stats.bytes_written += len;
+        self.stats.lines_written += 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let rotated = indexed_path(&self.path, self.next_index);
+        fs::rename(&self.path, &rotated)?;
+        debug!("Rotated log file to {:?}", rotated);
+        if self.compress {
+            gzip_file(&rotated)?;
+        }
+        self.next_index += 1;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&self.path)?;
+        self.opened_at = Instant::now();
+        self.current_size = 0;
+        self.stats.rotations += 1;
+
+        if let Some(max_files) = self.max_files {
+            let rotated = rotated_indices(&self.path)?;
+            let excess = rotated.len().saturating_sub(max_files);
+            for (_, path) in rotated.into_iter().take(excess) {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("Failed to remove old log file {:?}: {}", path, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<RecorderStats> {
+        self.file.flush()?;
+        Ok(std::mem::take(&mut self.stats))
+    }
+}
+
+fn indexed_path(path: &Path, index: u64) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// List rotated files for `path` (plain or `.gz`), sorted oldest first
+fn rotated_indices(path: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let prefix = format!("{}.", file_name);
+    let mut rotated = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let Some(suffix) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let index = suffix.strip_suffix(".gz").unwrap_or(suffix);
+        if let Ok(index) = index.parse::<u64>() {
+            rotated.push((index, entry.path()));
+        }
+    }
+    rotated.sort();
+    Ok(rotated)
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_file(path: &Path) -> io::Result<()> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+
+    let mut input = File::open(path)?;
+    let output = File::create(PathBuf::from(gz_name))?;
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_file(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hdc-rs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let dir = temp_dir("recorder-size");
+        let path = dir.join("device.log");
+        let config = LogRecorder::new(&path).max_file_size(20).max_files(2);
+
+        let mut writer = RotatingWriter::open(&config).unwrap();
+        for i in 0..5 {
+            writer.write_line(&format!("line number {}", i)).unwrap();
+        }
+        let stats = writer.finish().unwrap();
+
+        assert_eq!(stats.lines_written, 5);
+        assert_eq!(stats.rotations, 4);
+        let rotated = rotated_indices(&path).unwrap();
+        let indices: Vec<u64> = rotated.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![3, 4]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line number 4\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_rotate_with_gzip() {
+        let dir = temp_dir("recorder-gzip");
+        let path = dir.join("device.log");
+        let config = LogRecorder::new(&path).max_file_size(10).compress(true);
+
+        let mut writer = RotatingWriter::open(&config).unwrap();
+        writer.write_line("first line").unwrap();
+        writer.write_line("second line").unwrap();
+        writer.finish().unwrap();
+
+        assert!(dir.join("device.log.1.gz").exists());
+        assert!(!dir.join("device.log.1").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,243 @@
+//! Retrying transient failures
+//!
+//! Over flaky USB links the server reports timeouts and dropped connections
+//! that succeed on a second try. A [`RetryPolicy`] says how often to try,
+//! how long to wait in between and which errors are worth another attempt.
+//! Set one for every command with
+//! [`HdcClient::set_retry_policy`](crate::HdcClient::set_retry_policy), for
+//! one call with [`HdcClient::with_retry`](crate::HdcClient::with_retry), or
+//! wrap any operation with [`RetryPolicy::run`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::{HdcClient, RetryPolicy};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = HdcClient::connect("127.0.0.1:8710").await?;
+//! client.set_retry_policy(Some(
+//!     RetryPolicy::new(5).initial_backoff(Duration::from_millis(500)),
+//! ));
+//! client.connect_device("device-serial").await?;
+//! println!("{}", client.shell("uptime").await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::error::{HdcError, Result};
+
+/// Exponential delay between attempts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// Delay before the second attempt (default 200ms)
+    pub initial: Duration,
+    /// Upper bound for any delay (default 5s)
+    pub max: Duration,
+    /// Factor applied to the delay after each attempt (default 2.0)
+    pub multiplier: f64,
+}
+
+impl Backoff {
+    /// No delay between attempts
+    pub const NONE: Self = Self {
+        initial: Duration::ZERO,
+        max: Duration::ZERO,
+        multiplier: 1.0,
+    };
+
+    /// Delay after failed attempt number `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        if secs.is_finite() && secs < self.max.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(200),
+            max: Duration::from_secs(5),
+            multiplier: 2.0,
+        }
+    }
+}
+
+/// How to retry failed operations
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retrying
+    pub max_attempts: u32,
+    /// Delay between attempts
+    pub backoff: Backoff,
+    /// Which errors are retried (default [`HdcError::is_transient`])
+    pub retry_on: fn(&HdcError) -> bool,
+}
+
+impl RetryPolicy {
+    /// Up to `max_attempts` attempts with the default backoff and error filter
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    /// Never retry
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// One immediate retry after a dropped connection
+    ///
+    /// What [`HdcClient::set_auto_reconnect`](crate::HdcClient::set_auto_reconnect)
+    /// applies when no policy is set, to commands that are safe to repeat.
+    pub fn reconnect_once() -> Self {
+        Self {
+            max_attempts: 2,
+            backoff: Backoff::NONE,
+            retry_on: HdcError::is_connection_lost,
+        }
+    }
+
+    /// Set the delay before the second attempt
+    pub fn initial_backoff(mut self, delay: Duration) -> Self {
+        self.backoff.initial = delay;
+        self
+    }
+
+    /// Set the upper bound for delays
+    pub fn max_backoff(mut self, delay: Duration) -> Self {
+        self.backoff.max = delay;
+        self
+    }
+
+    /// Set the backoff completely
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Retry only errors for which `retry_on` returns true
+    pub fn retry_on(mut self, retry_on: fn(&HdcError) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Whether to try again after `error` ended attempt number `attempt`
+    pub fn should_retry(&self, attempt: u32, error: &HdcError) -> bool {
+        attempt < self.max_attempts && (self.retry_on)(error)
+    }
+
+    /// Run `op` until it succeeds, fails permanently or runs out of attempts
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{RetryPolicy, SharedHdcClient};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = SharedHdcClient::new("127.0.0.1:8710");
+    /// let output = RetryPolicy::default()
+    ///     .run(|| client.shell("device-serial", "uptime"))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(e) if self.should_retry(attempt, &e) => {
+                    let delay = self.backoff.delay(attempt);
+                    info!(
+                        "Attempt {} failed ({}), retrying in {:?}",
+                        attempt, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::default(),
+            retry_on: HdcError::is_transient,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(1), Duration::from_millis(200));
+        assert_eq!(backoff.delay(2), Duration::from_millis(400));
+        assert_eq!(backoff.delay(3), Duration::from_millis(800));
+        assert_eq!(backoff.delay(10), Duration::from_secs(5));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(5));
+        assert_eq!(Backoff::NONE.delay(3), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_errors() {
+        let policy = RetryPolicy::new(3).backoff(Backoff::NONE);
+
+        let mut calls = 0;
+        let result = policy
+            .run(|| {
+                calls += 1;
+                let attempt = calls;
+                async move {
+                    if attempt < 3 {
+                        Err(HdcError::Timeout)
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<()> = policy
+            .run(|| {
+                calls += 1;
+                async { Err(HdcError::DeviceNotFound("x".to_string())) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_sleeps_backoff() {
+        let started = tokio::time::Instant::now();
+        let result: Result<()> = RetryPolicy::new(4)
+            .run(|| async { Err(HdcError::Timeout) })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(started.elapsed(), Duration::from_millis(200 + 400 + 800));
+    }
+}
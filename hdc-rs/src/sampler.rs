@@ -0,0 +1,240 @@
+//! Periodic device metrics sampling
+//!
+//! [`HdcClient::sample_metrics`](crate::HdcClient::sample_metrics) reads the
+//! selected [`MetricKind`] values every interval and yields them as
+//! timestamped [`MetricsSample`] values, e.g. to chart a device during a
+//! test run:
+//!
+//! | Kind | Source |
+//! |------|--------|
+//! | [`MetricKind::Cpu`] | `/proc/stat`, as usage since the previous sample |
+//! | [`MetricKind::Memory`] | `/proc/meminfo` |
+//! | [`MetricKind::Battery`] | `hidumper -s BatteryService` |
+//! | [`MetricKind::BundleRss`] | `ps -eo rss,args`, summed over the bundle's processes |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::sampler::MetricKind;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let kinds = [
+//!     MetricKind::Cpu,
+//!     MetricKind::Memory,
+//!     MetricKind::BundleRss("com.example.app".into()),
+//! ];
+//! let mut samples = client.sample_metrics(Duration::from_secs(1), &kinds).await?;
+//! while let Some(sample) = samples.next().await {
+//!     println!("{:?} cpu={:?} rss={:?}", sample.timestamp, sample.cpu_percent, sample.rss_kb);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use crate::client::HdcClient;
+use crate::error::{HdcError, Result};
+use crate::sysinfo::{BatteryInfo, MemInfo, BATTERY_COMMAND, MEMINFO_COMMAND};
+
+/// Command printing the aggregate CPU line of `/proc/stat`
+pub const CPU_STAT_COMMAND: &str = "head -n 1 /proc/stat";
+/// Command listing the resident set size of every process
+pub const RSS_COMMAND: &str = "ps -eo rss,args";
+
+/// Metric read by the sampler
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MetricKind {
+    /// Total CPU usage
+    Cpu,
+    /// System memory statistics
+    Memory,
+    /// Battery level, charging state and temperature
+    Battery,
+    /// Resident memory of one bundle, including its `<bundle>:<name>`
+    /// extension processes
+    BundleRss(String),
+}
+
+/// Metrics read at one point in time
+///
+/// Fields of kinds that were not requested stay empty, as do those the
+/// device could not report (e.g. no battery, bundle not running).
+#[derive(Debug, Clone)]
+pub struct MetricsSample {
+    /// When the sample was taken
+    pub timestamp: SystemTime,
+    /// CPU usage since the previous sample, in percent of all cores
+    pub cpu_percent: Option<f64>,
+    /// Memory statistics
+    pub memory: Option<MemInfo>,
+    /// Battery state
+    pub battery: Option<BatteryInfo>,
+    /// Resident memory of each running bundle, in kilobytes
+    pub rss_kb: BTreeMap<String, u64>,
+}
+
+/// Cumulative CPU times from the `cpu` line of `/proc/stat`, in ticks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    /// Ticks spent in any state
+    pub total: u64,
+    /// Ticks spent idle or waiting for I/O
+    pub idle: u64,
+}
+
+impl CpuTimes {
+    /// Parse the aggregate `cpu` line of `/proc/stat`
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sampler::CpuTimes;
+    ///
+    /// let times = CpuTimes::parse("cpu  100 0 50 800 50 0 0 0 0 0\n").unwrap();
+    /// assert_eq!((times.total, times.idle), (1000, 850));
+    /// ```
+    pub fn parse(output: &str) -> Option<CpuTimes> {
+        let line = output
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some("cpu"))?;
+        // user nice system idle iowait irq softirq steal; guest time is
+        // already counted in user
+        let ticks: Vec<u64> = line
+            .split_whitespace()
+            .skip(1)
+            .take(8)
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .ok()?;
+        if ticks.len() < 4 {
+            return None;
+        }
+        Some(CpuTimes {
+            total: ticks.iter().sum(),
+            idle: ticks[3] + ticks.get(4).copied().unwrap_or_default(),
+        })
+    }
+
+    /// Busy share of the ticks elapsed since `earlier`, in percent
+    pub fn usage_since(&self, earlier: &CpuTimes) -> Option<f64> {
+        let total = self.total.checked_sub(earlier.total)?;
+        let idle = self.idle.checked_sub(earlier.idle)?;
+        if total == 0 {
+            return None;
+        }
+        Some(total.saturating_sub(idle) as f64 * 100.0 / total as f64)
+    }
+}
+
+/// Resident memory of `bundle` in the output of [`RSS_COMMAND`], in
+/// kilobytes, or `None` if it is not running
+pub fn bundle_rss(output: &str, bundle: &str) -> Option<u64> {
+    let extension = format!("{}:", bundle);
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let rss: u64 = fields.next()?.parse().ok()?;
+            let process = fields.next()?;
+            (process == bundle || process.starts_with(&extension)).then_some(rss)
+        })
+        .reduce(|a, b| a + b)
+}
+
+/// Reads samples of a fixed set of kinds, remembering the CPU times
+/// between them
+pub(crate) struct Sampler {
+    kinds: Vec<MetricKind>,
+    last_cpu: Option<CpuTimes>,
+}
+
+impl Sampler {
+    pub(crate) fn new(kinds: &[MetricKind]) -> Result<Sampler> {
+        if kinds.is_empty() {
+            return Err(HdcError::Config("No metrics to sample".to_string()));
+        }
+        Ok(Sampler {
+            kinds: kinds.to_vec(),
+            last_cpu: None,
+        })
+    }
+
+    /// Read the CPU times the first sample's usage is measured from
+    pub(crate) async fn prime(&mut self, client: &mut HdcClient) -> Result<()> {
+        if self.kinds.contains(&MetricKind::Cpu) {
+            let output = client.shell(CPU_STAT_COMMAND).await?;
+            self.last_cpu = CpuTimes::parse(&output);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn sample(&mut self, client: &mut HdcClient) -> Result<MetricsSample> {
+        let mut sample = MetricsSample {
+            timestamp: SystemTime::now(),
+            cpu_percent: None,
+            memory: None,
+            battery: None,
+            rss_kb: BTreeMap::new(),
+        };
+        let mut rss_output = None;
+        for kind in &self.kinds {
+            match kind {
+                MetricKind::Cpu => {
+                    let times = CpuTimes::parse(&client.shell(CPU_STAT_COMMAND).await?);
+                    sample.cpu_percent = match (&times, &self.last_cpu) {
+                        (Some(now), Some(earlier)) => now.usage_since(earlier),
+                        _ => None,
+                    };
+                    self.last_cpu = times;
+                }
+                MetricKind::Memory => {
+                    let output = client.shell(MEMINFO_COMMAND).await?;
+                    sample.memory = Some(MemInfo::parse(&HdcError::check_response(output)?));
+                }
+                MetricKind::Battery => {
+                    sample.battery = BatteryInfo::parse(&client.shell(BATTERY_COMMAND).await?);
+                }
+                MetricKind::BundleRss(bundle) => {
+                    if rss_output.is_none() {
+                        rss_output = Some(client.shell(RSS_COMMAND).await?);
+                    }
+                    if let Some(rss) = rss_output.as_deref().and_then(|o| bundle_rss(o, bundle)) {
+                        sample.rss_kb.insert(bundle.clone(), rss);
+                    }
+                }
+            }
+        }
+        Ok(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_usage() {
+        let earlier = CpuTimes::parse("cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 1 2 3 4\n").unwrap();
+        let now = CpuTimes::parse("cpu  400 0 150 1300 150 0 0 0 0 0\n").unwrap();
+        assert_eq!(now.usage_since(&earlier), Some(40.0));
+        assert_eq!(now.usage_since(&now), None);
+        assert_eq!(earlier.usage_since(&now), None);
+        assert!(CpuTimes::parse("head: /proc/stat: Permission denied\n").is_none());
+    }
+
+    #[test]
+    fn test_bundle_rss() {
+        let output = "  RSS ARGS
+ 1824 init --second-stage
+182040 com.example.app
+ 40112 com.example.app:widget
+ 9000 com.example.application
+";
+        assert_eq!(bundle_rss(output, "com.example.app"), Some(222152));
+        assert_eq!(bundle_rss(output, "com.other.app"), None);
+    }
+}
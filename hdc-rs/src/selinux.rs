@@ -0,0 +1,273 @@
+//! SELinux status and AVC denials
+//!
+//! Permission failures during file pushes and app installs are often
+//! SELinux denials rather than file mode problems.
+//! [`HdcClient::selinux_mode`](crate::HdcClient::selinux_mode) reads the
+//! enforcement mode and [`HdcClient::avc_denials`](crate::HdcClient::avc_denials)
+//! collects recent `avc: denied` records from the kernel log and hilog.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! println!("SELinux is {}", client.selinux_mode().await?);
+//! for denial in client.avc_denials().await? {
+//!     println!("{}", denial.allow_rule());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::HdcError;
+
+/// Command printing the enforcement mode
+pub const GETENFORCE_COMMAND: &str = "getenforce";
+/// Commands printing the log lines holding AVC records
+pub const AVC_COMMANDS: &[&str] = &["dmesg | grep 'avc:'", "hilog -x | grep 'avc:'"];
+
+/// SELinux enforcement mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelinuxMode {
+    /// Denials are enforced and logged
+    Enforcing,
+    /// Denials are only logged
+    Permissive,
+    /// SELinux is off
+    Disabled,
+}
+
+impl SelinuxMode {
+    /// Name as printed by `getenforce`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Enforcing => "Enforcing",
+            Self::Permissive => "Permissive",
+            Self::Disabled => "Disabled",
+        }
+    }
+}
+
+impl fmt::Display for SelinuxMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SelinuxMode {
+    type Err = HdcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "enforcing" => Ok(Self::Enforcing),
+            "permissive" => Ok(Self::Permissive),
+            "disabled" => Ok(Self::Disabled),
+            _ => Err(HdcError::Protocol(format!(
+                "Unexpected SELinux mode: {}",
+                s.trim()
+            ))),
+        }
+    }
+}
+
+/// One `avc: denied` record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcDenial {
+    /// Denied permissions, e.g. `read`, `write`
+    pub permissions: Vec<String>,
+    /// Process that was denied
+    pub pid: Option<u32>,
+    /// Command name of the process
+    pub comm: Option<String>,
+    /// Name of the target, e.g. a file name
+    pub name: Option<String>,
+    /// Source context, e.g. `u:r:normal_hap:s0`
+    pub scontext: String,
+    /// Target context, e.g. `u:object_r:data_file:s0`
+    pub tcontext: String,
+    /// Target class, e.g. `file`
+    pub tclass: String,
+    /// Whether the access was allowed anyway (permissive domain or mode)
+    pub permissive: bool,
+    /// Audit stamp, e.g. `1697520000.123:456`, if present
+    pub audit: Option<String>,
+    /// Log line the record was parsed from
+    pub line: String,
+}
+
+impl AvcDenial {
+    /// Parse the record in a kernel or hilog line, if it has one
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::selinux::AvcDenial;
+    ///
+    /// let line = r#"avc:  denied  { write } for  pid=4321 comm="app" name="a.txt" scontext=u:r:normal_hap:s0 tcontext=u:object_r:data_file:s0 tclass=file permissive=0"#;
+    /// let denial = AvcDenial::parse(line).unwrap();
+    /// assert_eq!(denial.permissions, ["write"]);
+    /// assert_eq!(denial.allow_rule(), "allow normal_hap data_file:file { write };");
+    /// ```
+    pub fn parse(line: &str) -> Option<AvcDenial> {
+        let start = line.find("avc:")?;
+        let record = &line[start + 4..];
+        let record = record.trim_start().strip_prefix("denied")?;
+        let open = record.find('{')?;
+        let close = record[open..].find('}')? + open;
+        let permissions = record[open + 1..close]
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+
+        let mut pid = None;
+        let mut comm = None;
+        let mut name = None;
+        let mut scontext = None;
+        let mut tcontext = None;
+        let mut tclass = None;
+        let mut permissive = false;
+        for field in record[close + 1..].split_whitespace() {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let value = value.trim_matches('"');
+            match key {
+                "pid" => pid = value.parse().ok(),
+                "comm" => comm = Some(value.to_string()),
+                "name" => name = Some(value.to_string()),
+                "scontext" => scontext = Some(value.to_string()),
+                "tcontext" => tcontext = Some(value.to_string()),
+                "tclass" => tclass = Some(value.to_string()),
+                "permissive" => permissive = value == "1",
+                _ => {}
+            }
+        }
+
+        let audit = line
+            .find("audit(")
+            .and_then(|i| line[i + 6..].split_once(')'))
+            .map(|(stamp, _)| stamp.to_string());
+        Some(AvcDenial {
+            permissions,
+            pid,
+            comm,
+            name,
+            scontext: scontext?,
+            tcontext: tcontext?,
+            tclass: tclass?,
+            permissive,
+            audit,
+            line: line.trim().to_string(),
+        })
+    }
+
+    /// Parse every record in `output`, dropping repeats of one audit event
+    /// (the kernel log and hilog both carry it)
+    pub fn parse_list(output: &str) -> Vec<AvcDenial> {
+        let mut seen = HashSet::new();
+        output
+            .lines()
+            .filter_map(Self::parse)
+            .filter(|denial| match &denial.audit {
+                Some(audit) => seen.insert(audit.clone()),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Type of the source context, e.g. `normal_hap`
+    pub fn source_type(&self) -> &str {
+        context_type(&self.scontext)
+    }
+
+    /// Type of the target context, e.g. `data_file`
+    pub fn target_type(&self) -> &str {
+        context_type(&self.tcontext)
+    }
+
+    /// Policy rule that would allow the access, as a starting point for a fix
+    pub fn allow_rule(&self) -> String {
+        format!(
+            "allow {} {}:{} {{ {} }};",
+            self.source_type(),
+            self.target_type(),
+            self.tclass,
+            self.permissions.join(" ")
+        )
+    }
+}
+
+impl fmt::Display for AvcDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "denied {{ {} }} {} -> {}:{}",
+            self.permissions.join(" "),
+            self.scontext,
+            self.tcontext,
+            self.tclass
+        )?;
+        if let Some(name) = &self.name {
+            write!(f, " name={}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Type field of a `user:role:type:level` context
+fn context_type(context: &str) -> &str {
+    context.split(':').nth(2).unwrap_or(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode() {
+        assert_eq!(
+            "Enforcing\n".parse::<SelinuxMode>().unwrap(),
+            SelinuxMode::Enforcing
+        );
+        assert_eq!(
+            "permissive".parse::<SelinuxMode>().unwrap(),
+            SelinuxMode::Permissive
+        );
+        assert!("getenforce: not found".parse::<SelinuxMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_denials() {
+        let output = r#"[ 4121.532211] audit: type=1400 audit(1792229412.120:3121): avc:  denied  { read write } for  pid=4321 comm="example.app" name="config.json" dev="dm-9" ino=5512 scontext=u:r:normal_hap:s0 tcontext=u:object_r:data_local_tmp:s0 tclass=file permissive=0
+[ 4121.600000] audit: type=1400 audit(1792229412.180:3122): avc:  denied  { search } for  pid=612 comm="hdcd" name="el2" scontext=u:r:hdcd:s0 tcontext=u:object_r:data_app_el2_file:s0 tclass=dir permissive=1
+10-17 09:30:12.120  4321  4321 W C02500/kernel: audit(1792229412.120:3121): avc:  denied  { read write } for  pid=4321 comm="example.app" name="config.json" scontext=u:r:normal_hap:s0 tcontext=u:object_r:data_local_tmp:s0 tclass=file permissive=0
+avc: granted { read } for pid=1 scontext=u:r:init:s0 tcontext=u:object_r:system_file:s0 tclass=file
+"#;
+        let denials = AvcDenial::parse_list(output);
+        assert_eq!(denials.len(), 2);
+
+        let first = &denials[0];
+        assert_eq!(first.permissions, ["read", "write"]);
+        assert_eq!(first.pid, Some(4321));
+        assert_eq!(first.comm.as_deref(), Some("example.app"));
+        assert_eq!(first.name.as_deref(), Some("config.json"));
+        assert_eq!(first.audit.as_deref(), Some("1792229412.120:3121"));
+        assert!(!first.permissive);
+        assert_eq!(first.source_type(), "normal_hap");
+        assert_eq!(
+            first.allow_rule(),
+            "allow normal_hap data_local_tmp:file { read write };"
+        );
+        assert_eq!(
+            first.to_string(),
+            "denied { read write } u:r:normal_hap:s0 -> u:object_r:data_local_tmp:s0:file name=config.json"
+        );
+
+        assert!(denials[1].permissive);
+        assert_eq!(denials[1].tclass, "dir");
+    }
+}
@@ -0,0 +1,233 @@
+//! Client handle shareable across tasks
+//!
+//! [`HdcClient`] methods take `&mut self`, so sharing one between tasks
+//! means wrapping it in a mutex and running every command one after the
+//! other. [`SharedHdcClient`] is `Send + Sync + Clone` and takes `&self`;
+//! each command runs on a channel checked out of an [`HdcPool`], so commands
+//! from different tasks run concurrently up to the pool size per device.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::SharedHdcClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = SharedHdcClient::new("127.0.0.1:8710");
+//!
+//! let mut tasks = Vec::new();
+//! for device in client.list_targets().await? {
+//!     let client = client.clone();
+//!     tasks.push(tokio::spawn(async move {
+//!         client.shell(&device, "uptime").await
+//!     }));
+//! }
+//! for task in tasks {
+//!     println!("{}", task.await??);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::app::{InstallOptions, UninstallOptions};
+use crate::client::{HdcClient, HdcClientBuilder};
+use crate::error::Result;
+use crate::file::FileTransferOptions;
+use crate::forward::{ForwardEntry, ForwardNode};
+use crate::hilog::HilogFilter;
+use crate::pool::{HdcPool, PooledClient};
+use crate::transport::ServerAddr;
+
+/// Channels kept per device unless configured otherwise
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Cloneable client usable from many tasks at once
+///
+/// Device commands name their device explicitly, since there is no shared
+/// "current device". Use [`get`](Self::get) for commands not wrapped here.
+#[derive(Clone)]
+pub struct SharedHdcClient {
+    pool: HdcPool,
+}
+
+impl SharedHdcClient {
+    /// Client for the server at `address` with [`DEFAULT_POOL_SIZE`] channels per device
+    ///
+    /// Connections are opened on first use.
+    pub fn new(address: impl Into<ServerAddr>) -> Self {
+        Self::from_pool(HdcPool::new(address, DEFAULT_POOL_SIZE))
+    }
+
+    /// Client configured by `builder`, with up to `size` channels per device
+    pub fn from_builder(builder: HdcClientBuilder, size: usize) -> Self {
+        Self::from_pool(HdcPool::from_builder(builder, size))
+    }
+
+    /// Client drawing its channels from `pool`
+    pub fn from_pool(pool: HdcPool) -> Self {
+        Self { pool }
+    }
+
+    /// Pool the commands run on
+    pub fn pool(&self) -> &HdcPool {
+        &self.pool
+    }
+
+    /// Check out a client bound to `device_id` for exclusive use
+    pub async fn get(&self, device_id: &str) -> Result<PooledClient> {
+        self.pool.get(device_id).await
+    }
+
+    /// Check out a server-level client for exclusive use
+    pub async fn get_server(&self) -> Result<PooledClient> {
+        self.pool.get_server().await
+    }
+
+    /// See [`HdcClient::list_targets`]
+    pub async fn list_targets(&self) -> Result<Vec<String>> {
+        self.get_server().await?.list_targets().await
+    }
+
+    /// See [`HdcClient::check_server`]
+    pub async fn check_server(&self) -> Result<String> {
+        self.get_server().await?.check_server().await
+    }
+
+    /// See [`HdcClient::fport_list_tasks`]
+    pub async fn fport_list_tasks(&self) -> Result<Vec<ForwardEntry>> {
+        self.get_server().await?.fport_list_tasks().await
+    }
+
+    /// See [`HdcClient::fport_remove`]
+    pub async fn fport_remove(&self, task_str: &str) -> Result<String> {
+        self.get_server().await?.fport_remove(task_str).await
+    }
+
+    /// Run a shell command on `device_id`
+    pub async fn shell(&self, device_id: &str, cmd: &str) -> Result<String> {
+        self.get(device_id).await?.shell(cmd).await
+    }
+
+    /// Install packages on `device_id`
+    pub async fn install(
+        &self,
+        device_id: &str,
+        paths: &[&str],
+        options: InstallOptions,
+    ) -> Result<String> {
+        self.get(device_id).await?.install(paths, options).await
+    }
+
+    /// Uninstall a package from `device_id`
+    pub async fn uninstall(
+        &self,
+        device_id: &str,
+        package: &str,
+        options: UninstallOptions,
+    ) -> Result<String> {
+        self.get(device_id).await?.uninstall(package, options).await
+    }
+
+    /// Send a file to `device_id`
+    pub async fn file_send(
+        &self,
+        device_id: &str,
+        local_path: &str,
+        remote_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.get(device_id)
+            .await?
+            .file_send(local_path, remote_path, options)
+            .await
+    }
+
+    /// Receive a file from `device_id`
+    pub async fn file_recv(
+        &self,
+        device_id: &str,
+        remote_path: &str,
+        local_path: &str,
+        options: FileTransferOptions,
+    ) -> Result<String> {
+        self.get(device_id)
+            .await?
+            .file_recv(remote_path, local_path, options)
+            .await
+    }
+
+    /// Forward a local port to `device_id`
+    pub async fn fport(
+        &self,
+        device_id: &str,
+        local: ForwardNode,
+        remote: ForwardNode,
+    ) -> Result<String> {
+        self.get(device_id).await?.fport(local, remote).await
+    }
+
+    /// Forward a port on `device_id` back to the host
+    pub async fn rport(
+        &self,
+        device_id: &str,
+        remote: ForwardNode,
+        local: ForwardNode,
+    ) -> Result<String> {
+        self.get(device_id).await?.rport(remote, local).await
+    }
+
+    /// Read buffered logs from `device_id`
+    pub async fn hilog(&self, device_id: &str, filter: impl Into<HilogFilter>) -> Result<String> {
+        self.get(device_id).await?.hilog_filtered(filter).await
+    }
+}
+
+impl From<HdcPool> for SharedHdcClient {
+    fn from(pool: HdcPool) -> Self {
+        Self::from_pool(pool)
+    }
+}
+
+impl From<&HdcClient> for SharedHdcClient {
+    /// Shared client for the same server with the same settings
+    fn from(client: &HdcClient) -> Self {
+        Self::from_builder(client.to_builder(), DEFAULT_POOL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+
+    fn assert_shareable<T: Send + Sync + Clone + 'static>() {}
+
+    #[tokio::test]
+    async fn test_shared_client_from_many_tasks() {
+        assert_shareable::<SharedHdcClient>();
+
+        let server = MockServer::new()
+            .respond("list targets", "device-1\n")
+            .respond("shell echo hi", "hi\n")
+            .spawn()
+            .await
+            .unwrap();
+        let client = SharedHdcClient::new(server.address());
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    if i % 2 == 0 {
+                        client.list_targets().await.unwrap().join(",")
+                    } else {
+                        client.shell("device-1", "echo hi").await.unwrap()
+                    }
+                })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            let output = task.await.unwrap();
+            assert_eq!(output, if i % 2 == 0 { "device-1" } else { "hi\n" });
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Quoting for commands run by the device shell
+
+/// Single-quote `arg` so the device shell passes it through as one word
+///
+/// Embedded single quotes are closed, escaped and reopened (`'\''`), so no
+/// character inside `arg` is interpreted by the shell.
+pub(crate) fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b;reboot"), "'a b;reboot'");
+        assert_eq!(quote("$(id)|`id`"), "'$(id)|`id`'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+}
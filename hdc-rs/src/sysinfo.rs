@@ -0,0 +1,824 @@
+//! Typed device introspection
+//!
+//! Parsers for the shell tools OpenHarmony ships: `ps -ef` for processes,
+//! `df -k` for storage, `/proc/meminfo` for memory, `bm dump -a` for
+//! installed bundles, `hidumper` for the battery and Wi-Fi and
+//! `ifconfig`/`netstat` for the network. [`HdcClient`](crate::HdcClient)
+//! runs the commands through [`processes`](crate::HdcClient::processes),
+//! [`storage`](crate::HdcClient::storage),
+//! [`meminfo`](crate::HdcClient::meminfo),
+//! [`list_bundles`](crate::HdcClient::list_bundles),
+//! [`battery`](crate::HdcClient::battery),
+//! [`net_stats`](crate::HdcClient::net_stats),
+//! [`connections`](crate::HdcClient::connections) and
+//! [`wifi_info`](crate::HdcClient::wifi_info).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let mem = client.meminfo().await?;
+//! println!("{} of {} kB available", mem.available_kb, mem.total_kb);
+//! for fs in client.storage().await? {
+//!     println!("{}: {} kB free", fs.mounted_on, fs.available_kb);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Command listing processes
+pub const PS_COMMAND: &str = "ps -ef";
+/// Command listing filesystem usage in kilobytes
+pub const DF_COMMAND: &str = "df -k";
+/// Command printing memory statistics
+pub const MEMINFO_COMMAND: &str = "cat /proc/meminfo";
+/// Command listing installed bundles
+pub const BUNDLES_COMMAND: &str = "bm dump -a";
+/// Command dumping the battery service state
+pub const BATTERY_COMMAND: &str = "hidumper -s BatteryService -a -i";
+/// Command listing network interfaces with their counters
+pub const IFCONFIG_COMMAND: &str = "ifconfig -a";
+/// Command listing TCP and UDP sockets with their owning processes
+pub const NETSTAT_COMMAND: &str = "netstat -tuanp";
+/// Command dumping the Wi-Fi station state
+pub const WIFI_COMMAND: &str = "hidumper -s WifiDevice -a";
+/// Interface Wi-Fi station connections use
+pub const WIFI_INTERFACE: &str = "wlan0";
+
+/// One row of `ps -ef`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Owning user name
+    pub user: String,
+    /// Process ID
+    pub pid: u32,
+    /// Parent process ID
+    pub ppid: u32,
+    /// Command line, or the bracketed name of kernel threads
+    pub command: String,
+}
+
+impl ProcessInfo {
+    /// Parse `ps -ef` output, skipping the header and malformed lines
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sysinfo::ProcessInfo;
+    ///
+    /// let output = "UID PID PPID C STIME TTY TIME CMD\n\
+    ///               root 1 0 0 10:00:00 ? 00:00:01 init --second-stage\n";
+    /// let processes = ProcessInfo::parse_list(output);
+    /// assert_eq!(processes[0].pid, 1);
+    /// assert_eq!(processes[0].command, "init --second-stage");
+    /// ```
+    pub fn parse_list(output: &str) -> Vec<ProcessInfo> {
+        output.lines().filter_map(Self::parse_line).collect()
+    }
+
+    fn parse_line(line: &str) -> Option<ProcessInfo> {
+        // UID PID PPID C STIME TTY TIME CMD, where CMD may contain spaces
+        let mut fields = line.split_whitespace();
+        let user = fields.next()?;
+        let pid = fields.next()?.parse().ok()?;
+        let ppid = fields.next()?.parse().ok()?;
+        let command = fields.skip(4).collect::<Vec<_>>().join(" ");
+        if command.is_empty() {
+            return None;
+        }
+        Some(ProcessInfo {
+            user: user.to_string(),
+            pid,
+            ppid,
+            command,
+        })
+    }
+}
+
+/// One row of `df -k`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageInfo {
+    /// Filesystem or device name
+    pub filesystem: String,
+    /// Size in kilobytes
+    pub total_kb: u64,
+    /// Used space in kilobytes
+    pub used_kb: u64,
+    /// Free space in kilobytes
+    pub available_kb: u64,
+    /// Mount point
+    pub mounted_on: String,
+}
+
+impl StorageInfo {
+    /// Parse `df -k` output, skipping the header and malformed lines
+    pub fn parse_list(output: &str) -> Vec<StorageInfo> {
+        output.lines().filter_map(Self::parse_line).collect()
+    }
+
+    fn parse_line(line: &str) -> Option<StorageInfo> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [filesystem, total, used, available, _percent, mounted_on] = fields[..] else {
+            return None;
+        };
+        Some(StorageInfo {
+            filesystem: filesystem.to_string(),
+            total_kb: total.parse().ok()?,
+            used_kb: used.parse().ok()?,
+            available_kb: available.parse().ok()?,
+            mounted_on: mounted_on.to_string(),
+        })
+    }
+
+    /// Used share of the filesystem, from 0.0 to 1.0
+    pub fn usage(&self) -> f64 {
+        if self.total_kb == 0 {
+            0.0
+        } else {
+            self.used_kb as f64 / self.total_kb as f64
+        }
+    }
+}
+
+/// Memory statistics from `/proc/meminfo`, in kilobytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemInfo {
+    /// `MemTotal`
+    pub total_kb: u64,
+    /// `MemFree`
+    pub free_kb: u64,
+    /// `MemAvailable`
+    pub available_kb: u64,
+    /// `Cached`
+    pub cached_kb: u64,
+    /// `SwapTotal`
+    pub swap_total_kb: u64,
+    /// `SwapFree`
+    pub swap_free_kb: u64,
+    /// Every field, keyed by name
+    pub fields: BTreeMap<String, u64>,
+}
+
+impl MemInfo {
+    /// Parse `/proc/meminfo`
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sysinfo::MemInfo;
+    ///
+    /// let mem = MemInfo::parse("MemTotal: 7989448 kB\nMemAvailable: 3057212 kB\n");
+    /// assert_eq!(mem.total_kb, 7989448);
+    /// assert_eq!(mem.fields["MemAvailable"], 3057212);
+    /// ```
+    pub fn parse(output: &str) -> MemInfo {
+        let fields: BTreeMap<String, u64> = output
+            .lines()
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                let value = value.split_whitespace().next()?.parse().ok()?;
+                Some((name.trim().to_string(), value))
+            })
+            .collect();
+        let get = |name: &str| fields.get(name).copied().unwrap_or_default();
+        MemInfo {
+            total_kb: get("MemTotal"),
+            free_kb: get("MemFree"),
+            available_kb: get("MemAvailable"),
+            cached_kb: get("Cached"),
+            swap_total_kb: get("SwapTotal"),
+            swap_free_kb: get("SwapFree"),
+            fields,
+        }
+    }
+}
+
+/// Charging state reported by the battery service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChargeStatus {
+    /// Not charging
+    NotCharging,
+    /// Charging
+    Charging,
+    /// Charger attached but charging disabled
+    Disabled,
+    /// Fully charged
+    Full,
+    /// Not reported or unknown value
+    Unknown,
+}
+
+impl ChargeStatus {
+    /// Map the service's `chargingStatus` value
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => Self::NotCharging,
+            1 => Self::Charging,
+            2 => Self::Disabled,
+            3 => Self::Full,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Lowercase name, e.g. `charging`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotCharging => "not_charging",
+            Self::Charging => "charging",
+            Self::Disabled => "disabled",
+            Self::Full => "full",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ChargeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Power source plugged into the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChargerType {
+    /// Running on battery
+    None,
+    /// Wall charger
+    Ac,
+    /// USB port
+    Usb,
+    /// Wireless charger
+    Wireless,
+    /// Not reported or unknown value
+    Unknown,
+}
+
+impl ChargerType {
+    /// Map the service's `pluggedType` value
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => Self::None,
+            1 => Self::Ac,
+            2 => Self::Usb,
+            3 => Self::Wireless,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Lowercase name, e.g. `usb`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Ac => "ac",
+            Self::Usb => "usb",
+            Self::Wireless => "wireless",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ChargerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Battery state from the battery service
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryInfo {
+    /// Charge level in percent
+    pub level: u8,
+    /// Charging state
+    pub status: ChargeStatus,
+    /// Attached power source
+    pub charger: ChargerType,
+    /// Battery temperature in degrees Celsius
+    pub temperature_c: f64,
+    /// Battery voltage in millivolts, 0 if not reported
+    pub voltage_mv: u32,
+    /// Every `key: value` field of the dump
+    pub fields: BTreeMap<String, String>,
+}
+
+impl BatteryInfo {
+    /// Parse `hidumper -s BatteryService -a -i`
+    ///
+    /// Returns `None` if the dump has no `capacity` field, e.g. on boards
+    /// without a battery.
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sysinfo::{BatteryInfo, ChargeStatus, ChargerType};
+    ///
+    /// let output = "capacity: 87\nchargingStatus: 1\npluggedType: 2\n\
+    ///               voltage: 4302000\ntemperature: 312\n";
+    /// let battery = BatteryInfo::parse(output).unwrap();
+    /// assert_eq!(battery.level, 87);
+    /// assert_eq!(battery.status, ChargeStatus::Charging);
+    /// assert_eq!(battery.charger, ChargerType::Usb);
+    /// assert_eq!(battery.voltage_mv, 4302);
+    /// assert_eq!(battery.temperature_c, 31.2);
+    /// ```
+    pub fn parse(output: &str) -> Option<BatteryInfo> {
+        let fields: BTreeMap<String, String> = output
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                let key = key.trim();
+                if key.is_empty() || key.contains(char::is_whitespace) {
+                    return None;
+                }
+                Some((key.to_string(), value.trim().to_string()))
+            })
+            .collect();
+        let number = |key: &str| fields.get(key)?.parse::<i64>().ok();
+        let code = |key: &str| number(key).and_then(|n| u32::try_from(n).ok());
+        Some(BatteryInfo {
+            level: number("capacity")?.clamp(0, 100) as u8,
+            status: code("chargingStatus").map_or(ChargeStatus::Unknown, ChargeStatus::from_code),
+            charger: code("pluggedType").map_or(ChargerType::Unknown, ChargerType::from_code),
+            temperature_c: number("temperature").unwrap_or_default() as f64 / 10.0,
+            voltage_mv: code("voltage").unwrap_or_default() / 1000,
+            fields,
+        })
+    }
+
+    /// Whether a charger is attached
+    pub fn is_plugged(&self) -> bool {
+        !matches!(self.charger, ChargerType::None | ChargerType::Unknown)
+    }
+}
+
+/// One interface of `ifconfig -a`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// Interface name, e.g. `wlan0`
+    pub name: String,
+    /// Hardware address, if any
+    pub mac: Option<String>,
+    /// IPv4 address, if assigned
+    pub ipv4: Option<String>,
+    /// IPv6 addresses with prefix length, e.g. `fe80::1/64`
+    pub ipv6: Vec<String>,
+    /// Whether the interface is up
+    pub up: bool,
+    /// Maximum transmission unit
+    pub mtu: u32,
+    /// Bytes received
+    pub rx_bytes: u64,
+    /// Bytes sent
+    pub tx_bytes: u64,
+    /// Packets received
+    pub rx_packets: u64,
+    /// Packets sent
+    pub tx_packets: u64,
+    /// Receive errors
+    pub rx_errors: u64,
+    /// Transmit errors
+    pub tx_errors: u64,
+    /// Received packets dropped
+    pub rx_dropped: u64,
+    /// Outgoing packets dropped
+    pub tx_dropped: u64,
+}
+
+impl InterfaceStats {
+    /// Parse `ifconfig -a` output, one entry per interface block
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sysinfo::InterfaceStats;
+    ///
+    /// let output = "lo        Link encap:Local Loopback\n\
+    ///               \x20         inet addr:127.0.0.1  Mask:255.0.0.0\n\
+    ///               \x20         UP LOOPBACK RUNNING  MTU:65536  Metric:1\n\
+    ///               \x20         RX bytes:1200 TX bytes:1200\n";
+    /// let interfaces = InterfaceStats::parse_list(output);
+    /// assert_eq!(interfaces[0].name, "lo");
+    /// assert_eq!(interfaces[0].ipv4.as_deref(), Some("127.0.0.1"));
+    /// assert_eq!(interfaces[0].rx_bytes, 1200);
+    /// ```
+    pub fn parse_list(output: &str) -> Vec<InterfaceStats> {
+        let mut interfaces: Vec<InterfaceStats> = Vec::new();
+        for line in output.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                let name = line.split_whitespace().next().unwrap_or_default();
+                interfaces.push(InterfaceStats {
+                    name: name.trim_end_matches(':').to_string(),
+                    ..InterfaceStats::default()
+                });
+            }
+            if let Some(interface) = interfaces.last_mut() {
+                interface.parse_line(line.trim());
+            }
+        }
+        interfaces
+    }
+
+    fn parse_line(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if let Some(i) = words.iter().position(|w| *w == "HWaddr") {
+            self.mac = words.get(i + 1).map(|mac| mac.to_string());
+        }
+        match words.as_slice() {
+            ["inet", addr, ..] => {
+                self.ipv4 = addr.strip_prefix("addr:").map(str::to_string);
+                return;
+            }
+            ["inet6", "addr:", addr, ..] | ["inet6", addr, ..] => {
+                self.ipv6.push(addr.trim_start_matches("addr:").to_string());
+                return;
+            }
+            _ => {}
+        }
+        if words.contains(&"UP") {
+            self.up = true;
+        }
+
+        // RX and TX lines carry `key:value` counters, scoped by their prefix
+        let mut direction = None;
+        for word in &words {
+            match *word {
+                "RX" => direction = Some(true),
+                "TX" => direction = Some(false),
+                _ => {}
+            }
+            let Some((key, value)) = word.split_once(':') else {
+                continue;
+            };
+            let Ok(value) = value.parse::<u64>() else {
+                continue;
+            };
+            let rx = direction == Some(true);
+            match (key, direction.is_some()) {
+                ("MTU", _) => self.mtu = value as u32,
+                ("bytes", true) if rx => self.rx_bytes = value,
+                ("bytes", true) => self.tx_bytes = value,
+                ("packets", true) if rx => self.rx_packets = value,
+                ("packets", true) => self.tx_packets = value,
+                ("errors", true) if rx => self.rx_errors = value,
+                ("errors", true) => self.tx_errors = value,
+                ("dropped", true) if rx => self.rx_dropped = value,
+                ("dropped", true) => self.tx_dropped = value,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// One socket of `netstat -tuanp`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketInfo {
+    /// `tcp`, `tcp6`, `udp` or `udp6`
+    pub protocol: String,
+    /// Bytes not yet read by the owning process
+    pub recv_q: u64,
+    /// Bytes not yet acknowledged by the peer
+    pub send_q: u64,
+    /// Local address and port, e.g. `0.0.0.0:8710`
+    pub local: String,
+    /// Remote address and port, `*` when unconnected
+    pub remote: String,
+    /// TCP state such as `LISTEN`; often absent for UDP
+    pub state: Option<String>,
+    /// Owning process ID, if shown
+    pub pid: Option<u32>,
+    /// Owning process name, if shown
+    pub program: Option<String>,
+}
+
+impl SocketInfo {
+    /// Parse `netstat` output, skipping headers and malformed lines
+    pub fn parse_list(output: &str) -> Vec<SocketInfo> {
+        output.lines().filter_map(Self::parse_line).collect()
+    }
+
+    fn parse_line(line: &str) -> Option<SocketInfo> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [protocol, recv_q, send_q, local, remote, rest @ ..] = fields.as_slice() else {
+            return None;
+        };
+        if !protocol.starts_with("tcp") && !protocol.starts_with("udp") {
+            return None;
+        }
+        let is_owner = |field: &str| field == "-" || field.contains('/');
+        let (state, owner) = match rest {
+            [] => (None, None),
+            [field] if is_owner(field) => (None, Some(*field)),
+            [state] => (Some(*state), None),
+            [state, owner, ..] => (Some(*state), Some(*owner)),
+        };
+        let (pid, program) = match owner.and_then(|owner| owner.split_once('/')) {
+            Some((pid, program)) => (pid.parse().ok(), Some(program.to_string())),
+            None => (None, None),
+        };
+        Some(SocketInfo {
+            protocol: protocol.to_string(),
+            recv_q: recv_q.parse().ok()?,
+            send_q: send_q.parse().ok()?,
+            local: local.to_string(),
+            remote: remote.to_string(),
+            state: state.map(str::to_string),
+            pid,
+            program,
+        })
+    }
+
+    /// Port of the local address
+    pub fn local_port(&self) -> Option<u16> {
+        self.local.rsplit_once(':')?.1.parse().ok()
+    }
+
+    /// Port of the remote address, `None` when unconnected
+    pub fn remote_port(&self) -> Option<u16> {
+        self.remote.rsplit_once(':')?.1.parse().ok()
+    }
+}
+
+/// Wi-Fi station state from the Wi-Fi service
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WifiInfo {
+    /// Whether Wi-Fi is switched on
+    pub enabled: bool,
+    /// Whether the station is associated with an access point
+    pub connected: bool,
+    /// Network name
+    pub ssid: Option<String>,
+    /// Access point MAC address
+    pub bssid: Option<String>,
+    /// Signal strength in dBm
+    pub rssi: Option<i32>,
+    /// Negotiated link speed in Mbit/s
+    pub link_speed_mbps: Option<u32>,
+    /// Channel frequency in MHz
+    pub frequency_mhz: Option<u32>,
+    /// IPv4 address of the station
+    pub ip: Option<String>,
+    /// Every `key: value` field of the dump, without the `Connection.` prefix
+    pub fields: BTreeMap<String, String>,
+}
+
+impl WifiInfo {
+    /// Parse `hidumper -s WifiDevice -a`
+    ///
+    /// The IP address is only filled in if the dump carries one;
+    /// [`HdcClient::wifi_info`](crate::HdcClient::wifi_info) falls back to
+    /// the address of [`WIFI_INTERFACE`].
+    ///
+    /// # Example
+    /// ```
+    /// use hdc_rs::sysinfo::WifiInfo;
+    ///
+    /// let output = "WiFi active state: activated\n\
+    ///               WiFi connection status: connected\n\
+    ///               \x20 Connection.ssid: lab-5g\n\
+    ///               \x20 Connection.rssi: -52\n";
+    /// let wifi = WifiInfo::parse(output);
+    /// assert!(wifi.enabled && wifi.connected);
+    /// assert_eq!(wifi.ssid.as_deref(), Some("lab-5g"));
+    /// assert_eq!(wifi.rssi, Some(-52));
+    /// ```
+    pub fn parse(output: &str) -> WifiInfo {
+        let mut info = WifiInfo::default();
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = value.trim();
+            match key {
+                "WiFi active state" => info.enabled = value == "activated",
+                "WiFi connection status" => info.connected = value == "connected",
+                _ => {
+                    let key = key.strip_prefix("Connection.").unwrap_or(key);
+                    if !key.is_empty() && !key.contains(char::is_whitespace) {
+                        info.fields.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+        }
+
+        let text = |key: &str| {
+            info.fields
+                .get(key)
+                .filter(|value| !value.is_empty())
+                .cloned()
+        };
+        info.ssid = text("ssid").map(|ssid| ssid.trim_matches('"').to_string());
+        info.bssid = text("bssid");
+        info.ip = text("ipAddress").filter(|ip| ip.contains('.') && ip != "0.0.0.0");
+        info.rssi = text("rssi").and_then(|v| v.parse().ok());
+        info.link_speed_mbps = text("linkSpeed").and_then(|v| v.parse().ok());
+        info.frequency_mhz = text("frequency").and_then(|v| v.parse().ok());
+        if info.ssid.is_some() && info.rssi.is_some() {
+            info.enabled = true;
+        }
+        info
+    }
+
+    /// `ip:port` to pass to `tconn` once the device listens on `port`
+    /// (see [`HdcClient::tmode_port`](crate::HdcClient::tmode_port))
+    pub fn tconn_addr(&self, port: u16) -> Option<String> {
+        Some(format!("{}:{}", self.ip.as_ref()?, port))
+    }
+}
+
+/// Parse bundle names from `bm dump -a`
+///
+/// The output starts with an `ID: <user>:` line followed by one indented
+/// bundle name per line; names are returned deduplicated in listing order.
+pub fn parse_bundles(output: &str) -> Vec<String> {
+    let mut bundles: Vec<String> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("ID:") || line.contains(' ') {
+            continue;
+        }
+        if !bundles.iter().any(|b| b == line) {
+            bundles.push(line.to_string());
+        }
+    }
+    bundles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_processes() {
+        let output = "\
+UID            PID  PPID C STIME TTY          TIME CMD
+root             1     0 0 09:12:01 ?     00:00:03 init --second-stage 2283241
+root             2     0 0 09:12:01 ?     00:00:00 [kthreadd]
+20010034      4321   612 2 09:15:44 ?     00:00:12 com.example.app
+garbage line
+";
+        let processes = ProcessInfo::parse_list(output);
+        assert_eq!(processes.len(), 3);
+        assert_eq!(processes[0].command, "init --second-stage 2283241");
+        assert_eq!(processes[1].command, "[kthreadd]");
+        assert_eq!(processes[2].user, "20010034");
+        assert_eq!((processes[2].pid, processes[2].ppid), (4321, 612));
+    }
+
+    #[test]
+    fn test_parse_storage_and_bundles() {
+        let output = "\
+Filesystem      1K-blocks    Used Available Use% Mounted on
+/dev/block/dm-0   1937408 1925580     11828 100% /
+tmpfs             3994724    1012   3993712   1% /dev
+/dev/block/dm-9 113590940 9876543 103714397   9% /data
+";
+        let storage = StorageInfo::parse_list(output);
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage[2].mounted_on, "/data");
+        assert_eq!(storage[2].available_kb, 103714397);
+        assert!(storage[0].usage() > 0.99);
+
+        let output =
+            "ID: 100:\n\tcom.ohos.settings\n\tcom.example.app\nID: 0:\n\tcom.ohos.settings\n";
+        assert_eq!(
+            parse_bundles(output),
+            ["com.ohos.settings", "com.example.app"]
+        );
+    }
+
+    #[test]
+    fn test_parse_battery() {
+        let output = "\
+-------------------------------[ability]-------------------------------
+
+
+----------------------------------BatteryService---------------------------------
+capacity: 100
+batteryLevel: 1
+chargingStatus: 3
+healthState: 1
+pluggedType: 1
+voltage: 4402000
+present: 1
+technology: Li-poly
+temperature: -45
+";
+        let battery = BatteryInfo::parse(output).unwrap();
+        assert_eq!(battery.level, 100);
+        assert_eq!(battery.status, ChargeStatus::Full);
+        assert_eq!(battery.charger, ChargerType::Ac);
+        assert!(battery.is_plugged());
+        assert_eq!(battery.temperature_c, -4.5);
+        assert_eq!(battery.voltage_mv, 4402);
+        assert_eq!(battery.fields["technology"], "Li-poly");
+
+        let battery = BatteryInfo::parse("capacity: 5\npluggedType: 0\n").unwrap();
+        assert_eq!(battery.status, ChargeStatus::Unknown);
+        assert!(!battery.is_plugged());
+        assert!(BatteryInfo::parse("[Fail]Unknown command").is_none());
+    }
+
+    #[test]
+    fn test_parse_interfaces() {
+        let output = "\
+lo        Link encap:Local Loopback
+          inet addr:127.0.0.1  Mask:255.0.0.0
+          inet6 addr: ::1/128 Scope: Host
+          UP LOOPBACK RUNNING  MTU:65536  Metric:1
+          RX packets:42 errors:0 dropped:0 overruns:0 frame:0
+          TX packets:42 errors:0 dropped:0 overruns:0 carrier:0
+          collisions:0 txqueuelen:1000
+          RX bytes:3360 TX bytes:3360
+
+wlan0     Link encap:Ethernet  HWaddr 8c:aa:ce:01:02:03  Driver cfg80211
+          inet addr:192.168.1.23  Bcast:192.168.1.255  Mask:255.255.255.0
+          inet6 addr: fe80::8eaa:ceff:fe01:203/64 Scope: Link
+          UP BROADCAST RUNNING MULTICAST  MTU:1500  Metric:1
+          RX packets:10021 errors:1 dropped:7 overruns:0 frame:0
+          TX packets:8034 errors:2 dropped:3 overruns:0 carrier:0
+          collisions:0 txqueuelen:1000
+          RX bytes:12873456 TX bytes:987654
+
+p2p0      Link encap:Ethernet  HWaddr 8e:aa:ce:01:02:03
+          BROADCAST MULTICAST  MTU:1500  Metric:1
+";
+        let interfaces = InterfaceStats::parse_list(output);
+        assert_eq!(interfaces.len(), 3);
+        assert_eq!(interfaces[0].ipv6, ["::1/128"]);
+        assert_eq!(interfaces[0].mtu, 65536);
+        let wlan = &interfaces[1];
+        assert_eq!(wlan.mac.as_deref(), Some("8c:aa:ce:01:02:03"));
+        assert_eq!(wlan.ipv4.as_deref(), Some("192.168.1.23"));
+        assert!(wlan.up);
+        assert_eq!((wlan.rx_packets, wlan.tx_packets), (10021, 8034));
+        assert_eq!((wlan.rx_errors, wlan.tx_errors), (1, 2));
+        assert_eq!((wlan.rx_dropped, wlan.tx_dropped), (7, 3));
+        assert_eq!((wlan.rx_bytes, wlan.tx_bytes), (12873456, 987654));
+        assert!(!interfaces[2].up);
+        assert_eq!(interfaces[2].ipv4, None);
+    }
+
+    #[test]
+    fn test_parse_wifi() {
+        let output = "\
+WiFi active state: activated
+
+WiFi connection status: connected
+  Connection.ssid: \"lab-5g\"
+  Connection.bssid: 64:6e:97:aa:bb:cc
+  Connection.rssi: -48
+  Connection.band: 2
+  Connection.frequency: 5745
+  Connection.linkSpeed: 866
+  Connection.macAddress: 8c:aa:ce:01:02:03
+  Connection.ipAddress: 192.168.1.23
+  Connection.isHiddenSSID: false
+";
+        let wifi = WifiInfo::parse(output);
+        assert!(wifi.enabled && wifi.connected);
+        assert_eq!(wifi.ssid.as_deref(), Some("lab-5g"));
+        assert_eq!(wifi.bssid.as_deref(), Some("64:6e:97:aa:bb:cc"));
+        assert_eq!(wifi.rssi, Some(-48));
+        assert_eq!(wifi.link_speed_mbps, Some(866));
+        assert_eq!(wifi.frequency_mhz, Some(5745));
+        assert_eq!(wifi.tconn_addr(5555).as_deref(), Some("192.168.1.23:5555"));
+        assert_eq!(wifi.fields["isHiddenSSID"], "false");
+
+        let wifi = WifiInfo::parse("WiFi active state: inactivated\n");
+        assert!(!wifi.enabled && !wifi.connected);
+        assert_eq!(wifi.tconn_addr(5555), None);
+    }
+
+    #[test]
+    fn test_parse_sockets() {
+        let output = "\
+Active Internet connections (servers and established)
+Proto Recv-Q Send-Q Local Address           Foreign Address         State       PID/Program Name
+tcp        0      0 0.0.0.0:8710            0.0.0.0:*               LISTEN      612/hdcd
+tcp        0     52 192.168.1.23:43210      1.2.3.4:443             ESTABLISHED 4321/com.example.app
+tcp6       0      0 :::8080                 :::*                    LISTEN      -
+udp        0      0 0.0.0.0:5353            0.0.0.0:*                           880/mdnsd
+udp        0      0 0.0.0.0:68              0.0.0.0:*
+";
+        let sockets = SocketInfo::parse_list(output);
+        assert_eq!(sockets.len(), 5);
+        assert_eq!(sockets[0].local_port(), Some(8710));
+        assert_eq!(sockets[0].remote_port(), None);
+        assert_eq!(sockets[1].state.as_deref(), Some("ESTABLISHED"));
+        assert_eq!(sockets[1].send_q, 52);
+        assert_eq!(sockets[1].pid, Some(4321));
+        assert_eq!(sockets[1].program.as_deref(), Some("com.example.app"));
+        assert_eq!(sockets[1].remote_port(), Some(443));
+        assert_eq!(sockets[2].local_port(), Some(8080));
+        assert_eq!(
+            (sockets[2].pid, sockets[2].program.as_deref()),
+            (None, None)
+        );
+        assert_eq!(sockets[3].state, None);
+        assert_eq!(sockets[3].program.as_deref(), Some("mdnsd"));
+        assert_eq!((sockets[4].state.as_deref(), sockets[4].pid), (None, None));
+    }
+}
@@ -0,0 +1,365 @@
+//! Fake devices behind a [`MockServer`](super::MockServer)
+//!
+//! A [`FakeDevice`] answers the commands sent on channels opened with its
+//! connect key from a table programmed per test, and shows up in
+//! `list targets`. Clones share state, so a test can keep one to unplug
+//! the device or check the commands it received while the server runs.
+//!
+//! Rules match a whole command, or with [`FakeDevice::when_prefix`] any
+//! command starting with a prefix, for commands carrying generated names.
+//! A `file recv` rule can [send a file](When::sends_file): the contents are
+//! written to the local path of the command, as the hdc server would.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::testing::{FakeDevice, MockServer};
+//! use hdc_rs::HdcClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let phone = FakeDevice::new("FMR0223C13000649")
+//!     .model("NOH-AN00")
+//!     .when("param get const.product.model")
+//!     .reply("NOH-AN00\n")
+//!     .when_command("install -r app.hap")
+//!     .reply("[Info]App install path:app.hap msg:install bundle successfully.\n");
+//! let server = MockServer::new().device(phone.clone()).spawn().await?;
+//!
+//! let mut device = HdcClient::connect(server.address()).await?.device("FMR0223C13000649");
+//! assert_eq!(device.shell("param get const.product.model").await?, "NOH-AN00\n");
+//! assert_eq!(phone.commands(), ["shell param get const.product.model"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Reply to a channel whose device is missing, as the hdc server words it
+pub(super) const DEVICE_NOT_FOUND: &[u8] = b"[Fail]Device not founded or connected";
+
+/// Scriptable device served by a [`MockServer`](super::MockServer)
+#[derive(Debug, Clone)]
+pub struct FakeDevice {
+    inner: Arc<Mutex<DeviceState>>,
+}
+
+#[derive(Debug)]
+struct DeviceState {
+    connect_key: String,
+    connection_type: String,
+    state: String,
+    model: String,
+    plugged: bool,
+    rules: Vec<Rule>,
+    /// Commands received, in order
+    commands: Vec<String>,
+}
+
+/// Replies to one command or command prefix; the last one repeats
+#[derive(Debug)]
+struct Rule {
+    command: String,
+    prefix: bool,
+    replies: VecDeque<Option<Reply>>,
+}
+
+/// What a rule answers with
+#[derive(Debug, Clone)]
+enum Reply {
+    Output(Vec<u8>),
+    /// Contents written to the local path of a `file recv` command
+    File(Vec<u8>),
+}
+
+impl Rule {
+    fn matches(&self, command: &str) -> bool {
+        if self.prefix {
+            command.starts_with(&self.command)
+        } else {
+            command == self.command
+        }
+    }
+}
+
+impl FakeDevice {
+    /// Connected USB device with the given connect key
+    pub fn new(connect_key: impl Into<String>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(DeviceState {
+                connect_key: connect_key.into(),
+                connection_type: "USB".to_string(),
+                state: "Connected".to_string(),
+                model: String::new(),
+                plugged: true,
+                rules: Vec::new(),
+                commands: Vec::new(),
+            })),
+        }
+    }
+
+    /// Device name listed by `list targets -v`
+    pub fn model(self, model: impl Into<String>) -> Self {
+        self.lock().model = model.into();
+        self
+    }
+
+    /// Transport listed by `list targets -v` (default `USB`)
+    pub fn connection_type(self, connection_type: impl Into<String>) -> Self {
+        self.lock().connection_type = connection_type.into();
+        self
+    }
+
+    /// Program the reply to the shell command `command`
+    pub fn when(self, command: impl AsRef<str>) -> When {
+        self.when_command(format!("shell {}", command.as_ref()))
+    }
+
+    /// Program the reply to a device command other than `shell`, such as
+    /// `install -r app.hap`
+    pub fn when_command(self, command: impl Into<String>) -> When {
+        When {
+            device: self,
+            command: command.into(),
+            prefix: false,
+        }
+    }
+
+    /// Program the reply to every shell command starting with `prefix`
+    ///
+    /// Rules are tried in the order they were added.
+    pub fn when_prefix(self, prefix: impl AsRef<str>) -> When {
+        self.when_command_prefix(format!("shell {}", prefix.as_ref()))
+    }
+
+    /// Program the reply to every device command starting with `prefix`,
+    /// such as `file recv /data/local/tmp/`
+    pub fn when_command_prefix(self, prefix: impl Into<String>) -> When {
+        When {
+            device: self,
+            command: prefix.into(),
+            prefix: true,
+        }
+    }
+
+    /// Connect key the device is addressed by
+    pub fn connect_key(&self) -> String {
+        self.lock().connect_key.clone()
+    }
+
+    /// Change the listed state, e.g. to `Offline` or `Unauthorized`
+    pub fn set_state(&self, state: impl Into<String>) {
+        self.lock().state = state.into();
+    }
+
+    /// Remove the device from the target list; its channels fail from now on
+    pub fn unplug(&self) {
+        self.lock().plugged = false;
+    }
+
+    /// Attach the device again after [`unplug`](Self::unplug)
+    pub fn plug(&self) {
+        self.lock().plugged = true;
+    }
+
+    /// Commands received so far, such as `shell ls`
+    pub fn commands(&self) -> Vec<String> {
+        self.lock().commands.clone()
+    }
+
+    /// Line for `list targets` (`verbose` for `-v`), if plugged
+    pub(super) fn target_line(&self, verbose: bool) -> Option<String> {
+        let state = self.lock();
+        if !state.plugged {
+            None
+        } else if verbose {
+            Some(format!(
+                "{}\t\t{}\t{}\t{}\n",
+                state.connect_key, state.connection_type, state.state, state.model
+            ))
+        } else {
+            Some(format!("{}\n", state.connect_key))
+        }
+    }
+
+    /// Reply to `command` from a channel with this device's key
+    ///
+    /// `None` if no rule matches; `Some(None)` if the command goes
+    /// unanswered.
+    pub(super) fn answer(&self, command: &str) -> Option<Option<Vec<u8>>> {
+        let mut state = self.lock();
+        state.commands.push(command.to_string());
+        if !state.plugged {
+            return Some(Some(DEVICE_NOT_FOUND.to_vec()));
+        }
+        let rule = state.rules.iter_mut().find(|rule| rule.matches(command))?;
+        let reply = if rule.replies.len() > 1 {
+            rule.replies.pop_front()
+        } else {
+            rule.replies.front().cloned()
+        }?;
+        drop(state);
+        let Some(reply) = reply else {
+            return Some(None);
+        };
+        Some(Some(match reply {
+            Reply::Output(output) => output,
+            Reply::File(contents) => send_file(command, &contents),
+        }))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DeviceState> {
+        self.inner.lock().unwrap()
+    }
+}
+
+/// Write `contents` to the local path of the `file recv` `command`
+fn send_file(command: &str, contents: &[u8]) -> Vec<u8> {
+    let local = command
+        .strip_prefix("file recv ")
+        .and_then(|paths| paths.rsplit_once(' '))
+        .map(|(_, local)| local);
+    let Some(local) = local else {
+        return b"[Fail]Not a file recv command".to_vec();
+    };
+    match std::fs::write(local, contents) {
+        Ok(()) => format!("FileTransfer finish, Size:{}", contents.len()).into_bytes(),
+        Err(e) => format!("[Fail]Error opening file: {}", e).into_bytes(),
+    }
+}
+
+/// Reply being programmed for one command, see [`FakeDevice::when`]
+#[must_use = "the rule is only added by reply, replies, sends_file, sends_files or hang"]
+pub struct When {
+    device: FakeDevice,
+    command: String,
+    prefix: bool,
+}
+
+impl When {
+    /// Answer with `output` every time
+    pub fn reply(self, output: impl Into<Vec<u8>>) -> FakeDevice {
+        self.rule([Some(Reply::Output(output.into()))])
+    }
+
+    /// Answer with each output in turn, repeating the last one
+    pub fn replies<O: Into<Vec<u8>>>(self, outputs: impl IntoIterator<Item = O>) -> FakeDevice {
+        self.rule(
+            outputs
+                .into_iter()
+                .map(|output| Some(Reply::Output(output.into()))),
+        )
+    }
+
+    /// Answer a `file recv` by writing `contents` to its local path
+    pub fn sends_file(self, contents: impl Into<Vec<u8>>) -> FakeDevice {
+        self.rule([Some(Reply::File(contents.into()))])
+    }
+
+    /// Answer each `file recv` with the next file, repeating the last one
+    pub fn sends_files<C: Into<Vec<u8>>>(self, files: impl IntoIterator<Item = C>) -> FakeDevice {
+        self.rule(
+            files
+                .into_iter()
+                .map(|contents| Some(Reply::File(contents.into()))),
+        )
+    }
+
+    /// Never answer, e.g. to make the client time out
+    pub fn hang(self) -> FakeDevice {
+        self.rule([None])
+    }
+
+    fn rule(self, replies: impl IntoIterator<Item = Option<Reply>>) -> FakeDevice {
+        let replies: VecDeque<_> = replies.into_iter().collect();
+        if !replies.is_empty() {
+            let mut state = self.device.lock();
+            state
+                .rules
+                .retain(|rule| rule.command != self.command || rule.prefix != self.prefix);
+            state.rules.push(Rule {
+                command: self.command,
+                prefix: self.prefix,
+                replies,
+            });
+        }
+        self.device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::MockServer;
+    use crate::{ErrorKind, HdcClient, InstallOptions};
+
+    #[tokio::test]
+    async fn test_fake_device() {
+        let phone = FakeDevice::new("dev-1")
+            .model("NOH-AN00")
+            .when("param get const.product.model")
+            .reply("NOH-AN00\n")
+            .when("cat /sys/class/power_supply/Battery/capacity")
+            .replies(["80\n", "79\n"])
+            .when_command("install -r app.hap")
+            .reply("[Info]App install path:app.hap msg:install bundle successfully.\n");
+        let server = MockServer::new()
+            .device(phone.clone())
+            .device(FakeDevice::new("dev-2").connection_type("TCP"))
+            .spawn_in_memory();
+
+        let client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let targets = client.clone().list_targets_verbose().await.unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].model, "NOH-AN00");
+        assert_eq!(targets[1].connection_type, "TCP");
+
+        let mut device = client.device("dev-1");
+        let battery = "cat /sys/class/power_supply/Battery/capacity";
+        assert_eq!(device.shell(battery).await.unwrap(), "80\n");
+        assert_eq!(device.shell(battery).await.unwrap(), "79\n");
+        assert_eq!(device.shell(battery).await.unwrap(), "79\n");
+        let installed = device
+            .install(&["app.hap"], InstallOptions::new().replace(true))
+            .await
+            .unwrap();
+        assert!(installed.contains("successfully"));
+        assert!(device.shell("reboot").await.is_err());
+        assert_eq!(
+            phone.commands(),
+            [
+                format!("shell {}", battery),
+                format!("shell {}", battery),
+                format!("shell {}", battery),
+                "install -r app.hap".to_string(),
+                "shell reboot".to_string(),
+            ]
+        );
+
+        phone.unplug();
+        let err = device.shell(battery).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceNotFound);
+        let err = client.device("dev-3").shell("ls").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceNotFound);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_monitor_sees_unplug() {
+        let phone = FakeDevice::new("dev-1");
+        let server = MockServer::new().device(phone.clone()).spawn_in_memory();
+
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let mut seen = Vec::new();
+        client
+            .monitor_devices(Duration::from_secs(1), |devices| {
+                seen.push(devices.to_vec());
+                phone.unplug();
+                !devices.is_empty()
+            })
+            .await
+            .unwrap();
+        assert_eq!(seen, [vec!["dev-1".to_string()], vec![]]);
+    }
+}
@@ -0,0 +1,434 @@
+//! Helpers for testing code built on this crate (requires the `testing` feature)
+//!
+//! Build wire frames by hand, or run a [`MockServer`] that performs the
+//! channel handshake and answers commands with canned responses, so code
+//! using [`HdcClient`](crate::HdcClient) can be unit tested without a device
+//! or an hdc server.
+//!
+//! [`MockServer::spawn_in_memory`] serves over in-process pipes instead of
+//! a loopback port. Nothing then waits on the OS, so tests can run on a
+//! paused tokio clock (`#[tokio::test(start_paused = true)]`) and exercise
+//! timeouts and retries without sleeping in real time.
+//!
+//! A [`FakeDevice`] adds a device whose shell commands are programmed per
+//! test, for high-level tests of install flows, monitors and parsers.
+//!
+//! A [`Transcript`] scripts the exact packets of one hdc server version, for
+//! compatibility tests against servers that are not at hand; the crate's
+//! own fixtures are in `tests/fixtures/transcripts`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::testing::MockServer;
+//! use hdc_rs::HdcClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let server = MockServer::new()
+//!     .respond("list targets", "device-1\ndevice-2\n")
+//!     .respond("shell echo hi", "hi\n")
+//!     .spawn()
+//!     .await?;
+//!
+//! let mut client = HdcClient::connect(server.address()).await?;
+//! assert_eq!(client.list_targets().await?, vec!["device-1", "device-2"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::error::Result;
+use crate::protocol::{ChannelHandShake, HdcChannelCodec, HdcCommand};
+use crate::transport::{HdcStream, ServerAddr};
+
+mod device;
+mod transcript;
+
+pub use device::{FakeDevice, When};
+pub use transcript::{RunningTranscript, Session, Step, Transcript};
+
+/// Version string sent by [`handshake`] frames
+pub const MOCK_SERVER_VERSION: &str = "Ver: 3.1.0e";
+
+/// Length-prefix `data` as one channel packet
+pub fn packet(data: &[u8]) -> Vec<u8> {
+    let mut frame = (data.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// Payload prefixed with a command code (not length-prefixed)
+pub fn command_payload(command: HdcCommand, payload: &[u8]) -> Vec<u8> {
+    let mut data = command.as_u16().to_le_bytes().to_vec();
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Length-prefixed packet carrying a command code and payload
+pub fn command_packet(command: HdcCommand, payload: &[u8]) -> Vec<u8> {
+    packet(&command_payload(command, payload))
+}
+
+/// Server handshake as sent on a new channel (108 bytes, with version)
+pub fn handshake(channel_id: u32) -> ChannelHandShake {
+    let mut hs = ChannelHandShake::default();
+    hs.banner[..8].copy_from_slice(crate::protocol::HANDSHAKE_BANNER);
+    hs.set_channel_id(channel_id);
+    hs.set_version(MOCK_SERVER_VERSION);
+    hs
+}
+
+/// Length-prefixed full server handshake
+pub fn handshake_frame(channel_id: u32) -> Vec<u8> {
+    packet(&handshake(channel_id).to_bytes())
+}
+
+/// Length-prefixed legacy server handshake without version (44 bytes)
+pub fn short_handshake_frame(channel_id: u32) -> Vec<u8> {
+    packet(&handshake(channel_id).to_bytes_without_version())
+}
+
+/// Length-prefixed handshake with a wrong banner, rejected by clients
+pub fn invalid_handshake_frame() -> Vec<u8> {
+    let mut hs = handshake(1);
+    hs.banner[..8].copy_from_slice(b"NOT HDC!");
+    packet(&hs.to_bytes())
+}
+
+/// Length-prefixed channel close packet
+pub fn channel_close_frame(reason: &str) -> Vec<u8> {
+    command_packet(HdcCommand::KernelChannelClose, reason.as_bytes())
+}
+
+/// Server side of one accepted channel
+pub struct MockConnection<S = TcpStream> {
+    stream: S,
+    codec: HdcChannelCodec,
+    connect_key: String,
+}
+
+impl MockConnection {
+    /// Accept a client on `listener` and complete the handshake
+    pub async fn accept(listener: &TcpListener) -> Result<Self> {
+        let (stream, _) = listener.accept().await?;
+        Self::handshake(stream, 1).await
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> MockConnection<S> {
+    /// Complete the server side of the handshake on `stream`
+    pub async fn handshake(mut stream: S, channel_id: u32) -> Result<Self> {
+        let mut codec = HdcChannelCodec::new();
+        codec
+            .write_packet(&mut stream, &handshake(channel_id).to_bytes())
+            .await?;
+        let reply = ChannelHandShake::from_bytes(&codec.read_packet(&mut stream).await?)?;
+        Ok(Self {
+            stream,
+            codec,
+            connect_key: reply.get_connect_key(),
+        })
+    }
+
+    /// Connect key the client sent (empty for server-level channels)
+    pub fn connect_key(&self) -> &str {
+        &self.connect_key
+    }
+
+    /// Read the next packet from the client
+    pub async fn read_packet(&mut self) -> Result<Vec<u8>> {
+        Ok(self.codec.read_packet(&mut self.stream).await?.to_vec())
+    }
+
+    /// Read the next command string from the client
+    pub async fn read_command(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.read_packet().await?).into_owned())
+    }
+
+    /// Send one packet
+    pub async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.codec.write_packet(&mut self.stream, data).await
+    }
+
+    /// Send one packet prefixed with a command code
+    pub async fn send_command(&mut self, command: HdcCommand, payload: &[u8]) -> Result<()> {
+        self.send(&command_payload(command, payload)).await
+    }
+
+    /// Take the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// A local server answering commands with canned responses
+///
+/// Every connection gets the handshake, then each command packet is answered
+/// with one packet: the configured response, or `[Fail]Unknown command` if
+/// none matches. Commands registered with [`ignore`](Self::ignore) get no
+/// answer at all. Heartbeats are echoed, keepalive requests ignored and a
+/// channel-close packet ends the connection.
+///
+/// With [`device`](Self::device)s added, `list targets` lists them and
+/// channels opened with their connect key are answered by their rules
+/// first. Channels for other connect keys fail like on a real server.
+#[derive(Debug, Clone, Default)]
+pub struct MockServer {
+    /// Reply per command, `None` for commands left unanswered
+    responses: HashMap<String, Option<Vec<u8>>>,
+    devices: Vec<FakeDevice>,
+}
+
+impl MockServer {
+    /// Create a server without canned responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `command` (e.g. `"shell ls"`) with `response`
+    pub fn respond(mut self, command: impl Into<String>, response: impl Into<Vec<u8>>) -> Self {
+        self.responses.insert(command.into(), Some(response.into()));
+        self
+    }
+
+    /// Never answer `command`, e.g. to make the client time out
+    pub fn ignore(mut self, command: impl Into<String>) -> Self {
+        self.responses.insert(command.into(), None);
+        self
+    }
+
+    /// Attach `device`; keep a clone to change it while serving
+    pub fn device(mut self, device: FakeDevice) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    /// Bind a loopback port and start serving in the background
+    pub async fn spawn(self) -> Result<RunningMockServer> {
+        let server = Arc::new(self);
+        spawn_tcp(move |stream, channel_id| serve(stream, channel_id, server.clone())).await
+    }
+
+    /// Start serving over in-process pipes instead of a port
+    ///
+    /// Connect with [`RunningMockServer::server_addr`]. Unlike sockets the
+    /// pipes never leave the runtime idle while data is in flight, so the
+    /// server also works when the tokio clock is paused.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use hdc_rs::testing::MockServer;
+    /// # use hdc_rs::{HdcClient, Timeouts};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// // In a #[tokio::test(start_paused = true)] test
+    /// let server = MockServer::new().ignore("shell sleep 60").spawn_in_memory();
+    /// let mut client = HdcClient::connect(server.server_addr()).await?;
+    /// client.set_timeouts(Timeouts::default().shell(Duration::from_secs(30)));
+    /// // Fails after 30s of virtual time, without waiting in real time
+    /// assert!(client.shell("sleep 60").await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_in_memory(self) -> RunningMockServer {
+        let server = Arc::new(self);
+        spawn_memory(move |stream, channel_id| serve(stream, channel_id, server.clone()))
+    }
+
+    /// Reply to `command` on a channel for `connect_key`
+    ///
+    /// `None` if nothing matches; `Some(None)` if the command goes
+    /// unanswered.
+    fn answer(&self, connect_key: &str, command: &str) -> Option<Option<Vec<u8>>> {
+        if !connect_key.is_empty() && !self.devices.is_empty() {
+            let device = self
+                .devices
+                .iter()
+                .find(|device| device.connect_key() == connect_key);
+            let Some(device) = device else {
+                return Some(Some(device::DEVICE_NOT_FOUND.to_vec()));
+            };
+            if let Some(reply) = device.answer(command) {
+                return Some(reply);
+            }
+        }
+        if let Some(reply) = self.responses.get(command) {
+            return Some(reply.clone());
+        }
+        let verbose = match command {
+            "list targets" => false,
+            "list targets -v" => true,
+            _ => return None,
+        };
+        if self.devices.is_empty() {
+            return None;
+        }
+        let targets: String = self
+            .devices
+            .iter()
+            .filter_map(|device| device.target_line(verbose))
+            .collect();
+        Some(Some(if targets.is_empty() {
+            b"[Empty]".to_vec()
+        } else {
+            targets.into_bytes()
+        }))
+    }
+}
+
+/// Bind a loopback port and run `serve` on every connection
+///
+/// Channel ids count up from 1 in the order connections arrive.
+async fn spawn_tcp<F, Fut>(serve: F) -> Result<RunningMockServer>
+where
+    F: Fn(HdcStream, u32) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?.to_string();
+    let task = tokio::spawn(async move {
+        let mut next_channel = 1;
+        while let Ok((stream, _)) = listener.accept().await {
+            spawn_connection(serve(HdcStream::Tcp(stream), next_channel));
+            next_channel += 1;
+        }
+    });
+    Ok(RunningMockServer {
+        addr: ServerAddr::Tcp(address),
+        task,
+    })
+}
+
+/// [`spawn_tcp`] over in-process pipes
+fn spawn_memory<F, Fut>(serve: F) -> RunningMockServer
+where
+    F: Fn(HdcStream, u32) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let (listener, mut connections) = mpsc::unbounded_channel();
+    let task = tokio::spawn(async move {
+        let mut next_channel = 1;
+        while let Some(stream) = connections.recv().await {
+            spawn_connection(serve(HdcStream::Memory(stream), next_channel));
+            next_channel += 1;
+        }
+    });
+    RunningMockServer {
+        addr: ServerAddr::Memory(listener),
+        task,
+    }
+}
+
+fn spawn_connection(connection: impl Future<Output = Result<()>> + Send + 'static) {
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            debug!("Mock connection ended: {}", e);
+        }
+    });
+}
+
+async fn serve(stream: HdcStream, channel_id: u32, server: Arc<MockServer>) -> Result<()> {
+    let mut conn = MockConnection::handshake(stream, channel_id).await?;
+    let close = HdcCommand::KernelChannelClose.as_u16().to_le_bytes();
+    let keepalive = HdcCommand::KernelEnableKeepalive.as_u16().to_le_bytes();
+    let heartbeat = HdcCommand::HeartbeatMsg.as_u16().to_le_bytes();
+    loop {
+        let packet = conn.read_packet().await?;
+        if packet.starts_with(&close) {
+            return Ok(());
+        }
+        if packet == keepalive {
+            continue;
+        }
+        if packet == heartbeat {
+            conn.send(&heartbeat).await?;
+            continue;
+        }
+        let command = String::from_utf8_lossy(&packet);
+        match server.answer(conn.connect_key(), &command) {
+            Some(Some(response)) => conn.send(&response).await?,
+            Some(None) => debug!("Ignoring command: {}", command),
+            None => conn.send(b"[Fail]Unknown command").await?,
+        }
+    }
+}
+
+/// Handle to a [`MockServer`] serving in the background; stops on drop
+#[derive(Debug)]
+pub struct RunningMockServer {
+    addr: ServerAddr,
+    task: JoinHandle<()>,
+}
+
+impl RunningMockServer {
+    /// Address to pass to [`HdcClient::connect`](crate::HdcClient::connect)
+    ///
+    /// Empty for servers started with
+    /// [`spawn_in_memory`](MockServer::spawn_in_memory).
+    pub fn address(&self) -> &str {
+        self.addr.host_port()
+    }
+
+    /// Address of the server, including in-memory ones
+    pub fn server_addr(&self) -> ServerAddr {
+        self.addr.clone()
+    }
+}
+
+impl Drop for RunningMockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HdcClient;
+
+    #[test]
+    fn test_frames() {
+        assert_eq!(packet(b"ab"), vec![0, 0, 0, 2, b'a', b'b']);
+        assert_eq!(handshake_frame(1).len(), 4 + ChannelHandShake::SIZE);
+        assert_eq!(
+            short_handshake_frame(1).len(),
+            4 + ChannelHandShake::SIZE_WITHOUT_VERSION
+        );
+
+        let frame = invalid_handshake_frame();
+        let hs = ChannelHandShake::from_bytes(&frame[4..]).unwrap();
+        assert!(hs.verify_banner().is_err());
+
+        let close = channel_close_frame("bye");
+        assert_eq!(&close[4..6], &2u16.to_le_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::new()
+            .respond("list targets", "device-1\ndevice-2\n")
+            .spawn()
+            .await
+            .unwrap();
+
+        let mut client = HdcClient::connect(server.address()).await.unwrap();
+        assert_eq!(
+            client.list_targets().await.unwrap(),
+            vec!["device-1", "device-2"]
+        );
+        assert!(matches!(
+            client.check_server().await,
+            Err(crate::HdcError::Remote { code: None, ref message }) if message == "Unknown command"
+        ));
+    }
+}
@@ -0,0 +1,521 @@
+//! Golden transcripts of hdc server sessions
+//!
+//! A transcript pins down the packets one hdc server version exchanges with
+//! the client, so client changes can be checked against servers that are
+//! not at hand. [`Transcript::spawn_in_memory`] plays the server side and
+//! notes every packet that differs from the script;
+//! [`RunningTranscript::finish`] reports them.
+//!
+//! ```text
+//! # hdc 3.1.0e
+//! version Ver: 3.1.0e
+//! huge-buffers
+//!
+//! connect
+//! > list targets
+//! < FMR0223C13000649\n
+//!
+//! connect FMR0223C13000649
+//! > shell echo hi
+//! < hi\n
+//! ```
+//!
+//! | Line | Meaning |
+//! |---|---|
+//! | `version <text>` | Version in a 108-byte handshake; without it the server sends the 44-byte legacy handshake |
+//! | `huge-buffers` | Advertise packets up to 511 KiB |
+//! | `connect [key]` | Next channel, opened with this connect key (none for server channels) |
+//! | `> <text>` | Packet the client must send |
+//! | `< <text>` | Packet the server sends |
+//! | `>@ <Command> [text]`, `<@ ...` | Packet prefixed with an [`HdcCommand`] code, e.g. `>@ KernelEnableKeepalive` |
+//! | `close` | Server closes the channel |
+//!
+//! Text may use `\n`, `\r`, `\t`, `\\` and `\xNN` escapes. Blank lines and
+//! lines starting with `#` are ignored. Connections are matched to
+//! `connect` sections in the order the client opens them.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use super::{command_payload, spawn_memory, spawn_tcp, RunningMockServer};
+use crate::error::{HdcError, Result};
+use crate::protocol::{ChannelHandShake, HdcChannelCodec, HdcCommand};
+use crate::transport::{HdcStream, ServerAddr};
+
+/// Time [`RunningTranscript::finish`] waits for connections still running
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One packet or action of a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Packet the client must send
+    Expect(Vec<u8>),
+    /// Packet the server sends
+    Send(Vec<u8>),
+    /// Server closes the channel
+    Close,
+}
+
+/// Script for one channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    /// Connect key the client must send in its handshake
+    pub connect_key: String,
+    /// Steps in order
+    pub steps: Vec<Step>,
+}
+
+/// Known exchange with one hdc server version
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    /// Version string in the handshake, `None` for the 44-byte handshake
+    pub version: Option<String>,
+    /// Whether the server advertises huge buffers
+    pub huge_buffers: bool,
+    /// Channels in the order the client opens them
+    pub sessions: Vec<Session>,
+}
+
+impl Transcript {
+    /// Read a transcript file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse transcript text, see the [module docs](self) for the format
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut transcript = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let invalid = |message: String| {
+                HdcError::Config(format!("Transcript line {}: {}", index + 1, message))
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let step = match keyword {
+                "version" => {
+                    transcript.version = Some(rest.to_string());
+                    continue;
+                }
+                "huge-buffers" => {
+                    transcript.huge_buffers = true;
+                    continue;
+                }
+                "connect" => {
+                    transcript.sessions.push(Session {
+                        connect_key: rest.to_string(),
+                        steps: Vec::new(),
+                    });
+                    continue;
+                }
+                ">" => Step::Expect(unescape(rest).map_err(invalid)?),
+                "<" => Step::Send(unescape(rest).map_err(invalid)?),
+                ">@" => Step::Expect(command_packet(rest).map_err(invalid)?),
+                "<@" => Step::Send(command_packet(rest).map_err(invalid)?),
+                "close" => Step::Close,
+                _ => return Err(invalid(format!("unknown keyword {:?}", keyword))),
+            };
+            transcript
+                .sessions
+                .last_mut()
+                .ok_or_else(|| invalid("packet before the first `connect`".to_string()))?
+                .steps
+                .push(step);
+        }
+        Ok(transcript)
+    }
+
+    /// Play the server side on a loopback port
+    pub async fn spawn(self) -> Result<RunningTranscript> {
+        let shared = Shared::new(self);
+        let player = shared.clone();
+        let server = spawn_tcp(move |stream, id| play(player.clone(), stream, id)).await?;
+        Ok(RunningTranscript { server, shared })
+    }
+
+    /// Play the server side over in-process pipes
+    ///
+    /// Works on a paused tokio clock, see
+    /// [`MockServer::spawn_in_memory`](super::MockServer::spawn_in_memory).
+    pub fn spawn_in_memory(self) -> RunningTranscript {
+        let shared = Shared::new(self);
+        let player = shared.clone();
+        let server = spawn_memory(move |stream, id| play(player.clone(), stream, id));
+        RunningTranscript { server, shared }
+    }
+
+    /// Handshake the server opens channel `channel_id` with
+    fn handshake(&self, channel_id: u32) -> Vec<u8> {
+        let mut hs = super::handshake(channel_id);
+        hs.set_huge_buf(self.huge_buffers);
+        match &self.version {
+            Some(version) => {
+                hs.set_version(version);
+                hs.to_bytes()
+            }
+            None => hs.to_bytes_without_version(),
+        }
+    }
+}
+
+/// Handle to a [`Transcript`] being played; stops on drop
+pub struct RunningTranscript {
+    server: RunningMockServer,
+    shared: Arc<Shared>,
+}
+
+impl RunningTranscript {
+    /// Address to connect to; empty for in-memory transcripts
+    pub fn address(&self) -> &str {
+        self.server.address()
+    }
+
+    /// Address of the server, including in-memory ones
+    pub fn server_addr(&self) -> ServerAddr {
+        self.server.server_addr()
+    }
+
+    /// Wait for the script to complete and report any differences
+    ///
+    /// Fails with [`HdcError::Protocol`] listing unexpected packets,
+    /// connections and sessions the client never finished.
+    pub async fn finish(self) -> Result<()> {
+        let settled = async {
+            loop {
+                let changed = self.shared.changed.notified();
+                if self.shared.progress.lock().unwrap().settled() {
+                    return;
+                }
+                changed.await;
+            }
+        };
+        let _ = tokio::time::timeout(FINISH_TIMEOUT, settled).await;
+
+        let progress = self.shared.progress.lock().unwrap();
+        let mut problems = progress.errors.clone();
+        for (index, done) in progress.completed.iter().enumerate() {
+            if !done {
+                problems.push(format!("connection {} did not complete", index + 1));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(HdcError::Protocol(format!(
+                "Transcript mismatch: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+}
+
+struct Shared {
+    transcript: Transcript,
+    progress: Mutex<Progress>,
+    changed: Notify,
+}
+
+struct Progress {
+    /// Whether each session ran to its end
+    completed: Vec<bool>,
+    errors: Vec<String>,
+}
+
+impl Progress {
+    fn settled(&self) -> bool {
+        !self.errors.is_empty() || self.completed.iter().all(|done| *done)
+    }
+}
+
+impl Shared {
+    fn new(transcript: Transcript) -> Arc<Self> {
+        Arc::new(Self {
+            progress: Mutex::new(Progress {
+                completed: vec![false; transcript.sessions.len()],
+                errors: Vec::new(),
+            }),
+            transcript,
+            changed: Notify::new(),
+        })
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Progress)) {
+        update(&mut self.progress.lock().unwrap());
+        self.changed.notify_waiters();
+    }
+}
+
+async fn play(shared: Arc<Shared>, mut stream: HdcStream, channel_id: u32) -> Result<()> {
+    let index = channel_id as usize - 1;
+    let mut codec = HdcChannelCodec::new();
+    let outcome = match shared.transcript.sessions.get(index) {
+        Some(session) => {
+            play_session(
+                &shared.transcript,
+                session,
+                &mut codec,
+                &mut stream,
+                channel_id,
+            )
+            .await
+        }
+        None => Err("unexpected connection".to_string()),
+    };
+    let closed = match outcome {
+        Ok(closed) => {
+            shared.update(|progress| progress.completed[index] = true);
+            closed
+        }
+        Err(e) => {
+            shared.update(|progress| {
+                progress
+                    .errors
+                    .push(format!("connection {}: {}", channel_id, e))
+            });
+            true
+        }
+    };
+    if closed {
+        return Ok(());
+    }
+
+    // Anything after the script but a channel close is unexpected
+    let close = HdcCommand::KernelChannelClose.as_u16().to_le_bytes();
+    while let Ok(packet) = codec.read_packet(&mut stream).await {
+        if packet.starts_with(&close) {
+            break;
+        }
+        shared.update(|progress| {
+            progress.errors.push(format!(
+                "connection {}: unexpected packet \"{}\" after the script",
+                channel_id,
+                escape(&packet)
+            ))
+        });
+    }
+    Ok(())
+}
+
+/// Run the steps of `session`; returns whether the script closed the channel
+async fn play_session(
+    transcript: &Transcript,
+    session: &Session,
+    codec: &mut HdcChannelCodec,
+    stream: &mut HdcStream,
+    channel_id: u32,
+) -> std::result::Result<bool, String> {
+    let io = |e: HdcError| e.to_string();
+    codec
+        .write_packet(stream, &transcript.handshake(channel_id))
+        .await
+        .map_err(io)?;
+    let reply = codec.read_packet(stream).await.map_err(io)?;
+    let connect_key = ChannelHandShake::from_bytes(&reply)
+        .map_err(io)?
+        .get_connect_key();
+    if connect_key != session.connect_key {
+        return Err(format!(
+            "connect key {:?}, expected {:?}",
+            connect_key, session.connect_key
+        ));
+    }
+
+    for (number, step) in session.steps.iter().enumerate() {
+        match step {
+            Step::Expect(expected) => {
+                let packet = codec
+                    .read_packet(stream)
+                    .await
+                    .map_err(|e| format!("step {}: {}", number + 1, e))?;
+                if packet[..] != expected[..] {
+                    return Err(format!(
+                        "step {}: got \"{}\", expected \"{}\"",
+                        number + 1,
+                        escape(&packet),
+                        escape(expected)
+                    ));
+                }
+            }
+            Step::Send(data) => codec.write_packet(stream, data).await.map_err(io)?,
+            Step::Close => return Ok(true),
+        }
+    }
+    Ok(false)
+}
+
+/// Packet for `<Command> [text]`
+fn command_packet(spec: &str) -> std::result::Result<Vec<u8>, String> {
+    let (name, payload) = spec.split_once(' ').unwrap_or((spec, ""));
+    let command = (0..=u16::MAX)
+        .filter_map(HdcCommand::from_u16)
+        .find(|command| format!("{:?}", command) == name)
+        .ok_or_else(|| format!("unknown command {:?}", name))?;
+    Ok(command_payload(command, &unescape(payload)?))
+}
+
+fn unescape(text: &str) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            out.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'x') => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                let value = std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|hex| hex.len() == 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| "invalid \\x escape".to_string())?;
+                out.push(value);
+            }
+            Some(other) => return Err(format!("unknown escape \\{}", char::from(other))),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// Inverse of [`unescape`], for messages
+fn escape(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len());
+    for &byte in data {
+        match byte {
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b'\\' => out.push_str("\\\\"),
+            b' '..=b'~' => out.push(char::from(byte)),
+            _ => out.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileTransferOptions;
+    use crate::hilog::HilogFilter;
+    use crate::HdcClient;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/transcripts");
+
+    #[test]
+    fn test_parse() {
+        let transcript = Transcript::parse(
+            "# comment\nversion Ver: 3.1.0e\n\nconnect\n> list targets\n< a\\tb\\n\n\
+             connect dev-1\n>@ KernelEnableKeepalive\n<@ KernelChannelClose \\x01\nclose\n",
+        )
+        .unwrap();
+        assert_eq!(transcript.version.as_deref(), Some("Ver: 3.1.0e"));
+        assert!(!transcript.huge_buffers);
+        assert_eq!(
+            transcript.sessions,
+            vec![
+                Session {
+                    connect_key: String::new(),
+                    steps: vec![
+                        Step::Expect(b"list targets".to_vec()),
+                        Step::Send(b"a\tb\n".to_vec()),
+                    ],
+                },
+                Session {
+                    connect_key: "dev-1".to_string(),
+                    steps: vec![
+                        Step::Expect(command_payload(HdcCommand::KernelEnableKeepalive, b"")),
+                        Step::Send(command_payload(HdcCommand::KernelChannelClose, &[1])),
+                        Step::Close,
+                    ],
+                },
+            ]
+        );
+
+        for bad in [
+            "> before connect",
+            "connect\nsend x",
+            "connect\n< \\q",
+            "connect\n<@ Nope",
+        ] {
+            assert!(
+                matches!(Transcript::parse(bad), Err(HdcError::Config(_))),
+                "{}",
+                bad
+            );
+        }
+        assert_eq!(escape(b"a\n\x01"), "a\\n\\x01");
+    }
+
+    #[tokio::test]
+    async fn test_reports_mismatch() {
+        let transcript = Transcript::parse("connect\n> list targets\n< dev-1\n").unwrap();
+        let server = transcript.spawn_in_memory();
+        let mut client = HdcClient::connect(server.server_addr()).await.unwrap();
+        let _ = client.check_server().await;
+        let err = server.finish().await.unwrap_err().to_string();
+        assert!(
+            err.contains("step 1: got \"checkserver\", expected \"list targets\""),
+            "{}",
+            err
+        );
+    }
+
+    /// Commands every supported server version must understand
+    async fn run_client(addr: ServerAddr) -> Result<()> {
+        let mut client = HdcClient::connect(addr).await?;
+        let devices = client.list_targets_verbose().await?;
+        assert_eq!(devices[0].connect_key, "FMR0223C13000649");
+
+        client.connect_device("FMR0223C13000649").await?;
+        assert_eq!(client.shell("echo hi").await?, "hi\n");
+
+        let compressed = FileTransferOptions::new().compress(true);
+        client
+            .file_send("Cargo.toml", "/data/local/tmp/Cargo.toml", compressed)
+            .await?;
+
+        client.connect_device("FMR0223C13000649").await?;
+        let mut lines = Vec::new();
+        client
+            .hilog_stream_filtered(HilogFilter::new(), |chunk| {
+                lines.push(chunk.to_string());
+                false
+            })
+            .await?;
+        assert_eq!(lines.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_compatibility() {
+        let mut fixtures: Vec<_> = std::fs::read_dir(FIXTURES)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        fixtures.sort();
+        assert!(fixtures.len() >= 3);
+
+        for path in fixtures {
+            let server = Transcript::load(&path).unwrap().spawn_in_memory();
+            let result = run_client(server.server_addr()).await;
+            let finished = server.finish().await;
+            assert!(
+                result.is_ok() && finished.is_ok(),
+                "{}: client {:?}, transcript {:?}",
+                path.display(),
+                result,
+                finished
+            );
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! System trace capture with `hitrace`
+//!
+//! [`HdcClient::capture_trace`](crate::HdcClient::capture_trace) records
+//! the selected [`TraceCategory`] values for a while and returns the trace
+//! in ftrace text format, which Perfetto and `chrome://tracing` open
+//! directly. Devices without `hitrace` fall back to the older `bytrace`,
+//! which takes the same arguments.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hdc_rs::trace::TraceCategory;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = hdc_rs::HdcClient::connect("127.0.0.1:8710").await?;
+//! client.connect_device("device-serial").await?;
+//! let trace = client
+//!     .capture_trace(
+//!         &[TraceCategory::Sched, TraceCategory::Freq, TraceCategory::Ability],
+//!         Duration::from_secs(5),
+//!     )
+//!     .await?;
+//! std::fs::write("launch.ftrace", trace)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::HdcError;
+
+/// Device directory traces are written to before being pulled
+pub const TRACE_DIR: &str = "/data/local/tmp";
+
+/// Kernel trace buffer size per CPU, in kilobytes
+pub const TRACE_BUFFER_KB: u32 = 20480;
+
+/// Trace tool on current devices
+pub const HITRACE: &str = "hitrace";
+/// Trace tool on devices before OpenHarmony 3.2
+pub const BYTRACE: &str = "bytrace";
+
+/// Tag accepted by `hitrace`, as listed by `hitrace -l`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TraceCategory {
+    /// `ability`: ability manager
+    Ability,
+    /// `ace`: ArkUI framework
+    Ace,
+    /// `app`: app-defined trace points
+    App,
+    /// `ark`: ArkTS runtime
+    Ark,
+    /// `binder`: binder kernel driver
+    Binder,
+    /// `disk`: disk I/O
+    Disk,
+    /// `dsoftbus`: distributed soft bus
+    Dsoftbus,
+    /// `freq`: CPU frequency
+    Freq,
+    /// `graphic`: graphics subsystem
+    Graphic,
+    /// `idle`: CPU idle states
+    Idle,
+    /// `irq`: interrupts
+    Irq,
+    /// `memory`: memory management
+    Memory,
+    /// `mmc`: eMMC commands
+    Mmc,
+    /// `multimodalinput`: input events
+    MultimodalInput,
+    /// `notification`: notification service
+    Notification,
+    /// `ohos`: OpenHarmony system services
+    Ohos,
+    /// `pagecache`: page cache
+    PageCache,
+    /// `rpc`: IPC and RPC calls
+    Rpc,
+    /// `sched`: CPU scheduling
+    Sched,
+    /// `sync`: synchronisation primitives
+    Sync,
+    /// `window`: window manager
+    Window,
+    /// `workq`: kernel work queues
+    Workq,
+    /// `zaudio`: audio
+    Audio,
+    /// `zcamera`: camera
+    Camera,
+    /// `zmedia`: media
+    Media,
+    /// Any other tag, passed through as is
+    Other(String),
+}
+
+impl TraceCategory {
+    /// Tag as passed to `hitrace`
+    pub fn as_str(&self) -> &str {
+        match self {
+            TraceCategory::Ability => "ability",
+            TraceCategory::Ace => "ace",
+            TraceCategory::App => "app",
+            TraceCategory::Ark => "ark",
+            TraceCategory::Binder => "binder",
+            TraceCategory::Disk => "disk",
+            TraceCategory::Dsoftbus => "dsoftbus",
+            TraceCategory::Freq => "freq",
+            TraceCategory::Graphic => "graphic",
+            TraceCategory::Idle => "idle",
+            TraceCategory::Irq => "irq",
+            TraceCategory::Memory => "memory",
+            TraceCategory::Mmc => "mmc",
+            TraceCategory::MultimodalInput => "multimodalinput",
+            TraceCategory::Notification => "notification",
+            TraceCategory::Ohos => "ohos",
+            TraceCategory::PageCache => "pagecache",
+            TraceCategory::Rpc => "rpc",
+            TraceCategory::Sched => "sched",
+            TraceCategory::Sync => "sync",
+            TraceCategory::Window => "window",
+            TraceCategory::Workq => "workq",
+            TraceCategory::Audio => "zaudio",
+            TraceCategory::Camera => "zcamera",
+            TraceCategory::Media => "zmedia",
+            TraceCategory::Other(tag) => tag,
+        }
+    }
+
+    /// Categories for a typical app performance trace
+    pub fn app_defaults() -> Vec<TraceCategory> {
+        vec![
+            TraceCategory::Ability,
+            TraceCategory::Ace,
+            TraceCategory::App,
+            TraceCategory::Ark,
+            TraceCategory::Graphic,
+            TraceCategory::Ohos,
+            TraceCategory::Sched,
+            TraceCategory::Freq,
+            TraceCategory::Idle,
+            TraceCategory::Window,
+        ]
+    }
+}
+
+impl fmt::Display for TraceCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TraceCategory {
+    type Err = HdcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tag = s.trim();
+        if tag.is_empty() || tag.contains(char::is_whitespace) {
+            return Err(HdcError::Config(format!("Invalid trace category: {:?}", s)));
+        }
+        Ok(match tag.to_ascii_lowercase().as_str() {
+            "ability" => TraceCategory::Ability,
+            "ace" => TraceCategory::Ace,
+            "app" => TraceCategory::App,
+            "ark" => TraceCategory::Ark,
+            "binder" => TraceCategory::Binder,
+            "disk" => TraceCategory::Disk,
+            "dsoftbus" => TraceCategory::Dsoftbus,
+            "freq" => TraceCategory::Freq,
+            "graphic" => TraceCategory::Graphic,
+            "idle" => TraceCategory::Idle,
+            "irq" => TraceCategory::Irq,
+            "memory" => TraceCategory::Memory,
+            "mmc" => TraceCategory::Mmc,
+            "multimodalinput" => TraceCategory::MultimodalInput,
+            "notification" => TraceCategory::Notification,
+            "ohos" => TraceCategory::Ohos,
+            "pagecache" => TraceCategory::PageCache,
+            "rpc" => TraceCategory::Rpc,
+            "sched" => TraceCategory::Sched,
+            "sync" => TraceCategory::Sync,
+            "window" => TraceCategory::Window,
+            "workq" => TraceCategory::Workq,
+            "zaudio" => TraceCategory::Audio,
+            "zcamera" => TraceCategory::Camera,
+            "zmedia" => TraceCategory::Media,
+            _ => TraceCategory::Other(tag.to_string()),
+        })
+    }
+}
+
+/// Command line recording `categories` for `duration` into `output`
+///
+/// The duration is rounded up to whole seconds, as the tools expect.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use hdc_rs::trace::{trace_command, TraceCategory, HITRACE};
+///
+/// let cmd = trace_command(
+///     HITRACE,
+///     &[TraceCategory::Sched, TraceCategory::Freq],
+///     Duration::from_millis(2500),
+///     "/data/local/tmp/t.ftrace",
+/// );
+/// assert_eq!(cmd, "hitrace -b 20480 -t 3 -o /data/local/tmp/t.ftrace sched freq");
+/// ```
+pub fn trace_command(
+    tool: &str,
+    categories: &[TraceCategory],
+    duration: Duration,
+    output: &str,
+) -> String {
+    let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    let mut cmd = format!(
+        "{} -b {} -t {} -o {}",
+        tool,
+        TRACE_BUFFER_KB,
+        seconds.max(1),
+        output
+    );
+    for category in categories {
+        cmd.push(' ');
+        cmd.push_str(category.as_str());
+    }
+    cmd
+}
+
+/// Whether the shell could not find the trace tool
+pub(crate) fn is_missing_tool(output: &str) -> bool {
+    output.contains("not found") || output.contains("No such file")
+}
+
+/// Whether trace tool output reports an error
+pub(crate) fn is_failure(output: &str) -> bool {
+    output.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.contains("fail") || line.starts_with("error")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_roundtrip() {
+        for category in TraceCategory::app_defaults()
+            .into_iter()
+            .chain([TraceCategory::Audio, TraceCategory::Other("hdf".into())])
+        {
+            assert_eq!(
+                category.as_str().parse::<TraceCategory>().unwrap(),
+                category
+            );
+        }
+        assert_eq!(
+            "SCHED".parse::<TraceCategory>().unwrap(),
+            TraceCategory::Sched
+        );
+        assert!("two tags".parse::<TraceCategory>().is_err());
+        assert!("".parse::<TraceCategory>().is_err());
+    }
+
+    #[test]
+    fn test_trace_command() {
+        assert_eq!(
+            trace_command(BYTRACE, &[], Duration::ZERO, "/tmp/t"),
+            "bytrace -b 20480 -t 1 -o /tmp/t"
+        );
+        assert!(is_missing_tool(
+            "/bin/sh: hitrace: inaccessible or not found\n"
+        ));
+        assert!(is_failure("error: tag(foo) is not supported\n"));
+        assert!(!is_failure("capture done\nTRACE_DUMP_SUCCESS\n"));
+    }
+}
@@ -0,0 +1,584 @@
+//! Server addresses and the byte streams that reach them
+//!
+//! [`ServerAddr`] selects how the client reaches the hdc server. Plain
+//! strings convert into [`ServerAddr::Tcp`], so existing code passing
+//! `"127.0.0.1:8710"` keeps working. Strings starting with `unix:` and paths
+//! select a Unix domain socket, which avoids TCP port collisions between
+//! users on shared hosts. With the `tls` feature the server can also be
+//! reached through a TLS-terminating tunnel.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::warn;
+
+use crate::error::{HdcError, Result};
+
+/// Address the hdc server listens on by default
+pub const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:8710";
+
+/// Host the hdc server listens on by default
+const DEFAULT_SERVER_HOST: &str = "127.0.0.1";
+
+/// Environment variable overriding the server port, as read by the official client
+pub const SERVER_PORT_ENV: &str = "OHOS_HDC_SERVER_PORT";
+
+/// Idle time before the OS starts TCP keepalive probes
+const TCP_KEEPALIVE_TIME: Duration = Duration::from_secs(60);
+
+/// Bytes buffered in each direction of an in-memory connection
+#[cfg(any(test, feature = "testing"))]
+const MEMORY_PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Time limits for connecting and for the commands that wait on the server
+///
+/// Limits are measured on the tokio clock, so tests running with paused
+/// time (`tokio::time::pause`) see them expire without waiting; the
+/// `testing` module has a mock server that works on a paused clock.
+///
+/// # Example
+/// ```no_run
+/// # use hdc_rs::{HdcClient, Timeouts};
+/// # use std::time::Duration;
+/// let mut client = HdcClient::new("127.0.0.1:8710");
+/// client.set_timeouts(
+///     Timeouts::default()
+///         .install(Duration::from_secs(120))
+///         .transfer(Duration::from_secs(300)),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Opening a connection, including TLS setup (default 10s)
+    pub connect: Duration,
+    /// Waiting for more output of one-shot reads such as `hilog` (default 5s)
+    pub read: Duration,
+    /// Waiting for shell command output (default 5s)
+    pub shell: Duration,
+    /// Waiting for progress during file transfers (default 60s)
+    pub transfer: Duration,
+    /// Waiting for progress during app installs (default 30s)
+    pub install: Duration,
+}
+
+impl Timeouts {
+    /// The same limit for every kind of wait
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            connect: timeout,
+            read: timeout,
+            shell: timeout,
+            transfer: timeout,
+            install: timeout,
+        }
+    }
+
+    /// Set the connect timeout
+    pub fn connect(mut self, timeout: Duration) -> Self {
+        self.connect = timeout;
+        self
+    }
+
+    /// Set the read timeout
+    pub fn read(mut self, timeout: Duration) -> Self {
+        self.read = timeout;
+        self
+    }
+
+    /// Set the shell timeout
+    pub fn shell(mut self, timeout: Duration) -> Self {
+        self.shell = timeout;
+        self
+    }
+
+    /// Set the file transfer timeout
+    pub fn transfer(mut self, timeout: Duration) -> Self {
+        self.transfer = timeout;
+        self
+    }
+
+    /// Set the install timeout
+    pub fn install(mut self, timeout: Duration) -> Self {
+        self.install = timeout;
+        self
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            read: Duration::from_secs(5),
+            shell: Duration::from_secs(5),
+            transfer: Duration::from_secs(60),
+            install: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Address of an hdc server
+#[derive(Debug, Clone)]
+pub enum ServerAddr {
+    /// Plain TCP, e.g. `127.0.0.1:8710`
+    Tcp(String),
+    /// Unix domain socket, e.g. `unix:/run/user/1000/hdc.sock`
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+    /// TCP wrapped in TLS (requires the `tls` feature)
+    #[cfg(feature = "tls")]
+    Tls {
+        /// `host:port` of the TLS endpoint
+        address: String,
+        /// Name the server certificate must be valid for
+        server_name: String,
+        /// TLS client configuration
+        config: std::sync::Arc<tokio_rustls::rustls::ClientConfig>,
+    },
+    /// Another address with every packet written to a capture, see
+    /// [`ServerAddr::record`]
+    Recording {
+        /// Address actually connected to
+        inner: Box<ServerAddr>,
+        /// Capture the packets go to
+        recorder: std::sync::Arc<crate::capture::Recorder>,
+    },
+    /// Recorded connections played back, see [`ServerAddr::replay`]
+    Replay(std::sync::Arc<crate::capture::Replayer>),
+    /// In-process pipes to a test server, see
+    /// [`MockServer::spawn_in_memory`](crate::testing::MockServer::spawn_in_memory)
+    #[cfg(any(test, feature = "testing"))]
+    Memory(tokio::sync::mpsc::UnboundedSender<tokio::io::DuplexStream>),
+}
+
+impl ServerAddr {
+    /// Prefix selecting a Unix domain socket in address strings
+    pub const UNIX_PREFIX: &'static str = "unix:";
+
+    /// Unix domain socket at `path`
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, ServerAddr};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = HdcClient::connect(ServerAddr::unix("/run/user/1000/hdc.sock")).await?;
+    /// // Equivalent:
+    /// let mut client = HdcClient::connect("unix:/run/user/1000/hdc.sock").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<std::path::PathBuf>) -> Self {
+        Self::Unix(path.into())
+    }
+
+    /// Server address resolved the way the official client does
+    ///
+    /// Uses the port in `OHOS_HDC_SERVER_PORT` if set, otherwise
+    /// [`DEFAULT_SERVER_ADDR`]. A set but invalid port is an error rather
+    /// than silently reaching a different server.
+    pub fn from_env() -> Result<Self> {
+        Self::resolve(std::env::var(SERVER_PORT_ENV).ok().as_deref())
+    }
+
+    /// Default address with an optional port override
+    fn resolve(port: Option<&str>) -> Result<Self> {
+        let Some(port) = port.map(str::trim).filter(|p| !p.is_empty()) else {
+            return Ok(Self::from(DEFAULT_SERVER_ADDR));
+        };
+        match port.parse::<u16>() {
+            Ok(port) if port > 0 => Ok(Self::Tcp(format!("{}:{}", DEFAULT_SERVER_HOST, port))),
+            _ => Err(HdcError::Config(format!(
+                "{} must be a port in 1-65535, got {:?}",
+                SERVER_PORT_ENV, port
+            ))),
+        }
+    }
+
+    /// Parse an address string: `unix:<path>` or `host:port`
+    pub fn parse(address: &str) -> Self {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix(Self::UNIX_PREFIX) {
+            return Self::Unix(path.into());
+        }
+        Self::Tcp(address.to_string())
+    }
+
+    /// TLS endpoint verified against the bundled Mozilla root certificates
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use hdc_rs::{HdcClient, ServerAddr};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let addr = ServerAddr::tls("hdc.example.com:8711", "hdc.example.com");
+    /// let mut client = HdcClient::connect(addr).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls(address: impl Into<String>, server_name: impl Into<String>) -> Self {
+        let roots = tokio_rustls::rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = tokio_rustls::rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self::tls_with_config(address, server_name, std::sync::Arc::new(config))
+    }
+
+    /// TLS endpoint with a custom client configuration (private CAs, client
+    /// certificates, ...)
+    #[cfg(feature = "tls")]
+    pub fn tls_with_config(
+        address: impl Into<String>,
+        server_name: impl Into<String>,
+        config: std::sync::Arc<tokio_rustls::rustls::ClientConfig>,
+    ) -> Self {
+        Self::Tls {
+            address: address.into(),
+            server_name: server_name.into(),
+            config,
+        }
+    }
+
+    /// The `host:port` part of the address (the path for Unix sockets)
+    pub fn host_port(&self) -> &str {
+        match self {
+            Self::Tcp(address) => address,
+            #[cfg(unix)]
+            Self::Unix(path) => path.to_str().unwrap_or_default(),
+            #[cfg(feature = "tls")]
+            Self::Tls { address, .. } => address,
+            Self::Recording { inner, .. } => inner.host_port(),
+            Self::Replay(_) => "",
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(_) => "",
+        }
+    }
+
+    /// Open a stream to the server
+    pub(crate) async fn connect(&self, connect_timeout: Duration) -> Result<HdcStream> {
+        match self {
+            Self::Tcp(address) => Ok(HdcStream::Tcp(connect_tcp(address, connect_timeout).await?)),
+            #[cfg(unix)]
+            Self::Unix(path) => {
+                let stream = timeout(connect_timeout, tokio::net::UnixStream::connect(path))
+                    .await
+                    .map_err(|_| HdcError::Timeout)??;
+                Ok(HdcStream::Unix(stream))
+            }
+            #[cfg(feature = "tls")]
+            Self::Tls {
+                address,
+                server_name,
+                config,
+            } => {
+                let name =
+                    tokio_rustls::rustls::pki_types::ServerName::try_from(server_name.clone())
+                        .map_err(|e| {
+                            HdcError::Protocol(format!("Invalid TLS server name: {}", e))
+                        })?;
+                let tcp = connect_tcp(address, connect_timeout).await?;
+                let connector = tokio_rustls::TlsConnector::from(config.clone());
+                let tls = timeout(connect_timeout, connector.connect(name, tcp))
+                    .await
+                    .map_err(|_| HdcError::Timeout)??;
+                Ok(HdcStream::Tls(Box::new(tls)))
+            }
+            Self::Recording { inner, recorder } => {
+                let stream = Box::pin(inner.connect(connect_timeout)).await?;
+                Ok(HdcStream::Recording(Box::new(recorder.wrap(stream))))
+            }
+            Self::Replay(replayer) => Ok(HdcStream::Replay(Box::new(replayer.connect()?))),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(listener) => {
+                let (client, server) = tokio::io::duplex(MEMORY_PIPE_CAPACITY);
+                listener.send(server).map_err(|_| {
+                    HdcError::Io(io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        "In-memory server stopped",
+                    ))
+                })?;
+                Ok(HdcStream::Memory(client))
+            }
+        }
+    }
+}
+
+/// Open a TCP connection with keepalive enabled
+async fn connect_tcp(address: &str, connect_timeout: Duration) -> Result<TcpStream> {
+    let tcp = timeout(connect_timeout, TcpStream::connect(address))
+        .await
+        .map_err(|_| HdcError::Timeout)?
+        .map_err(HdcError::Io)?;
+
+    // Let the OS detect dead peers during long silent streams
+    let keepalive = socket2::TcpKeepalive::new().with_time(TCP_KEEPALIVE_TIME);
+    if let Err(e) = socket2::SockRef::from(&tcp).set_tcp_keepalive(&keepalive) {
+        warn!("Failed to enable TCP keepalive: {}", e);
+    }
+    Ok(tcp)
+}
+
+impl fmt::Display for ServerAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => f.write_str(address),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "{}{}", Self::UNIX_PREFIX, path.display()),
+            #[cfg(feature = "tls")]
+            Self::Tls { address, .. } => write!(f, "tls://{}", address),
+            Self::Recording { inner, .. } => write!(f, "{} (recording)", inner),
+            Self::Replay(_) => f.write_str("replay"),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(_) => f.write_str("memory"),
+        }
+    }
+}
+
+impl From<&str> for ServerAddr {
+    fn from(address: &str) -> Self {
+        Self::parse(address)
+    }
+}
+
+impl From<String> for ServerAddr {
+    fn from(address: String) -> Self {
+        Self::parse(&address)
+    }
+}
+
+impl From<&String> for ServerAddr {
+    fn from(address: &String) -> Self {
+        Self::parse(address)
+    }
+}
+
+#[cfg(unix)]
+impl From<&std::path::Path> for ServerAddr {
+    fn from(path: &std::path::Path) -> Self {
+        Self::Unix(path.to_path_buf())
+    }
+}
+
+#[cfg(unix)]
+impl From<std::path::PathBuf> for ServerAddr {
+    fn from(path: std::path::PathBuf) -> Self {
+        Self::Unix(path)
+    }
+}
+
+impl From<&ServerAddr> for ServerAddr {
+    fn from(addr: &ServerAddr) -> Self {
+        addr.clone()
+    }
+}
+
+/// Byte stream to an hdc server
+#[derive(Debug)]
+pub enum HdcStream {
+    /// Plain TCP
+    Tcp(TcpStream),
+    /// Unix domain socket
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+    /// TLS over TCP
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::client::TlsStream<TcpStream>>),
+    /// USB bulk endpoints of a device (daemon sessions only)
+    #[cfg(feature = "usb")]
+    Usb(crate::usb::UsbStream),
+    /// Another stream whose packets are recorded
+    Recording(Box<crate::capture::RecordingStream>),
+    /// Recorded connection played back
+    Replay(Box<crate::capture::ReplayStream>),
+    /// In-process pipe to a test server
+    #[cfg(any(test, feature = "testing"))]
+    Memory(tokio::io::DuplexStream),
+}
+
+impl From<TcpStream> for HdcStream {
+    fn from(stream: TcpStream) -> Self {
+        Self::Tcp(stream)
+    }
+}
+
+#[cfg(feature = "usb")]
+impl From<crate::usb::UsbStream> for HdcStream {
+    fn from(stream: crate::usb::UsbStream) -> Self {
+        Self::Usb(stream)
+    }
+}
+
+impl AsyncRead for HdcStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            #[cfg(feature = "usb")]
+            Self::Usb(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Recording(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            Self::Replay(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for HdcStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            #[cfg(feature = "usb")]
+            Self::Usb(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Recording(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            Self::Replay(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            #[cfg(feature = "usb")]
+            Self::Usb(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Recording(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            Self::Replay(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            #[cfg(feature = "usb")]
+            Self::Usb(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Recording(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            Self::Replay(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Memory(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_port() {
+        assert_eq!(
+            ServerAddr::resolve(None).unwrap().host_port(),
+            DEFAULT_SERVER_ADDR
+        );
+        assert_eq!(
+            ServerAddr::resolve(Some("")).unwrap().host_port(),
+            DEFAULT_SERVER_ADDR
+        );
+        assert_eq!(
+            ServerAddr::resolve(Some("8711")).unwrap().host_port(),
+            "127.0.0.1:8711"
+        );
+        assert!(matches!(
+            ServerAddr::resolve(Some("0")),
+            Err(HdcError::Config(_))
+        ));
+        assert!(ServerAddr::resolve(Some("70000")).is_err());
+        assert!(ServerAddr::resolve(Some("http")).is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let addr = ServerAddr::from("127.0.0.1:8710");
+        assert_eq!(addr.host_port(), "127.0.0.1:8710");
+        assert_eq!(addr.to_string(), "127.0.0.1:8710");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_addr() {
+        let addr = ServerAddr::from("unix:/tmp/hdc.sock");
+        assert!(matches!(&addr, ServerAddr::Unix(p) if p == std::path::Path::new("/tmp/hdc.sock")));
+        assert_eq!(addr.to_string(), "unix:/tmp/hdc.sock");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_connect() {
+        let path = std::env::temp_dir().join(format!("hdc-rs-uds-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::io::AsyncWriteExt::write_all(&mut stream, b"hi")
+                .await
+                .unwrap();
+        });
+
+        let mut stream = ServerAddr::from(path.as_path())
+            .connect(Duration::from_secs(5))
+            .await
+            .unwrap();
+        let mut buf = [0u8; 2];
+        tokio::io::AsyncReadExt::read_exact(&mut stream, &mut buf)
+            .await
+            .unwrap();
+        assert_eq!(&buf, b"hi");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_addr() {
+        let addr = ServerAddr::tls("hdc.example.com:8711", "hdc.example.com");
+        assert_eq!(addr.host_port(), "hdc.example.com:8711");
+        assert_eq!(addr.to_string(), "tls://hdc.example.com:8711");
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_tls_rejects_plain_server() {
+        let _ = tokio_rustls::rustls::crypto::ring::default_provider().install_default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::io::AsyncWriteExt::write_all(&mut stream, b"OHOS HDC not tls")
+                .await
+                .unwrap();
+        });
+
+        let addr = ServerAddr::tls(address, "localhost");
+        assert!(addr.connect(Duration::from_secs(5)).await.is_err());
+    }
+}
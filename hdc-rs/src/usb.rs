@@ -0,0 +1,420 @@
+//! USB transport for direct device connections (requires the `usb` feature)
+//!
+//! Devices expose hdc as a vendor-specific USB interface. [`list_devices`]
+//! finds them and [`UsbStream`] carries the daemon session protocol over the
+//! interface's bulk endpoints, so [`DaemonClient::connect_usb`] works without
+//! the platform hdc server. The host server must not be running, since only
+//! one process can claim the interface.
+//!
+//! [`DaemonClient::connect_usb`]: crate::DaemonClient::connect_usb
+//!
+//! # Example
+//!
+//! ```no_run
+//! use hdc_rs::{usb, DaemonClient};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! for device in usb::list_devices().await? {
+//!     let mut daemon = DaemonClient::connect_usb(&device).await?;
+//!     println!("{:?}: {}", device.serial, daemon.shell("getprop ro.build.version").await?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::{Buf, Bytes, BytesMut};
+use rusb::{DeviceHandle, Direction, GlobalContext, TransferType, UsbContext};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::error::{HdcError, Result};
+use crate::protocol::session::PAYLOAD_HEAD_SIZE;
+
+/// Interface class of the hdc function
+pub const HDC_CLASS: u8 = 0xff;
+/// Interface subclass of the hdc function
+pub const HDC_SUBCLASS: u8 = 0x50;
+/// Interface protocol of the hdc function
+pub const HDC_PROTOCOL: u8 = 0x01;
+
+/// Size of the header preceding every USB transfer
+const USB_HEAD_SIZE: usize = 11;
+/// Header option for a regular data transfer
+const USB_OPTION_HEADER: u8 = 1;
+/// Header option asking the daemon to drop stale session state
+const USB_OPTION_RESET: u8 = 2;
+
+/// Bulk read size and timeout; the timeout bounds how long the reader
+/// thread outlives a dropped stream
+const USB_READ_SIZE: usize = 512 * 1024;
+const USB_READ_TIMEOUT: Duration = Duration::from_secs(1);
+const USB_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An hdc device found on the USB bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsbDeviceInfo {
+    /// Bus number
+    pub bus: u8,
+    /// Device address on the bus
+    pub address: u8,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Serial number, if it could be read
+    pub serial: Option<String>,
+}
+
+/// Enumerate USB devices exposing the hdc interface
+pub async fn list_devices() -> Result<Vec<UsbDeviceInfo>> {
+    tokio::task::spawn_blocking(list_devices_blocking)
+        .await
+        .map_err(|e| HdcError::Io(io::Error::new(io::ErrorKind::Other, e)))?
+}
+
+fn list_devices_blocking() -> Result<Vec<UsbDeviceInfo>> {
+    let mut found = Vec::new();
+    for device in GlobalContext::default()
+        .devices()
+        .map_err(usb_error)?
+        .iter()
+    {
+        let Ok(descriptor) = device.device_descriptor() else {
+            continue;
+        };
+        if find_endpoints(&device).is_none() {
+            continue;
+        }
+
+        let serial = device
+            .open()
+            .ok()
+            .and_then(|handle| handle.read_serial_number_string_ascii(&descriptor).ok());
+        found.push(UsbDeviceInfo {
+            bus: device.bus_number(),
+            address: device.address(),
+            vendor_id: descriptor.vendor_id(),
+            product_id: descriptor.product_id(),
+            serial,
+        });
+    }
+    debug!("Found {} hdc USB devices", found.len());
+    Ok(found)
+}
+
+/// Interface number and bulk IN/OUT endpoints of the hdc function
+#[derive(Debug, Clone, Copy)]
+struct Endpoints {
+    interface: u8,
+    bulk_in: u8,
+    bulk_out: u8,
+}
+
+fn find_endpoints(device: &rusb::Device<GlobalContext>) -> Option<Endpoints> {
+    let config = device.active_config_descriptor().ok()?;
+    for interface in config.interfaces() {
+        for alt in interface.descriptors() {
+            if (alt.class_code(), alt.sub_class_code(), alt.protocol_code())
+                != (HDC_CLASS, HDC_SUBCLASS, HDC_PROTOCOL)
+            {
+                continue;
+            }
+
+            let mut bulk_in = None;
+            let mut bulk_out = None;
+            for endpoint in alt.endpoint_descriptors() {
+                if endpoint.transfer_type() != TransferType::Bulk {
+                    continue;
+                }
+                match endpoint.direction() {
+                    Direction::In => bulk_in = Some(endpoint.address()),
+                    Direction::Out => bulk_out = Some(endpoint.address()),
+                }
+            }
+            if let (Some(bulk_in), Some(bulk_out)) = (bulk_in, bulk_out) {
+                return Some(Endpoints {
+                    interface: alt.interface_number(),
+                    bulk_in,
+                    bulk_out,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Byte stream over the hdc USB interface
+///
+/// Writes must be whole session packets (as written by
+/// [`SessionCodec`](crate::protocol::SessionCodec)); each is sent as one USB
+/// transfer. Blocking libusb calls run on two helper threads that stop once
+/// the stream is dropped.
+pub struct UsbStream {
+    session_id: u32,
+    outgoing: PacketSplitter,
+    tx: mpsc::UnboundedSender<Bytes>,
+    rx: mpsc::UnboundedReceiver<io::Result<Bytes>>,
+    pending: Bytes,
+}
+
+impl std::fmt::Debug for UsbStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UsbStream")
+            .field("session_id", &self.session_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UsbStream {
+    /// Claim the hdc interface of `device` for session `session_id`
+    pub async fn open(device: &UsbDeviceInfo, session_id: u32) -> Result<Self> {
+        let device = device.clone();
+        let (handle, endpoints) =
+            tokio::task::spawn_blocking(move || open_blocking(&device, session_id))
+                .await
+                .map_err(|e| HdcError::Io(io::Error::new(io::ErrorKind::Other, e)))??;
+        let handle = Arc::new(handle);
+
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let writer = handle.clone();
+        std::thread::spawn(move || write_loop(writer, endpoints.bulk_out, session_id, out_rx));
+        std::thread::spawn(move || read_loop(handle, endpoints.bulk_in, in_tx));
+
+        Ok(Self {
+            session_id,
+            outgoing: PacketSplitter::default(),
+            tx: out_tx,
+            rx: in_rx,
+            pending: Bytes::new(),
+        })
+    }
+
+    /// Session ID carried in the USB headers
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+}
+
+fn open_blocking(
+    info: &UsbDeviceInfo,
+    session_id: u32,
+) -> Result<(DeviceHandle<GlobalContext>, Endpoints)> {
+    let device = GlobalContext::default()
+        .devices()
+        .map_err(usb_error)?
+        .iter()
+        .find(|d| d.bus_number() == info.bus && d.address() == info.address)
+        .ok_or_else(|| HdcError::DeviceNotFound(format!("USB {}:{}", info.bus, info.address)))?;
+    let endpoints = find_endpoints(&device)
+        .ok_or_else(|| HdcError::Protocol("Device has no hdc interface".to_string()))?;
+
+    let handle = device.open().map_err(usb_error)?;
+    // Not supported on every platform; claiming reports the real problem
+    let _ = handle.set_auto_detach_kernel_driver(true);
+    handle
+        .claim_interface(endpoints.interface)
+        .map_err(usb_error)?;
+    info!(
+        "Claimed hdc interface {} on USB {}:{}",
+        endpoints.interface, info.bus, info.address
+    );
+
+    // Clear whatever an earlier host left behind on the daemon side
+    handle
+        .write_bulk(
+            endpoints.bulk_out,
+            &usb_head(USB_OPTION_RESET, session_id, 0),
+            USB_WRITE_TIMEOUT,
+        )
+        .map_err(usb_error)?;
+    Ok((handle, endpoints))
+}
+
+fn write_loop(
+    handle: Arc<DeviceHandle<GlobalContext>>,
+    endpoint: u8,
+    session_id: u32,
+    mut rx: mpsc::UnboundedReceiver<Bytes>,
+) {
+    while let Some(packet) = rx.blocking_recv() {
+        let head = usb_head(USB_OPTION_HEADER, session_id, packet.len() as u32);
+        let result = handle
+            .write_bulk(endpoint, &head, USB_WRITE_TIMEOUT)
+            .and_then(|_| handle.write_bulk(endpoint, &packet, USB_WRITE_TIMEOUT));
+        if let Err(e) = result {
+            warn!("USB write failed: {}", e);
+            return;
+        }
+    }
+}
+
+fn read_loop(
+    handle: Arc<DeviceHandle<GlobalContext>>,
+    endpoint: u8,
+    tx: mpsc::UnboundedSender<io::Result<Bytes>>,
+) {
+    let mut buf = vec![0u8; USB_READ_SIZE];
+    let mut remaining = 0usize;
+    while !tx.is_closed() {
+        let len = match handle.read_bulk(endpoint, &mut buf, USB_READ_TIMEOUT) {
+            Ok(len) => len,
+            Err(rusb::Error::Timeout) => continue,
+            Err(e) => {
+                let _ = tx.send(Err(io::Error::new(io::ErrorKind::Other, e)));
+                return;
+            }
+        };
+
+        let mut chunk = &buf[..len];
+        if remaining == 0 {
+            // Each transfer sequence starts with a USB header
+            let Some(size) = parse_usb_head(chunk) else {
+                warn!("Dropping USB transfer without hdc header ({} bytes)", len);
+                continue;
+            };
+            remaining = size;
+            chunk = &chunk[USB_HEAD_SIZE..];
+        }
+
+        let take = chunk.len().min(remaining);
+        remaining -= take;
+        if take > 0 && tx.send(Ok(Bytes::copy_from_slice(&chunk[..take]))).is_err() {
+            return;
+        }
+    }
+}
+
+/// Build the header preceding a USB transfer of `size` bytes
+fn usb_head(option: u8, session_id: u32, size: u32) -> [u8; USB_HEAD_SIZE] {
+    let mut head = [0u8; USB_HEAD_SIZE];
+    head[..2].copy_from_slice(b"HW");
+    head[2] = option;
+    head[3..7].copy_from_slice(&session_id.to_be_bytes());
+    head[7..].copy_from_slice(&size.to_be_bytes());
+    head
+}
+
+/// Payload size announced by a USB header
+fn parse_usb_head(data: &[u8]) -> Option<usize> {
+    if data.len() < USB_HEAD_SIZE || &data[..2] != b"HW" {
+        return None;
+    }
+    Some(u32::from_be_bytes([data[7], data[8], data[9], data[10]]) as usize)
+}
+
+/// Cuts a written byte stream into whole session packets
+#[derive(Debug, Default)]
+struct PacketSplitter {
+    buf: BytesMut,
+}
+
+impl PacketSplitter {
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn next_packet(&mut self) -> Option<Bytes> {
+        if self.buf.len() < PAYLOAD_HEAD_SIZE {
+            return None;
+        }
+        let mut sizes = &self.buf[5..PAYLOAD_HEAD_SIZE];
+        let total = PAYLOAD_HEAD_SIZE + sizes.get_u16() as usize + sizes.get_u32() as usize;
+        (self.buf.len() >= total).then(|| self.buf.split_to(total).freeze())
+    }
+}
+
+impl AsyncRead for UsbStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.pending.is_empty() {
+            match this.rx.poll_recv(cx) {
+                Poll::Ready(Some(Ok(data))) => this.pending = data,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                // Reader thread gone: end of stream
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let n = this.pending.len().min(buf.remaining());
+        buf.put_slice(&this.pending.split_to(n));
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for UsbStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.outgoing.push(buf);
+        while let Some(packet) = this.outgoing.next_packet() {
+            if this.tx.send(packet).is_err() {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn usb_error(e: rusb::Error) -> HdcError {
+    HdcError::Io(io::Error::new(io::ErrorKind::Other, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{HdcCommand, SessionCodec};
+
+    #[test]
+    fn test_usb_head() {
+        let head = usb_head(USB_OPTION_HEADER, 0x0102_0304, 42);
+        assert_eq!(&head[..3], b"HW\x01");
+        assert_eq!(&head[3..7], &[1, 2, 3, 4]);
+        assert_eq!(parse_usb_head(&head), Some(42));
+        assert_eq!(parse_usb_head(b"short"), None);
+    }
+
+    #[test]
+    fn test_packet_splitter() {
+        let codec = SessionCodec::new();
+        let first = codec.encode(1, HdcCommand::UnityExecute, b"ls").unwrap();
+        let second = codec
+            .encode(1, HdcCommand::KernelChannelClose, &[0])
+            .unwrap();
+        let mut wire = first.to_vec();
+        wire.extend_from_slice(&second);
+
+        let mut splitter = PacketSplitter::default();
+        let mut packets = Vec::new();
+        for piece in wire.chunks(5) {
+            splitter.push(piece);
+            while let Some(packet) = splitter.next_packet() {
+                packets.push(packet);
+            }
+        }
+        assert_eq!(packets, vec![first.freeze(), second.freeze()]);
+    }
+}